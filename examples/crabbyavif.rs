@@ -13,46 +13,107 @@
 // limitations under the License.
 
 use clap::value_parser;
+use clap::Args;
 use clap::Parser;
+use clap::Subcommand;
 
 use crabby_avif::decoder::*;
 #[cfg(feature = "encoder")]
 use crabby_avif::encoder::*;
+#[cfg(feature = "encoder")]
+use crabby_avif::gainmap::GainMap;
+use crabby_avif::image::Image;
+#[cfg(feature = "png")]
+use crabby_avif::image::Plane;
+use crabby_avif::image::YuvRange;
+use crabby_avif::parser::dump::*;
+use crabby_avif::reformat::rgb;
+#[cfg(feature = "encoder")]
+use crabby_avif::reformat::rgb::ChromaDownsampling;
 use crabby_avif::utils::clap::CleanAperture;
 use crabby_avif::utils::clap::CropRect;
+use crabby_avif::utils::Fraction;
 use crabby_avif::utils::IFraction;
 use crabby_avif::utils::UFraction;
 use crabby_avif::*;
 
+#[cfg(all(feature = "encoder", feature = "bmp"))]
+use crabby_avif::utils::reader::bmp::BmpReader;
+#[cfg(feature = "encoder")]
+use crabby_avif::utils::reader::exr::ExrReader;
 #[cfg(all(feature = "encoder", feature = "gif"))]
 use crabby_avif::utils::reader::gif::GifReader;
 #[cfg(all(feature = "encoder", feature = "jpeg"))]
 use crabby_avif::utils::reader::jpeg::JpegReader;
+#[cfg(feature = "encoder")]
+use crabby_avif::utils::reader::pfm::PfmReader;
 #[cfg(all(feature = "encoder", feature = "png"))]
 use crabby_avif::utils::reader::png::PngReader;
+#[cfg(all(feature = "encoder", feature = "pnm"))]
+use crabby_avif::utils::reader::pnm::PnmReader;
+#[cfg(all(feature = "encoder", feature = "tiff"))]
+use crabby_avif::utils::reader::tiff::TiffReader;
+#[cfg(all(feature = "encoder", feature = "webp"))]
+use crabby_avif::utils::reader::webp::WebpReader;
 #[cfg(feature = "encoder")]
 use crabby_avif::utils::reader::y4m::Y4MReader;
 #[cfg(feature = "encoder")]
+use crabby_avif::utils::reader::yuv::YuvReader;
+#[cfg(feature = "encoder")]
 use crabby_avif::utils::reader::Config;
 #[cfg(feature = "encoder")]
 use crabby_avif::utils::reader::Reader;
 
+#[cfg(feature = "bmp")]
+use crabby_avif::utils::writer::bmp::BmpWriter;
+use crabby_avif::utils::writer::exr::ExrWriter;
+#[cfg(feature = "gif")]
+use crabby_avif::utils::writer::gif::GifWriter;
 #[cfg(feature = "jpeg")]
 use crabby_avif::utils::writer::jpeg::JpegWriter;
+use crabby_avif::utils::writer::pfm::PfmWriter;
+#[cfg(feature = "png")]
+use crabby_avif::utils::writer::png::ApngWriter;
 #[cfg(feature = "png")]
 use crabby_avif::utils::writer::png::PngWriter;
+#[cfg(feature = "pnm")]
+use crabby_avif::utils::writer::pnm::PnmFormat;
+#[cfg(feature = "pnm")]
+use crabby_avif::utils::writer::pnm::PnmWriter;
+#[cfg(feature = "tiff")]
+use crabby_avif::utils::writer::tiff::TiffWriter;
+#[cfg(feature = "webp")]
+use crabby_avif::utils::writer::webp::WebpWriter;
 use crabby_avif::utils::writer::y4m::Y4MWriter;
+use crabby_avif::utils::writer::AnimationWriter;
 use crabby_avif::utils::writer::Writer;
 
+use std::collections::HashMap;
 use std::fs::File;
 #[cfg(feature = "encoder")]
 use std::io;
-#[cfg(feature = "encoder")]
 use std::io::Read;
-#[cfg(feature = "encoder")]
 use std::io::Write;
 use std::num::NonZero;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+// Set when the output is written to stdout, in which case the progress messages are printed to
+// stderr instead.
+static LOG_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if LOG_TO_STDERR.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
 
 fn depth_parser(s: &str) -> Result<u8, String> {
     match s.parse::<u8>() {
@@ -153,10 +214,89 @@ fn yuv_format_parser(s: &str) -> Result<PixelFormat, String> {
         "422" => Ok(PixelFormat::Yuv422),
         "444" => Ok(PixelFormat::Yuv444),
         "400" => Ok(PixelFormat::Yuv400),
+        "nv12" => Ok(PixelFormat::AndroidNv12),
+        "nv21" => Ok(PixelFormat::AndroidNv21),
+        "p010" => Ok(PixelFormat::AndroidP010),
         _ => Err(format!("Invalid yuv format: {s}")),
     }
 }
 
+fn yuv_range_parser(s: &str) -> Result<YuvRange, String> {
+    match s {
+        "l" | "limited" => Ok(YuvRange::Limited),
+        "f" | "full" => Ok(YuvRange::Full),
+        _ => Err(format!("Invalid range: {s}")),
+    }
+}
+
+fn size_parser(s: &str) -> Result<(u32, u32), String> {
+    let values = split_and_check_count!("size", s, "x", 2, u32);
+    Ok((values[0], values[1]))
+}
+
+fn grid_parser(s: &str) -> Result<(u32, u32), String> {
+    let values = split_and_check_count!("grid", s, "x", 2, u32);
+    if values.iter().any(|value| !(1..=256).contains(value)) {
+        return Err("Grid dimensions must be in 1..256".into());
+    }
+    Ok((values[0], values[1]))
+}
+
+// Codec specific option, as [CATEGORY:]KEY=VALUE. Options without a category apply to all the
+// categories.
+fn codec_option_parser(s: &str) -> Result<(Option<Category>, String, String), String> {
+    let (category, option) = match s.split_once(':') {
+        Some(("c" | "color", option)) => (Some(Category::Color), option),
+        Some(("a" | "alpha", option)) => (Some(Category::Alpha), option),
+        Some(("g" | "gainmap", option)) => (Some(Category::Gainmap), option),
+        _ => (None, s),
+    };
+    match option.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((category, key.into(), value.into())),
+        _ => Err(format!("Invalid codec option: {s}")),
+    }
+}
+
+// Range of frames to decode. The last frame is included.
+#[derive(Clone, Copy)]
+struct FrameRange {
+    first: u32,
+    // The last frame of the sequence if None.
+    last: Option<u32>,
+}
+
+fn frame_range_parser(s: &str) -> Result<FrameRange, String> {
+    if s == "all" {
+        return Ok(FrameRange {
+            first: 0,
+            last: None,
+        });
+    }
+    let parse = |value: &str| {
+        value
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid frame range: {s}"))
+    };
+    let (first, last) = match s.split_once('-') {
+        Some((first, "")) => (parse(first)?, None),
+        Some((first, last)) => (parse(first)?, Some(parse(last)?)),
+        None => (parse(s)?, Some(parse(s)?)),
+    };
+    if last.is_some_and(|last| last < first) {
+        return Err(format!("Invalid frame range: {s}"));
+    }
+    Ok(FrameRange { first, last })
+}
+
+fn extension_mapping_parser(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_lowercase(), to.to_lowercase()))
+        }
+        _ => Err("Invalid extension mapping. Must be of the form FROM=TO".into()),
+    }
+}
+
 fn codec_choice_parser(s: &str) -> Result<CodecChoice, String> {
     match s {
         "auto" | "default" => Ok(CodecChoice::default()),
@@ -170,6 +310,16 @@ fn codec_choice_parser(s: &str) -> Result<CodecChoice, String> {
     }
 }
 
+#[cfg(feature = "encoder")]
+fn recipe_parser(s: &str) -> Result<Recipe, String> {
+    match s {
+        "auto" => Ok(Recipe::Auto),
+        "none" => Ok(Recipe::None),
+        "bit-depth-extension-8b8b" => Ok(Recipe::BitDepthExtension8b8b),
+        _ => Err(format!("Invalid recipe: {s}")),
+    }
+}
+
 fn header_format_parser(s: &str) -> Result<HeaderFormat, String> {
     match s {
         "meta" | "default" => Ok(HeaderFormat::Default),
@@ -178,7 +328,7 @@ fn header_format_parser(s: &str) -> Result<HeaderFormat, String> {
     }
 }
 
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 struct CommandLineArgs {
     /// Prints the version of this crate and the version of each available codec
     #[arg(long, default_value = "false")]
@@ -198,6 +348,15 @@ struct CommandLineArgs {
     #[arg(short = 'i', long, default_value = "false")]
     info: bool,
 
+    /// AVIF Decode only: With --info, print the image information as JSON
+    #[arg(long, default_value = "false")]
+    json: bool,
+
+    /// AVIF Decode only: Print the box tree and the item graph of the file instead of decoding it.
+    /// Files using a MinimizedImageBox are shown with their equivalent item graph
+    #[arg(long, default_value = "false", conflicts_with = "info")]
+    dump: bool,
+
     /// Number of threads to use for AVIF encoding/decoding
     #[arg(long)]
     jobs: Option<u32>,
@@ -207,8 +366,15 @@ struct CommandLineArgs {
     #[arg(long, short = 'I')]
     index: Option<u32>,
 
-    /// Output depth, either 8 or 16. (AVIF/PNG only; For y4m/yuv, source depth is retained; JPEG
-    /// is always 8bit)
+    /// AVIF Decode only: Decode a range of frames of an image sequence, given as FIRST-LAST
+    /// (inclusive), FIRST- or all. The frames are written to the files named by a numbered
+    /// output pattern such as frame_%04d.png, to a single y4m or yuv file, or to an animated png,
+    /// gif or webp file. The timing of the frames is written to a .durations.txt sidecar file
+    #[arg(long, value_parser = frame_range_parser, conflicts_with = "index")]
+    frames: Option<FrameRange>,
+
+    /// Output depth, either 8 or 16. (AVIF/PNG/TIFF/PNM only; For y4m/yuv, source depth is
+    /// retained; JPEG, GIF, WebP and BMP are always 8bit)
     #[arg(long, short = 'd', value_parser = depth_parser)]
     depth: Option<u8>,
 
@@ -279,8 +445,10 @@ struct CommandLineArgs {
     #[arg(long, value_parser = clli_parser)]
     clli: Option<ContentLightLevelInformation>,
 
-    /// AVIF Encode only: Set CICP values (nclx colr box) (P/T/M 3 raw numbers, use -r to set range
-    /// flag)
+    /// AVIF Encode only: Set CICP values (nclx colr box) (P/T/M 3 raw numbers). The range flag is
+    /// full for RGB input, read from the header for y4m input and set with -r for raw yuv input.
+    /// For PFM and EXR input, T is also the transfer function applied to the linear samples
+    /// (default: 16, PQ)
     #[arg(long, value_parser = cicp_parser)]
     cicp: Option<Nclx>,
 
@@ -314,22 +482,178 @@ struct CommandLineArgs {
     autotiling: bool,
 
     /// AVIF Encode only: Output format, one of 444, 422, 420 or 400. Ignored for y4m. For all
-    /// other cases, auto defaults to 444.
+    /// other cases, auto defaults to 444. For raw yuv input, this is the layout of the input
+    /// frames, which can also be nv12, nv21 or p010 (default: 420).
     #[arg(long = "yuv", value_parser = yuv_format_parser)]
     yuv_format: Option<PixelFormat>,
 
+    /// AVIF Encode only: Raw yuv input only: Frame dimensions, as WxH. Required for raw yuv input
+    #[arg(long, value_parser = size_parser)]
+    size: Option<(u32, u32)>,
+
+    /// AVIF Encode only: Raw yuv input only: YUV range, limited or full (default: limited)
+    #[arg(long, short = 'r', value_parser = yuv_range_parser)]
+    range: Option<YuvRange>,
+
+    /// AVIF Encode only: Raw yuv input only: Samples stored with more than 8 bits are big-endian
+    #[arg(long, default_value = "false")]
+    big_endian: bool,
+
+    /// AVIF Encode only: Frame rate of the output sequence, in frames per second. Overrides the
+    /// durations of the input frames. Required to know the timing of raw yuv input frames
+    #[arg(long, value_parser = value_parser!(f64))]
+    fps: Option<f64>,
+
+    /// AVIF Encode only: Number of time units per second of the output sequence (Default: 1000)
+    #[arg(long, value_parser = value_parser!(u64).range(1..))]
+    timescale: Option<u64>,
+
+    /// AVIF Encode only: Maximum number of frames between two keyframes of the output sequence
+    /// (Default: 0, decided by the codec)
+    #[arg(long, value_parser = value_parser!(i32).range(0..))]
+    keyframe: Option<i32>,
+
+    /// AVIF Encode only: Alpha quality in 0..100 (Default: 60)
+    #[arg(long, value_parser = value_parser!(f32))]
+    qalpha: Option<f32>,
+
+    /// AVIF Encode only: Gain map quality in 0..100 (Default: 60)
+    #[arg(long, value_parser = value_parser!(f32))]
+    qgain_map: Option<f32>,
+
+    /// AVIF Encode only: Encode losslessly. Sets the color and alpha qualities to 100, the
+    /// matrix coefficients to identity and the output format to 444
+    #[arg(long, default_value = "false")]
+    lossless: bool,
+
+    /// AVIF Encode only: Split the input image into a grid of MxN cells (M columns, N rows) each
+    /// encoded separately
+    #[arg(long, value_parser = grid_parser)]
+    grid: Option<(u32, u32)>,
+
+    /// AVIF Encode only: Store the color samples premultiplied by the alpha samples
+    #[arg(long, default_value = "false")]
+    premultiply: bool,
+
+    /// AVIF Encode only: Use sharp RGB to YUV420 conversion. Not supported with --yuv 422, no
+    /// effect with --yuv 444 or 400
+    #[arg(long, default_value = "false")]
+    sharpyuv: bool,
+
+    /// AVIF Encode only: Recipe applied to the input samples, one of auto, none or
+    /// bit-depth-extension-8b8b (Default: none)
+    #[cfg(feature = "encoder")]
+    #[arg(long, value_parser = recipe_parser)]
+    recipe: Option<Recipe>,
+
+    /// AVIF Encode only: Codec specific option, as [CATEGORY:]KEY=VALUE where CATEGORY is
+    /// c/color, a/alpha or g/gainmap. Options without a category apply to all the categories. May
+    /// be repeated
+    #[arg(short = 'a', long = "advanced", value_parser = codec_option_parser)]
+    codec_options: Vec<(Option<Category>, String, String)>,
+
+    /// AVIF Encode only: Gain map image to store along with the input image. Requires
+    /// --gain-map-metadata. Replaces the gain map of Ultra HDR JPEG input. Computing a gain map
+    /// from an HDR alternate image is not supported, the gain map must be given precomputed
+    #[arg(long, requires = "gain_map_metadata")]
+    gain_map: Option<String>,
+
+    /// AVIF Encode only: JSON file describing the gain map given by --gain-map, in the format of
+    /// the "gain_map" object printed by --info --json (the "metadata" object is required, the
+    /// "alternate_image" object is optional)
+    #[arg(long, requires = "gain_map")]
+    gain_map_metadata: Option<String>,
+
     /// AVIF Encode only: Number of times an animated image sequence will be repeated, or
-    /// 'infinite' for infinite repetitions. (Default: infinite)
-    #[arg(long, default_value = "infinite", value_parser = repetition_count_parser)]
-    repetition_count: RepetitionCount,
+    /// 'infinite' for infinite repetitions. (Default: the loop count of the input animation if
+    /// any, infinite otherwise)
+    #[arg(long, value_parser = repetition_count_parser)]
+    repetition_count: Option<RepetitionCount>,
+
+    /// AVIF Encode only: JPEG input only: Copy the YCbCr samples of the JPEG instead of converting
+    /// them to RGB and back. The output format defaults to the subsampling of the JPEG. Requires
+    /// a depth of 8 and bt601 matrix coefficients. Subsampled JPEGs require the mozjpeg feature
+    #[arg(long, default_value = "false")]
+    jpeg_passthrough: bool,
+
+    /// AVIF Decode only: GIF output only: Dither the colors when reducing them to a palette
+    #[arg(long, default_value = "false")]
+    dither: bool,
+
+    /// AVIF Decode only: PNG output only: Write all the frames of an image sequence to an animated
+    /// PNG. Without this flag, only the frame at --index is written. Implied by the apng output
+    /// format
+    #[arg(long, default_value = "false", conflicts_with = "index")]
+    apng: bool,
+
+    /// Batch mode: Convert all the given files, directories or wildcard patterns (such as
+    /// photos/*.jpg) into --output-dir, on --jobs worker threads. Outputs that are newer than
+    /// their input are skipped
+    #[arg(long, num_args = 1.., value_name = "INPUT")]
+    batch: Vec<String>,
+
+    /// Batch mode only: Directory the converted files are written to
+    #[arg(long)]
+    output_dir: Option<String>,
 
-    /// Input AVIF file
+    /// Batch mode only: Output extension of the files with the given input extension, as
+    /// FROM=TO (such as avif=jpg). May be repeated. By default, avif files are converted to png
+    /// and all other files are converted to avif
+    #[arg(long, value_parser = extension_mapping_parser)]
+    batch_ext: Vec<(String, String)>,
+
+    /// Format of the input file, as a file extension such as png. Overrides the extension of
+    /// input_file. Required when reading anything but AVIF from stdin
+    #[arg(long)]
+    input_format: Option<String>,
+
+    /// Format of the output file, as a file extension such as png. Overrides the extension of
+    /// output_file. Required when writing to stdout
+    #[arg(long)]
+    output_format: Option<String>,
+
+    /// Input file, or - to read from stdin
     #[arg(allow_hyphen_values = false)]
     input_file: Option<String>,
 
-    /// Output file
+    /// Output file, or - to write to stdout
     #[arg(allow_hyphen_values = false)]
     output_file: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Compare two images and report the differences of each channel once converted to RGB.
+    /// Exits with status 2 if a threshold is exceeded
+    Compare(CompareArgs),
+}
+
+#[derive(Clone, Args)]
+struct CompareArgs {
+    /// Reference image (AVIF or any supported input format)
+    first: String,
+
+    /// Image to compare with the reference (AVIF or any supported input format)
+    second: String,
+
+    /// Write the absolute differences as a PNG, amplified so that the maximum error is white
+    #[arg(long)]
+    diff: Option<String>,
+
+    /// Fail if the PSNR of any channel is below this value, in dB
+    #[arg(long)]
+    min_psnr: Option<f64>,
+
+    /// Fail if the SSIM of any channel is below this value
+    #[arg(long)]
+    min_ssim: Option<f64>,
+
+    /// Fail if the absolute error of any sample is above this value
+    #[arg(long)]
+    max_error: Option<u32>,
 }
 
 fn print_data_as_columns(rows: &[(usize, &str, String)]) {
@@ -346,7 +670,7 @@ fn print_data_as_columns(rows: &[(usize, &str, String)]) {
     }
 
     for (col1, col2) in &rows {
-        println!("{col1:<max_col1_width$} : {col2}");
+        log!("{col1:<max_col1_width$} : {col2}");
     }
 }
 
@@ -519,6 +843,184 @@ fn print_image_info(decoder: &Decoder) {
     print_data_as_columns(&image_data);
 }
 
+// The enum strings below are part of the JSON schema and must not change when the Rust enums
+// are renamed.
+fn json_compression_format(compression_format: CompressionFormat) -> &'static str {
+    match compression_format {
+        CompressionFormat::Avif => "avif",
+        CompressionFormat::Heic => "heic",
+        #[cfg(feature = "jpegxl")]
+        CompressionFormat::JpegXl => "jpegxl",
+    }
+}
+
+fn json_yuv_format(yuv_format: PixelFormat) -> &'static str {
+    match yuv_format {
+        PixelFormat::None => "none",
+        PixelFormat::Yuv444 => "yuv444",
+        PixelFormat::Yuv422 => "yuv422",
+        PixelFormat::Yuv420 => "yuv420",
+        PixelFormat::Yuv400 => "yuv400",
+        PixelFormat::AndroidP010 => "androidp010",
+        PixelFormat::AndroidNv12 => "androidnv12",
+        PixelFormat::AndroidNv21 => "androidnv21",
+    }
+}
+
+fn json_yuv_range(yuv_range: YuvRange) -> &'static str {
+    match yuv_range {
+        YuvRange::Limited => "limited",
+        YuvRange::Full => "full",
+    }
+}
+
+fn json_chroma_sample_position(chroma_sample_position: ChromaSamplePosition) -> &'static str {
+    match chroma_sample_position {
+        ChromaSamplePosition::Unknown => "unknown",
+        ChromaSamplePosition::Vertical => "vertical",
+        ChromaSamplePosition::Colocated => "colocated",
+        ChromaSamplePosition::Reserved => "reserved",
+    }
+}
+
+fn json_progressive_state(progressive_state: ProgressiveState) -> &'static str {
+    match progressive_state {
+        ProgressiveState::Unavailable => "unavailable",
+        ProgressiveState::Available => "available",
+        ProgressiveState::Active => "active",
+    }
+}
+
+fn json_repetition_count(repetition_count: RepetitionCount) -> serde_json::Value {
+    match repetition_count {
+        RepetitionCount::Unknown => serde_json::json!("unknown"),
+        RepetitionCount::Infinite => serde_json::json!("infinite"),
+        RepetitionCount::Finite(count) => serde_json::json!(count),
+    }
+}
+
+fn json_fraction(fraction: Fraction) -> serde_json::Value {
+    serde_json::json!([fraction.0, fraction.1])
+}
+
+fn json_ufraction(fraction: UFraction) -> serde_json::Value {
+    serde_json::json!([fraction.0, fraction.1])
+}
+
+fn json_cicp(
+    color_primaries: ColorPrimaries,
+    transfer_characteristics: TransferCharacteristics,
+    matrix_coefficients: MatrixCoefficients,
+) -> serde_json::Value {
+    serde_json::json!({
+        "color_primaries": color_primaries as u16,
+        "transfer_characteristics": transfer_characteristics as u16,
+        "matrix_coefficients": matrix_coefficients as u16,
+    })
+}
+
+fn json_clli(clli: Option<ContentLightLevelInformation>) -> serde_json::Value {
+    match clli {
+        Some(clli) => serde_json::json!({"max_cll": clli.max_cll, "max_pall": clli.max_pall}),
+        None => serde_json::Value::Null,
+    }
+}
+
+// Same data as print_image_info(), with a stable schema. Absent properties are null.
+fn image_info_json(decoder: &Decoder) -> serde_json::Value {
+    let image = decoder.image().unwrap();
+    let clap = image.clap.map(|clap| {
+        let crop = CropRect::create_from(&clap, image.width, image.height, image.yuv_format)
+            .ok()
+            .map(|rect| {
+                serde_json::json!({
+                    "x": rect.x,
+                    "y": rect.y,
+                    "width": rect.width,
+                    "height": rect.height,
+                })
+            });
+        serde_json::json!({
+            "width": json_ufraction(clap.width),
+            "height": json_ufraction(clap.height),
+            "horiz_off": json_ufraction(clap.horiz_off),
+            "vert_off": json_ufraction(clap.vert_off),
+            "crop_rect": crop,
+        })
+    });
+    let gainmap = decoder.gainmap_present().then(|| {
+        let gainmap = decoder.gainmap();
+        let metadata = &gainmap.metadata;
+        let fractions = |values: &[Fraction; 3]| -> Vec<serde_json::Value> {
+            values.iter().map(|value| json_fraction(*value)).collect()
+        };
+        serde_json::json!({
+            "width": gainmap.image.width,
+            "height": gainmap.image.height,
+            "depth": gainmap.image.depth,
+            "yuv_format": json_yuv_format(gainmap.image.yuv_format),
+            "yuv_range": json_yuv_range(gainmap.image.yuv_range),
+            "matrix_coefficients": gainmap.image.matrix_coefficients as u16,
+            "metadata": {
+                "min": fractions(&metadata.min),
+                "max": fractions(&metadata.max),
+                "gamma": metadata.gamma.iter().map(|value| json_ufraction(*value)).collect::<Vec<_>>(),
+                "base_offset": fractions(&metadata.base_offset),
+                "alternate_offset": fractions(&metadata.alternate_offset),
+                "base_hdr_headroom": json_ufraction(metadata.base_hdr_headroom),
+                "alternate_hdr_headroom": json_ufraction(metadata.alternate_hdr_headroom),
+                "use_base_color_space": metadata.use_base_color_space,
+            },
+            "alternate_image": {
+                "cicp": json_cicp(
+                    gainmap.alt_color_primaries,
+                    gainmap.alt_transfer_characteristics,
+                    gainmap.alt_matrix_coefficients,
+                ),
+                "yuv_range": json_yuv_range(gainmap.alt_yuv_range),
+                "icc_size": gainmap.alt_icc.len(),
+                "depth": gainmap.alt_plane_depth,
+                "plane_count": gainmap.alt_plane_count,
+                "clli": json_clli(Some(gainmap.alt_clli)),
+            },
+        })
+    });
+    serde_json::json!({
+        "compression_format": json_compression_format(decoder.compression_format()),
+        "width": image.width,
+        "height": image.height,
+        "depth": image.depth,
+        "yuv_format": json_yuv_format(image.yuv_format),
+        "chroma_sample_position": json_chroma_sample_position(image.chroma_sample_position),
+        "yuv_range": json_yuv_range(image.yuv_range),
+        "alpha_present": image.alpha_present,
+        "alpha_premultiplied": image.alpha_premultiplied,
+        "cicp": json_cicp(
+            image.color_primaries,
+            image.transfer_characteristics,
+            image.matrix_coefficients,
+        ),
+        "icc_size": image.icc.len(),
+        "exif_size": image.exif.len(),
+        "xmp_size": image.xmp.len(),
+        "pasp": image.pasp.map(|pasp| {
+            serde_json::json!({"h_spacing": pasp.h_spacing, "v_spacing": pasp.v_spacing})
+        }),
+        "clap": clap,
+        "irot_angle": image.irot_angle,
+        "imir_axis": image.imir_axis,
+        "clli": json_clli(image.clli),
+        "progressive_state": json_progressive_state(image.progressive_state),
+        "gain_map": gainmap,
+        "image_sequence_track_present": image.image_sequence_track_present,
+        "repetition_count": json_repetition_count(decoder.repetition_count()),
+        "timescale": decoder.timescale(),
+        "duration": decoder.duration(),
+        "duration_in_timescales": decoder.duration_in_timescales(),
+        "image_count": decoder.image_count(),
+    })
+}
+
 fn max_threads(jobs: &Option<u32>) -> u32 {
     match jobs {
         Some(x) => {
@@ -539,7 +1041,7 @@ fn create_decoder_and_parse(args: &CommandLineArgs, input_file: &String) -> Avif
     let mut settings = decoder::Settings {
         strictness: if args.no_strict { Strictness::None } else { Strictness::All },
         image_content_to_decode: ImageContentType::All,
-        codec_choice: args.codec,
+        codec_choice: args.codec.clone(),
         max_threads: max_threads(&args.jobs),
         allow_progressive: args.progressive,
         ignore_exif: args.ignore_exif,
@@ -556,20 +1058,172 @@ fn create_decoder_and_parse(args: &CommandLineArgs, input_file: &String) -> Avif
     }
     let mut decoder = Decoder::default();
     decoder.settings = settings;
-    decoder
-        .set_io_file(input_file)
-        .or(Err(AvifError::UnknownError(
-            "Cannot open input file".into(),
-        )))?;
+    if input_file == STDIO {
+        let data = read_stdin()?;
+        if !Decoder::peek_compatible_file_type(&data) {
+            return Err(AvifError::UnknownError(
+                "stdin is not an AVIF file, use --input-format to read other formats".into(),
+            ));
+        }
+        decoder.set_io_vec(data);
+    } else {
+        decoder
+            .set_io_file(input_file)
+            .or(Err(AvifError::UnknownError(
+                "Cannot open input file".into(),
+            )))?;
+    }
     decoder.parse()?;
     Ok(decoder)
 }
 
+fn info_json(decoder: &mut Decoder, input_file: &str) -> AvifResult<()> {
+    let mut info = image_info_json(decoder);
+    let mut frames = Vec::new();
+    loop {
+        match decoder.next_image() {
+            Ok(_) => {}
+            Err(AvifError::NoImagesRemaining) => break,
+            Err(err) => return Err(err),
+        }
+        let index = decoder.image_index() as u32;
+        let timing = decoder.image_timing();
+        let image = decoder.image().unwrap();
+        frames.push(serde_json::json!({
+            "index": index,
+            "pts": timing.pts,
+            "pts_in_timescales": timing.pts_in_timescales,
+            "duration": timing.duration,
+            "duration_in_timescales": timing.duration_in_timescales,
+            "width": image.width,
+            "height": image.height,
+            "keyframe": decoder.is_keyframe(index),
+        }));
+    }
+    // The IO stats are totals over the whole file, not per frame.
+    let io_stats = decoder.io_stats();
+    info["file"] = serde_json::json!(input_file);
+    info["io_stats"] = serde_json::json!({
+        "color_obu_size": io_stats.color_obu_size,
+        "alpha_obu_size": io_stats.alpha_obu_size,
+    });
+    info["frames"] = serde_json::json!(frames);
+    log!(
+        "{}",
+        serde_json::to_string_pretty(&info)
+            .map_err(|err| AvifError::UnknownError(err.to_string()))?
+    );
+    Ok(())
+}
+
+fn print_box(node: &BoxNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let version_and_flags = match node.version_and_flags {
+        Some((version, flags)) => format!(", version {version}, flags {flags:#x}"),
+        None => String::new(),
+    };
+    log!(
+        "{indent}{} (offset {}, size {}, header {}{version_and_flags})",
+        node.box_type,
+        node.offset,
+        node.size,
+        node.header_size
+    );
+    for (name, value) in &node.fields {
+        log!("{indent}  - {name}: {value}");
+    }
+    for child in &node.children {
+        print_box(child, depth + 1);
+    }
+}
+
+fn print_item_graph(graph: &ItemGraph) {
+    log!(
+        "Item graph{}:",
+        if graph.from_mini { " (expanded from mini)" } else { "" }
+    );
+    log!("  primary item: {}", graph.primary_item_id);
+    for item in &graph.items {
+        let mut description = format!("  item {} '{}'", item.id, item.item_type);
+        if !item.item_name.is_empty() {
+            description += &format!(" name \"{}\"", item.item_name);
+        }
+        if !item.content_type.is_empty() {
+            description += &format!(" content_type \"{}\"", item.content_type);
+        }
+        log!("{description}");
+        for property in &item.properties {
+            log!(
+                "    property {}: {}{} {}",
+                property.index,
+                property.box_type,
+                if property.essential { " (essential)" } else { "" },
+                property.description
+            );
+        }
+        for reference in &item.references {
+            log!(
+                "    reference: {} -> item {}",
+                reference.reference_type,
+                reference.to_item_id
+            );
+        }
+        if !item.extents.is_empty() {
+            let extents: Vec<String> = item
+                .extents
+                .iter()
+                .map(|extent| format!("{}+{}", extent.offset, extent.size))
+                .collect();
+            log!(
+                "    location: construction_method {}, base_offset {}, extents [{}]",
+                item.construction_method,
+                item.base_offset,
+                extents.join(", ")
+            );
+        }
+    }
+    for group in &graph.entity_groups {
+        let entity_ids: Vec<String> = group.entity_ids.iter().map(|x| x.to_string()).collect();
+        log!(
+            "  entity group '{}': {}",
+            group.grouping_type,
+            entity_ids.join(", ")
+        );
+    }
+}
+
+fn dump(input_file: &str) -> AvifResult<()> {
+    let data = if input_file == STDIO {
+        read_stdin()?
+    } else {
+        std::fs::read(input_file)
+            .map_err(|err| AvifError::UnknownError(format!("Could not read {input_file}: {err}")))?
+    };
+    let root = parse_box_tree(&data);
+    log!("File: {input_file} ({} bytes)", root.size);
+    for (name, value) in &root.fields {
+        log!("  - {name}: {value}");
+    }
+    for node in &root.children {
+        print_box(node, 1);
+    }
+    // The item graph is built by the same parser as the decoder, so it may fail where the box
+    // tree above shows partial results.
+    match parse_item_graph(&data) {
+        Ok(graph) => print_item_graph(&graph),
+        Err(err) => log!("Item graph: not available ({err})"),
+    }
+    Ok(())
+}
+
 fn info(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
     let mut decoder = create_decoder_and_parse(args, input_file)?;
-    println!("Image decoded: {}", input_file);
+    if args.json {
+        return info_json(&mut decoder, input_file);
+    }
+    log!("Image decoded: {}", input_file);
     print_image_info(&decoder);
-    println!(
+    log!(
         " * {} timescales per second, {} seconds ({} timescales), {} frame{}",
         decoder.timescale(),
         decoder.duration(),
@@ -579,7 +1233,7 @@ fn info(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
     );
     if decoder.image_count() > 1 {
         let image = decoder.image().unwrap();
-        println!(
+        log!(
             " * {} Frames: ({} expected frames)",
             if image.image_sequence_track_present {
                 "Image Sequence"
@@ -589,14 +1243,14 @@ fn info(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
             decoder.image_count()
         );
     } else {
-        println!(" * Frame:");
+        log!(" * Frame:");
     }
 
     let mut index = 0;
     loop {
         match decoder.next_image() {
             Ok(_) => {
-                println!("     * Decoded frame [{}] [pts {} ({} timescales)] [duration {} ({} timescales)] [{}x{}]",
+                log!("     * Decoded frame [{}] [pts {} ({} timescales)] [duration {} ({} timescales)] [{}x{}]",
                     index,
                     decoder.image_timing().pts,
                     decoder.image_timing().pts_in_timescales,
@@ -624,45 +1278,180 @@ fn get_extension(filename: &str) -> String {
         .to_lowercase()
 }
 
-fn decode(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
-    let max_threads = max_threads(&args.jobs);
-    println!(
-        "Decoding with {max_threads} worker thread{}, please wait...",
-        if max_threads == 1 { "" } else { "s" }
-    );
-    let mut decoder = create_decoder_and_parse(args, input_file)?;
-    decoder.nth_image(args.index.unwrap_or(0))?;
-    println!("Image Decoded: {}", input_file);
-    println!("Image details:");
-    print_image_info(&decoder);
+// Name of the input and output files standing for stdin and stdout.
+const STDIO: &str = "-";
 
-    let output_filename = &args.output_file.as_ref().unwrap().as_str();
-    let image = decoder.image().unwrap();
-    let extension = get_extension(output_filename);
-    let mut writer: Box<dyn Writer> = match extension.as_str() {
+// Input files read from stdin are AVIF files unless --input-format says otherwise.
+fn input_format(args: &CommandLineArgs) -> String {
+    match (&args.input_format, args.input_file.as_deref()) {
+        (Some(format), _) => format.to_lowercase(),
+        (None, Some(STDIO)) => "avif".into(),
+        (None, input_file) => get_extension(input_file.unwrap_or_default()),
+    }
+}
+
+fn output_format(args: &CommandLineArgs) -> String {
+    match &args.output_format {
+        Some(format) => format.to_lowercase(),
+        None => get_extension(args.output_file.as_deref().unwrap_or_default()),
+    }
+}
+
+fn read_stdin() -> AvifResult<Vec<u8>> {
+    let mut data = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|err| AvifError::UnknownError(format!("Could not read stdin: {err}")))?;
+    Ok(data)
+}
+
+fn write_stdout(data: &[u8]) -> AvifResult<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(data)
+        .and_then(|_| stdout.flush())
+        .map_err(|err| AvifError::UnknownError(format!("Could not write stdout: {err}")))
+}
+
+fn create_writer(
+    args: &CommandLineArgs,
+    extension: &str,
+    image: &Image,
+) -> AvifResult<Box<dyn Writer>> {
+    Ok(match extension {
         "y4m" | "yuv" => {
             if !image.icc.is_empty() || !image.exif.is_empty() || !image.xmp.is_empty() {
-                println!("Warning: metadata dropped when saving to {extension}");
+                log!("Warning: metadata dropped when saving to {extension}");
             }
             Box::new(Y4MWriter::create(extension == "yuv"))
         }
         #[cfg(feature = "png")]
-        "png" => Box::new(PngWriter { depth: args.depth }),
+        "png" | "apng" => Box::new(PngWriter { depth: args.depth }),
+        #[cfg(feature = "gif")]
+        "gif" => Box::new(GifWriter::create(RepetitionCount::Finite(0), args.dither)),
         #[cfg(feature = "jpeg")]
         "jpg" | "jpeg" => Box::new(JpegWriter {
             quality: args.quality.map(|quality| quality as u8),
         }),
+        #[cfg(feature = "webp")]
+        "webp" => Box::new(WebpWriter::default()),
+        #[cfg(feature = "tiff")]
+        "tif" | "tiff" => Box::new(TiffWriter { depth: args.depth }),
+        #[cfg(feature = "bmp")]
+        "bmp" => Box::new(BmpWriter::default()),
+        "pfm" => Box::new(PfmWriter::default()),
+        "exr" => Box::new(ExrWriter::default()),
+        #[cfg(feature = "pnm")]
+        "pnm" | "pgm" | "ppm" | "pam" => Box::new(PnmWriter {
+            depth: args.depth,
+            format: match extension {
+                "pgm" => PnmFormat::Pgm,
+                "ppm" => PnmFormat::Ppm,
+                "pam" => PnmFormat::Pam,
+                _ => PnmFormat::Auto,
+            },
+        }),
         _ => {
             return Err(AvifError::UnknownError(format!(
                 "Unknown output file extension ({extension})"
             )));
         }
-    };
-    let mut output_file = File::create(output_filename).or(Err(AvifError::UnknownError(
+    })
+}
+
+fn create_animation_writer(
+    args: &CommandLineArgs,
+    extension: &str,
+    frame_count: u32,
+    repetition_count: RepetitionCount,
+) -> Option<Box<dyn AnimationWriter>> {
+    match extension {
+        #[cfg(feature = "png")]
+        "png" | "apng" => Some(Box::new(ApngWriter::create(
+            args.depth,
+            frame_count,
+            repetition_count,
+        ))),
+        #[cfg(feature = "gif")]
+        "gif" => Some(Box::new(GifWriter::create(repetition_count, args.dither))),
+        #[cfg(feature = "webp")]
+        "webp" => Some(Box::new(WebpWriter::create(repetition_count))),
+        _ => None,
+    }
+}
+
+fn create_output_file(output_filename: &str) -> AvifResult<File> {
+    File::create(output_filename).or(Err(AvifError::UnknownError(
         "Could not open output file".into(),
-    )))?;
-    writer.write_frame(&mut output_file, image)?;
-    println!(
+    )))
+}
+
+// Writes the current image of decoder, along with its gain map if the output is a JPEG.
+fn write_image(
+    args: &CommandLineArgs,
+    decoder: &Decoder,
+    extension: &str,
+    output_filename: &str,
+) -> AvifResult<()> {
+    let image = decoder.image().unwrap();
+    let mut writer = create_writer(args, extension, image)?;
+    let mut output_file = create_output_file(output_filename)?;
+    if decoder.gainmap_present() && matches!(extension, "jpg" | "jpeg") {
+        // Write an Ultra HDR JPEG.
+        writer.write_frame_with_gainmap(&mut output_file, image, decoder.gainmap())
+    } else {
+        writer.write_frame(&mut output_file, image)
+    }
+}
+
+fn decode(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
+    let output_filename = args.output_file.as_ref().unwrap();
+    if output_filename != STDIO {
+        return decode_to_file(args, input_file, output_filename);
+    }
+    // The writers need a file, which is copied to stdout once complete.
+    let output_file =
+        tempfile::NamedTempFile::new().map_err(|err| AvifError::UnknownError(err.to_string()))?;
+    decode_to_file(args, input_file, &output_file.path().to_string_lossy())?;
+    write_stdout(
+        &std::fs::read(output_file.path())
+            .map_err(|err| AvifError::UnknownError(err.to_string()))?,
+    )
+}
+
+fn decode_to_file(
+    args: &CommandLineArgs,
+    input_file: &String,
+    output_filename: &str,
+) -> AvifResult<()> {
+    let max_threads = max_threads(&args.jobs);
+    log!(
+        "Decoding with {max_threads} worker thread{}, please wait...",
+        if max_threads == 1 { "" } else { "s" }
+    );
+    let mut decoder = create_decoder_and_parse(args, input_file)?;
+    let extension = output_format(args);
+    if let Some(frame_range) = args.frames {
+        return decode_frames(args, &mut decoder, frame_range, output_filename);
+    }
+    // A png output file is animated only if explicitly requested.
+    if args.index.is_none() && decoder.image_count() > 1 && (extension != "png" || args.apng) {
+        if let Some(animation_writer) = create_animation_writer(
+            args,
+            &extension,
+            decoder.image_count(),
+            decoder.repetition_count(),
+        ) {
+            return decode_animation(&mut decoder, animation_writer, output_filename);
+        }
+    }
+    decoder.nth_image(args.index.unwrap_or(0))?;
+    log!("Image Decoded: {}", input_file);
+    log!("Image details:");
+    print_image_info(&decoder);
+
+    write_image(args, &decoder, &extension, output_filename)?;
+    log!(
         "Wrote image at index {} to output {}",
         args.index.unwrap_or(0),
         output_filename,
@@ -670,6 +1459,168 @@ fn decode(args: &CommandLineArgs, input_file: &String) -> AvifResult<()> {
     Ok(())
 }
 
+// Returns the position of the %d or %0Nd pattern in filename and its width.
+fn frame_pattern(filename: &str) -> Option<(std::ops::Range<usize>, usize)> {
+    let start = filename.find('%')?;
+    let length = filename[start + 1..].find('d')?;
+    let width = &filename[start + 1..start + 1 + length];
+    if !width.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((start..start + length + 2, width.parse().unwrap_or(0)))
+}
+
+// Replaces the pattern of filename with index, zero-padded to the width of the pattern.
+fn frame_filename(filename: &str, index: u32) -> String {
+    match frame_pattern(filename) {
+        Some((range, width)) => format!(
+            "{}{index:0width$}{}",
+            &filename[..range.start],
+            &filename[range.end..]
+        ),
+        None => filename.to_string(),
+    }
+}
+
+// frame_%04d.png gives frame_durations.txt and out.y4m gives out.durations.txt.
+fn durations_filename(output_filename: &str) -> String {
+    let stem = Path::new(output_filename).with_extension("");
+    let stem = stem.to_string_lossy();
+    match frame_pattern(&stem) {
+        Some((range, _)) => format!(
+            "{}durations{}.txt",
+            &stem[..range.start],
+            &stem[range.end..]
+        ),
+        None => format!("{stem}.durations.txt"),
+    }
+}
+
+enum FramesOutput {
+    // One file per frame.
+    Files,
+    Single(File, Box<dyn Writer>),
+    Animation(File, Box<dyn AnimationWriter>),
+}
+
+fn decode_frames(
+    args: &CommandLineArgs,
+    decoder: &mut Decoder,
+    frame_range: FrameRange,
+    output_filename: &str,
+) -> AvifResult<()> {
+    let image_count = decoder.image_count();
+    let first = frame_range.first;
+    if first >= image_count {
+        return Err(AvifError::UnknownError(format!(
+            "First frame {first} is out of bounds ({image_count} frames)"
+        )));
+    }
+    let last = frame_range.last.unwrap_or(image_count - 1);
+    if last >= image_count || first > last {
+        return Err(AvifError::UnknownError(format!(
+            "Frame range {first}-{last} is out of bounds ({image_count} frames)"
+        )));
+    }
+    let extension = output_format(args);
+    let mut output = if frame_pattern(output_filename).is_some() {
+        FramesOutput::Files
+    } else if matches!(extension.as_str(), "y4m" | "yuv") {
+        FramesOutput::Single(
+            create_output_file(output_filename)?,
+            Box::new(Y4MWriter::create(extension == "yuv")),
+        )
+    } else if let Some(writer) = create_animation_writer(
+        args,
+        &extension,
+        last - first + 1,
+        decoder.repetition_count(),
+    ) {
+        FramesOutput::Animation(create_output_file(output_filename)?, writer)
+    } else {
+        return Err(AvifError::UnknownError(format!(
+            "Writing several frames to {extension} requires a numbered output pattern such as \
+             frame_%04d.{extension}"
+        )));
+    };
+    let mut durations = format!(
+        "# timescale {}\n# index pts_in_timescales duration_in_timescales duration_in_seconds\n",
+        decoder.timescale()
+    );
+    for index in first..=last {
+        if index == first {
+            // Decodes from the nearest keyframe.
+            decoder.nth_image(first)?;
+            log!("Image details:");
+            print_image_info(decoder);
+        } else {
+            decoder.next_image()?;
+        }
+        let timing = decoder.image_timing();
+        match &mut output {
+            FramesOutput::Files => write_image(
+                args,
+                decoder,
+                &extension,
+                &frame_filename(output_filename, index),
+            )?,
+            FramesOutput::Single(file, writer) => {
+                writer.write_frame(file, decoder.image().unwrap())?
+            }
+            FramesOutput::Animation(file, writer) => {
+                writer.write_animation_frame(file, decoder.image().unwrap(), &timing)?
+            }
+        }
+        durations.push_str(&format!(
+            "{index} {} {} {}\n",
+            timing.pts_in_timescales, timing.duration_in_timescales, timing.duration
+        ));
+    }
+    if let FramesOutput::Animation(_, writer) = &mut output {
+        writer.finish()?;
+    }
+    if args.output_file.as_deref() == Some(STDIO) {
+        log!("Wrote frames {first} to {last} to stdout");
+        return Ok(());
+    }
+    let durations_filename = durations_filename(output_filename);
+    std::fs::write(&durations_filename, durations).or(Err(AvifError::UnknownError(
+        "Could not write the durations file".into(),
+    )))?;
+    log!(
+        "Wrote frames {first} to {last} to output {output_filename} and their durations to \
+         {durations_filename}"
+    );
+    Ok(())
+}
+
+fn decode_animation(
+    decoder: &mut Decoder,
+    mut writer: Box<dyn AnimationWriter>,
+    output_filename: &str,
+) -> AvifResult<()> {
+    let mut output_file = create_output_file(output_filename)?;
+    for index in 0..decoder.image_count() {
+        decoder.next_image()?;
+        if index == 0 {
+            log!("Image details:");
+            print_image_info(decoder);
+        }
+        writer.write_animation_frame(
+            &mut output_file,
+            decoder.image().unwrap(),
+            &decoder.image_timing(),
+        )?;
+    }
+    writer.finish()?;
+    log!(
+        "Wrote {} frames to output {}",
+        decoder.image_count(),
+        output_filename
+    );
+    Ok(())
+}
+
 #[cfg(feature = "encoder")]
 fn read_file(filepath: &String) -> io::Result<Vec<u8>> {
     let mut file = File::open(filepath)?;
@@ -679,29 +1630,266 @@ fn read_file(filepath: &String) -> io::Result<Vec<u8>> {
 }
 
 #[cfg(feature = "encoder")]
-fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
-    const DEFAULT_ENCODE_QUALITY: f32 = 90.0;
-    let extension = get_extension(input_file);
-    let mut reader: Box<dyn Reader> = match extension.as_str() {
+fn create_reader(
+    args: &CommandLineArgs,
+    input_file: &str,
+    extension: &str,
+) -> AvifResult<Box<dyn Reader>> {
+    Ok(match extension {
         "y4m" => Box::new(Y4MReader::create(input_file)?),
+        "yuv" => Box::new(YuvReader::create(input_file)?),
         #[cfg(feature = "jpeg")]
-        "jpg" | "jpeg" => Box::new(JpegReader::create(input_file)?),
+        "jpg" | "jpeg" => {
+            let mut reader = JpegReader::create(input_file)?;
+            reader.set_ycbcr_passthrough(args.jpeg_passthrough);
+            Box::new(reader)
+        }
         #[cfg(feature = "png")]
         "png" => Box::new(PngReader::create(input_file)?),
         #[cfg(feature = "gif")]
         "gif" => Box::new(GifReader::create(input_file)?),
+        #[cfg(feature = "webp")]
+        "webp" => Box::new(WebpReader::create(input_file)?),
+        #[cfg(feature = "tiff")]
+        "tif" | "tiff" => Box::new(TiffReader::create(input_file)?),
+        #[cfg(feature = "bmp")]
+        "bmp" => Box::new(BmpReader::create(input_file)?),
+        #[cfg(feature = "pnm")]
+        "pnm" | "pbm" | "pgm" | "ppm" | "pam" => Box::new(PnmReader::create(input_file)?),
+        // Linear light samples are encoded with the transfer characteristics given by --cicp, PQ
+        // by default.
+        "pfm" => {
+            let mut reader = PfmReader::create(input_file)?;
+            if let Some(nclx) = &args.cicp {
+                reader.set_transfer_characteristics(nclx.transfer_characteristics);
+            }
+            Box::new(reader)
+        }
+        "exr" => {
+            let mut reader = ExrReader::create(input_file)?;
+            if let Some(nclx) = &args.cicp {
+                reader.set_transfer_characteristics(nclx.transfer_characteristics);
+            }
+            Box::new(reader)
+        }
         _ => {
             return Err(AvifError::UnknownError(format!(
                 "Unknown input file extension ({extension})"
             )));
         }
+    })
+}
+
+// Reads gain map metadata in the format of the "gain_map" object printed by --info --json. The
+// whole output of --info --json is also accepted.
+#[cfg(feature = "encoder")]
+fn read_gainmap_metadata(filename: &str, gainmap: &mut GainMap) -> AvifResult<()> {
+    let invalid = |key: &str| AvifError::UnknownError(format!("Invalid gain map metadata: {key}"));
+    let data = std::fs::read(filename).map_err(|err| AvifError::UnknownError(err.to_string()))?;
+    let json: serde_json::Value =
+        serde_json::from_slice(&data).map_err(|err| AvifError::UnknownError(err.to_string()))?;
+    let json = json.get("gain_map").unwrap_or(&json);
+    let metadata = json.get("metadata").ok_or(invalid("metadata"))?;
+    let pair = |value: &serde_json::Value, key: &str| -> AvifResult<(i64, u64)> {
+        match value.as_array().map(|pair| pair.as_slice()) {
+            Some([n, d]) => Ok((
+                n.as_i64().ok_or(invalid(key))?,
+                d.as_u64().ok_or(invalid(key))?,
+            )),
+            _ => Err(invalid(key)),
+        }
+    };
+    let fraction = |value: &serde_json::Value, key: &str| -> AvifResult<Fraction> {
+        let (n, d) = pair(value, key)?;
+        Ok(Fraction(
+            i32::try_from(n).or(Err(invalid(key)))?,
+            u32::try_from(d).or(Err(invalid(key)))?,
+        ))
+    };
+    let ufraction = |value: &serde_json::Value, key: &str| -> AvifResult<UFraction> {
+        let (n, d) = pair(value, key)?;
+        Ok(UFraction(
+            u32::try_from(n).or(Err(invalid(key)))?,
+            u32::try_from(d).or(Err(invalid(key)))?,
+        ))
+    };
+    let channels = |key: &str| -> AvifResult<&Vec<serde_json::Value>> {
+        metadata[key]
+            .as_array()
+            .filter(|values| values.len() == 3)
+            .ok_or(invalid(key))
     };
+    for c in 0..3 {
+        gainmap.metadata.min[c] = fraction(&channels("min")?[c], "min")?;
+        gainmap.metadata.max[c] = fraction(&channels("max")?[c], "max")?;
+        gainmap.metadata.gamma[c] = ufraction(&channels("gamma")?[c], "gamma")?;
+        gainmap.metadata.base_offset[c] = fraction(&channels("base_offset")?[c], "base_offset")?;
+        gainmap.metadata.alternate_offset[c] =
+            fraction(&channels("alternate_offset")?[c], "alternate_offset")?;
+    }
+    gainmap.metadata.base_hdr_headroom =
+        ufraction(&metadata["base_hdr_headroom"], "base_hdr_headroom")?;
+    gainmap.metadata.alternate_hdr_headroom = ufraction(
+        &metadata["alternate_hdr_headroom"],
+        "alternate_hdr_headroom",
+    )?;
+    gainmap.metadata.use_base_color_space = metadata["use_base_color_space"]
+        .as_bool()
+        .ok_or(invalid("use_base_color_space"))?;
+
+    let Some(alternate_image) = json.get("alternate_image") else {
+        return Ok(());
+    };
+    let u16_value = |value: &serde_json::Value, key: &str| -> AvifResult<u16> {
+        value
+            .as_u64()
+            .and_then(|value| u16::try_from(value).ok())
+            .ok_or(invalid(key))
+    };
+    if let Some(cicp) = alternate_image.get("cicp") {
+        gainmap.alt_color_primaries =
+            u16_value(&cicp["color_primaries"], "color_primaries")?.into();
+        gainmap.alt_transfer_characteristics = u16_value(
+            &cicp["transfer_characteristics"],
+            "transfer_characteristics",
+        )?
+        .into();
+        gainmap.alt_matrix_coefficients =
+            u16_value(&cicp["matrix_coefficients"], "matrix_coefficients")?.into();
+    }
+    if let Some(yuv_range) = alternate_image.get("yuv_range") {
+        gainmap.alt_yuv_range = yuv_range_parser(yuv_range.as_str().unwrap_or_default())
+            .or(Err(invalid("yuv_range")))?;
+    }
+    if let Some(depth) = alternate_image.get("depth") {
+        gainmap.alt_plane_depth =
+            u8::try_from(u16_value(depth, "depth")?).or(Err(invalid("depth")))?;
+    }
+    if let Some(plane_count) = alternate_image.get("plane_count") {
+        gainmap.alt_plane_count =
+            u8::try_from(u16_value(plane_count, "plane_count")?).or(Err(invalid("plane_count")))?;
+    }
+    if let Some(clli) = alternate_image.get("clli").filter(|clli| !clli.is_null()) {
+        gainmap.alt_clli = ContentLightLevelInformation {
+            max_cll: u16_value(&clli["max_cll"], "max_cll")?,
+            max_pall: u16_value(&clli["max_pall"], "max_pall")?,
+        };
+    }
+    Ok(())
+}
+
+#[cfg(feature = "encoder")]
+fn read_gainmap(args: &CommandLineArgs) -> AvifResult<GainMap> {
+    let filename = args.gain_map.as_ref().unwrap();
+    let mut reader = create_reader(args, filename, &get_extension(filename))?;
+    let (mut image, _) = reader.read_frame(&Config::default())?;
+    // The color space of the gain map image is given by its metadata.
+    image.color_primaries = ColorPrimaries::Unspecified;
+    image.transfer_characteristics = TransferCharacteristics::Unspecified;
+    let mut gainmap = GainMap {
+        image,
+        ..Default::default()
+    };
+    read_gainmap_metadata(args.gain_map_metadata.as_ref().unwrap(), &mut gainmap)?;
+    Ok(gainmap)
+}
+
+// Returns the gain map of each cell of the grid.
+#[cfg(feature = "encoder")]
+fn split_gainmap_into_grid(
+    gainmap: &GainMap,
+    grid_columns: u32,
+    grid_rows: u32,
+) -> AvifResult<Vec<GainMap>> {
+    Ok(gainmap
+        .image
+        .split_into_grid(grid_columns, grid_rows)?
+        .into_iter()
+        .map(|image| GainMap {
+            image,
+            metadata: gainmap.metadata.clone(),
+            alt_icc: gainmap.alt_icc.clone(),
+            alt_color_primaries: gainmap.alt_color_primaries,
+            alt_transfer_characteristics: gainmap.alt_transfer_characteristics,
+            alt_matrix_coefficients: gainmap.alt_matrix_coefficients,
+            alt_yuv_range: gainmap.alt_yuv_range,
+            alt_plane_count: gainmap.alt_plane_count,
+            alt_plane_depth: gainmap.alt_plane_depth,
+            alt_clli: gainmap.alt_clli,
+        })
+        .collect())
+}
+
+// Converts the durations of the input frames, in milliseconds, to durations in timescales. The
+// end time of each frame is rounded rather than its duration so that the rounding errors do not
+// accumulate.
+#[cfg(feature = "encoder")]
+struct FrameDurations {
+    timescale: u64,
+    fps: Option<f64>,
+    frame_count: u64,
+    elapsed_ms: u64,
+    elapsed: u64,
+}
+
+#[cfg(feature = "encoder")]
+impl FrameDurations {
+    fn next(&mut self, duration_ms: u64) -> u64 {
+        self.frame_count += 1;
+        self.elapsed_ms += duration_ms;
+        let end = match self.fps {
+            Some(fps) => self.frame_count as f64 * self.timescale as f64 / fps,
+            None => self.elapsed_ms as f64 * self.timescale as f64 / 1000.0,
+        }
+        .round() as u64;
+        let duration = end.saturating_sub(self.elapsed);
+        self.elapsed = end;
+        duration
+    }
+}
+
+#[cfg(feature = "encoder")]
+fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
+    const DEFAULT_ENCODE_QUALITY: f32 = 90.0;
+    // The readers need a file, which is kept until the end of the encoding.
+    let stdin_file = if input_file == STDIO {
+        let mut file = tempfile::NamedTempFile::new()
+            .map_err(|err| AvifError::UnknownError(err.to_string()))?;
+        file.write_all(&read_stdin()?)
+            .map_err(|err| AvifError::UnknownError(err.to_string()))?;
+        Some(file)
+    } else {
+        None
+    };
+    let input_path = match &stdin_file {
+        Some(file) => file.path().to_string_lossy().into_owned(),
+        None => input_file.to_string(),
+    };
+    let mut reader = create_reader(args, &input_path, &input_format(args))?;
     let reader_config = Config {
-        yuv_format: args.yuv_format,
+        yuv_format: if args.lossless { Some(PixelFormat::Yuv444) } else { args.yuv_format },
         depth: args.depth,
-        ..Default::default()
+        matrix_coefficients: args.lossless.then_some(MatrixCoefficients::Identity),
+        width: args.size.map(|size| size.0),
+        height: args.size.map(|size| size.1),
+        yuv_range: args.range,
+        big_endian: args.big_endian,
+        frame_rate: args.fps,
+        chroma_downsampling: if args.sharpyuv {
+            ChromaDownsampling::SharpYuv
+        } else {
+            ChromaDownsampling::Automatic
+        },
+        premultiply_alpha: args.premultiply,
     };
     let (mut image, mut duration_ms) = reader.read_frame(&reader_config)?;
+    let gainmap = match args.gain_map {
+        Some(_) => Some(read_gainmap(args)?),
+        None => reader.take_gainmap().unwrap_or_else(|err| {
+            log!("Warning: Ignoring the gain map: {err:?}");
+            None
+        }),
+    };
     image.irot_angle = args.irot_angle;
     image.imir_axis = args.imir_axis;
     if let Some(clap) = args.clap {
@@ -716,7 +1904,9 @@ fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
         )?);
     }
     image.pasp = args.pasp;
-    image.clli = args.clli;
+    if args.clli.is_some() {
+        image.clli = args.clli;
+    }
     if let Some(nclx) = &args.cicp {
         image.color_primaries = nclx.color_primaries;
         image.transfer_characteristics = nclx.transfer_characteristics;
@@ -726,21 +1916,31 @@ fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
         image.icc = read_file(icc).expect("failed to read icc file");
     }
     if let Some(exif) = &args.exif {
-        image.xmp = read_file(exif).expect("failed to read exif file");
+        image.exif = read_file(exif).expect("failed to read exif file");
     }
     if let Some(xmp) = &args.xmp {
         image.xmp = read_file(xmp).expect("failed to read xmp file");
     }
+    let quality = if args.lossless {
+        100.0
+    } else {
+        args.quality.unwrap_or(DEFAULT_ENCODE_QUALITY)
+    };
     let mut settings = encoder::Settings {
         extra_layer_count: if args.progressive { 1 } else { 0 },
-        codec_choice: args.codec,
+        codec_choice: args.codec.clone(),
         speed: args.speed,
         header_format: args.header,
         force_write_extended_pixi: args.force_write_extended_pixi,
-        timescale: 1000, // ms.
-        repetition_count: args.repetition_count,
+        keyframe_interval: args.keyframe.unwrap_or(0),
+        timescale: args.timescale.unwrap_or(1000),
+        recipe: args.recipe.unwrap_or(Recipe::None),
+        repetition_count: args
+            .repetition_count
+            .or(reader.repetition_count())
+            .unwrap_or(RepetitionCount::Infinite),
         mutable: MutableSettings {
-            quality: args.quality.unwrap_or(DEFAULT_ENCODE_QUALITY),
+            quality,
             tiling_mode: if args.autotiling {
                 TilingMode::Auto
             } else {
@@ -753,6 +1953,14 @@ fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
         },
         ..Default::default()
     };
+    if args.lossless {
+        settings.mutable.quality_alpha = 100.0;
+    } else if let Some(quality_alpha) = args.qalpha {
+        settings.mutable.quality_alpha = quality_alpha;
+    }
+    if let Some(quality_gainmap) = args.qgain_map {
+        settings.mutable.quality_gainmap = quality_gainmap;
+    }
     if let Some(scaling_mode) = args.scaling_mode {
         settings.mutable.scaling_mode = ScalingMode {
             horizontal: scaling_mode,
@@ -760,36 +1968,66 @@ fn encode(args: &CommandLineArgs, input_file: &str) -> AvifResult<()> {
         };
     }
     let mut encoder = Encoder::create_with_settings(&settings)?;
+    for (category, key, value) in &args.codec_options {
+        encoder.set_codec_specific_option(*category, key.clone(), value.clone());
+    }
     if reader.has_more_frames() {
-        if args.progressive {
-            println!("Automatic progressive encoding can only have one input image.");
+        if args.progressive || args.grid.is_some() {
+            log!("Automatic progressive encoding and grids can only have one input image.");
             return Err(AvifError::InvalidArgument);
         }
+        let mut durations = FrameDurations {
+            timescale: settings.timescale,
+            fps: args.fps,
+            frame_count: 0,
+            elapsed_ms: 0,
+            elapsed: 0,
+        };
         loop {
-            encoder.add_image_for_sequence(&image, duration_ms)?;
+            encoder.add_image_for_sequence(&image, durations.next(duration_ms))?;
             if !reader.has_more_frames() {
                 break;
             }
             (image, duration_ms) = reader.read_frame(&reader_config)?;
         }
     } else if args.progressive {
+        if gainmap.is_some() {
+            log!("Progressive encoding does not support gain maps, ignoring the gain map.");
+        }
         // Encode the base layer with very low quality.
         settings.mutable.quality = 2.0;
         encoder.update_settings(&settings.mutable)?;
         encoder.add_image(&image)?;
         // Encode the second layer with the requested quality.
-        settings.mutable.quality = args.quality.unwrap_or(DEFAULT_ENCODE_QUALITY);
+        settings.mutable.quality = quality;
         encoder.update_settings(&settings.mutable)?;
         encoder.add_image(&image)?;
+    } else if let Some((grid_columns, grid_rows)) = args.grid {
+        let cells = image.split_into_grid(grid_columns, grid_rows)?;
+        let cells: Vec<_> = cells.iter().collect();
+        if let Some(gainmap) = &gainmap {
+            let gainmaps = split_gainmap_into_grid(gainmap, grid_columns, grid_rows)?;
+            let gainmaps: Vec<_> = gainmaps.iter().collect();
+            encoder.add_image_gainmap_grid(grid_columns, grid_rows, &cells, &gainmaps)?;
+        } else {
+            encoder.add_image_grid(grid_columns, grid_rows, &cells)?;
+        }
+    } else if let Some(gainmap) = &gainmap {
+        encoder.add_image_gainmap(&image, gainmap)?;
     } else {
         encoder.add_image(&image)?;
     }
 
     let encoded_data = encoder.finish()?;
     let output_file = args.output_file.as_ref().unwrap();
+    if output_file == STDIO {
+        write_stdout(&encoded_data)?;
+        log!("Wrote output AVIF to stdout");
+        return Ok(());
+    }
     let mut file = File::create(output_file).expect("file creation failed");
     file.write_all(&encoded_data).expect("file writing failed");
-    println!("Write output AVIF: {output_file}");
+    log!("Write output AVIF: {output_file}");
     Ok(())
 }
 
@@ -798,8 +2036,305 @@ fn encode(_args: &CommandLineArgs, _input_file: &str) -> AvifResult<()> {
     Err(AvifError::InvalidArgument)
 }
 
-fn can_decode(filename: &str) -> bool {
-    match get_extension(filename).as_str() {
+// Reads the first frame of |filename| and converts it to RGBA at its own bit depth. Also returns
+// whether the image has an alpha channel.
+fn read_rgb_image(args: &CommandLineArgs, filename: &str) -> AvifResult<(rgb::Image, bool)> {
+    let extension = get_extension(filename);
+    let to_rgb = |image: &Image| -> AvifResult<(rgb::Image, bool)> {
+        let mut rgb = rgb::Image::create_from_yuv(image);
+        rgb.format = rgb::Format::Rgba;
+        rgb.allocate()?;
+        rgb.convert_from_yuv(image)?;
+        Ok((rgb, image.has_alpha()))
+    };
+    if extension == "avif" {
+        let mut decoder = create_decoder_and_parse(args, &filename.to_string())?;
+        decoder.next_image()?;
+        return to_rgb(decoder.image().unwrap());
+    }
+    read_rgb_image_with_reader(args, filename, &extension, to_rgb)
+}
+
+#[cfg(feature = "encoder")]
+fn read_rgb_image_with_reader(
+    args: &CommandLineArgs,
+    filename: &str,
+    extension: &str,
+    to_rgb: impl Fn(&Image) -> AvifResult<(rgb::Image, bool)>,
+) -> AvifResult<(rgb::Image, bool)> {
+    let mut reader = create_reader(args, filename, extension)?;
+    // The identity matrix with 4:4:4 makes the round trip through YUV lossless.
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        width: args.size.map(|size| size.0),
+        height: args.size.map(|size| size.1),
+        yuv_range: args.range,
+        big_endian: args.big_endian,
+        ..Default::default()
+    };
+    let (image, _duration) = reader.read_frame(&config)?;
+    to_rgb(&image)
+}
+
+#[cfg(not(feature = "encoder"))]
+fn read_rgb_image_with_reader(
+    _args: &CommandLineArgs,
+    _filename: &str,
+    extension: &str,
+    _to_rgb: impl Fn(&Image) -> AvifResult<(rgb::Image, bool)>,
+) -> AvifResult<(rgb::Image, bool)> {
+    Err(AvifError::UnknownError(format!(
+        "Reading {extension} files requires the encoder feature"
+    )))
+}
+
+// Returns the samples of each channel of |rgb| (R, G, B and A), scaled to |depth| bits.
+fn rgb_planes(rgb: &rgb::Image, depth: u8) -> AvifResult<[Vec<u32>; 4]> {
+    let offsets = [
+        rgb.format.r_offset(),
+        rgb.format.g_offset(),
+        rgb.format.b_offset(),
+        rgb.format.alpha_offset(),
+    ];
+    let max_channel = rgb.max_channel() as u32;
+    let target_max_channel = (1u32 << depth) - 1;
+    let scale = |value: u32| (value * target_max_channel + max_channel / 2) / max_channel;
+    let mut planes: [Vec<u32>; 4] = Default::default();
+    for y in 0..rgb.height {
+        let row: Vec<u32> = if rgb.depth == 8 {
+            rgb.row(y)?.iter().map(|x| *x as u32).collect()
+        } else {
+            rgb.row16(y)?.iter().map(|x| *x as u32).collect()
+        };
+        for (plane, offset) in planes.iter_mut().zip(offsets) {
+            plane.extend(
+                row.chunks_exact(4)
+                    .take(rgb.width as usize)
+                    .map(|pixel| scale(pixel[offset])),
+            );
+        }
+    }
+    Ok(planes)
+}
+
+// Mean of the SSIM computed on 8x8 windows every 4 samples.
+fn ssim(first: &[u32], second: &[u32], width: usize, height: usize, max_channel: f64) -> f64 {
+    const WINDOW_SIZE: usize = 8;
+    const WINDOW_STEP: usize = 4;
+    let c1 = (0.01 * max_channel).powi(2);
+    let c2 = (0.03 * max_channel).powi(2);
+    let window_width = WINDOW_SIZE.min(width);
+    let window_height = WINDOW_SIZE.min(height);
+    let sample_count = (window_width * window_height) as f64;
+    let mut ssim_sum = 0.0;
+    let mut window_count = 0;
+    for window_y in (0..=height - window_height).step_by(WINDOW_STEP) {
+        for window_x in (0..=width - window_width).step_by(WINDOW_STEP) {
+            let (mut sum1, mut sum2, mut sum11, mut sum22, mut sum12) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in window_y..window_y + window_height {
+                for x in window_x..window_x + window_width {
+                    let sample1 = first[y * width + x] as f64;
+                    let sample2 = second[y * width + x] as f64;
+                    sum1 += sample1;
+                    sum2 += sample2;
+                    sum11 += sample1 * sample1;
+                    sum22 += sample2 * sample2;
+                    sum12 += sample1 * sample2;
+                }
+            }
+            let mean1 = sum1 / sample_count;
+            let mean2 = sum2 / sample_count;
+            let variance1 = sum11 / sample_count - mean1 * mean1;
+            let variance2 = sum22 / sample_count - mean2 * mean2;
+            let covariance = sum12 / sample_count - mean1 * mean2;
+            ssim_sum += ((2.0 * mean1 * mean2 + c1) * (2.0 * covariance + c2))
+                / ((mean1 * mean1 + mean2 * mean2 + c1) * (variance1 + variance2 + c2));
+            window_count += 1;
+        }
+    }
+    ssim_sum / window_count as f64
+}
+
+struct ChannelComparison {
+    name: &'static str,
+    psnr: f64,
+    ssim: f64,
+    max_error: u32,
+    differing_samples: u64,
+}
+
+#[cfg(feature = "png")]
+fn write_diff_image(
+    filename: &str,
+    errors: &[Vec<u32>],
+    width: u32,
+    height: u32,
+    max_error: u32,
+) -> AvifResult<()> {
+    // Identity matrix coefficients store G, B and R in the Y, U and V planes.
+    let mut image = Image {
+        width,
+        height,
+        depth: 8,
+        yuv_format: PixelFormat::Yuv444,
+        yuv_range: YuvRange::Full,
+        matrix_coefficients: MatrixCoefficients::Identity,
+        ..Default::default()
+    };
+    image.allocate_planes(Category::Color)?;
+    let amplify = |error: u32| match max_error {
+        0 => 0,
+        _ => (error * 255 / max_error) as u8,
+    };
+    for (plane, channel) in [(Plane::Y, 1), (Plane::U, 2), (Plane::V, 0)] {
+        for y in 0..height {
+            let row = &mut image.row_mut(plane, y)?[..width as usize];
+            for (x, sample) in row.iter_mut().enumerate() {
+                let index = y as usize * width as usize + x;
+                // Alpha differences show up in all the color channels.
+                *sample = amplify(errors[channel][index].max(errors[3][index]));
+            }
+        }
+    }
+    let mut file = create_output_file(filename)?;
+    PngWriter { depth: Some(8) }.write_frame(&mut file, &image)
+}
+
+#[cfg(not(feature = "png"))]
+fn write_diff_image(
+    _filename: &str,
+    _errors: &[Vec<u32>],
+    _width: u32,
+    _height: u32,
+    _max_error: u32,
+) -> AvifResult<()> {
+    Err(AvifError::UnknownError(
+        "Writing the diff image requires the png feature".into(),
+    ))
+}
+
+// Returns whether the images are within the thresholds given in |compare_args|.
+fn compare(args: &CommandLineArgs, compare_args: &CompareArgs) -> AvifResult<bool> {
+    let (first, first_has_alpha) = read_rgb_image(args, &compare_args.first)?;
+    let (second, second_has_alpha) = read_rgb_image(args, &compare_args.second)?;
+    if first.width != second.width || first.height != second.height {
+        return Err(AvifError::UnknownError(format!(
+            "Image dimensions differ: {}x{} and {}x{}",
+            first.width, first.height, second.width, second.height
+        )));
+    }
+    let (width, height) = (first.width as usize, first.height as usize);
+    let depth = first.depth.max(second.depth);
+    let max_channel = ((1u32 << depth) - 1) as f64;
+    println!(
+        "Comparing {} ({}-bit) and {} ({}-bit), {width}x{height}, at {depth} bits",
+        compare_args.first, first.depth, compare_args.second, second.depth
+    );
+    let first_planes = rgb_planes(&first, depth)?;
+    let second_planes = rgb_planes(&second, depth)?;
+    let errors: Vec<Vec<u32>> = first_planes
+        .iter()
+        .zip(&second_planes)
+        .map(|(plane1, plane2)| {
+            plane1
+                .iter()
+                .zip(plane2)
+                .map(|(a, b)| a.abs_diff(*b))
+                .collect()
+        })
+        .collect();
+
+    let channel_count = if first_has_alpha || second_has_alpha { 4 } else { 3 };
+    let mut comparisons = Vec::new();
+    for (channel, name) in ["R", "G", "B", "A"]
+        .into_iter()
+        .enumerate()
+        .take(channel_count)
+    {
+        let squared_error_sum: f64 = errors[channel].iter().map(|x| (*x as f64).powi(2)).sum();
+        let mse = squared_error_sum / (width * height) as f64;
+        comparisons.push(ChannelComparison {
+            name,
+            psnr: if mse == 0.0 {
+                f64::INFINITY
+            } else {
+                10.0 * (max_channel * max_channel / mse).log10()
+            },
+            ssim: ssim(
+                &first_planes[channel],
+                &second_planes[channel],
+                width,
+                height,
+                max_channel,
+            ),
+            max_error: errors[channel].iter().copied().max().unwrap_or(0),
+            differing_samples: errors[channel].iter().filter(|x| **x != 0).count() as u64,
+        });
+    }
+    println!(
+        "{:<8}{:>12}{:>10}{:>12}{:>20}",
+        "channel", "PSNR (dB)", "SSIM", "max error", "differing pixels"
+    );
+    for comparison in &comparisons {
+        println!(
+            "{:<8}{:>12.2}{:>10.6}{:>12}{:>20}",
+            comparison.name,
+            comparison.psnr,
+            comparison.ssim,
+            comparison.max_error,
+            comparison.differing_samples
+        );
+    }
+    let differing_pixels = (0..width * height)
+        .filter(|index| errors.iter().any(|plane| plane[*index] != 0))
+        .count();
+    println!(
+        "{differing_pixels} of {} pixels differ ({:.2}%)",
+        width * height,
+        100.0 * differing_pixels as f64 / (width * height) as f64
+    );
+
+    let max_error = comparisons.iter().map(|x| x.max_error).max().unwrap_or(0);
+    if let Some(diff) = &compare_args.diff {
+        write_diff_image(diff, &errors, first.width, first.height, max_error)?;
+    }
+
+    let mut passed = true;
+    for comparison in &comparisons {
+        if let Some(min_psnr) = compare_args.min_psnr {
+            if comparison.psnr < min_psnr {
+                eprintln!(
+                    "{} PSNR {:.2} dB is below --min-psnr {min_psnr}",
+                    comparison.name, comparison.psnr
+                );
+                passed = false;
+            }
+        }
+        if let Some(min_ssim) = compare_args.min_ssim {
+            if comparison.ssim < min_ssim {
+                eprintln!(
+                    "{} SSIM {:.6} is below --min-ssim {min_ssim}",
+                    comparison.name, comparison.ssim
+                );
+                passed = false;
+            }
+        }
+        if let Some(threshold) = compare_args.max_error {
+            if comparison.max_error > threshold {
+                eprintln!(
+                    "{} max error {} is above --max-error {threshold}",
+                    comparison.name, comparison.max_error
+                );
+                passed = false;
+            }
+        }
+    }
+    Ok(passed)
+}
+
+fn can_decode(extension: &str) -> bool {
+    match extension {
         "avif" => true,
         #[cfg(feature = "heic")]
         "heic" | "heif" => true,
@@ -807,29 +2342,76 @@ fn can_decode(filename: &str) -> bool {
     }
 }
 
-fn can_encode(filename: &str) -> bool {
-    get_extension(filename) == "avif"
+fn can_encode(extension: &str) -> bool {
+    extension == "avif"
 }
 
 fn validate_args(args: &CommandLineArgs) -> AvifResult<()> {
-    if let Some(input_file) = &args.input_file {
-        if can_decode(input_file) {
-            if args.info {
+    if args.output_file.as_deref() == Some(STDIO) && args.output_format.is_none() {
+        return Err(AvifError::UnknownError(
+            "--output-format is required to write to stdout".into(),
+        ));
+    }
+    if args.output_file.as_deref() == Some(STDIO)
+        && args.frames.is_some()
+        && !matches!(output_format(args).as_str(), "y4m" | "yuv")
+    {
+        return Err(AvifError::UnknownError(
+            "--frames only supports y4m and yuv output to stdout".into(),
+        ));
+    }
+    if !args.batch.is_empty() {
+        if args.output_dir.is_none() {
+            return Err(AvifError::UnknownError(
+                "--batch requires --output-dir".into(),
+            ));
+        }
+        if args.input_file.is_some() || args.output_file.is_some() {
+            return Err(AvifError::UnknownError(
+                "--batch does not take input_file and output_file".into(),
+            ));
+        }
+        if args.info
+            || args.dump
+            || args.frames.is_some()
+            || args.input_format.is_some()
+            || args.output_format.is_some()
+        {
+            return Err(AvifError::UnknownError(
+                "--info, --dump, --frames, --input-format and --output-format are not supported \
+                 with --batch"
+                    .into(),
+            ));
+        }
+        return Ok(());
+    }
+    if args.output_dir.is_some() || !args.batch_ext.is_empty() {
+        return Err(AvifError::UnknownError(
+            "--output-dir and --batch-ext require --batch".into(),
+        ));
+    }
+    if args.input_file.is_some() {
+        if can_decode(&input_format(args)) {
+            if args.json && !args.info {
+                return Err(AvifError::UnknownError("--json requires --info".into()));
+            }
+            if args.info || args.dump {
                 if args.output_file.is_some()
                     || args.quality.is_some()
                     || args.depth.is_some()
                     || args.index.is_some()
+                    || args.frames.is_some()
                 {
-                    return Err(AvifError::UnknownError(
-                        "--info contains unsupported extra arguments".into(),
-                    ));
+                    return Err(AvifError::UnknownError(format!(
+                        "{} contains unsupported extra arguments",
+                        if args.info { "--info" } else { "--dump" }
+                    )));
                 }
             } else {
                 if args.output_file.is_none() {
                     return Err(AvifError::UnknownError("output_file is required".into()));
                 }
-                let output_filename = &args.output_file.as_ref().unwrap().as_str();
-                let extension = get_extension(output_filename);
+                let extension = output_format(args);
                 if let Some(quality) = args.quality {
                     if extension != "jpg" && extension != "jpeg" {
                         return Err(AvifError::UnknownError(
@@ -842,14 +2424,24 @@ fn validate_args(args: &CommandLineArgs) -> AvifResult<()> {
                         ));
                     }
                 }
-                if args.depth.is_some() && extension != "png" {
+                if args.depth.is_some()
+                    && !matches!(
+                        extension.as_str(),
+                        "png" | "apng" | "tif" | "tiff" | "pnm" | "pgm" | "ppm" | "pam"
+                    )
+                {
                     return Err(AvifError::UnknownError(
-                        "depth is only supported for png output".into(),
+                        "depth is only supported for png, tiff and pnm output".into(),
                     ));
                 }
             }
         } else {
             // TODO: b/403090413 - validate encoding args.
+            if args.frames.is_some() {
+                return Err(AvifError::UnknownError(
+                    "frames is only supported when decoding".into(),
+                ));
+            }
             if let Some(quality) = args.quality {
                 if !(0.0..=100.0).contains(&quality) {
                     return Err(AvifError::UnknownError(
@@ -857,6 +2449,78 @@ fn validate_args(args: &CommandLineArgs) -> AvifResult<()> {
                     ));
                 }
             }
+            for quality in [args.qalpha, args.qgain_map].into_iter().flatten() {
+                if !(0.0..=100.0).contains(&quality) {
+                    return Err(AvifError::UnknownError(
+                        "qalpha and qgain-map must be between 0 and 100 inclusive".into(),
+                    ));
+                }
+            }
+            if args.lossless {
+                if args.quality.is_some() || args.qalpha.is_some() {
+                    return Err(AvifError::UnknownError(
+                        "--lossless cannot be combined with --quality or --qalpha".into(),
+                    ));
+                }
+                if args
+                    .yuv_format
+                    .is_some_and(|format| format != PixelFormat::Yuv444)
+                    || args.cicp.as_ref().is_some_and(|nclx| {
+                        nclx.matrix_coefficients != MatrixCoefficients::Identity
+                    })
+                {
+                    return Err(AvifError::UnknownError(
+                        "--lossless requires 444 and identity matrix coefficients".into(),
+                    ));
+                }
+            }
+            if args.sharpyuv {
+                let yuv_format =
+                    if args.lossless { Some(PixelFormat::Yuv444) } else { args.yuv_format };
+                match yuv_format {
+                    Some(PixelFormat::Yuv422) => {
+                        return Err(AvifError::UnknownError(
+                            "--sharpyuv is not supported with --yuv 422".into(),
+                        ));
+                    }
+                    Some(PixelFormat::Yuv444 | PixelFormat::Yuv400) => {
+                        log!("Warning: --sharpyuv has no effect without chroma subsampling");
+                    }
+                    _ => {}
+                }
+            }
+            if args.fps.is_some_and(|fps| fps.is_nan() || fps <= 0.0) {
+                return Err(AvifError::UnknownError("fps must be positive".into()));
+            }
+            if args.grid.is_some() && args.progressive {
+                return Err(AvifError::UnknownError(
+                    "--grid cannot be combined with --progressive".into(),
+                ));
+            }
+            let is_raw_yuv = input_format(args) == "yuv";
+            if is_raw_yuv && args.size.is_none() {
+                return Err(AvifError::UnknownError(
+                    "size is required for raw yuv input".into(),
+                ));
+            }
+            if !is_raw_yuv
+                && (args.size.is_some()
+                    || args.range.is_some()
+                    || args.big_endian
+                    || matches!(
+                        args.yuv_format,
+                        Some(
+                            PixelFormat::AndroidNv12
+                                | PixelFormat::AndroidNv21
+                                | PixelFormat::AndroidP010
+                        )
+                    ))
+            {
+                return Err(AvifError::UnknownError(
+                    "size, range, big-endian, nv12, nv21 and p010 are only supported for raw yuv input"
+                        .into(),
+                ));
+            }
         }
     } else if !args.version {
         return Err(AvifError::UnknownError("input_file is required".into()));
@@ -864,6 +2528,212 @@ fn validate_args(args: &CommandLineArgs) -> AvifResult<()> {
     Ok(())
 }
 
+// Input extensions picked up from directories and wildcard patterns in batch mode. Raw yuv files
+// are left out since they require --size.
+const BATCH_INPUT_EXTENSIONS: &[&str] = &[
+    "avif", "heic", "heif", "y4m", "jpg", "jpeg", "png", "gif", "webp", "tif", "tiff", "bmp",
+    "pnm", "pbm", "pgm", "ppm", "pam", "pfm", "exr",
+];
+
+// Matches name against pattern, where * matches any sequence of characters and ? matches a
+// single character.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, _) => name.is_empty(),
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+// Returns the files of directory whose name matches pattern, in alphabetical order.
+fn list_directory(directory: &Path, pattern: &str) -> AvifResult<Vec<String>> {
+    let entries = std::fs::read_dir(directory).map_err(|err| {
+        AvifError::UnknownError(format!("Could not read {}: {err}", directory.display()))
+    })?;
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter(|entry| wildcard_match(pattern.as_bytes(), entry.file_name().as_encoded_bytes()))
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .filter(|file| BATCH_INPUT_EXTENSIONS.contains(&get_extension(file).as_str()))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn batch_input_files(inputs: &[String]) -> AvifResult<Vec<String>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            files.extend(list_directory(path, "*")?);
+        } else if input.contains(['*', '?']) {
+            let directory = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            let pattern = path.file_name().unwrap_or_default().to_string_lossy();
+            files.extend(list_directory(directory, &pattern)?);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn batch_output_extension(args: &CommandLineArgs, input_file: &str) -> String {
+    let extension = get_extension(input_file);
+    match args.batch_ext.iter().find(|(from, _)| *from == extension) {
+        Some((_, to)) => to.clone(),
+        None if can_decode(&extension) => "png".into(),
+        None => "avif".into(),
+    }
+}
+
+// Returns true if output_file was modified after input_file.
+fn is_up_to_date(input_file: &str, output_file: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(Path::new(input_file)), modified(output_file)) {
+        (Ok(input), Ok(output)) => output >= input,
+        _ => false,
+    }
+}
+
+fn convert(args: &CommandLineArgs) -> AvifResult<()> {
+    validate_args(args)?;
+    let input_file = args.input_file.as_ref().unwrap();
+    if can_decode(&input_format(args)) {
+        decode(args, input_file)
+    } else if can_encode(&output_format(args)) {
+        encode(args, input_file)
+    } else {
+        Err(AvifError::UnknownError(
+            "Input/output file extensions not supported".into(),
+        ))
+    }
+}
+
+enum BatchResult {
+    Converted,
+    Skipped,
+    Failed(String),
+}
+
+fn batch(args: &CommandLineArgs) -> AvifResult<()> {
+    let output_dir = Path::new(args.output_dir.as_ref().unwrap());
+    std::fs::create_dir_all(output_dir).map_err(|err| {
+        AvifError::UnknownError(format!("Could not create {}: {err}", output_dir.display()))
+    })?;
+    let input_files = batch_input_files(&args.batch)?;
+    // Each file is converted on a single thread, the parallelism comes from converting several
+    // files at once.
+    let worker_count = (max_threads(&args.jobs) as usize).clamp(1, input_files.len().max(1));
+    log!(
+        "Converting {} file{} with {worker_count} worker thread{}...",
+        input_files.len(),
+        if input_files.len() == 1 { "" } else { "s" },
+        if worker_count == 1 { "" } else { "s" }
+    );
+    let mut results: Vec<Option<BatchResult>> = Vec::new();
+    let mut jobs = Vec::new();
+    let mut output_files = HashMap::new();
+    for input_file in &input_files {
+        let stem = Path::new(input_file).file_stem().unwrap_or_default();
+        let output_file = output_dir
+            .join(stem)
+            .with_extension(batch_output_extension(args, input_file));
+        if let Some(other) = output_files.insert(output_file.clone(), input_file) {
+            results.push(Some(BatchResult::Failed(format!(
+                "Output {} is also the output of {other}",
+                output_file.display()
+            ))));
+        } else if is_up_to_date(input_file, &output_file) {
+            results.push(Some(BatchResult::Skipped));
+        } else {
+            let mut file_args = args.clone();
+            file_args.batch.clear();
+            file_args.output_dir = None;
+            file_args.jobs = Some(1);
+            file_args.input_file = Some(input_file.clone());
+            // Write to a temporary file that is renamed once complete, so that a failed or
+            // interrupted conversion does not leave an output that looks up to date.
+            let extension = output_file
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let partial_file = output_file.with_extension(format!("partial.{extension}"));
+            file_args.output_file = Some(partial_file.to_string_lossy().into_owned());
+            jobs.push((results.len(), file_args, output_file));
+            results.push(None);
+        }
+    }
+
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(results);
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count {
+            scope.spawn(|| {
+                while let Some((index, file_args, output_file)) =
+                    jobs.get(next_job.fetch_add(1, Ordering::Relaxed))
+                {
+                    let partial_file = file_args.output_file.as_ref().unwrap();
+                    // A panic only fails the file that caused it.
+                    let result =
+                        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            convert(file_args)
+                        })) {
+                            Ok(Ok(())) => match std::fs::rename(partial_file, output_file) {
+                                Ok(()) => BatchResult::Converted,
+                                Err(err) => BatchResult::Failed(format!(
+                                    "Could not rename {partial_file}: {err}"
+                                )),
+                            },
+                            Ok(Err(err)) => BatchResult::Failed(format!("{err:?}")),
+                            Err(_) => BatchResult::Failed("panicked".into()),
+                        };
+                    if !matches!(result, BatchResult::Converted) {
+                        let _ = std::fs::remove_file(partial_file);
+                    }
+                    results.lock().unwrap()[*index] = Some(result);
+                }
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let mut converted = 0;
+    let mut skipped = 0;
+    let mut errors = Vec::new();
+    for (input_file, result) in input_files.iter().zip(results) {
+        match result.unwrap() {
+            BatchResult::Converted => converted += 1,
+            BatchResult::Skipped => skipped += 1,
+            BatchResult::Failed(err) => errors.push((input_file, err)),
+        }
+    }
+    log!(
+        "Converted {converted}, skipped {skipped} up to date, failed {}",
+        errors.len()
+    );
+    for (input_file, err) in &errors {
+        eprintln!("ERROR: {input_file}: {err}");
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AvifError::UnknownError(format!(
+            "{} of {} files failed",
+            errors.len(),
+            input_files.len()
+        )))
+    }
+}
+
 fn binary_name() -> String {
     match std::env::args_os().next() {
         Some(binary_path) => {
@@ -889,19 +2759,36 @@ fn main() {
         println!("Available codecs:");
         println!("  {}", codec_versions());
     }
+    if let Some(Command::Compare(compare_args)) = &args.command {
+        match compare(&args, compare_args) {
+            Ok(true) => std::process::exit(0),
+            Ok(false) => std::process::exit(2),
+            Err(err) => {
+                eprintln!("ERROR: {err:#?}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.output_file.as_deref() == Some(STDIO) {
+        LOG_TO_STDERR.store(true, Ordering::Relaxed);
+    }
     if let Err(err) = validate_args(&args) {
         eprintln!("ERROR: {err:#?}");
         std::process::exit(1);
     }
-    let res = if let Some(input_file) = &args.input_file {
-        if can_decode(input_file) {
+    let res = if !args.batch.is_empty() {
+        batch(&args)
+    } else if let Some(input_file) = &args.input_file {
+        if can_decode(&input_format(&args)) {
             if args.info {
                 info(&args, input_file)
+            } else if args.dump {
+                dump(input_file)
             } else {
                 decode(&args, input_file)
             }
-        } else if let Some(output_file) = &args.output_file {
-            if can_encode(output_file) {
+        } else if args.output_file.is_some() {
+            if can_encode(&output_format(&args)) {
                 encode(&args, input_file)
             } else {
                 eprintln!("Input/output file extensions not supported");
@@ -910,7 +2797,7 @@ fn main() {
         } else {
             eprintln!(
                 "Input file extension not supported: {}",
-                get_extension(input_file)
+                input_format(&args)
             );
             std::process::exit(1);
         }
@@ -219,6 +219,8 @@ impl From<&avifDecoder> for Settings {
             image_content_to_decode: image_content_to_decode_flags,
             codec_choice: match decoder.codecChoice {
                 avifCodecChoice::Auto => CodecChoice::Auto,
+                #[cfg(feature = "aom")]
+                avifCodecChoice::Aom => CodecChoice::Aom,
                 avifCodecChoice::Dav1d => CodecChoice::Dav1d,
                 avifCodecChoice::Libgav1 => CodecChoice::Libgav1,
                 // Silently treat all other choices the same as Auto.
@@ -18,6 +18,7 @@ use super::types::*;
 use crate::image::*;
 use crate::internal_utils::*;
 use crate::reformat::rgb;
+use crate::reformat::scale_impl::ScaleFilter;
 use crate::utils::pixels::*;
 use crate::*;
 
@@ -258,7 +259,7 @@ pub unsafe extern "C" fn crabby_avifImageScale(
     }
 
     let mut rust_image: image::Image = deref_const!(image).into();
-    let res = rust_image.scale(dstWidth, dstHeight, Category::Color);
+    let res = rust_image.scale(dstWidth, dstHeight, Category::Color, ScaleFilter::Box, 1);
     if res.is_err() {
         return res.into();
     }
@@ -267,7 +268,7 @@ pub unsafe extern "C" fn crabby_avifImageScale(
     // alpha plane.
     rust_image.width = deref_const!(image).width;
     rust_image.height = deref_const!(image).height;
-    let res = rust_image.scale(dstWidth, dstHeight, Category::Alpha);
+    let res = rust_image.scale(dstWidth, dstHeight, Category::Alpha, ScaleFilter::Box, 1);
     if res.is_err() {
         return res.into();
     }
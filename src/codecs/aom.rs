@@ -13,12 +13,19 @@
 // limitations under the License.
 
 #![allow(non_upper_case_globals)]
+// The type of the fields from aom_sys::bindings::* are dependent on the compiler that is used to
+// generate the bindings, version of libaom, etc. So allow clippy to ignore unnecessary cast
+// warnings.
+#![allow(clippy::unnecessary_cast)]
 
 use crate::codecs::*;
+use crate::decoder::GridImageHelper;
 use crate::encoder::Sample;
 use crate::encoder::ScalingMode;
 use crate::image::Image;
+use crate::image::Plane;
 use crate::image::YuvRange;
+use crate::utils::pixels::*;
 use crate::utils::IFraction;
 use crate::*;
 
@@ -591,3 +598,316 @@ impl Aom {
         format!("aom: {version}")
     }
 }
+
+#[derive(Default)]
+pub struct AomDecoder {
+    decoder: Option<aom_codec_ctx_t>,
+    // Copy of the last alpha frame, re-used when an alpha payload does not output a frame. The
+    // frames owned by libaom are only valid until the next call to aom_codec_decode().
+    alpha_image: Option<Image>,
+    config: Option<DecoderConfig>,
+}
+
+fn aom_image_to_image(
+    aom_image: &aom_image_t,
+    image: &mut Image,
+    category: Category,
+) -> AvifResult<()> {
+    let width = aom_image.d_w as u32;
+    let height = aom_image.d_h as u32;
+    let depth = aom_image.bit_depth as u8;
+    if ((aom_image.fmt & AOM_IMG_FMT_HIGHBITDEPTH) != 0) != (depth > 8) {
+        // 8-bit samples are expected to be stored in 8-bit buffers because of allow_lowbitdepth.
+        return AvifError::unknown_error(format!(
+            "unexpected aom image format {} for depth {depth}",
+            aom_image.fmt
+        ));
+    }
+    let yuv_range = if aom_image.range == aom_color_range_AOM_CR_STUDIO_RANGE {
+        YuvRange::Limited
+    } else {
+        YuvRange::Full
+    };
+    match category {
+        Category::Alpha => {
+            if image.width > 0
+                && image.height > 0
+                && (image.width != width || image.height != height || image.depth != depth)
+            {
+                // Alpha plane does not match the previous alpha plane.
+                return AvifError::unknown_error("");
+            }
+            image.width = width;
+            image.height = height;
+            image.depth = depth;
+            image.row_bytes[3] = aom_image.stride[0] as u32;
+            image.planes[3] = Some(Pixels::from_raw_pointer(
+                aom_image.planes[0],
+                image.depth as u32,
+                image.height,
+                image.row_bytes[3],
+            )?);
+            image.image_owns_planes[3] = false;
+            image.yuv_range = yuv_range;
+        }
+        _ => {
+            image.width = width;
+            image.height = height;
+            image.depth = depth;
+
+            image.yuv_format = if aom_image.monochrome != 0 {
+                PixelFormat::Yuv400
+            } else {
+                match aom_image.fmt & !AOM_IMG_FMT_HIGHBITDEPTH {
+                    aom_img_fmt_AOM_IMG_FMT_I420 => PixelFormat::Yuv420,
+                    aom_img_fmt_AOM_IMG_FMT_I422 => PixelFormat::Yuv422,
+                    aom_img_fmt_AOM_IMG_FMT_I444 => PixelFormat::Yuv444,
+                    format => {
+                        return AvifError::unknown_error(format!(
+                            "unsupported aom image format {format}"
+                        ))
+                    }
+                }
+            };
+            image.yuv_range = yuv_range;
+            image.chroma_sample_position = (aom_image.csp as u32).into();
+
+            image.color_primaries = (aom_image.cp as u16).into();
+            image.transfer_characteristics = (aom_image.tc as u16).into();
+            image.matrix_coefficients = (aom_image.mc as u16).into();
+
+            for plane in 0usize..image.yuv_format.plane_count() {
+                image.row_bytes[plane] = aom_image.stride[plane] as u32;
+                image.planes[plane] = Some(Pixels::from_raw_pointer(
+                    aom_image.planes[plane],
+                    image.depth as u32,
+                    image.height,
+                    image.row_bytes[plane],
+                )?);
+                image.image_owns_planes[plane] = false;
+            }
+            if image.yuv_format == PixelFormat::Yuv400 {
+                // Clear left over chroma planes from previous frames.
+                image.clear_chroma_planes();
+            }
+        }
+    }
+    Ok(())
+}
+
+impl AomDecoder {
+    fn initialize_impl(&mut self) -> AvifResult<()> {
+        if self.decoder.is_some() {
+            return Ok(());
+        }
+        let config = self.config.unwrap_ref();
+        let operating_point = config.operating_point as i32;
+        let all_layers = if config.all_layers { 1 } else { 0 };
+        let aom_config = aom_codec_dec_cfg_t {
+            threads: cmp::max(config.max_threads, 1) as _,
+            w: 0,
+            h: 0,
+            // Output 8-bit streams into 8-bit buffers.
+            allow_lowbitdepth: 1,
+        };
+        let mut decoder_uninit: MaybeUninit<aom_codec_ctx_t> = MaybeUninit::uninit();
+        // # Safety: Calling a C function with valid parameters.
+        let err = unsafe {
+            aom_codec_dec_init_ver(
+                decoder_uninit.as_mut_ptr(),
+                aom_codec_av1_dx(),
+                &aom_config as *const _,
+                0,
+                AOM_DECODER_ABI_VERSION as _,
+            )
+        };
+        if err != aom_codec_err_t_AOM_CODEC_OK {
+            return AvifError::unknown_error(format!("aom_codec_dec_init failed. err: {err}"));
+        }
+        // # Safety: decoder_uninit was initialized in the C function call above.
+        self.decoder = Some(unsafe { decoder_uninit.assume_init() });
+
+        for (key, value) in [
+            (aom_dec_control_id_AV1D_SET_OUTPUT_ALL_LAYERS, all_layers),
+            (aom_dec_control_id_AV1D_SET_OPERATING_POINT, operating_point),
+        ] {
+            // # Safety: Calling a C function with valid parameters.
+            if unsafe { aom_codec_control(self.decoder.unwrap_mut() as *mut _, key as _, value) }
+                != aom_codec_err_t_AOM_CODEC_OK
+            {
+                return AvifError::unknown_error(format!("aom_codec_control({key}) failed"));
+            }
+        }
+        Ok(())
+    }
+
+    // Decodes |av1_payload| and returns the first output frame that belongs to |spatial_id|, if
+    // any.
+    fn decode_frame(
+        &mut self,
+        av1_payload: &[u8],
+        spatial_id: u8,
+    ) -> AvifResult<Option<*mut aom_image_t>> {
+        // # Safety: Calling a C function with valid parameters.
+        let err = unsafe {
+            aom_codec_decode(
+                self.decoder.unwrap_mut() as *mut _,
+                av1_payload.as_ptr(),
+                av1_payload.len(),
+                std::ptr::null_mut(),
+            )
+        };
+        if err != aom_codec_err_t_AOM_CODEC_OK {
+            return AvifError::unknown_error(format!("aom_codec_decode returned {err}"));
+        }
+        let mut next_frame = None;
+        let mut iter: aom_codec_iter_t = std::ptr::null_mut();
+        loop {
+            // # Safety: Calling a C function with valid parameters.
+            let frame = unsafe {
+                aom_codec_get_frame(self.decoder.unwrap_mut() as *mut _, &mut iter as *mut _)
+            };
+            if frame.is_null() {
+                break;
+            }
+            // # Safety: frame is guaranteed to be valid and not null (libaom API contract).
+            let frame_spatial_id = unsafe { (*frame).spatial_id } as u8;
+            if next_frame.is_none() && (spatial_id == 0xFF || spatial_id == frame_spatial_id) {
+                next_frame = Some(frame);
+            }
+        }
+        if let Some(frame) = next_frame {
+            // # Safety: frame is guaranteed to be valid and not null (libaom API contract).
+            let frame = unsafe { &*frame };
+            let frame_size = frame.d_w as u64 * frame.d_h as u64;
+            if let Some(image_size_limit) = self.config.unwrap_ref().image_size_limit {
+                if frame_size > image_size_limit.get() as u64 {
+                    return AvifError::unknown_error(format!(
+                        "aom frame size {frame_size} exceeds the image size limit"
+                    ));
+                }
+            }
+        }
+        Ok(next_frame)
+    }
+
+    fn drop_impl(&mut self) {
+        self.alpha_image = None;
+        if self.decoder.is_some() {
+            // # Safety: Calling a C function with valid parameters.
+            unsafe {
+                aom_codec_destroy(self.decoder.unwrap_mut() as *mut _);
+            }
+        }
+        self.decoder = None;
+    }
+
+    fn get_next_image_grid_impl(
+        &mut self,
+        payloads: &[Vec<u8>],
+        spatial_id: u8,
+        grid_image_helper: &mut GridImageHelper,
+    ) -> AvifResult<()> {
+        self.initialize_impl()?;
+        for payload in payloads {
+            if grid_image_helper.is_grid_complete()? {
+                break;
+            }
+            let frame = match self.decode_frame(payload, spatial_id)? {
+                Some(frame) => frame,
+                None => return AvifError::unknown_error("aom did not output a grid cell"),
+            };
+            let mut cell_image = Image::default();
+            // # Safety: frame is valid until the next call to aom_codec_decode().
+            aom_image_to_image(
+                unsafe { &*frame },
+                &mut cell_image,
+                grid_image_helper.category,
+            )?;
+            grid_image_helper.copy_from_cell_image(&mut cell_image)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for AomDecoder {
+    fn codec(&self) -> CodecChoice {
+        CodecChoice::Aom
+    }
+
+    fn initialize(&mut self, config: &DecoderConfig) -> AvifResult<()> {
+        self.config = Some(config.clone());
+        Ok(())
+    }
+
+    fn get_next_image(
+        &mut self,
+        av1_payload: &[u8],
+        spatial_id: u8,
+        image: &mut Image,
+        category: Category,
+    ) -> AvifResult<()> {
+        self.initialize_impl()?;
+        match self.decode_frame(av1_payload, spatial_id)? {
+            Some(frame) => {
+                // # Safety: The image is owned by libaom and is valid until the next call to
+                // aom_codec_decode().
+                aom_image_to_image(unsafe { &*frame }, image, category)?;
+                if category == Category::Alpha {
+                    let mut alpha_image = Image {
+                        width: image.width,
+                        height: image.height,
+                        depth: image.depth,
+                        yuv_range: image.yuv_range,
+                        ..Default::default()
+                    };
+                    alpha_image.allocate_planes(Category::Alpha)?;
+                    for y in 0..image.height {
+                        if image.depth == 8 {
+                            alpha_image
+                                .row_exact_mut(Plane::A, y)?
+                                .copy_from_slice(image.row_exact(Plane::A, y)?);
+                        } else {
+                            alpha_image
+                                .row16_exact_mut(Plane::A, y)?
+                                .copy_from_slice(image.row16_exact(Plane::A, y)?);
+                        }
+                    }
+                    self.alpha_image = Some(alpha_image);
+                }
+                Ok(())
+            }
+            None if category == Category::Alpha && self.alpha_image.is_some() => {
+                // Special case for alpha, re-use last frame.
+                let alpha_image = self.alpha_image.unwrap_ref();
+                image.width = alpha_image.width;
+                image.height = alpha_image.height;
+                image.depth = alpha_image.depth;
+                image.yuv_range = alpha_image.yuv_range;
+                image.steal_or_copy_planes_from(alpha_image, Category::Alpha)?;
+                image.image_owns_planes[3] = true;
+                Ok(())
+            }
+            None => AvifError::unknown_error(""),
+        }
+    }
+
+    fn get_next_image_grid(
+        &mut self,
+        payloads: &[Vec<u8>],
+        spatial_id: u8,
+        grid_image_helper: &mut GridImageHelper,
+    ) -> AvifResult<()> {
+        let res = self.get_next_image_grid_impl(payloads, spatial_id, grid_image_helper);
+        if res.is_err() {
+            self.drop_impl();
+        }
+        res
+    }
+}
+
+impl Drop for AomDecoder {
+    fn drop(&mut self) {
+        self.drop_impl();
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Public interface for AV1 codecs implemented outside of this crate. A CodecFactory is selected
+// with CodecChoice::Custom in decoder::Settings or encoder::Settings, and is asked for a new
+// decoder or encoder instance per decoded or encoded item (or track).
+
+use crate::codecs::Decoder;
+use crate::codecs::DecoderConfig;
+use crate::decoder::GridImageHelper;
+use crate::image::Image;
+use crate::parser::mp4box::CodecConfiguration;
+use crate::*;
+
+#[cfg(feature = "encoder")]
+use crate::codecs::Encoder;
+#[cfg(feature = "encoder")]
+use crate::codecs::EncoderConfig;
+#[cfg(feature = "encoder")]
+use crate::encoder::Sample;
+
+use std::num::NonZero;
+use std::sync::Arc;
+
+/// Creates the decoder and encoder instances of a codec implemented outside of this crate.
+pub trait CodecFactory: Send + Sync {
+    /// Name and version of the codec, for logging purposes.
+    fn version(&self) -> String;
+
+    /// Returns a new AV1 decoder, or None if the codec cannot decode.
+    fn create_decoder(&self) -> Option<Box<dyn CustomDecoder>> {
+        None
+    }
+
+    /// Returns a new AV1 encoder, or None if the codec cannot encode.
+    #[cfg(feature = "encoder")]
+    fn create_encoder(&self) -> Option<Box<dyn CustomEncoder>> {
+        None
+    }
+}
+
+impl std::fmt::Debug for dyn CodecFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "CodecFactory({})", self.version())
+    }
+}
+
+// Two custom codec choices are equal if they share the same factory instance.
+impl PartialEq for dyn CodecFactory {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// Parameters of the stream given to CustomDecoder::initialize().
+#[derive(Clone, Debug, Default)]
+pub struct CustomDecoderConfig {
+    pub operating_point: u8,
+    pub all_layers: bool,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u8,
+    pub max_threads: u32,
+    pub image_size_limit: Option<NonZero<u32>>,
+    pub category: Category,
+    /// Contents of the AV1CodecConfigurationBox ('av1C'), including the configOBUs if any.
+    pub av1_codec_configuration: Vec<u8>,
+}
+
+pub trait CustomDecoder {
+    /// Called once before the first call to decode().
+    fn initialize(&mut self, config: &CustomDecoderConfig) -> AvifResult<()>;
+
+    /// Decodes one AV1 temporal unit and returns the frame with the given |spatial_id| (or the
+    /// highest spatial layer if |spatial_id| is 0xFF). The frame is returned in the Y, U and V
+    /// planes of an image whose planes are owned by the image, even for the alpha |category|
+    /// where only the Y plane is used.
+    fn decode(
+        &mut self,
+        av1_payload: &[u8],
+        spatial_id: u8,
+        category: Category,
+    ) -> AvifResult<Image>;
+}
+
+/// Parameters given to each CustomEncoder::encode_image() call.
+#[cfg(feature = "encoder")]
+#[derive(Clone, Debug, Default)]
+pub struct CustomEncoderConfig {
+    pub tile_rows_log2: i32,
+    pub tile_columns_log2: i32,
+    /// From 0 (maximum distortion) to 100 (lossless) inclusive.
+    pub quality: f32,
+    pub disable_lagged_output: bool,
+    pub is_single_image: bool,
+    pub speed: Option<u32>,
+    pub extra_layer_count: u32,
+    pub threads: u32,
+    pub scaling_mode: crate::encoder::ScalingMode,
+    /// Options set with encoder::Encoder::set_codec_specific_option() for the encoded category.
+    pub codec_specific_options: Vec<(String, String)>,
+}
+
+#[cfg(feature = "encoder")]
+pub trait CustomEncoder {
+    /// Encodes |image| and appends the AV1 temporal units that are ready to |output_samples|.
+    fn encode_image(
+        &mut self,
+        image: &Image,
+        category: Category,
+        config: &CustomEncoderConfig,
+        output_samples: &mut Vec<Sample>,
+    ) -> AvifResult<()>;
+
+    /// Flushes the remaining AV1 temporal units to |output_samples|.
+    fn finish(&mut self, output_samples: &mut Vec<Sample>) -> AvifResult<()>;
+}
+
+// Wraps a CustomDecoder into the internal codecs::Decoder interface.
+pub(crate) struct CustomDecoderWrapper {
+    pub factory: Arc<dyn CodecFactory>,
+    pub decoder: Box<dyn CustomDecoder>,
+}
+
+impl Decoder for CustomDecoderWrapper {
+    fn codec(&self) -> CodecChoice {
+        CodecChoice::Custom(self.factory.clone())
+    }
+
+    fn initialize(&mut self, config: &DecoderConfig) -> AvifResult<()> {
+        self.decoder.initialize(&CustomDecoderConfig {
+            operating_point: config.operating_point,
+            all_layers: config.all_layers,
+            width: config.width,
+            height: config.height,
+            depth: config.depth,
+            max_threads: config.max_threads,
+            image_size_limit: config.image_size_limit,
+            category: config.category,
+            av1_codec_configuration: match &config.codec_config {
+                CodecConfiguration::Av1(av1_config) => av1_config.raw_data.clone(),
+                _ => Vec::new(),
+            },
+        })
+    }
+
+    fn get_next_image(
+        &mut self,
+        av1_payload: &[u8],
+        spatial_id: u8,
+        image: &mut Image,
+        category: Category,
+        #[cfg(feature = "android_mediacodec")] _signal_eos: bool,
+    ) -> AvifResult<()> {
+        let mut frame = self.decoder.decode(av1_payload, spatial_id, category)?;
+        if !frame.has_plane(Plane::Y) {
+            return AvifError::unknown_error("custom decoder returned no samples");
+        }
+        match category {
+            Category::Alpha => {
+                if image.width > 0
+                    && image.height > 0
+                    && (image.width != frame.width
+                        || image.height != frame.height
+                        || image.depth != frame.depth)
+                {
+                    // Alpha plane does not match the previous alpha plane.
+                    return AvifError::unknown_error("");
+                }
+                image.width = frame.width;
+                image.height = frame.height;
+                image.depth = frame.depth;
+                image.yuv_range = frame.yuv_range;
+                image.row_bytes[3] = frame.row_bytes[0];
+                image.planes[3] = frame.planes[0].take();
+                image.image_owns_planes[3] = true;
+            }
+            _ => {
+                image.width = frame.width;
+                image.height = frame.height;
+                image.depth = frame.depth;
+                image.yuv_format = frame.yuv_format;
+                image.yuv_range = frame.yuv_range;
+                image.chroma_sample_position = frame.chroma_sample_position;
+                image.color_primaries = frame.color_primaries;
+                image.transfer_characteristics = frame.transfer_characteristics;
+                image.matrix_coefficients = frame.matrix_coefficients;
+                for plane in 0usize..image.yuv_format.plane_count() {
+                    image.row_bytes[plane] = frame.row_bytes[plane];
+                    image.planes[plane] = frame.planes[plane].take();
+                    image.image_owns_planes[plane] = true;
+                }
+                if image.yuv_format == PixelFormat::Yuv400 {
+                    // Clear left over chroma planes from previous frames.
+                    image.clear_chroma_planes();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_next_image_grid(
+        &mut self,
+        _payloads: &[Vec<u8>],
+        _spatial_id: u8,
+        _grid_image_helper: &mut GridImageHelper,
+    ) -> AvifResult<()> {
+        AvifError::not_implemented()
+    }
+}
+
+// Wraps a CustomEncoder into the internal codecs::Encoder interface.
+#[cfg(feature = "encoder")]
+pub(crate) struct CustomEncoderWrapper {
+    pub encoder: Box<dyn CustomEncoder>,
+}
+
+#[cfg(feature = "encoder")]
+impl Encoder for CustomEncoderWrapper {
+    fn encode_image(
+        &mut self,
+        image: &Image,
+        category: Category,
+        config: &EncoderConfig,
+        output_samples: &mut Vec<Sample>,
+    ) -> AvifResult<()> {
+        let config = CustomEncoderConfig {
+            tile_rows_log2: config.tile_rows_log2,
+            tile_columns_log2: config.tile_columns_log2,
+            quality: config.quality,
+            disable_lagged_output: config.disable_lagged_output,
+            is_single_image: config.is_single_image,
+            speed: config.speed,
+            extra_layer_count: config.extra_layer_count,
+            threads: config.threads,
+            scaling_mode: config.scaling_mode,
+            codec_specific_options: config.codec_specific_options(category),
+        };
+        self.encoder
+            .encode_image(image, category, &config, output_samples)
+    }
+
+    fn finish(&mut self, output_samples: &mut Vec<Sample>) -> AvifResult<()> {
+        self.encoder.finish(output_samples)
+    }
+}
@@ -12,10 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// The type of the fields from libgav1_sys::bindings::* are dependent on the compiler that is used
+// to generate the bindings, version of libgav1, etc. So allow clippy to ignore unnecessary cast
+// warnings.
+#![allow(clippy::unnecessary_cast)]
+#![allow(non_upper_case_globals)]
+
 use crate::codecs::Decoder;
 use crate::codecs::DecoderConfig;
 use crate::decoder::GridImageHelper;
 use crate::image::Image;
+use crate::image::Plane;
 use crate::image::YuvRange;
 use crate::utils::pixels::*;
 use crate::*;
@@ -25,184 +32,209 @@ use libgav1_sys::bindings::*;
 use std::ffi::CStr;
 use std::mem::MaybeUninit;
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Libgav1 {
     decoder: Option<*mut Libgav1Decoder>,
-    image: Option<Libgav1DecoderBuffer>,
+    // Copy of the last alpha frame, re-used when an alpha payload does not output a frame. The
+    // frames owned by libgav1 are only valid until the next call to Libgav1DecoderDequeueFrame().
+    alpha_image: Option<Image>,
+    config: Option<DecoderConfig>,
 }
 
-#[allow(non_upper_case_globals)]
-// The type of the fields from from dav1d_sys::bindings::* are dependent on the compiler that
-// is used to generate the bindings, version of dav1d, etc. So allow clippy to ignore
-// unnecessary cast warnings.
-#[allow(clippy::unnecessary_cast)]
-impl Decoder for Libgav1 {
-    fn codec(&self) -> CodecChoice {
-        CodecChoice::Libgav1
+fn buffer_to_image(
+    gav1_image: &Libgav1DecoderBuffer,
+    image: &mut Image,
+    category: Category,
+) -> AvifResult<()> {
+    let width = gav1_image.displayed_width[0] as u32;
+    let height = gav1_image.displayed_height[0] as u32;
+    let depth = gav1_image.bitdepth as u8;
+    let yuv_range = if gav1_image.color_range == Libgav1ColorRange_kLibgav1ColorRangeStudio {
+        YuvRange::Limited
+    } else {
+        YuvRange::Full
+    };
+    match category {
+        Category::Alpha => {
+            if image.width > 0
+                && image.height > 0
+                && (image.width != width || image.height != height || image.depth != depth)
+            {
+                // Alpha plane does not match the previous alpha plane.
+                return AvifError::unknown_error("");
+            }
+            image.width = width;
+            image.height = height;
+            image.depth = depth;
+            image.row_bytes[3] = gav1_image.stride[0] as u32;
+            image.planes[3] = Some(Pixels::from_raw_pointer(
+                gav1_image.plane[0],
+                image.depth as u32,
+                image.height,
+                image.row_bytes[3],
+            )?);
+            image.image_owns_planes[3] = false;
+            image.yuv_range = yuv_range;
+        }
+        _ => {
+            image.width = width;
+            image.height = height;
+            image.depth = depth;
+
+            image.yuv_format = match gav1_image.image_format {
+                Libgav1ImageFormat_kLibgav1ImageFormatMonochrome400 => PixelFormat::Yuv400,
+                Libgav1ImageFormat_kLibgav1ImageFormatYuv420 => PixelFormat::Yuv420,
+                Libgav1ImageFormat_kLibgav1ImageFormatYuv422 => PixelFormat::Yuv422,
+                Libgav1ImageFormat_kLibgav1ImageFormatYuv444 => PixelFormat::Yuv444,
+                format => {
+                    return AvifError::unknown_error(format!(
+                        "unsupported libgav1 image format {format}"
+                    ))
+                }
+            };
+            image.yuv_range = yuv_range;
+            image.chroma_sample_position = (gav1_image.chroma_sample_position as u32).into();
+
+            image.color_primaries = (gav1_image.color_primary as u16).into();
+            image.transfer_characteristics = (gav1_image.transfer_characteristics as u16).into();
+            image.matrix_coefficients = (gav1_image.matrix_coefficients as u16).into();
+
+            for plane in 0usize..image.yuv_format.plane_count() {
+                image.row_bytes[plane] = gav1_image.stride[plane] as u32;
+                image.planes[plane] = Some(Pixels::from_raw_pointer(
+                    gav1_image.plane[plane],
+                    image.depth as u32,
+                    image.height,
+                    image.row_bytes[plane],
+                )?);
+                image.image_owns_planes[plane] = false;
+            }
+            if image.yuv_format == PixelFormat::Yuv400 {
+                // Clear left over chroma planes from previous frames.
+                image.clear_chroma_planes();
+            }
+        }
     }
+    Ok(())
+}
 
-    fn initialize(&mut self, config: &DecoderConfig) -> AvifResult<()> {
+impl Libgav1 {
+    fn initialize_impl(&mut self) -> AvifResult<()> {
         if self.decoder.is_some() {
             return Ok(()); // Already initialized.
         }
+        let config = self.config.unwrap_ref();
         let mut settings_uninit: MaybeUninit<Libgav1DecoderSettings> = MaybeUninit::uninit();
-        unsafe {
-            Libgav1DecoderSettingsInitDefault(settings_uninit.as_mut_ptr());
-        }
+        // # Safety: Calling a C function with valid parameters.
+        unsafe { Libgav1DecoderSettingsInitDefault(settings_uninit.as_mut_ptr()) };
+        // # Safety: settings_uninit was initialized in the C function above.
         let mut settings = unsafe { settings_uninit.assume_init() };
-        settings.threads = i32::try_from(config.max_threads).unwrap_or(1);
+        settings.threads = i32::try_from(config.max_threads).unwrap_or(i32::MAX).max(1);
         settings.operating_point = config.operating_point as i32;
         settings.output_all_layers = if config.all_layers { 1 } else { 0 };
-        unsafe {
-            let mut dec = MaybeUninit::uninit();
-            let ret = Libgav1DecoderCreate(&settings, dec.as_mut_ptr());
-            if ret != Libgav1StatusCode_kLibgav1StatusOk {
-                return AvifError::unknown_error(format!("Libgav1DecoderCreate returned {ret}"));
-            }
-            self.decoder = Some(dec.assume_init());
+
+        let mut dec = MaybeUninit::uninit();
+        // # Safety: Calling a C function with valid parameters.
+        let ret = unsafe { Libgav1DecoderCreate(&settings, dec.as_mut_ptr()) };
+        if ret != Libgav1StatusCode_kLibgav1StatusOk {
+            return AvifError::unknown_error(format!("Libgav1DecoderCreate returned {ret}"));
         }
+        // # Safety: dec was initialized in the C function above.
+        self.decoder = Some(unsafe { dec.assume_init() });
         Ok(())
     }
 
-    fn get_next_image(
+    fn drop_impl(&mut self) {
+        self.alpha_image = None;
+        if self.decoder.is_some() {
+            // # Safety: Calling a C function with valid parameters.
+            unsafe { Libgav1DecoderDestroy(self.decoder.unwrap()) };
+        }
+        self.decoder = None;
+    }
+
+    // Decodes |av1_payload| and returns the first output frame that belongs to |spatial_id|, if
+    // any.
+    fn decode_frame(
         &mut self,
         av1_payload: &[u8],
         spatial_id: u8,
-        image: &mut Image,
-        category: Category,
-    ) -> AvifResult<()> {
-        if self.decoder.is_none() {
-            self.initialize(&DecoderConfig::default())?;
-        }
-        unsafe {
-            let ret = Libgav1DecoderEnqueueFrame(
-                self.decoder.unwrap(),
+    ) -> AvifResult<Option<Libgav1DecoderBuffer>> {
+        let decoder = self.decoder.unwrap();
+        // # Safety: Calling a C function with valid parameters.
+        let ret = unsafe {
+            Libgav1DecoderEnqueueFrame(
+                decoder,
                 av1_payload.as_ptr(),
                 av1_payload.len(),
                 0,
                 std::ptr::null_mut(),
-            );
+            )
+        };
+        if ret != Libgav1StatusCode_kLibgav1StatusOk {
+            return AvifError::unknown_error(format!("Libgav1DecoderEnqueueFrame returned {ret}"));
+        }
+        let mut next_frame: *const Libgav1DecoderBuffer = std::ptr::null_mut();
+        loop {
+            // # Safety: Calling a C function with valid parameters.
+            let ret = unsafe { Libgav1DecoderDequeueFrame(decoder, &mut next_frame) };
             if ret != Libgav1StatusCode_kLibgav1StatusOk {
                 return AvifError::unknown_error(format!(
-                    "Libgav1DecoderEnqueueFrame returned {ret}"
+                    "Libgav1DecoderDequeueFrame returned {ret}"
                 ));
             }
-            self.image = None;
-            let mut next_frame: *const Libgav1DecoderBuffer = std::ptr::null_mut();
-            loop {
-                let ret = Libgav1DecoderDequeueFrame(self.decoder.unwrap(), &mut next_frame);
-                if ret != Libgav1StatusCode_kLibgav1StatusOk {
-                    return AvifError::unknown_error(format!(
-                        "Libgav1DecoderDequeueFrame returned {ret}"
-                    ));
-                }
-                if !next_frame.is_null()
-                    && spatial_id != 0xFF
-                    && (*next_frame).spatial_id as u8 != spatial_id
-                {
-                    next_frame = std::ptr::null_mut();
-                } else {
-                    break;
-                }
-            }
-            // Got an image.
-            if next_frame.is_null() {
-                if category == Category::Alpha {
-                    // Special case for alpha, re-use last frame.
-                } else {
-                    return AvifError::unknown_error("");
-                }
+            // # Safety: next_frame is either null or valid (libgav1 API contract).
+            if !next_frame.is_null()
+                && spatial_id != 0xFF
+                && unsafe { (*next_frame).spatial_id } as u8 != spatial_id
+            {
+                // Skip the layers that were not requested.
+                next_frame = std::ptr::null_mut();
             } else {
-                self.image = Some(*next_frame);
+                break;
             }
-
-            let gav1_image = &self.image.unwrap();
-            match category {
-                Category::Alpha => {
-                    if image.width > 0
-                        && image.height > 0
-                        && (image.width != (gav1_image.displayed_width[0] as u32)
-                            || image.height != (gav1_image.displayed_height[0] as u32)
-                            || image.depth != (gav1_image.bitdepth as u8))
-                    {
-                        // Alpha plane does not match the previous alpha plane.
-                        return AvifError::unknown_error("");
-                    }
-                    image.width = gav1_image.displayed_width[0] as u32;
-                    image.height = gav1_image.displayed_height[0] as u32;
-                    image.depth = gav1_image.bitdepth as u8;
-                    image.row_bytes[3] = gav1_image.stride[0] as u32;
-                    image.planes[3] = Some(Pixels::from_raw_pointer(
-                        gav1_image.plane[0],
-                        image.depth as u32,
-                        image.height,
-                        image.row_bytes[3],
-                    )?);
-                    image.image_owns_planes[3] = false;
-                    image.yuv_range =
-                        if gav1_image.color_range == Libgav1ColorRange_kLibgav1ColorRangeStudio {
-                            YuvRange::Limited
-                        } else {
-                            YuvRange::Full
-                        };
-                }
-                _ => {
-                    image.width = gav1_image.displayed_width[0] as u32;
-                    image.height = gav1_image.displayed_height[0] as u32;
-                    image.depth = gav1_image.bitdepth as u8;
-
-                    image.yuv_format = match gav1_image.image_format {
-                        Libgav1ImageFormat_kLibgav1ImageFormatMonochrome400 => PixelFormat::Yuv400,
-                        Libgav1ImageFormat_kLibgav1ImageFormatYuv420 => PixelFormat::Yuv420,
-                        Libgav1ImageFormat_kLibgav1ImageFormatYuv422 => PixelFormat::Yuv422,
-                        Libgav1ImageFormat_kLibgav1ImageFormatYuv444 => PixelFormat::Yuv444,
-                        _ => PixelFormat::Yuv420, // not reached.
-                    };
-                    image.yuv_range =
-                        if gav1_image.color_range == Libgav1ColorRange_kLibgav1ColorRangeStudio {
-                            YuvRange::Limited
-                        } else {
-                            YuvRange::Full
-                        };
-                    image.chroma_sample_position =
-                        (gav1_image.chroma_sample_position as u32).into();
-
-                    image.color_primaries = (gav1_image.color_primary as u16).into();
-                    image.transfer_characteristics =
-                        (gav1_image.transfer_characteristics as u16).into();
-                    image.matrix_coefficients = (gav1_image.matrix_coefficients as u16).into();
-
-                    for plane in 0usize..image.yuv_format.plane_count() {
-                        image.row_bytes[plane] = gav1_image.stride[plane] as u32;
-                        image.planes[plane] = Some(Pixels::from_raw_pointer(
-                            gav1_image.plane[plane],
-                            image.depth as u32,
-                            image.height,
-                            image.row_bytes[plane],
-                        )?);
-                        image.image_owns_planes[plane] = false;
-                    }
-                    if image.yuv_format == PixelFormat::Yuv400 {
-                        // Clear left over chroma planes from previous frames.
-                        image.clear_chroma_planes();
-                    }
-                }
+        }
+        if next_frame.is_null() {
+            return Ok(None);
+        }
+        // # Safety: next_frame is not null and valid (libgav1 API contract).
+        let frame = unsafe { *next_frame };
+        // Libgav1 has no frame size limit setting, so check the output instead.
+        let frame_size = frame.displayed_width[0] as u64 * frame.displayed_height[0] as u64;
+        if let Some(image_size_limit) = self.config.unwrap_ref().image_size_limit {
+            if frame_size > image_size_limit.get() as u64 {
+                return AvifError::unknown_error(format!(
+                    "libgav1 frame size {frame_size} exceeds the image size limit"
+                ));
             }
         }
-        Ok(())
+        Ok(Some(frame))
     }
 
-    fn get_next_image_grid(
+    fn get_next_image_grid_impl(
         &mut self,
-        _payloads: &[Vec<u8>],
-        _spatial_id: u8,
-        _grid_image_helper: &mut GridImageHelper,
+        payloads: &[Vec<u8>],
+        spatial_id: u8,
+        grid_image_helper: &mut GridImageHelper,
     ) -> AvifResult<()> {
-        AvifError::not_implemented()
+        self.initialize_impl()?;
+        // All the cells share this decoder instance, and each cell is copied into the grid
+        // before the next one is decoded.
+        for payload in payloads {
+            if grid_image_helper.is_grid_complete()? {
+                break;
+            }
+            let frame = match self.decode_frame(payload, spatial_id)? {
+                Some(frame) => frame,
+                None => return AvifError::unknown_error("libgav1 did not output a grid cell"),
+            };
+            let mut cell_image = Image::default();
+            buffer_to_image(&frame, &mut cell_image, grid_image_helper.category)?;
+            grid_image_helper.copy_from_cell_image(&mut cell_image)?;
+        }
+        Ok(())
     }
-}
 
-impl Libgav1 {
     pub(crate) fn version() -> String {
         let version = match unsafe { CStr::from_ptr(Libgav1GetVersionString()) }.to_str() {
             Ok(s) => s.to_owned(),
@@ -212,10 +244,84 @@ impl Libgav1 {
     }
 }
 
+impl Decoder for Libgav1 {
+    fn codec(&self) -> CodecChoice {
+        CodecChoice::Libgav1
+    }
+
+    fn initialize(&mut self, config: &DecoderConfig) -> AvifResult<()> {
+        self.config = Some(config.clone());
+        Ok(())
+    }
+
+    fn get_next_image(
+        &mut self,
+        av1_payload: &[u8],
+        spatial_id: u8,
+        image: &mut Image,
+        category: Category,
+    ) -> AvifResult<()> {
+        self.initialize_impl()?;
+        match self.decode_frame(av1_payload, spatial_id)? {
+            Some(frame) => {
+                // The planes of frame are owned by libgav1 and are valid until the next call to
+                // Libgav1DecoderDequeueFrame().
+                buffer_to_image(&frame, image, category)?;
+                if category == Category::Alpha {
+                    let mut alpha_image = Image {
+                        width: image.width,
+                        height: image.height,
+                        depth: image.depth,
+                        yuv_range: image.yuv_range,
+                        ..Default::default()
+                    };
+                    alpha_image.allocate_planes(Category::Alpha)?;
+                    for y in 0..image.height {
+                        if image.depth == 8 {
+                            alpha_image
+                                .row_exact_mut(Plane::A, y)?
+                                .copy_from_slice(image.row_exact(Plane::A, y)?);
+                        } else {
+                            alpha_image
+                                .row16_exact_mut(Plane::A, y)?
+                                .copy_from_slice(image.row16_exact(Plane::A, y)?);
+                        }
+                    }
+                    self.alpha_image = Some(alpha_image);
+                }
+                Ok(())
+            }
+            None if category == Category::Alpha && self.alpha_image.is_some() => {
+                // Special case for alpha, re-use last frame.
+                let alpha_image = self.alpha_image.unwrap_ref();
+                image.width = alpha_image.width;
+                image.height = alpha_image.height;
+                image.depth = alpha_image.depth;
+                image.yuv_range = alpha_image.yuv_range;
+                image.steal_or_copy_planes_from(alpha_image, Category::Alpha)?;
+                image.image_owns_planes[3] = true;
+                Ok(())
+            }
+            None => AvifError::unknown_error(""),
+        }
+    }
+
+    fn get_next_image_grid(
+        &mut self,
+        payloads: &[Vec<u8>],
+        spatial_id: u8,
+        grid_image_helper: &mut GridImageHelper,
+    ) -> AvifResult<()> {
+        let res = self.get_next_image_grid_impl(payloads, spatial_id, grid_image_helper);
+        if res.is_err() {
+            self.drop_impl();
+        }
+        res
+    }
+}
+
 impl Drop for Libgav1 {
     fn drop(&mut self) {
-        if self.decoder.is_some() {
-            unsafe { Libgav1DecoderDestroy(self.decoder.unwrap()) };
-        }
+        self.drop_impl();
     }
 }
@@ -13,19 +13,21 @@
 // limitations under the License.
 
 #[cfg(feature = "dav1d")]
-pub mod dav1d;
+pub(crate) mod dav1d;
 
 #[cfg(feature = "libgav1")]
-pub mod libgav1;
+pub(crate) mod libgav1;
 
 #[cfg(feature = "android_mediacodec")]
-pub mod android_mediacodec;
+pub(crate) mod android_mediacodec;
 
 #[cfg(feature = "aom")]
-pub mod aom;
+pub(crate) mod aom;
 
 #[cfg(feature = "jpegxl")]
-pub mod libjxl;
+pub(crate) mod libjxl;
+
+pub mod custom;
 
 use crate::decoder::GridImageHelper;
 use crate::image::Image;
@@ -20,6 +20,9 @@ use crate::decoder::item::*;
 use crate::decoder::tile::*;
 use crate::decoder::track::*;
 
+#[cfg(feature = "aom")]
+use crate::codecs::aom::AomDecoder;
+
 #[cfg(feature = "dav1d")]
 use crate::codecs::dav1d::Dav1d;
 
@@ -32,6 +35,7 @@ use crate::codecs::android_mediacodec::MediaCodec;
 #[cfg(feature = "jpegxl")]
 use crate::codecs::libjxl::Libjxl;
 
+use crate::codecs::custom::CustomDecoderWrapper;
 use crate::codecs::DecoderConfig;
 use crate::gainmap::*;
 use crate::image::*;
@@ -40,7 +44,9 @@ use crate::internal_utils::*;
 use crate::parser::exif;
 use crate::parser::mp4box;
 use crate::parser::mp4box::*;
+use crate::parser::obu;
 use crate::parser::obu::Av1SequenceHeader;
+use crate::reformat::scale_impl::ScaleFilter;
 use crate::utils::pixels::ChannelIdc;
 use crate::*;
 
@@ -73,13 +79,30 @@ impl CodecChoice {
     fn get_decoder_codec(&self, compression_format: CompressionFormat) -> Option<Codec> {
         match compression_format {
             CompressionFormat::Avif => match self {
-                CodecChoice::Aom => None, // Not used as a decoder.
                 #[cfg(feature = "android_mediacodec")]
                 CodecChoice::Auto | CodecChoice::MediaCodec => Some(Box::<MediaCodec>::default()),
                 #[cfg(feature = "dav1d")]
                 CodecChoice::Auto | CodecChoice::Dav1d => Some(Box::<Dav1d>::default()),
                 #[cfg(feature = "libgav1")]
                 CodecChoice::Auto | CodecChoice::Libgav1 => Some(Box::<Libgav1>::default()),
+                // libaom is only the default decoder when no other AV1 decoder is available.
+                #[cfg(all(
+                    feature = "aom",
+                    not(any(
+                        feature = "android_mediacodec",
+                        feature = "dav1d",
+                        feature = "libgav1"
+                    ))
+                ))]
+                CodecChoice::Auto => Some(Box::<AomDecoder>::default()),
+                #[cfg(feature = "aom")]
+                CodecChoice::Aom => Some(Box::<AomDecoder>::default()),
+                CodecChoice::Custom(factory) => factory.create_decoder().map(|decoder| {
+                    Box::new(CustomDecoderWrapper {
+                        factory: factory.clone(),
+                        decoder,
+                    }) as Codec
+                }),
                 _ => None,
             },
             CompressionFormat::Heic => match self {
@@ -203,6 +226,10 @@ pub enum StrictnessFlag {
     PixiRequired,
     ClapValid,
     AlphaIspeRequired,
+    // The HDR_CLL and HDR_MDCV metadata OBUs must be well-formed and must match the 'clli' and
+    // 'mdcv' properties, if both are present. Metadata OBUs are informative so this is only
+    // checked when explicitly included with Strictness::SpecificInclude.
+    HdrMetadataConsistent,
 }
 
 #[derive(Debug, Default)]
@@ -240,6 +267,15 @@ impl Strictness {
             _ => false,
         }
     }
+
+    pub(crate) fn hdr_metadata_consistent(&self) -> bool {
+        match self {
+            Strictness::SpecificInclude(flags) => flags
+                .iter()
+                .any(|x| matches!(x, StrictnessFlag::HdrMetadataConsistent)),
+            _ => false,
+        }
+    }
 }
 
 #[repr(C)]
@@ -378,6 +414,7 @@ pub(crate) struct GridImageHelper<'a> {
     first_cell_image: Option<Image>,
     tile_width: u32,
     tile_height: u32,
+    max_threads: u32,
 }
 
 // These functions are not used in all configurations.
@@ -394,7 +431,13 @@ impl GridImageHelper<'_> {
         if self.category == Category::Alpha && cell_image.yuv_range == YuvRange::Limited {
             cell_image.alpha_to_full_range()?;
         }
-        cell_image.scale(self.tile_width, self.tile_height, self.category)?;
+        cell_image.scale(
+            self.tile_width,
+            self.tile_height,
+            self.category,
+            ScaleFilter::Box,
+            self.max_threads,
+        )?;
         if self.cell_index == 0 {
             validate_grid_image_dimensions(cell_image, self.grid)?;
             if self.category != Category::Alpha {
@@ -419,6 +462,37 @@ impl GridImageHelper<'_> {
     }
 }
 
+// Returns the metadata OBUs found in |data|, the sample of the first cell of the color item. The
+// metadata OBUs are informative, so malformed or inconsistent ones are only an error if
+// StrictnessFlag::HdrMetadataConsistent is included.
+fn parse_av1_metadata(
+    data: &[u8],
+    strictness: &Strictness,
+    image: &Image,
+) -> AvifResult<Vec<Av1Metadata>> {
+    let strict = strictness.hdr_metadata_consistent();
+    let av1_metadata = obu::parse_metadata_obus(data, strict)?;
+    if !strict {
+        return Ok(av1_metadata);
+    }
+    for metadata in &av1_metadata {
+        match metadata {
+            Av1Metadata::HdrCll(clli) if image.clli.is_some_and(|x| x != *clli) => {
+                return AvifError::bmff_parse_failed(
+                    "HDR_CLL metadata OBU does not match the clli property",
+                );
+            }
+            Av1Metadata::HdrMdcv(mdcv) if image.mdcv.is_some_and(|x| !x.is_equivalent(mdcv)) => {
+                return AvifError::bmff_parse_failed(
+                    "HDR_MDCV metadata OBU does not match the mdcv property",
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(av1_metadata)
+}
+
 impl Decoder {
     pub fn image_count(&self) -> u32 {
         self.image_count
@@ -1356,6 +1430,7 @@ impl Decoder {
             }
 
             self.image.clli = find_property!(color_properties, ContentLightLevelInformation);
+            self.image.mdcv = find_property!(color_properties, MasteringDisplayColourVolume);
             self.image.pasp = find_property!(color_properties, PixelAspectRatio);
             self.image.clap = find_property!(color_properties, CleanAperture);
             self.image.irot_angle = find_property!(color_properties, ImageRotation);
@@ -1682,6 +1757,13 @@ impl Decoder {
             }
             (_, Err(err)) => return Err(err),
         };
+        if decoding_item == DecodingItem::COLOR
+            && tile_index == 0
+            && self.compression_format == CompressionFormat::Avif
+        {
+            self.image.av1_metadata =
+                parse_av1_metadata(data, &self.settings.strictness, &self.image)?;
+        }
         let next_image_result = codec.get_next_image(
             data,
             sample.spatial_id,
@@ -1710,7 +1792,13 @@ impl Decoder {
         if category == Category::Alpha && tile.image.yuv_range == YuvRange::Limited {
             tile.image.alpha_to_full_range()?;
         }
-        tile.image.scale(tile.width, tile.height, category)?;
+        tile.image.scale(
+            tile.width,
+            tile.height,
+            category,
+            ScaleFilter::Box,
+            self.settings.max_threads,
+        )?;
 
         let dst_image = match category {
             Category::Color | Category::Alpha if (decoding_item.item_idx == 0) => &mut self.image,
@@ -1850,6 +1938,13 @@ impl Decoder {
                 }
                 Err(err) => return Err(err),
             };
+            if decoding_item == DecodingItem::COLOR
+                && tile_index == 0
+                && self.compression_format == CompressionFormat::Avif
+            {
+                self.image.av1_metadata =
+                    parse_av1_metadata(data, &self.settings.strictness, &self.image)?;
+            }
             payloads.push(data.to_vec());
         }
         let grid = &self.tile_info[decoding_item.usize()].grid;
@@ -1875,6 +1970,7 @@ impl Decoder {
             first_cell_image: None,
             tile_width: first_tile.width,
             tile_height: first_tile.height,
+            max_threads: self.settings.max_threads,
         };
         let codec = &mut self.codecs[first_tile.codec_index];
         let next_image_result = codec.get_next_image_grid(
@@ -1923,7 +2019,13 @@ impl Decoder {
         // Has to be a grid.
         self.tile_info[decoding_item.usize()].is_grid()
             // Has to be one of the supported codecs.
-            && matches!(codec, CodecChoice::MediaCodec | CodecChoice::Dav1d)
+            && matches!(
+                codec,
+                CodecChoice::MediaCodec
+                    | CodecChoice::Dav1d
+                    | CodecChoice::Libgav1
+                    | CodecChoice::Aom
+            )
             // All the tiles must use the same codec instance.
             && self.tiles[decoding_item.usize()][1..]
                 .iter()
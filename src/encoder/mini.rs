@@ -25,7 +25,10 @@ pub fn is_mini_compatible(enc: &Encoder) -> bool {
     }
 
     // TODO: b/456440247 - Implement with JPEG XL.
-    if enc.settings.codec_choice.actual() != CodecChoice::Aom {
+    if !matches!(
+        enc.settings.codec_choice.actual(),
+        CodecChoice::Aom | CodecChoice::Custom(_)
+    ) {
         return false;
     }
 
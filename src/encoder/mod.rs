@@ -20,6 +20,7 @@ mod sampletransform;
 use crate::encoder::item::*;
 use crate::encoder::mp4box::*;
 
+use crate::codecs::custom::CustomEncoderWrapper;
 use crate::codecs::EncoderConfig;
 use crate::gainmap::GainMap;
 use crate::image::*;
@@ -138,10 +139,10 @@ pub enum Recipe {
 
 impl CodecChoice {
     // Returns the chosen or default codec.
-    pub(crate) fn actual(self) -> Self {
+    pub(crate) fn actual(&self) -> Self {
         match self {
             Self::Auto => Self::Aom,
-            _ => self,
+            _ => self.clone(),
         }
     }
 
@@ -152,12 +153,16 @@ impl CodecChoice {
             Self::Aom => Ok(("av01", Box::<Aom>::default())),
             #[cfg(feature = "jpegxl")]
             Self::Libjxl => Ok(("hxlI", Box::<Libjxl>::default())),
+            Self::Custom(factory) => match factory.create_encoder() {
+                Some(encoder) => Ok(("av01", Box::new(CustomEncoderWrapper { encoder }))),
+                None => AvifError::no_codec_available(),
+            },
             _ => AvifError::no_codec_available(),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Settings {
     pub codec_choice: CodecChoice,
     pub threads: u32,
@@ -215,8 +220,9 @@ impl Settings {
     }
 }
 
+/// An encoded temporal unit, as produced by a codec.
 #[derive(Debug, Default)]
-pub(crate) struct Sample {
+pub struct Sample {
     pub data: Vec<u8>,
     pub sync: bool,
 }
@@ -262,7 +268,7 @@ impl Encoder {
             return AvifError::invalid_argument();
         }
         Ok(Self {
-            settings: *settings,
+            settings: settings.clone(),
             ..Default::default()
         })
     }
@@ -755,7 +761,7 @@ impl Encoder {
 
             if !item.samples.is_empty() {
                 match self.settings.codec_choice {
-                    CodecChoice::Auto | CodecChoice::Aom => {
+                    CodecChoice::Auto | CodecChoice::Aom | CodecChoice::Custom(_) => {
                         // Harvest codec configuration from AV1 sequence header.
                         let sequence_header =
                             Av1SequenceHeader::parse_from_obus(&item.samples[0].data)?;
@@ -209,7 +209,7 @@ impl Encoder {
 
     pub(crate) fn write_ftyp(&self, stream: &mut OStream) -> AvifResult<()> {
         match self.settings.codec_choice.actual() {
-            CodecChoice::Aom => self.write_avif_ftyp(stream),
+            CodecChoice::Aom | CodecChoice::Custom(_) => self.write_avif_ftyp(stream),
             #[cfg(feature = "jpegxl")]
             CodecChoice::Libjxl => self.write_jpegxl_ftyp(stream),
             _ => unreachable!(),
@@ -45,7 +45,7 @@ impl GainMapMetadata {
         Ok(())
     }
 
-    #[cfg(feature = "encoder")]
+    #[cfg(any(feature = "encoder", feature = "jpeg"))]
     fn identical_channels(&self) -> bool {
         self.min[0] == self.min[1]
             && self.min[0] == self.min[2]
@@ -59,7 +59,7 @@ impl GainMapMetadata {
             && self.alternate_offset[0] == self.alternate_offset[2]
     }
 
-    #[cfg(feature = "encoder")]
+    #[cfg(any(feature = "encoder", feature = "jpeg"))]
     pub(crate) fn channel_count(&self) -> u8 {
         if self.identical_channels() {
             1
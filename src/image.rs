@@ -89,6 +89,7 @@ pub struct Image {
     pub matrix_coefficients: MatrixCoefficients,
 
     pub clli: Option<ContentLightLevelInformation>,
+    pub mdcv: Option<MasteringDisplayColourVolume>,
     pub pasp: Option<PixelAspectRatio>,
     pub clap: Option<CleanAperture>,
     pub irot_angle: Option<u8>,
@@ -98,6 +99,9 @@ pub struct Image {
     pub icc: Vec<u8>,
     pub xmp: Vec<u8>,
 
+    // Metadata OBUs found in the AV1 payload of the current frame (of the first cell for grids).
+    pub av1_metadata: Vec<Av1Metadata>,
+
     pub image_sequence_track_present: bool,
     pub progressive_state: ProgressiveState,
 }
@@ -124,6 +128,7 @@ impl Image {
             transfer_characteristics: self.transfer_characteristics,
             matrix_coefficients: self.matrix_coefficients,
             clli: self.clli,
+            mdcv: self.mdcv,
             pasp: self.pasp,
             clap: self.clap,
             irot_angle: self.irot_angle,
@@ -131,6 +136,7 @@ impl Image {
             exif: self.exif.clone(),
             icc: self.icc.clone(),
             xmp: self.xmp.clone(),
+            av1_metadata: self.av1_metadata.clone(),
             image_sequence_track_present: self.image_sequence_track_present,
             progressive_state: self.progressive_state,
             ..Default::default()
@@ -351,7 +357,6 @@ impl Image {
         }))
     }
 
-    #[cfg(any(feature = "dav1d", feature = "libgav1"))]
     pub(crate) fn clear_chroma_planes(&mut self) {
         for plane in [Plane::U, Plane::V] {
             let plane = plane.as_usize();
@@ -468,6 +473,70 @@ impl Image {
         Ok(())
     }
 
+    // Splits the image into grid_columns * grid_rows cells, in raster order, that can be passed to
+    // Encoder::add_image_grid(). The cells of the last column and row are smaller if the
+    // dimensions of the image are not a multiple of the cell dimensions.
+    #[cfg(feature = "encoder")]
+    pub fn split_into_grid(&self, grid_columns: u32, grid_rows: u32) -> AvifResult<Vec<Image>> {
+        if grid_columns == 0 || grid_rows == 0 {
+            return AvifError::invalid_image_grid("grid dimensions must not be zero");
+        }
+        // Chroma samples must not be shared by two cells.
+        let cell_width = self
+            .width
+            .div_ceil(grid_columns)
+            .next_multiple_of(1 << self.yuv_format.chroma_shift_x().0);
+        let cell_height = self
+            .height
+            .div_ceil(grid_rows)
+            .next_multiple_of(1 << self.yuv_format.chroma_shift_y());
+        if checked_mul!(cell_width, grid_columns - 1)? >= self.width
+            || checked_mul!(cell_height, grid_rows - 1)? >= self.height
+        {
+            return AvifError::invalid_image_grid("image is too small for the grid");
+        }
+        let mut cells = Vec::new();
+        for row_index in 0..grid_rows {
+            for column_index in 0..grid_columns {
+                let x = column_index * cell_width;
+                let y = row_index * cell_height;
+                let mut cell = self.shallow_clone();
+                cell.width = std::cmp::min(cell_width, self.width - x);
+                cell.height = std::cmp::min(cell_height, self.height - y);
+                cell.allocate_planes(Category::Color)?;
+                if self.has_alpha() {
+                    cell.allocate_planes(Category::Alpha)?;
+                }
+                for plane in ALL_PLANES {
+                    if !self.has_plane(plane) {
+                        continue;
+                    }
+                    let (x, y) = match plane {
+                        Plane::U | Plane::V => (
+                            self.yuv_format.apply_chroma_shift_x(x),
+                            self.yuv_format.apply_chroma_shift_y(y),
+                        ),
+                        _ => (x, y),
+                    };
+                    let x = usize_from_u32(x)?;
+                    let width = cell.width(plane);
+                    for cell_y in 0..u32_from_usize(cell.height(plane))? {
+                        if self.depth == 8 {
+                            let src_row = &self.row(plane, y + cell_y)?[x..x + width];
+                            cell.row_exact_mut(plane, cell_y)?.copy_from_slice(src_row);
+                        } else {
+                            let src_row = &self.row16(plane, y + cell_y)?[x..x + width];
+                            cell.row16_exact_mut(plane, cell_y)?
+                                .copy_from_slice(src_row);
+                        }
+                    }
+                }
+                cells.push(cell);
+            }
+        }
+        Ok(cells)
+    }
+
     pub(crate) fn copy_from_tile(
         &mut self,
         tile: &Image,
@@ -695,3 +764,90 @@ impl Image {
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "encoder"))]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(PixelFormat::Yuv420, 8, 3, 2, &[(4, 4), (4, 4), (1, 4), (4, 1), (4, 1), (1, 1)])]
+    #[test_case(PixelFormat::Yuv444, 10, 3, 1, &[(3, 5), (3, 5), (3, 5)])]
+    #[test_case(PixelFormat::Yuv400, 8, 1, 2, &[(9, 3), (9, 2)])]
+    fn split_into_grid(
+        yuv_format: PixelFormat,
+        depth: u8,
+        grid_columns: u32,
+        grid_rows: u32,
+        cell_sizes: &[(u32, u32)],
+    ) -> AvifResult<()> {
+        let mut image = Image {
+            width: 9,
+            height: 5,
+            depth,
+            yuv_format,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color)?;
+        image.allocate_planes(Category::Alpha)?;
+        for plane in ALL_PLANES {
+            for y in 0..image.height(plane) as u32 {
+                for x in 0..image.width(plane) {
+                    let value = (plane.as_usize() * 100 + y as usize * 10 + x) as u16;
+                    if depth == 8 {
+                        image.row_mut(plane, y)?[x] = value as u8;
+                    } else {
+                        image.row16_mut(plane, y)?[x] = value;
+                    }
+                }
+            }
+        }
+        let cells = image.split_into_grid(grid_columns, grid_rows)?;
+        let sizes: Vec<_> = cells.iter().map(|cell| (cell.width, cell.height)).collect();
+        assert_eq!(sizes, cell_sizes);
+        for (index, cell) in cells.iter().enumerate() {
+            let x = index as u32 % grid_columns * cells[0].width;
+            let y = index as u32 / grid_columns * cells[0].height;
+            for plane in ALL_PLANES {
+                assert_eq!(cell.has_plane(plane), image.has_plane(plane));
+                let (x, y) = match plane {
+                    Plane::U | Plane::V => (
+                        yuv_format.apply_chroma_shift_x(x),
+                        yuv_format.apply_chroma_shift_y(y),
+                    ),
+                    _ => (x, y),
+                };
+                let x = x as usize;
+                for cell_y in 0..cell.height(plane) as u32 {
+                    let width = cell.width(plane);
+                    if depth == 8 {
+                        assert_eq!(
+                            cell.row_exact(plane, cell_y)?,
+                            &image.row(plane, y + cell_y)?[x..x + width]
+                        );
+                    } else {
+                        assert_eq!(
+                            cell.row16_exact(plane, cell_y)?,
+                            &image.row16(plane, y + cell_y)?[x..x + width]
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test_case(0, 1)]
+    #[test_case(5, 1)]
+    #[test_case(1, 4)]
+    fn split_into_grid_invalid(grid_columns: u32, grid_rows: u32) {
+        let mut image = Image {
+            width: 4,
+            height: 4,
+            depth: 8,
+            yuv_format: PixelFormat::Yuv420,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color).unwrap();
+        assert!(image.split_into_grid(grid_columns, grid_rows).is_err());
+    }
+}
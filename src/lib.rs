@@ -35,9 +35,9 @@ pub mod utils;
 pub mod capi;
 
 /// cbindgen:ignore
-mod codecs;
+pub mod codecs;
 
-mod parser;
+pub mod parser;
 
 use image::*;
 
@@ -55,16 +55,19 @@ impl std::hash::BuildHasher for NonRandomHasherState {
 pub type HashMap<K, V> = std::collections::HashMap<K, V, NonRandomHasherState>;
 pub type HashSet<K> = std::collections::HashSet<K, NonRandomHasherState>;
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum CodecChoice {
     #[default]
     Auto, // Uses the first available codec in the following decreasing order of preference:
-    Aom,        // AVIF (AV1-HEIF) encoder.
+    Aom,        // AVIF (AV1-HEIF) encoder and decoder.
     MediaCodec, // AVIF (AV1-HEIF) and HEIC (HEVC-HEIF) decoder on Android.
     Dav1d,      // AVIF (AV1-HEIF) decoder.
     Libgav1,    // AVIF (AV1-HEIF) decoder.
     #[cfg(feature = "jpegxl")]
     Libjxl, // JPEG XL-HEIF encoder and decoder. WARNING: experimental.
+    // AVIF (AV1-HEIF) decoder and/or encoder implemented outside of this crate. Never selected by
+    // Auto.
+    Custom(std::sync::Arc<dyn codecs::custom::CodecFactory>),
 }
 
 #[repr(C)]
@@ -558,6 +561,92 @@ pub struct ContentLightLevelInformation {
     pub max_pall: u16,
 }
 
+// Section 12.1.7 of ISO/IEC 14496-12 (SMPTE ST 2086). The chromaticity coordinates are in
+// increments of 0.00002 and the luminance values are in increments of 0.0001 cd/m2.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MasteringDisplayColourVolume {
+    pub display_primaries_x: [u16; 3],
+    pub display_primaries_y: [u16; 3],
+    pub white_point_x: u16,
+    pub white_point_y: u16,
+    pub max_display_mastering_luminance: u32,
+    pub min_display_mastering_luminance: u32,
+}
+
+impl MasteringDisplayColourVolume {
+    // Returns true if both volumes are equal, up to the rounding error introduced when converting
+    // from the fixed point representation of the AV1 HDR_MDCV metadata OBU. The order of the
+    // primaries is only a recommendation, so they are compared as a set.
+    pub fn is_equivalent(&self, other: &MasteringDisplayColourVolume) -> bool {
+        let close_u16 = |a: u16, b: u16| a.abs_diff(b) <= 1;
+        let close_u32 = |a: u32, b: u32| a.abs_diff(b) <= 1;
+        const PERMUTATIONS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        PERMUTATIONS.iter().any(|permutation| {
+            (0..3).all(|i| {
+                close_u16(
+                    self.display_primaries_x[i],
+                    other.display_primaries_x[permutation[i]],
+                ) && close_u16(
+                    self.display_primaries_y[i],
+                    other.display_primaries_y[permutation[i]],
+                )
+            })
+        }) && close_u16(self.white_point_x, other.white_point_x)
+            && close_u16(self.white_point_y, other.white_point_y)
+            && close_u32(
+                self.max_display_mastering_luminance,
+                other.max_display_mastering_luminance,
+            )
+            && close_u32(
+                self.min_display_mastering_luminance,
+                other.min_display_mastering_luminance,
+            )
+    }
+}
+
+// See https://aomediacodec.github.io/av1-spec/#metadata-timecode-syntax.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Av1Timecode {
+    pub counting_type: u8,
+    pub full_timestamp: bool,
+    pub discontinuity: bool,
+    pub count_dropped: bool,
+    pub n_frames: u16,
+    pub seconds: Option<u8>,
+    pub minutes: Option<u8>,
+    pub hours: Option<u8>,
+    pub time_offset: Option<u32>,
+}
+
+// See https://aomediacodec.github.io/av1-spec/#metadata-obu-syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Av1Metadata {
+    HdrCll(ContentLightLevelInformation),
+    // Converted to the units of the 'mdcv' box. The primaries are reordered from the AV1 order
+    // (red, green, blue) to the order recommended for the box (green, blue, red).
+    HdrMdcv(MasteringDisplayColourVolume),
+    // Recommendation ITU-T T.35 payload, such as HDR10+ (SMPTE ST 2094-40). The payload does not
+    // include the country code bytes nor the OBU trailing bits.
+    ItutT35 {
+        country_code: u8,
+        country_code_extension: Option<u8>,
+        payload: Vec<u8>,
+    },
+    Timecode(Av1Timecode),
+    // Scalability and reserved metadata types. The payload does not include the OBU trailing bits.
+    Other {
+        metadata_type: u32,
+        payload: Vec<u8>,
+    },
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Nclx {
     pub color_primaries: ColorPrimaries,
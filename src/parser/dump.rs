@@ -0,0 +1,632 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::decoder::track::*;
+use crate::decoder::Extent;
+use crate::decoder::GenericIO;
+use crate::internal_utils::io::DecoderMemoryIO;
+use crate::internal_utils::stream::*;
+use crate::internal_utils::*;
+use crate::parser::mini;
+use crate::parser::mp4box;
+use crate::parser::mp4box::*;
+use crate::*;
+
+// Structural dump of an ISOBMFF file, meant for debugging files that fail to parse or decode.
+// Unlike mp4box::parse(), errors do not abort the dump. They are recorded in the "error" field of
+// the innermost box that could not be parsed.
+
+/// A box of the file with its decoded fields.
+#[derive(Debug, Default)]
+pub struct BoxNode {
+    pub box_type: String,
+    /// Position of the first byte of the box header, from the beginning of the file.
+    pub offset: u64,
+    /// Size of the box in bytes, header included.
+    pub size: u64,
+    pub header_size: u64,
+    /// Only set for FullBoxes.
+    pub version_and_flags: Option<(u8, u32)>,
+    /// Decoded fields as (name, value) pairs, in the order they appear in the box.
+    pub fields: Vec<(String, String)>,
+    pub children: Vec<BoxNode>,
+}
+
+impl BoxNode {
+    fn push_field(&mut self, name: &str, value: impl std::fmt::Display) {
+        self.fields.push((name.into(), value.to_string()));
+    }
+
+    /// Returns the first box of the given type in this subtree, depth first.
+    pub fn find(&self, box_type: &str) -> Option<&BoxNode> {
+        if self.box_type == box_type {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(box_type))
+    }
+
+    /// Returns the value of the first field with the given name.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A property associated with an item through the 'ipma' box.
+#[derive(Debug, Default)]
+pub struct PropertyNode {
+    /// 1-based index of the property in the 'ipco' box.
+    pub index: u16,
+    pub essential: bool,
+    pub box_type: String,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ReferenceNode {
+    pub reference_type: String,
+    pub to_item_id: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ItemNode {
+    pub id: u32,
+    pub item_type: String,
+    pub item_name: String,
+    pub content_type: String,
+    pub properties: Vec<PropertyNode>,
+    /// References from this item ('iref' boxes whose from_item_ID is this item).
+    pub references: Vec<ReferenceNode>,
+    pub construction_method: u8,
+    pub base_offset: u64,
+    pub extents: Vec<Extent>,
+}
+
+#[derive(Debug, Default)]
+pub struct EntityGroupNode {
+    pub grouping_type: String,
+    pub entity_ids: Vec<u32>,
+}
+
+/// The items of the file and how they relate to each other, as seen by the decoder.
+#[derive(Debug, Default)]
+pub struct ItemGraph {
+    pub primary_item_id: u32,
+    /// True if the graph was expanded from a MinimizedImageBox ('mini') rather than read from a
+    /// MetaBox ('meta').
+    pub from_mini: bool,
+    pub items: Vec<ItemNode>,
+    pub entity_groups: Vec<EntityGroupNode>,
+}
+
+/// Returns the tree of boxes contained in |data|. The returned root node has an empty box type
+/// and spans the whole input.
+pub fn parse_box_tree(data: &[u8]) -> BoxNode {
+    let mut root = BoxNode {
+        size: data.len() as u64,
+        ..Default::default()
+    };
+    parse_children(&mut root, data, 0, /*depth=*/ 0);
+    root
+}
+
+/// Parses |data| the same way the decoder does and returns the resulting item graph. Files using
+/// a MinimizedImageBox ('mini') are expanded to the equivalent MetaBox.
+pub fn parse_item_graph(data: &[u8]) -> AvifResult<ItemGraph> {
+    let mut io: GenericIO = Box::new(DecoderMemoryIO {
+        data: data.to_vec(),
+    });
+    let avif_boxes = mp4box::parse(&mut io)?;
+    let meta = avif_boxes.meta;
+    let mut graph = ItemGraph {
+        primary_item_id: meta.primary_item_id,
+        from_mini: avif_boxes.ftyp.needs_mini(),
+        ..Default::default()
+    };
+    for item_info in &meta.iinf {
+        let mut item = ItemNode {
+            id: item_info.item_id,
+            item_type: item_info.item_type.clone(),
+            item_name: item_info.item_name.clone(),
+            content_type: item_info.content_type.clone(),
+            ..Default::default()
+        };
+        for association in meta
+            .iprp
+            .associations
+            .iter()
+            .filter(|x| x.item_id == item.id)
+        {
+            for (index, essential) in &association.associations {
+                let (box_type, description) =
+                    match meta.iprp.properties.get((*index as usize).wrapping_sub(1)) {
+                        Some(property) => describe_property(property),
+                        None => (String::new(), "invalid property index".into()),
+                    };
+                item.properties.push(PropertyNode {
+                    index: *index,
+                    essential: *essential,
+                    box_type,
+                    description,
+                });
+            }
+        }
+        for reference in meta.iref.iter().filter(|x| x.from_item_id == item.id) {
+            item.references.push(ReferenceNode {
+                reference_type: reference.reference_type.clone(),
+                to_item_id: reference.to_item_id,
+            });
+        }
+        if let Some(location) = meta.iloc.items.iter().find(|x| x.item_id == item.id) {
+            item.construction_method = location.construction_method;
+            item.base_offset = location.base_offset;
+            item.extents = location.extents.clone();
+        }
+        graph.items.push(item);
+    }
+    for group in &meta.grpl {
+        graph.entity_groups.push(EntityGroupNode {
+            grouping_type: group.grouping_type.clone(),
+            entity_ids: group.entity_ids.clone(),
+        });
+    }
+    Ok(graph)
+}
+
+fn describe_property(property: &ItemProperty) -> (String, String) {
+    let (box_type, description) = match property {
+        ItemProperty::ImageSpatialExtents(ispe) => {
+            ("ispe", format!("{}x{}", ispe.width, ispe.height))
+        }
+        ItemProperty::PixelInformation(pixi) => {
+            let planes: Vec<String> = pixi
+                .planes
+                .iter()
+                .map(|plane| {
+                    let mut description = format!("{}", plane.depth);
+                    if let Some(channel_idc) = &plane.channel_idc {
+                        description += &format!(" {channel_idc:?}");
+                    }
+                    if let Some(subsampling_type) = &plane.subsampling_type {
+                        description += &format!(" {subsampling_type:?}");
+                    }
+                    if let Some(subsampling_location) = &plane.subsampling_location {
+                        description += &format!(" {subsampling_location:?}");
+                    }
+                    description
+                })
+                .collect();
+            ("pixi", format!("depths [{}]", planes.join(", ")))
+        }
+        ItemProperty::CodecConfiguration(CodecConfiguration::Av1(config)) => (
+            "av1C",
+            format!(
+                "profile {} level {} tier {} depth {} format {:?} chroma_sample_position {:?}",
+                config.seq_profile,
+                config.seq_level_idx0,
+                config.seq_tier0,
+                config.depth(),
+                config.pixel_format(),
+                config.chroma_sample_position
+            ),
+        ),
+        ItemProperty::CodecConfiguration(CodecConfiguration::Hevc(config)) => (
+            "hvcC",
+            format!(
+                "depth {} format {:?} nal_length_size {}",
+                config.bitdepth, config.pixel_format, config.nal_length_size
+            ),
+        ),
+        #[cfg(feature = "jpegxl")]
+        ItemProperty::CodecConfiguration(CodecConfiguration::JpegXl(_)) => ("jxlC", String::new()),
+        ItemProperty::ColorInformation(ColorInformation::Icc(icc)) => {
+            ("colr", format!("icc ({} bytes)", icc.len()))
+        }
+        ItemProperty::ColorInformation(ColorInformation::Nclx(nclx)) => {
+            ("colr", format!("{nclx:?}"))
+        }
+        ItemProperty::ColorInformation(ColorInformation::Unknown) => ("colr", "unknown".into()),
+        ItemProperty::PixelAspectRatio(pasp) => {
+            ("pasp", format!("{}:{}", pasp.h_spacing, pasp.v_spacing))
+        }
+        ItemProperty::AuxiliaryType(aux_type) => ("auxC", aux_type.clone()),
+        ItemProperty::CleanAperture(clap) => ("clap", format!("{clap:?}")),
+        ItemProperty::ImageRotation(angle) => ("irot", format!("{} degrees", *angle as u32 * 90)),
+        ItemProperty::ImageMirror(axis) => ("imir", format!("axis {axis}")),
+        ItemProperty::OperatingPointSelector(op_index) => ("a1op", format!("op_index {op_index}")),
+        ItemProperty::LayerSelector(layer_id) => ("lsel", format!("layer_id {layer_id}")),
+        ItemProperty::AV1LayeredImageIndexing(layer_sizes) => {
+            ("a1lx", format!("layer_sizes {layer_sizes:?}"))
+        }
+        ItemProperty::ContentLightLevelInformation(clli) => ("clli", format!("{clli:?}")),
+        ItemProperty::MasteringDisplayColourVolume(mdcv) => ("mdcv", format!("{mdcv:?}")),
+        ItemProperty::Unknown(box_type) => (box_type.as_str(), String::new()),
+        ItemProperty::Unused => ("free", String::new()),
+    };
+    (box_type.into(), description)
+}
+
+const FULL_BOXES: [&str; 29] = [
+    "meta", "hdlr", "pitm", "iloc", "iinf", "infe", "iref", "ipma", "ispe", "pixi", "auxC", "auxi",
+    "mvhd", "tkhd", "mdhd", "vmhd", "dref", "url ", "urn ", "stsd", "stts", "stss", "stsc", "stsz",
+    "stco", "co64", "ctts", "elst", "sgpd",
+];
+
+const CONTAINER_BOXES: [&str; 12] = [
+    "moov", "trak", "mdia", "minf", "stbl", "dinf", "edts", "udta", "iprp", "ipco", "grpl", "tref",
+];
+
+const PROPERTY_BOXES: [&str; 16] = [
+    "ispe", "pixi", "av1C", "hvcC", "colr", "pasp", "auxC", "auxi", "clap", "irot", "imir", "a1op",
+    "lsel", "a1lx", "clli", "mdcv",
+];
+
+// Real files nest boxes less than 10 levels deep. Crafted files can nest many more, which would
+// overflow the stack.
+const MAX_BOX_DEPTH: usize = 32;
+
+fn is_full_box(box_type: &str, parent_type: &str) -> bool {
+    // Section 8.15.3.1 of ISO/IEC 14496-12: all EntityToGroupBoxes are FullBoxes.
+    parent_type == "grpl" || FULL_BOXES.contains(&box_type)
+}
+
+// Parses the boxes in data, which are the children of parent, or the top-level boxes if depth is
+// 0.
+fn parse_children(parent: &mut BoxNode, data: &[u8], offset: u64, depth: usize) {
+    let top_level = depth == 0;
+    let mut children = Vec::new();
+    let mut error = None;
+    let mut stream = IStream::create(data);
+    while stream.offset < data.len() {
+        let box_start = stream.offset;
+        let header = match parse_header(&mut stream, top_level) {
+            Ok(header) => header,
+            Err(err) => {
+                error = Some(format!(
+                    "cannot parse box at offset {}: {err}",
+                    offset + box_start as u64
+                ));
+                break;
+            }
+        };
+        let header_size = stream.offset - box_start;
+        let payload_size = match header.size {
+            BoxSize::FixedSize(size) => size,
+            BoxSize::UntilEndOfStream => data.len() - stream.offset,
+        };
+        let box_end = stream.offset.saturating_add(payload_size);
+        let truncated = box_end > data.len();
+        // Only top-level boxes can be truncated, since parse_header() rejects nested boxes that
+        // do not fit in their parent. Still show what is available.
+        let mut node = parse_box(
+            header.box_type,
+            &data[box_start..box_end.min(data.len())],
+            header_size,
+            offset + box_start as u64,
+            &parent.box_type,
+            parent.version_and_flags,
+            depth + 1,
+        );
+        if truncated {
+            node.size = (box_end - box_start) as u64;
+            node.push_field(
+                "error",
+                format!("truncated, only {} bytes available", data.len() - box_start),
+            );
+        }
+        children.push(node);
+        if truncated {
+            break;
+        }
+        stream.offset = box_end;
+    }
+    parent.children = children;
+    if let Some(error) = error {
+        parent.push_field("error", error);
+    }
+}
+
+fn parse_box(
+    box_type: String,
+    box_data: &[u8],
+    header_size: usize,
+    offset: u64,
+    parent_type: &str,
+    parent_version_and_flags: Option<(u8, u32)>,
+    depth: usize,
+) -> BoxNode {
+    let mut node = BoxNode {
+        box_type,
+        offset,
+        size: box_data.len() as u64,
+        header_size: header_size as u64,
+        ..Default::default()
+    };
+    let payload = &box_data[header_size..];
+    if is_full_box(&node.box_type, parent_type) {
+        match IStream::create(payload).read_version_and_flags() {
+            Ok(version_and_flags) => node.version_and_flags = Some(version_and_flags),
+            Err(err) => {
+                node.push_field("error", err);
+                return node;
+            }
+        }
+    }
+    let parent_version = parent_version_and_flags.map_or(0, |(version, _)| version);
+    match parse_fields(
+        &mut node,
+        box_data,
+        header_size,
+        parent_type,
+        parent_version,
+    ) {
+        Ok(Some(_)) if depth >= MAX_BOX_DEPTH => node.push_field(
+            "error",
+            format!("children not parsed, boxes are nested more than {MAX_BOX_DEPTH} levels deep"),
+        ),
+        Ok(Some(children_offset)) if children_offset <= payload.len() => parse_children(
+            &mut node,
+            &payload[children_offset..],
+            offset + (header_size + children_offset) as u64,
+            depth,
+        ),
+        Ok(Some(_)) => node.push_field("error", "box is too small to contain its children"),
+        Ok(None) => {}
+        Err(err) => node.push_field("error", err),
+    }
+    node
+}
+
+// Decodes the fields of a box. Returns the offset of the first child box in the payload, if the
+// box is a container.
+fn parse_fields(
+    node: &mut BoxNode,
+    box_data: &[u8],
+    header_size: usize,
+    parent_type: &str,
+    parent_version: u8,
+) -> AvifResult<Option<usize>> {
+    let offset = node.offset;
+    let mut stream = IStream::create(&box_data[header_size..]);
+    match parent_type {
+        "iref" => {
+            // Section 8.11.12.2 of ISO/IEC 14496-12: SingleItemTypeReferenceBox.
+            let read_item_id = |stream: &mut IStream| -> AvifResult<u32> {
+                if parent_version == 0 {
+                    Ok(stream.read_u16()? as u32)
+                } else {
+                    stream.read_u32()
+                }
+            };
+            node.push_field("from_item_id", read_item_id(&mut stream)?);
+            let reference_count = stream.read_u16()?;
+            let mut to_item_ids = Vec::new();
+            for _ in 0..reference_count {
+                to_item_ids.push(read_item_id(&mut stream)?.to_string());
+            }
+            node.push_field("to_item_ids", to_item_ids.join(", "));
+            return Ok(None);
+        }
+        "grpl" => {
+            // Section 8.15.3.2 of ISO/IEC 14496-12: EntityToGroupBox.
+            stream.skip(4)?; // version and flags.
+            node.push_field("group_id", stream.read_u32()?);
+            let num_entities_in_group = stream.read_u32()?;
+            let mut entity_ids = Vec::new();
+            for _ in 0..num_entities_in_group {
+                entity_ids.push(stream.read_u32()?.to_string());
+            }
+            node.push_field("entity_ids", entity_ids.join(", "));
+            return Ok(None);
+        }
+        "tref" => {
+            // Section 8.3.3.2 of ISO/IEC 14496-12: TrackReferenceTypeBox.
+            let mut track_ids = Vec::new();
+            while stream.has_bytes_left()? {
+                track_ids.push(stream.read_u32()?.to_string());
+            }
+            node.push_field("track_ids", track_ids.join(", "));
+            return Ok(None);
+        }
+        "stsd" => {
+            // Sections 8.5.2.2 and 12.1.3.2 of ISO/IEC 14496-12: VisualSampleEntry.
+            stream.skip(6)?; // reserved.
+            node.push_field("data_reference_index", stream.read_u16()?);
+            stream.skip(16)?; // pre_defined and reserved.
+            node.push_field("width", stream.read_u16()?);
+            node.push_field("height", stream.read_u16()?);
+            // horizresolution, vertresolution, reserved, frame_count, compressorname, depth and
+            // pre_defined.
+            stream.skip(50)?;
+            return Ok(Some(stream.offset));
+        }
+        _ => {}
+    }
+    if PROPERTY_BOXES.contains(&node.box_type.as_str()) {
+        // Reuse the ItemPropertyContainerBox parser on this single property.
+        let mut property_stream = IStream::create(box_data);
+        let is_track = parent_type != "ipco";
+        if let Some(property) = parse_ipco(&mut property_stream, is_track)?.first() {
+            let (_, description) = describe_property(property);
+            if !description.is_empty() {
+                node.push_field("value", description);
+            }
+        }
+        return Ok(None);
+    }
+    if CONTAINER_BOXES.contains(&node.box_type.as_str()) {
+        return Ok(Some(0));
+    }
+    match node.box_type.as_str() {
+        "ftyp" => {
+            let ftyp = parse_ftyp(&mut stream)?;
+            node.push_field("major_brand", ftyp.major_brand);
+            // minor_version is stored as a four-character code but is an integer.
+            match <[u8; 4]>::try_from(ftyp.minor_version.as_bytes()) {
+                Ok(bytes) => node.push_field("minor_version", u32::from_be_bytes(bytes)),
+                Err(_) => node.push_field("minor_version", format!("{:?}", ftyp.minor_version)),
+            }
+            node.push_field("compatible_brands", ftyp.compatible_brands.join(", "));
+        }
+        "meta" => return Ok(Some(4)),
+        "hdlr" => node.push_field("handler_type", parse_hdlr(&mut stream)?),
+        "pitm" => node.push_field("item_id", parse_pitm(&mut stream)?),
+        "iloc" => {
+            for item in parse_iloc(&mut stream)?.items {
+                node.push_field(
+                    &format!("item {}", item.item_id),
+                    describe_location(item.construction_method, item.base_offset, &item.extents),
+                );
+            }
+        }
+        "iinf" => {
+            let (version, _flags) = stream.read_version_and_flags()?;
+            let entry_count =
+                if version == 0 { stream.read_u16()? as u32 } else { stream.read_u32()? };
+            node.push_field("entry_count", entry_count);
+            return Ok(Some(stream.offset));
+        }
+        "infe" => {
+            let item_info = parse_infe(&mut stream)?;
+            node.push_field("item_id", item_info.item_id);
+            node.push_field("item_protection_index", item_info.item_protection_index);
+            node.push_field("item_type", item_info.item_type);
+            node.push_field("item_name", item_info.item_name);
+            if !item_info.content_type.is_empty() {
+                node.push_field("content_type", item_info.content_type);
+            }
+        }
+        "iref" => return Ok(Some(4)),
+        "ipma" => {
+            for association in parse_ipma(&mut stream)? {
+                node.push_field(
+                    &format!("item {}", association.item_id),
+                    describe_associations(&association.associations),
+                );
+            }
+        }
+        "idat" => node.push_field("data_size", stream.bytes_left()?),
+        "mini" => {
+            let payload_offset = usize_from_u64(offset)? + header_size;
+            let meta = mini::parse_mini(&mut stream, payload_offset)?;
+            node.push_field("primary_item_id", meta.primary_item_id);
+            for item_info in &meta.iinf {
+                node.push_field(&format!("item {}", item_info.item_id), &item_info.item_type);
+            }
+            node.push_field("expanded", "see the item graph for the equivalent MetaBox");
+        }
+        "mvhd" => {
+            // Section 8.2.2.2 of ISO/IEC 14496-12.
+            let (version, _flags) = stream.read_version_and_flags()?;
+            if version == 1 {
+                stream.skip(16)?; // creation_time and modification_time.
+                node.push_field("timescale", stream.read_u32()?);
+                node.push_field("duration", stream.read_u64()?);
+            } else {
+                stream.skip(8)?; // creation_time and modification_time.
+                node.push_field("timescale", stream.read_u32()?);
+                node.push_field("duration", stream.read_u32()?);
+            }
+        }
+        "tkhd" => {
+            let mut track = Track::default();
+            parse_tkhd(&mut stream, &mut track)?;
+            node.push_field("track_id", track.id);
+            node.push_field("duration", track.track_duration);
+            node.push_field("width", track.width);
+            node.push_field("height", track.height);
+        }
+        "mdhd" => {
+            let mut track = Track::default();
+            parse_mdhd(&mut stream, &mut track)?;
+            node.push_field("timescale", track.media_timescale);
+            node.push_field("duration", track.media_duration);
+        }
+        "elst" => {
+            let mut track = Track::default();
+            parse_elst(&mut stream, &mut track)?;
+            node.push_field("repeating", track.is_repeating);
+            if track.is_repeating {
+                node.push_field("segment_duration", track.segment_duration);
+            }
+        }
+        "dref" | "stsd" => {
+            stream.skip(4)?; // version and flags.
+            node.push_field("entry_count", stream.read_u32()?);
+            return Ok(Some(stream.offset));
+        }
+        "stts" => {
+            let mut sample_table = SampleTable::default();
+            parse_stts(&mut stream, &mut sample_table)?;
+            node.push_field("entry_count", sample_table.time_to_sample.len());
+            node.push_field(
+                "sample_count",
+                sample_table
+                    .time_to_sample
+                    .iter()
+                    .map(|x| x.sample_count as u64)
+                    .sum::<u64>(),
+            );
+        }
+        "stss" => {
+            let mut sample_table = SampleTable::default();
+            parse_stss(&mut stream, &mut sample_table)?;
+            node.push_field("entry_count", sample_table.sync_samples.len());
+        }
+        "stsc" => {
+            let mut sample_table = SampleTable::default();
+            parse_stsc(&mut stream, &mut sample_table)?;
+            node.push_field("entry_count", sample_table.sample_to_chunk.len());
+        }
+        "stsz" => {
+            let mut sample_table = SampleTable::default();
+            parse_stsz(&mut stream, &mut sample_table)?;
+            match sample_table.sample_size {
+                SampleSize::FixedSize(size) => node.push_field("sample_size", size),
+                SampleSize::Sizes(sizes) => node.push_field("sample_count", sizes.len()),
+            }
+        }
+        "stco" | "co64" => {
+            let mut sample_table = SampleTable::default();
+            parse_stco(&mut stream, &mut sample_table, node.box_type == "co64")?;
+            node.push_field("entry_count", sample_table.chunk_offsets.len());
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+fn describe_location(construction_method: u8, base_offset: u64, extents: &[Extent]) -> String {
+    let extents: Vec<String> = extents
+        .iter()
+        .map(|extent| format!("{}+{}", extent.offset, extent.size))
+        .collect();
+    format!(
+        "construction_method {construction_method}, base_offset {base_offset}, extents [{}]",
+        extents.join(", ")
+    )
+}
+
+fn describe_associations(associations: &[(u16, bool)]) -> String {
+    associations
+        .iter()
+        .map(|(index, essential)| match essential {
+            true => format!("{index} (essential)"),
+            false => format!("{index}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
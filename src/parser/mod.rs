@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub mod exif;
-pub mod mini;
-pub mod mp4box;
-pub mod obu;
+pub mod dump;
+pub(crate) mod exif;
+pub(crate) mod mini;
+pub(crate) mod mp4box;
+pub(crate) mod obu;
@@ -24,6 +24,8 @@ use crate::internal_utils::stream::*;
 use crate::internal_utils::*;
 use crate::utils::clap::CleanAperture;
 use crate::utils::pixels::ChannelIdc;
+use crate::utils::Fraction;
+use crate::utils::UFraction;
 use crate::*;
 
 #[derive(Debug, PartialEq)]
@@ -33,9 +35,9 @@ pub enum BoxSize {
 }
 
 #[derive(Debug)]
-struct BoxHeader {
-    size: BoxSize,
-    box_type: String,
+pub(crate) struct BoxHeader {
+    pub size: BoxSize,
+    pub box_type: String,
 }
 
 impl BoxHeader {
@@ -363,6 +365,7 @@ pub enum ItemProperty {
     LayerSelector(u16),
     AV1LayeredImageIndexing([usize; 3]),
     ContentLightLevelInformation(ContentLightLevelInformation),
+    MasteringDisplayColourVolume(MasteringDisplayColourVolume),
     Unknown(String),
     Unused, // Such as a FreeBox 'free'.
 }
@@ -426,7 +429,7 @@ pub struct AvifBoxes {
     pub tracks: Vec<Track>,
 }
 
-fn parse_header(stream: &mut IStream, top_level: bool) -> AvifResult<BoxHeader> {
+pub(crate) fn parse_header(stream: &mut IStream, top_level: bool) -> AvifResult<BoxHeader> {
     // Section 4.2.2 of ISO/IEC 14496-12.
     let start_offset = stream.offset;
     // unsigned int(32) size;
@@ -497,7 +500,7 @@ fn parse_truncated_ftyp(stream: &mut IStream) -> FileTypeBox {
     }
 }
 
-fn parse_ftyp(stream: &mut IStream) -> AvifResult<FileTypeBox> {
+pub(crate) fn parse_ftyp(stream: &mut IStream) -> AvifResult<FileTypeBox> {
     // Section 4.3.2 of ISO/IEC 14496-12.
     // unsigned int(32) major_brand;
     let major_brand = stream.read_string(4)?;
@@ -521,7 +524,7 @@ fn parse_ftyp(stream: &mut IStream) -> AvifResult<FileTypeBox> {
     })
 }
 
-fn parse_hdlr(stream: &mut IStream) -> AvifResult<String> {
+pub(crate) fn parse_hdlr(stream: &mut IStream) -> AvifResult<String> {
     // Section 8.4.3.2 of ISO/IEC 14496-12.
     let (_version, _flags) = stream.read_and_enforce_version_and_flags(0)?;
     // unsigned int(32) pre_defined = 0;
@@ -545,7 +548,7 @@ fn parse_hdlr(stream: &mut IStream) -> AvifResult<String> {
     Ok(handler_type)
 }
 
-fn parse_iloc(stream: &mut IStream) -> AvifResult<ItemLocationBox> {
+pub(crate) fn parse_iloc(stream: &mut IStream) -> AvifResult<ItemLocationBox> {
     // Section 8.11.3.2 of ISO/IEC 14496-12.
     let (version, _flags) = stream.read_version_and_flags()?;
     if version > 2 {
@@ -645,7 +648,7 @@ fn parse_iloc(stream: &mut IStream) -> AvifResult<ItemLocationBox> {
 }
 
 // Returns the primary item ID.
-fn parse_pitm(stream: &mut IStream) -> AvifResult<u32> {
+pub(crate) fn parse_pitm(stream: &mut IStream) -> AvifResult<u32> {
     // Section 8.11.4.2 of ISO/IEC 14496-12.
     let (version, _flags) = stream.read_version_and_flags()?;
     if version == 0 {
@@ -1090,7 +1093,35 @@ impl ContentLightLevelInformation {
     }
 }
 
-fn parse_ipco(stream: &mut IStream, is_track: bool) -> AvifResult<Vec<ItemProperty>> {
+fn parse_mdcv(stream: &mut IStream) -> AvifResult<ItemProperty> {
+    Ok(ItemProperty::MasteringDisplayColourVolume(
+        MasteringDisplayColourVolume::parse(stream)?,
+    ))
+}
+
+impl MasteringDisplayColourVolume {
+    pub(crate) fn parse(stream: &mut IStream) -> AvifResult<MasteringDisplayColourVolume> {
+        // Section 12.1.7.2 of ISO/IEC 14496-12.
+        let mut mdcv = MasteringDisplayColourVolume::default();
+        for c in 0..3 {
+            // unsigned int(16) display_primaries_x;
+            mdcv.display_primaries_x[c] = stream.read_u16()?;
+            // unsigned int(16) display_primaries_y;
+            mdcv.display_primaries_y[c] = stream.read_u16()?;
+        }
+        // unsigned int(16) white_point_x;
+        mdcv.white_point_x = stream.read_u16()?;
+        // unsigned int(16) white_point_y;
+        mdcv.white_point_y = stream.read_u16()?;
+        // unsigned int(32) max_display_mastering_luminance;
+        mdcv.max_display_mastering_luminance = stream.read_u32()?;
+        // unsigned int(32) min_display_mastering_luminance;
+        mdcv.min_display_mastering_luminance = stream.read_u32()?;
+        Ok(mdcv)
+    }
+}
+
+pub(crate) fn parse_ipco(stream: &mut IStream, is_track: bool) -> AvifResult<Vec<ItemProperty>> {
     // Section 8.11.14.2 of ISO/IEC 14496-12.
     let mut properties: Vec<ItemProperty> = Vec::new();
     while stream.has_bytes_left()? {
@@ -1111,6 +1142,7 @@ fn parse_ipco(stream: &mut IStream, is_track: bool) -> AvifResult<Vec<ItemProper
             "lsel" => properties.push(parse_lsel(&mut sub_stream)?),
             "a1lx" => properties.push(parse_a1lx(&mut sub_stream)?),
             "clli" => properties.push(parse_clli(&mut sub_stream)?),
+            "mdcv" => properties.push(parse_mdcv(&mut sub_stream)?),
             #[cfg(feature = "heic")]
             "hvcC" => properties.push(parse_hvcC(&mut sub_stream)?),
             #[cfg(feature = "jpegxl")]
@@ -1121,7 +1153,7 @@ fn parse_ipco(stream: &mut IStream, is_track: bool) -> AvifResult<Vec<ItemProper
     Ok(properties)
 }
 
-fn parse_ipma(stream: &mut IStream) -> AvifResult<Vec<ItemPropertyAssociation>> {
+pub(crate) fn parse_ipma(stream: &mut IStream) -> AvifResult<Vec<ItemPropertyAssociation>> {
     // Section 8.11.14.2 of ISO/IEC 14496-12.
     let (version, flags) = stream.read_version_and_flags()?;
     // unsigned int(32) entry_count;
@@ -1200,7 +1232,7 @@ fn parse_iprp(stream: &mut IStream) -> AvifResult<ItemPropertyBox> {
     Ok(iprp)
 }
 
-fn parse_infe(stream: &mut IStream) -> AvifResult<ItemInfo> {
+pub(crate) fn parse_infe(stream: &mut IStream) -> AvifResult<ItemInfo> {
     // Section 8.11.6.2 of ISO/IEC 14496-12.
     let (version, _flags) = stream.read_version_and_flags()?;
     if version != 2 && version != 3 {
@@ -1399,7 +1431,7 @@ fn parse_meta(stream: &mut IStream) -> AvifResult<MetaBox> {
     Ok(meta)
 }
 
-fn parse_tkhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
+pub(crate) fn parse_tkhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     // Section 8.3.2.2 of ISO/IEC 14496-12.
     let (version, _flags) = stream.read_version_and_flags()?;
     if version == 1 {
@@ -1458,7 +1490,7 @@ fn parse_tkhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     Ok(())
 }
 
-fn parse_mdhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
+pub(crate) fn parse_mdhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     // Section 8.4.2.2 of ISO/IEC 14496-12.
     let (version, _flags) = stream.read_version_and_flags()?;
     if version == 1 {
@@ -1494,7 +1526,7 @@ fn parse_mdhd(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     Ok(())
 }
 
-fn parse_stco(
+pub(crate) fn parse_stco(
     stream: &mut IStream,
     sample_table: &mut SampleTable,
     large_offset: bool,
@@ -1517,7 +1549,7 @@ fn parse_stco(
     Ok(())
 }
 
-fn parse_stsc(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
+pub(crate) fn parse_stsc(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
     // Section 8.7.4.2 of ISO/IEC 14496-12.
     let (_version, _flags) = stream.read_and_enforce_version_and_flags(0)?;
     // unsigned int(32) entry_count;
@@ -1550,7 +1582,7 @@ fn parse_stsc(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResul
     Ok(())
 }
 
-fn parse_stsz(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
+pub(crate) fn parse_stsz(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
     // Section 8.7.3.2.1 of ISO/IEC 14496-12.
     let (_version, _flags) = stream.read_and_enforce_version_and_flags(0)?;
     // unsigned int(32) sample_size;
@@ -1571,7 +1603,7 @@ fn parse_stsz(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResul
     Ok(())
 }
 
-fn parse_stss(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
+pub(crate) fn parse_stss(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
     // Section 8.6.2.2 of ISO/IEC 14496-12.
     let (_version, _flags) = stream.read_and_enforce_version_and_flags(0)?;
     // unsigned int(32) entry_count;
@@ -1584,7 +1616,7 @@ fn parse_stss(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResul
     Ok(())
 }
 
-fn parse_stts(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
+pub(crate) fn parse_stts(stream: &mut IStream, sample_table: &mut SampleTable) -> AvifResult<()> {
     // Section 8.6.1.2.2 of ISO/IEC 14496-12.
     let (_version, _flags) = stream.read_and_enforce_version_and_flags(0)?;
     // unsigned int(32) entry_count;
@@ -1816,7 +1848,7 @@ fn parse_tref(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     Ok(())
 }
 
-fn parse_elst(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
+pub(crate) fn parse_elst(stream: &mut IStream, track: &mut Track) -> AvifResult<()> {
     if track.elst_seen {
         return AvifError::bmff_parse_failed("more than one elst box was found for track");
     }
@@ -2077,6 +2109,16 @@ pub(crate) fn parse_tmap(stream: &mut IStream) -> AvifResult<GainMapMetadata> {
     if version != 0 {
         return AvifError::not_implemented();
     }
+    parse_gainmap_metadata(stream, /*allow_common_denominator=*/ false)
+}
+
+// GainMapMetadata syntax as per clause C.2.2 of ISO 21496-1. Also used for the metadata of Ultra
+// HDR JPEG files. The use_common_denominator flag is only honored if allow_common_denominator is
+// true. Otherwise it is a reserved bit, as in the 'tmap' box.
+pub(crate) fn parse_gainmap_metadata(
+    stream: &mut IStream,
+    allow_common_denominator: bool,
+) -> AvifResult<GainMapMetadata> {
     // unsigned int(16) minimum_version;
     let minimum_version = stream.read_u16()?;
     let supported_version = 0;
@@ -2092,31 +2134,63 @@ pub(crate) fn parse_tmap(stream: &mut IStream) -> AvifResult<GainMapMetadata> {
     let channel_count = if is_multichannel { 3 } else { 1 };
     // unsigned int(1) use_base_colour_space;
     metadata.use_base_color_space = stream.read_bool()?;
-    // unsigned int(6) reserved;
-    stream.skip_bits(6)?;
-
-    // unsigned int(32) base_hdr_headroom_numerator;
-    // unsigned int(32) base_hdr_headroom_denominator;
-    metadata.base_hdr_headroom = stream.read_ufraction()?;
-    // unsigned int(32) alternate_hdr_headroom_numerator;
-    // unsigned int(32) alternate_hdr_headroom_denominator;
-    metadata.alternate_hdr_headroom = stream.read_ufraction()?;
-    for i in 0..channel_count {
-        // int(32) gain_map_min_numerator;
-        // unsigned int(32) gain_map_min_denominator
-        metadata.min[i] = stream.read_fraction()?;
-        // int(32) gain_map_max_numerator;
-        // unsigned int(32) gain_map_max_denominator;
-        metadata.max[i] = stream.read_fraction()?;
-        // unsigned int(32) gamma_numerator;
-        // unsigned int(32) gamma_denominator;
-        metadata.gamma[i] = stream.read_ufraction()?;
-        // int(32) base_offset_numerator;
-        // unsigned int(32) base_offset_denominator;
-        metadata.base_offset[i] = stream.read_fraction()?;
-        // int(32) alternate_offset_numerator;
-        // unsigned int(32) alternate_offset_denominator;
-        metadata.alternate_offset[i] = stream.read_fraction()?;
+    let use_common_denominator = if allow_common_denominator {
+        // unsigned int(2) reserved;
+        stream.skip_bits(2)?;
+        // unsigned int(1) use_common_denominator;
+        let use_common_denominator = stream.read_bool()?;
+        // unsigned int(3) reserved;
+        stream.skip_bits(3)?;
+        use_common_denominator
+    } else {
+        // unsigned int(6) reserved;
+        stream.skip_bits(6)?;
+        false
+    };
+
+    if use_common_denominator {
+        // unsigned int(32) common_denominator;
+        let denominator = stream.read_u32()?;
+        // unsigned int(32) base_hdr_headroom_numerator;
+        metadata.base_hdr_headroom = UFraction(stream.read_u32()?, denominator);
+        // unsigned int(32) alternate_hdr_headroom_numerator;
+        metadata.alternate_hdr_headroom = UFraction(stream.read_u32()?, denominator);
+        for i in 0..channel_count {
+            // int(32) gain_map_min_numerator;
+            metadata.min[i] = Fraction(stream.read_i32()?, denominator);
+            // int(32) gain_map_max_numerator;
+            metadata.max[i] = Fraction(stream.read_i32()?, denominator);
+            // unsigned int(32) gamma_numerator;
+            metadata.gamma[i] = UFraction(stream.read_u32()?, denominator);
+            // int(32) base_offset_numerator;
+            metadata.base_offset[i] = Fraction(stream.read_i32()?, denominator);
+            // int(32) alternate_offset_numerator;
+            metadata.alternate_offset[i] = Fraction(stream.read_i32()?, denominator);
+        }
+    } else {
+        // unsigned int(32) base_hdr_headroom_numerator;
+        // unsigned int(32) base_hdr_headroom_denominator;
+        metadata.base_hdr_headroom = stream.read_ufraction()?;
+        // unsigned int(32) alternate_hdr_headroom_numerator;
+        // unsigned int(32) alternate_hdr_headroom_denominator;
+        metadata.alternate_hdr_headroom = stream.read_ufraction()?;
+        for i in 0..channel_count {
+            // int(32) gain_map_min_numerator;
+            // unsigned int(32) gain_map_min_denominator
+            metadata.min[i] = stream.read_fraction()?;
+            // int(32) gain_map_max_numerator;
+            // unsigned int(32) gain_map_max_denominator;
+            metadata.max[i] = stream.read_fraction()?;
+            // unsigned int(32) gamma_numerator;
+            // unsigned int(32) gamma_denominator;
+            metadata.gamma[i] = stream.read_ufraction()?;
+            // int(32) base_offset_numerator;
+            // unsigned int(32) base_offset_denominator;
+            metadata.base_offset[i] = stream.read_fraction()?;
+            // int(32) alternate_offset_numerator;
+            // unsigned int(32) alternate_offset_denominator;
+            metadata.alternate_offset[i] = stream.read_fraction()?;
+        }
     }
 
     // Fill the remaining values by copying those from the first channel.
@@ -2197,9 +2271,48 @@ pub(crate) fn parse_sato(stream: &mut IStream, num_inputs: usize) -> AvifResult<
 
 #[cfg(test)]
 mod tests {
+    use crate::internal_utils::stream::IStream;
     use crate::parser::mp4box;
+    use crate::utils::Fraction;
+    use crate::utils::UFraction;
     use crate::AvifResult;
 
+    // Single channel GainMapMetadata with the given flags, followed by the given values.
+    fn gainmap_metadata(flags: u8, values: &[u32]) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 0, flags];
+        for value in values {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn parse_tmap_reserved_bits() -> AvifResult<()> {
+        // The bit used as use_common_denominator by ISO 21496-1 is reserved in the 'tmap' box.
+        let values = [0, 1, 2, 1, 0, 1, 3, 1, 1, 1, 1, 64, 1, 64];
+        for flags in [0x00, 0x08, 0x3F] {
+            let mut data = vec![0];
+            data.extend_from_slice(&gainmap_metadata(flags, &values));
+            let metadata = mp4box::parse_tmap(&mut IStream::create(&data))?;
+            assert_eq!(metadata.alternate_hdr_headroom, UFraction(2, 1));
+            assert_eq!(metadata.max, [Fraction(3, 1); 3]);
+            assert_eq!(metadata.base_offset, [Fraction(1, 64); 3]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_gainmap_metadata_common_denominator() -> AvifResult<()> {
+        let data = gainmap_metadata(0x08, &[64, 0, 128, 0, 192, 64, 1, 1]);
+        let metadata = mp4box::parse_gainmap_metadata(&mut IStream::create(&data), true)?;
+        assert_eq!(metadata.alternate_hdr_headroom, UFraction(128, 64));
+        assert_eq!(metadata.max, [Fraction(192, 64); 3]);
+        assert_eq!(metadata.base_offset, [Fraction(1, 64); 3]);
+        // Without the flag, the same bytes are too short for explicit denominators.
+        assert!(mp4box::parse_gainmap_metadata(&mut IStream::create(&data), false).is_err());
+        Ok(())
+    }
+
     #[test]
     fn peek_compatible_file_type() -> AvifResult<()> {
         let buf = [
@@ -18,12 +18,53 @@ use crate::internal_utils::*;
 use crate::parser::mp4box::*;
 use crate::*;
 
+const OBU_SEQUENCE_HEADER: u8 = 1;
+const OBU_METADATA: u8 = 5;
+
+const METADATA_TYPE_HDR_CLL: u32 = 1;
+const METADATA_TYPE_HDR_MDCV: u32 = 2;
+const METADATA_TYPE_ITUT_T35: u32 = 4;
+const METADATA_TYPE_TIMECODE: u32 = 5;
+
 #[derive(Debug)]
 struct ObuHeader {
     obu_type: u8,
     size: u32,
 }
 
+impl ObuHeader {
+    fn parse(stream: &mut IStream) -> AvifResult<ObuHeader> {
+        // Section 5.3.2 of AV1 specification.
+        // https://aomediacodec.github.io/av1-spec/#obu-header-syntax
+        let obu_forbidden_bit = stream.read_bits(1)?;
+        if obu_forbidden_bit != 0 {
+            return AvifError::bmff_parse_failed("invalid obu_forbidden_bit");
+        }
+        let obu_type = stream.read_bits(4)? as u8;
+        let obu_extension_flag = stream.read_bool()?;
+        let obu_has_size_field = stream.read_bool()?;
+        // obu_reserved_1bit
+        stream.skip_bits(1)?; // "The value is ignored by a decoder."
+
+        if obu_extension_flag {
+            // temporal_id
+            stream.skip_bits(3)?;
+            // spatial_id
+            stream.skip_bits(2)?;
+            // extension_header_reserved_3bits
+            stream.skip_bits(3)?;
+        }
+
+        let size = if obu_has_size_field {
+            stream.read_uleb128()?
+        } else {
+            u32_from_usize(stream.bytes_left()?)? // sz - 1 - obu_extension_flag
+        };
+
+        Ok(ObuHeader { obu_type, size })
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Av1SequenceHeader {
     reduced_still_picture_header: bool,
@@ -256,43 +297,12 @@ impl Av1SequenceHeader {
         Ok(())
     }
 
-    fn parse_obu_header(stream: &mut IStream) -> AvifResult<ObuHeader> {
-        // Section 5.3.2 of AV1 specification.
-        // https://aomediacodec.github.io/av1-spec/#obu-header-syntax
-        let obu_forbidden_bit = stream.read_bits(1)?;
-        if obu_forbidden_bit != 0 {
-            return AvifError::bmff_parse_failed("invalid obu_forbidden_bit");
-        }
-        let obu_type = stream.read_bits(4)? as u8;
-        let obu_extension_flag = stream.read_bool()?;
-        let obu_has_size_field = stream.read_bool()?;
-        // obu_reserved_1bit
-        stream.skip_bits(1)?; // "The value is ignored by a decoder."
-
-        if obu_extension_flag {
-            // temporal_id
-            stream.skip_bits(3)?;
-            // spatial_id
-            stream.skip_bits(2)?;
-            // extension_header_reserved_3bits
-            stream.skip_bits(3)?;
-        }
-
-        let size = if obu_has_size_field {
-            stream.read_uleb128()?
-        } else {
-            u32_from_usize(stream.bytes_left()?)? // sz - 1 - obu_extension_flag
-        };
-
-        Ok(ObuHeader { obu_type, size })
-    }
-
     pub(crate) fn parse_from_obus(data: &[u8]) -> AvifResult<Self> {
         let mut stream = IStream::create(data);
 
         while stream.has_bytes_left()? {
-            let obu = Self::parse_obu_header(&mut stream)?;
-            if obu.obu_type != /*OBU_SEQUENCE_HEADER=*/1 {
+            let obu = ObuHeader::parse(&mut stream)?;
+            if obu.obu_type != OBU_SEQUENCE_HEADER {
                 // Not a sequence header. Skip this obu.
                 stream.skip(usize_from_u32(obu.size)?)?;
                 continue;
@@ -316,3 +326,253 @@ impl Av1SequenceHeader {
         AvifError::bmff_parse_failed("could not parse sequence header")
     }
 }
+
+// Returns the payload without the trailing_bits() that end a byte aligned OBU.
+fn strip_trailing_bits(payload: &[u8]) -> &[u8] {
+    match payload.iter().rposition(|x| *x != 0) {
+        Some(index) if payload[index] == 0x80 => &payload[..index],
+        Some(index) => &payload[..=index],
+        None => &[],
+    }
+}
+
+// Rounds value * numerator / denominator to the nearest integer, saturating at u32::MAX.
+fn rescale(value: u32, numerator: u64, denominator: u64) -> u32 {
+    let rescaled = (value as u64 * numerator + denominator / 2) / denominator;
+    u32::try_from(rescaled).unwrap_or(u32::MAX)
+}
+
+fn parse_metadata_hdr_mdcv(stream: &mut IStream) -> AvifResult<MasteringDisplayColourVolume> {
+    // Section 5.8.4 of AV1 specification.
+    // https://aomediacodec.github.io/av1-spec/#metadata-high-dynamic-range-mastering-display-color-volume-syntax
+    let mut primaries_x = [0u32; 3];
+    let mut primaries_y = [0u32; 3];
+    for i in 0..3 {
+        // primary_chromaticity_x[i]
+        primaries_x[i] = stream.read_u16()? as u32;
+        // primary_chromaticity_y[i]
+        primaries_y[i] = stream.read_u16()? as u32;
+    }
+    let white_point_x = stream.read_u16()? as u32;
+    let white_point_y = stream.read_u16()? as u32;
+    let luminance_max = stream.read_u32()?;
+    let luminance_min = stream.read_u32()?;
+
+    // The chromaticity coordinates are 0.16 fixed-point values in AV1 and are in increments of
+    // 0.00002 in the 'mdcv' box. luminance_max is a 24.8 fixed-point value and luminance_min is a
+    // 18.14 fixed-point value in AV1. Both are in increments of 0.0001 cd/m2 in the 'mdcv' box.
+    let chromaticity = |value: u32| rescale(value, 50000, 1 << 16) as u16;
+    let mut mdcv = MasteringDisplayColourVolume::default();
+    // AV1 signals red, green, blue. The 'mdcv' box recommends green, blue, red.
+    for (box_index, av1_index) in [1, 2, 0].into_iter().enumerate() {
+        mdcv.display_primaries_x[box_index] = chromaticity(primaries_x[av1_index]);
+        mdcv.display_primaries_y[box_index] = chromaticity(primaries_y[av1_index]);
+    }
+    mdcv.white_point_x = chromaticity(white_point_x);
+    mdcv.white_point_y = chromaticity(white_point_y);
+    mdcv.max_display_mastering_luminance = rescale(luminance_max, 10000, 1 << 8);
+    mdcv.min_display_mastering_luminance = rescale(luminance_min, 10000, 1 << 14);
+    Ok(mdcv)
+}
+
+fn parse_metadata_timecode(stream: &mut IStream) -> AvifResult<Av1Timecode> {
+    // Section 5.8.7 of AV1 specification.
+    // https://aomediacodec.github.io/av1-spec/#metadata-timecode-syntax
+    let mut timecode = Av1Timecode {
+        counting_type: stream.read_bits(5)? as u8,
+        full_timestamp: stream.read_bool()?,
+        discontinuity: stream.read_bool()?,
+        count_dropped: stream.read_bool()?,
+        n_frames: stream.read_bits(9)? as u16,
+        ..Default::default()
+    };
+    if timecode.full_timestamp {
+        timecode.seconds = Some(stream.read_bits(6)? as u8);
+        timecode.minutes = Some(stream.read_bits(6)? as u8);
+        timecode.hours = Some(stream.read_bits(5)? as u8);
+    } else if stream.read_bool()? {
+        // seconds_flag
+        timecode.seconds = Some(stream.read_bits(6)? as u8);
+        if stream.read_bool()? {
+            // minutes_flag
+            timecode.minutes = Some(stream.read_bits(6)? as u8);
+            if stream.read_bool()? {
+                // hours_flag
+                timecode.hours = Some(stream.read_bits(5)? as u8);
+            }
+        }
+    }
+    let time_offset_length = stream.read_bits(5)?;
+    if time_offset_length > 0 {
+        timecode.time_offset = Some(stream.read_bits(time_offset_length as usize)?);
+    }
+    Ok(timecode)
+}
+
+fn parse_metadata(stream: &mut IStream) -> AvifResult<Av1Metadata> {
+    // Section 5.8.1 of AV1 specification.
+    // https://aomediacodec.github.io/av1-spec/#metadata-obu-syntax
+    let metadata_type = stream.read_uleb128()?;
+    Ok(match metadata_type {
+        METADATA_TYPE_HDR_CLL => Av1Metadata::HdrCll(ContentLightLevelInformation::parse(stream)?),
+        METADATA_TYPE_HDR_MDCV => Av1Metadata::HdrMdcv(parse_metadata_hdr_mdcv(stream)?),
+        METADATA_TYPE_ITUT_T35 => {
+            // Section 5.8.2 of AV1 specification.
+            let country_code = stream.read_u8()?;
+            let country_code_extension =
+                if country_code == 0xFF { Some(stream.read_u8()?) } else { None };
+            let payload = stream.get_slice(stream.bytes_left()?)?;
+            Av1Metadata::ItutT35 {
+                country_code,
+                country_code_extension,
+                payload: strip_trailing_bits(payload).to_vec(),
+            }
+        }
+        METADATA_TYPE_TIMECODE => Av1Metadata::Timecode(parse_metadata_timecode(stream)?),
+        _ => {
+            let payload = stream.get_slice(stream.bytes_left()?)?;
+            Av1Metadata::Other {
+                metadata_type,
+                payload: strip_trailing_bits(payload).to_vec(),
+            }
+        }
+    })
+}
+
+// Returns all the metadata OBUs found in the given temporal unit, in bitstream order. Only the OBU
+// headers are read, except for metadata OBUs. If |strict| is false, malformed metadata OBUs are
+// skipped and a malformed OBU header ends the search instead of returning an error.
+pub(crate) fn parse_metadata_obus(data: &[u8], strict: bool) -> AvifResult<Vec<Av1Metadata>> {
+    let mut stream = IStream::create(data);
+    let mut metadata = Vec::new();
+    while stream.has_bytes_left()? {
+        let obu_stream = ObuHeader::parse(&mut stream).and_then(|obu| {
+            let obu_size = usize_from_u32(obu.size)?;
+            if obu.obu_type != OBU_METADATA {
+                stream.skip(obu_size)?;
+                return Ok(None);
+            }
+            Ok(Some(stream.sub_stream(&BoxSize::FixedSize(obu_size))?))
+        });
+        let mut obu_stream = match obu_stream {
+            Ok(Some(obu_stream)) => obu_stream,
+            Ok(None) => continue,
+            Err(err) if strict => return Err(err),
+            Err(_) => break,
+        };
+        match parse_metadata(&mut obu_stream) {
+            Ok(value) => metadata.push(value),
+            Err(err) if strict => return Err(err),
+            Err(_) => {}
+        }
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPORAL_DELIMITER: [u8; 2] = [0x12, 0x00];
+
+    #[test]
+    fn parse_metadata_hdr_cll_and_itut_t35() {
+        let mut data = TEMPORAL_DELIMITER.to_vec();
+        // OBU_METADATA, METADATA_TYPE_HDR_CLL, max_cll=1000, max_fall=400, trailing bits.
+        data.extend_from_slice(&[0x2A, 0x06, 0x01, 0x03, 0xE8, 0x01, 0x90, 0x80]);
+        // OBU_METADATA, METADATA_TYPE_ITUT_T35, country code, payload, trailing bits.
+        data.extend_from_slice(&[0x2A, 0x07, 0x04, 0xB5, 0x00, 0x3C, 0x00, 0x01, 0x80]);
+        let metadata = parse_metadata_obus(&data, true).unwrap();
+        assert_eq!(
+            metadata,
+            vec![
+                Av1Metadata::HdrCll(ContentLightLevelInformation {
+                    max_cll: 1000,
+                    max_pall: 400
+                }),
+                Av1Metadata::ItutT35 {
+                    country_code: 0xB5,
+                    country_code_extension: None,
+                    payload: vec![0x00, 0x3C, 0x00, 0x01],
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_metadata_hdr_mdcv() {
+        // BT.2020 primaries and D65 white point, 1000 cd/m2 max, 0.005 cd/m2 min.
+        let data = [
+            0x2A, 0x1A, 0x02, 0xB5, 0x3F, 0x4A, 0xC1, 0x2B, 0x85, 0xCC, 0x08, 0x21, 0x89, 0x0B,
+            0xC7, 0x50, 0x0D, 0x54, 0x39, 0x00, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x00, 0x52, 0x80,
+        ];
+        let metadata = parse_metadata_obus(&data, true).unwrap();
+        let expected = MasteringDisplayColourVolume {
+            display_primaries_x: [8500, 6550, 35400],
+            display_primaries_y: [39850, 2300, 14600],
+            white_point_x: 15635,
+            white_point_y: 16450,
+            max_display_mastering_luminance: 10000000,
+            min_display_mastering_luminance: 50,
+        };
+        assert_eq!(metadata, vec![Av1Metadata::HdrMdcv(expected)]);
+        let mut rounded = expected;
+        rounded.white_point_x += 1;
+        assert!(rounded.is_equivalent(&expected));
+        rounded.white_point_x += 1;
+        assert!(!rounded.is_equivalent(&expected));
+        // The primaries may be listed in any order.
+        let mut reordered = expected;
+        reordered.display_primaries_x.rotate_left(1);
+        reordered.display_primaries_y.rotate_left(1);
+        assert!(reordered.is_equivalent(&expected));
+        // But the x and y coordinates of a primary must stay together.
+        reordered.display_primaries_y.rotate_left(1);
+        assert!(!reordered.is_equivalent(&expected));
+    }
+
+    #[test]
+    fn parse_metadata_timecode() {
+        // OBU_METADATA, METADATA_TYPE_TIMECODE, counting_type=0, full_timestamp_flag=1,
+        // n_frames=1, 01:02:03, time_offset_length=0.
+        let data = [0x2A, 0x06, 0x05, 0x04, 0x00, 0x86, 0x10, 0x41];
+        let metadata = parse_metadata_obus(&data, true).unwrap();
+        assert_eq!(
+            metadata,
+            vec![Av1Metadata::Timecode(Av1Timecode {
+                full_timestamp: true,
+                n_frames: 1,
+                seconds: Some(3),
+                minutes: Some(2),
+                hours: Some(1),
+                ..Default::default()
+            })]
+        );
+    }
+
+    #[test]
+    fn parse_metadata_truncated() {
+        assert!(parse_metadata_obus(&[0x2A, 0x06, 0x01, 0x03], true).is_err());
+        assert_eq!(
+            parse_metadata_obus(&[0x2A, 0x06, 0x01, 0x03], false),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn parse_metadata_malformed_is_skipped() {
+        let mut data = TEMPORAL_DELIMITER.to_vec();
+        // OBU_METADATA, METADATA_TYPE_HDR_CLL with a payload that is one byte too short.
+        data.extend_from_slice(&[0x2A, 0x04, 0x01, 0x03, 0xE8, 0x01]);
+        // OBU_METADATA, METADATA_TYPE_HDR_CLL, max_cll=1000, max_fall=400, trailing bits.
+        data.extend_from_slice(&[0x2A, 0x06, 0x01, 0x03, 0xE8, 0x01, 0x90, 0x80]);
+        assert!(parse_metadata_obus(&data, true).is_err());
+        assert_eq!(
+            parse_metadata_obus(&data, false),
+            Ok(vec![Av1Metadata::HdrCll(ContentLightLevelInformation {
+                max_cll: 1000,
+                max_pall: 400
+            })])
+        );
+    }
+}
@@ -37,6 +37,59 @@ impl ColorPrimaries {
             ColorPrimaries::Ebu3213 => expand_coeffs(0.2318, 0.096),
         }
     }
+
+    // Returns the chromaticity coordinates of the primaries and of the white point, in the order
+    // rX, rY, gX, gY, bX, bY, wX, wY. These values come from Table 2 of
+    // https://www.itu.int/rec/T-REC-H.273-201612-S
+    pub(crate) fn chromaticities(&self) -> Option<[f32; 8]> {
+        match self {
+            ColorPrimaries::Unknown | ColorPrimaries::Unspecified => None,
+            ColorPrimaries::Srgb => Some([0.64, 0.33, 0.3, 0.6, 0.15, 0.06, 0.3127, 0.329]),
+            ColorPrimaries::Bt470m => Some([0.67, 0.33, 0.21, 0.71, 0.14, 0.08, 0.31, 0.316]),
+            ColorPrimaries::Bt470bg => Some([0.64, 0.33, 0.29, 0.6, 0.15, 0.06, 0.3127, 0.329]),
+            ColorPrimaries::Bt601 | ColorPrimaries::Smpte240 => {
+                Some([0.63, 0.34, 0.31, 0.595, 0.155, 0.07, 0.3127, 0.329])
+            }
+            ColorPrimaries::GenericFilm => {
+                Some([0.681, 0.319, 0.243, 0.692, 0.145, 0.049, 0.31, 0.316])
+            }
+            ColorPrimaries::Bt2020 => {
+                Some([0.708, 0.292, 0.17, 0.797, 0.131, 0.046, 0.3127, 0.329])
+            }
+            ColorPrimaries::Xyz => Some([1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.3333, 0.3333]),
+            ColorPrimaries::Smpte431 => Some([0.68, 0.32, 0.265, 0.69, 0.15, 0.06, 0.314, 0.351]),
+            ColorPrimaries::Smpte432 => Some([0.68, 0.32, 0.265, 0.69, 0.15, 0.06, 0.3127, 0.329]),
+            ColorPrimaries::Ebu3213 => {
+                Some([0.63, 0.34, 0.295, 0.605, 0.155, 0.077, 0.3127, 0.329])
+            }
+        }
+    }
+
+    // Returns the color primaries whose chromaticities match the given ones, if any.
+    pub(crate) fn find(chromaticities: &[f32; 8]) -> Option<Self> {
+        const TOLERANCE: f32 = 0.001;
+        [
+            ColorPrimaries::Srgb,
+            ColorPrimaries::Bt470m,
+            ColorPrimaries::Bt470bg,
+            ColorPrimaries::Bt601,
+            ColorPrimaries::GenericFilm,
+            ColorPrimaries::Bt2020,
+            ColorPrimaries::Xyz,
+            ColorPrimaries::Smpte431,
+            ColorPrimaries::Smpte432,
+            ColorPrimaries::Ebu3213,
+        ]
+        .into_iter()
+        .find(|color_primaries| {
+            color_primaries
+                .chromaticities()
+                .unwrap()
+                .iter()
+                .zip(chromaticities)
+                .all(|(a, b)| (a - b).abs() <= TOLERANCE)
+        })
+    }
 }
 
 fn calculate_yuv_coefficients_from_cicp(
@@ -86,4 +139,27 @@ mod tests {
             &[0.212f32, 1f32 - 0.212 - 0.087, 0.087f32], // Kr,Kg,Kb as https://en.wikipedia.org/wiki/YCbCr#SMPTE_240M_conversion
         );
     }
+
+    #[test]
+    fn find_color_primaries() {
+        for color_primaries in [
+            ColorPrimaries::Srgb,
+            ColorPrimaries::Bt470bg,
+            ColorPrimaries::Bt2020,
+            ColorPrimaries::Smpte432,
+        ] {
+            let mut chromaticities = color_primaries.chromaticities().unwrap();
+            assert_eq!(ColorPrimaries::find(&chromaticities), Some(color_primaries));
+            chromaticities[0] += 0.0005;
+            assert_eq!(ColorPrimaries::find(&chromaticities), Some(color_primaries));
+        }
+        // Smpte240 has the same chromaticities as Bt601.
+        let chromaticities = ColorPrimaries::Smpte240.chromaticities().unwrap();
+        assert_eq!(
+            ColorPrimaries::find(&chromaticities),
+            Some(ColorPrimaries::Bt601)
+        );
+        assert_eq!(ColorPrimaries::Unspecified.chromaticities(), None);
+        assert_eq!(ColorPrimaries::find(&[0.5; 8]), None);
+    }
 }
@@ -21,8 +21,13 @@ pub mod sharpyuv;
 
 pub mod alpha;
 pub mod coeffs;
+pub mod resize;
 pub mod rgb;
 pub mod rgb_impl;
+mod rgb_simd;
+pub mod scale_impl;
+mod sharpyuv_impl;
+pub(crate) mod transfer;
 
 // If libyuv is not present, add placeholder functions so that the library will build successfully
 // without it.
@@ -51,30 +56,15 @@ pub mod libyuv {
     ) -> AvifResult<Option<()>> {
         Ok(None) // Not implemented.
     }
-
-    impl image::Image {
-        pub(crate) fn scale(
-            &mut self,
-            width: u32,
-            height: u32,
-            _category: Category,
-        ) -> AvifResult<()> {
-            if self.width == width && self.height == height {
-                return Ok(());
-            }
-            AvifError::not_implemented()
-        }
-    }
 }
 
-// If libsharpyuv is not present, add placeholder functions so that the library will build successfully
-// without it.
+// If libsharpyuv is not present, use the native implementation.
 #[cfg(not(feature = "sharpyuv"))]
 pub mod sharpyuv {
     use crate::reformat::*;
     use crate::*;
 
-    pub(crate) fn rgb_to_yuv(_rgb: &rgb::Image, _image: &mut image::Image) -> AvifResult<()> {
-        AvifError::not_implemented()
+    pub(crate) fn rgb_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> AvifResult<()> {
+        super::sharpyuv_impl::rgb_to_yuv(rgb, image)
     }
 }
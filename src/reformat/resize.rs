@@ -0,0 +1,535 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::rgb;
+use super::scale_impl::*;
+use super::transfer::*;
+
+use crate::image;
+use crate::internal_utils::*;
+use crate::utils::clap::CropRect;
+use crate::*;
+
+pub use super::scale_impl::ScaleFilter;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ResizeMode {
+    // The output is exactly the requested size. The aspect ratio is not preserved.
+    #[default]
+    Exact,
+    // The output is the largest image that fits within the requested size while preserving the
+    // display aspect ratio. One of the output dimensions may be smaller than requested.
+    Fit,
+    // The output is exactly the requested size. The image is scaled to cover the requested size
+    // while preserving the display aspect ratio, and the excess is cropped evenly on both sides.
+    Fill,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResizeSettings {
+    pub filter: ScaleFilter,
+    pub mode: ResizeMode,
+    // If true, the samples are converted to linear light using the transfer characteristics of
+    // the image before resampling, and converted back afterwards.
+    pub linear_light: bool,
+    pub max_threads: u32,
+}
+
+// The area of the source image to scale, and the dimensions to scale it to.
+#[derive(Debug, PartialEq)]
+struct ResizeGeometry {
+    rect: CropRect,
+    width: u32,
+    height: u32,
+}
+
+impl ResizeGeometry {
+    fn create(
+        src_width: u32,
+        src_height: u32,
+        pasp: Option<PixelAspectRatio>,
+        width: u32,
+        height: u32,
+        mode: ResizeMode,
+        pixel_format: PixelFormat,
+    ) -> AvifResult<Self> {
+        if src_width == 0 || src_height == 0 || width == 0 || height == 0 {
+            return AvifError::invalid_argument();
+        }
+        let mut rect = CropRect {
+            x: 0,
+            y: 0,
+            width: src_width,
+            height: src_height,
+        };
+        // Width of one source pixel relative to its height, as displayed.
+        let pixel_aspect_ratio = match pasp {
+            Some(pasp) if pasp.h_spacing != 0 && pasp.v_spacing != 0 => {
+                pasp.h_spacing as f64 / pasp.v_spacing as f64
+            }
+            _ => 1.0,
+        };
+        let display_width = src_width as f64 * pixel_aspect_ratio;
+        let display_height = src_height as f64;
+        match mode {
+            ResizeMode::Exact => Ok(Self {
+                rect,
+                width,
+                height,
+            }),
+            ResizeMode::Fit => {
+                let scale = (width as f64 / display_width).min(height as f64 / display_height);
+                Ok(Self {
+                    rect,
+                    width: ((display_width * scale).round() as u32).clamp(1, width),
+                    height: ((display_height * scale).round() as u32).clamp(1, height),
+                })
+            }
+            ResizeMode::Fill => {
+                let target_aspect_ratio = width as f64 / height as f64;
+                if display_width / display_height > target_aspect_ratio {
+                    let display_crop_width = display_height * target_aspect_ratio;
+                    rect.width = ((display_crop_width / pixel_aspect_ratio).round() as u32)
+                        .clamp(1, src_width);
+                } else {
+                    rect.height =
+                        ((display_width / target_aspect_ratio).round() as u32).clamp(1, src_height);
+                }
+                rect.x = (src_width - rect.width) / 2;
+                rect.y = (src_height - rect.height) / 2;
+                // The crop offsets have to be aligned with the chroma samples.
+                if pixel_format.chroma_shift_x().0 != 0 {
+                    rect.x &= !1;
+                }
+                if pixel_format.chroma_shift_y() != 0 {
+                    rect.y &= !1;
+                }
+                Ok(Self {
+                    rect,
+                    width,
+                    height,
+                })
+            }
+        }
+    }
+}
+
+impl rgb::Image {
+    // Resizes the image to width x height (see ResizeMode). pasp is the pixel aspect ratio of the
+    // source image and transfer_characteristics is only used if settings.linear_light is true.
+    // Fit and Fill output images have square pixels. The alpha channel is taken into account
+    // during resampling whether the image is premultiplied or not, so that fully transparent
+    // pixels do not bleed into their neighbors.
+    pub fn resize(
+        &mut self,
+        width: u32,
+        height: u32,
+        pasp: Option<PixelAspectRatio>,
+        transfer_characteristics: TransferCharacteristics,
+        settings: &ResizeSettings,
+    ) -> AvifResult<()> {
+        let geometry = ResizeGeometry::create(
+            self.width,
+            self.height,
+            pasp,
+            width,
+            height,
+            settings.mode,
+            PixelFormat::Yuv444,
+        )?;
+        self.resize_with_geometry(&geometry, transfer_characteristics, settings)
+    }
+
+    fn resize_with_geometry(
+        &mut self,
+        geometry: &ResizeGeometry,
+        transfer_characteristics: TransferCharacteristics,
+        settings: &ResizeSettings,
+    ) -> AvifResult<()> {
+        if matches!(self.format, rgb::Format::Rgb565 | rgb::Format::Rgba1010102) || self.is_float {
+            return AvifError::not_implemented();
+        }
+        if self.pixels.is_none() || self.row_bytes == 0 {
+            return AvifError::reformat_failed();
+        }
+        let channels = self.channel_count() as usize;
+        let alpha_offset = if self.has_alpha() { Some(self.format.alpha_offset()) } else { None };
+        let max_channel = self.max_channel_f();
+        let rect = &geometry.rect;
+
+        // Normalize the samples of the source area, linearize them if requested and premultiply
+        // them by alpha so that the color of transparent pixels does not contribute.
+        let src_width = rect.width as usize;
+        let src_len = checked_mul!(src_width * channels, rect.height as usize)?;
+        let mut src: Vec<f32> = create_vec_exact(src_len)?;
+        for y in rect.y..rect.y + rect.height {
+            let start = rect.x as usize * channels;
+            let end = start + src_width * channels;
+            if self.depth == 8 {
+                src.extend(
+                    self.row(y)?[start..end]
+                        .iter()
+                        .map(|v| *v as f32 / max_channel),
+                );
+            } else {
+                src.extend(
+                    self.row16(y)?[start..end]
+                        .iter()
+                        .map(|v| *v as f32 / max_channel),
+                );
+            }
+        }
+        for pixel in src.chunks_exact_mut(channels) {
+            let alpha = alpha_offset.map(|offset| pixel[offset]);
+            for (i, value) in pixel.iter_mut().enumerate() {
+                if Some(i) == alpha_offset {
+                    continue;
+                }
+                let mut v = *value;
+                if settings.linear_light {
+                    if self.premultiply_alpha {
+                        v = if alpha.unwrap() > 0.0 { v / alpha.unwrap() } else { 0.0 };
+                    }
+                    v = to_linear(transfer_characteristics, v);
+                    if let Some(alpha) = alpha {
+                        v *= alpha;
+                    }
+                } else if !self.premultiply_alpha {
+                    if let Some(alpha) = alpha {
+                        v *= alpha;
+                    }
+                }
+                *value = v;
+            }
+        }
+
+        let dst_width = geometry.width as usize;
+        let dst_height = geometry.height as usize;
+        let dst_len = checked_mul!(dst_width * channels, dst_height)?;
+        let mut dst: Vec<f32> = create_vec_exact(dst_len)?;
+        dst.resize(dst_len, 0.0);
+        scale_plane(
+            &src,
+            &PlaneGeometry {
+                width: src_width,
+                height: rect.height as usize,
+                stride: src_width * channels,
+            },
+            &mut dst,
+            &PlaneGeometry {
+                width: dst_width,
+                height: dst_height,
+                stride: dst_width * channels,
+            },
+            channels,
+            1.0,
+            settings.filter,
+            settings.max_threads,
+        )?;
+
+        // Undo the transformations and quantize.
+        for pixel in dst.chunks_exact_mut(channels) {
+            let alpha = alpha_offset.map(|offset| pixel[offset]);
+            for (i, value) in pixel.iter_mut().enumerate() {
+                if Some(i) == alpha_offset {
+                    continue;
+                }
+                let mut v = *value;
+                if settings.linear_light {
+                    if let Some(alpha) = alpha {
+                        v = if alpha > 0.0 { v / alpha } else { 0.0 };
+                    }
+                    v = from_linear(transfer_characteristics, v);
+                    if self.premultiply_alpha {
+                        v *= alpha.unwrap();
+                    }
+                } else if !self.premultiply_alpha {
+                    if let Some(alpha) = alpha {
+                        v = if alpha > 0.0 { v / alpha } else { 0.0 };
+                    }
+                }
+                // Premultiplied color cannot exceed alpha.
+                *value =
+                    if self.premultiply_alpha { v.min(alpha.unwrap_or(1.0)) } else { v.min(1.0) };
+            }
+        }
+        self.width = geometry.width;
+        self.height = geometry.height;
+        self.allocate()?;
+        let row_len = dst_width * channels;
+        for (y, dst_row) in dst.chunks_exact(row_len).enumerate() {
+            let y = u32_from_usize(y)?;
+            if self.depth == 8 {
+                for (sample, value) in self.row_mut(y)?.iter_mut().zip(dst_row) {
+                    *sample = (value * max_channel + 0.5).clamp(0.0, max_channel) as u8;
+                }
+            } else {
+                for (sample, value) in self.row16_mut(y)?.iter_mut().zip(dst_row) {
+                    *sample = (value * max_channel + 0.5).clamp(0.0, max_channel) as u16;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl image::Image {
+    // Resizes the image to width x height (see ResizeMode). Fit and Fill take the pasp of the
+    // image into account and output images with square pixels (pasp is cleared). clap is always
+    // cleared since it no longer applies.
+    //
+    // Linear light resampling and resampling of straight alpha images go through an intermediate
+    // 16-bit RGB image, so that the color of transparent pixels does not bleed into their
+    // neighbors. Otherwise, the planes are resampled directly.
+    pub fn resize(&mut self, width: u32, height: u32, settings: &ResizeSettings) -> AvifResult<()> {
+        if !self.has_plane(image::Plane::Y) || !self.depth_valid() {
+            return AvifError::reformat_failed();
+        }
+        let geometry = ResizeGeometry::create(
+            self.width,
+            self.height,
+            self.pasp,
+            width,
+            height,
+            settings.mode,
+            self.yuv_format,
+        )?;
+        if settings.linear_light || (self.has_alpha() && !self.alpha_premultiplied) {
+            let mut rgb = rgb::Image::create_from_yuv(self);
+            rgb.depth = 16;
+            rgb.format = if self.has_alpha() { rgb::Format::Rgba } else { rgb::Format::Rgb };
+            rgb.premultiply_alpha = self.alpha_premultiplied;
+            rgb.allocate()?;
+            rgb.convert_from_yuv(self)?;
+            rgb.resize_with_geometry(&geometry, self.transfer_characteristics, settings)?;
+            let mut resized = self.shallow_clone();
+            resized.width = geometry.width;
+            resized.height = geometry.height;
+            rgb.convert_to_yuv(&mut resized)?;
+            *self = resized;
+        } else {
+            let (src_width, src_height) = (self.width, self.height);
+            for category in [Category::Color, Category::Alpha] {
+                self.width = src_width;
+                self.height = src_height;
+                self.scale_region_rust(
+                    &geometry.rect,
+                    geometry.width,
+                    geometry.height,
+                    category,
+                    settings.filter,
+                    settings.max_threads,
+                )?;
+            }
+        }
+        if settings.mode != ResizeMode::Exact {
+            self.pasp = None;
+        }
+        self.clap = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(ResizeMode::Exact, None, (50, 50), CropRect { x: 0, y: 0, width: 200, height: 100 }, (50, 50))]
+    #[test_case(ResizeMode::Fit, None, (50, 50), CropRect { x: 0, y: 0, width: 200, height: 100 }, (50, 25))]
+    #[test_case(ResizeMode::Fit, Some((1, 2)), (50, 50), CropRect { x: 0, y: 0, width: 200, height: 100 }, (50, 50))]
+    #[test_case(ResizeMode::Fit, Some((1, 4)), (50, 50), CropRect { x: 0, y: 0, width: 200, height: 100 }, (25, 50))]
+    #[test_case(ResizeMode::Fill, None, (50, 50), CropRect { x: 50, y: 0, width: 100, height: 100 }, (50, 50))]
+    #[test_case(ResizeMode::Fill, Some((1, 4)), (50, 50), CropRect { x: 0, y: 24, width: 200, height: 50 }, (50, 50))]
+    #[test_case(ResizeMode::Fill, None, (30, 10), CropRect { x: 0, y: 16, width: 200, height: 67 }, (30, 10))]
+    fn geometry(
+        mode: ResizeMode,
+        pasp: Option<(u32, u32)>,
+        size: (u32, u32),
+        expected_rect: CropRect,
+        expected_size: (u32, u32),
+    ) {
+        let pasp = pasp.map(|(h_spacing, v_spacing)| PixelAspectRatio {
+            h_spacing,
+            v_spacing,
+        });
+        let geometry =
+            ResizeGeometry::create(200, 100, pasp, size.0, size.1, mode, PixelFormat::Yuv420)
+                .unwrap();
+        assert_eq!(geometry.rect, expected_rect);
+        assert_eq!((geometry.width, geometry.height), expected_size);
+    }
+
+    fn rgba_image(width: u32, height: u32, pixels: &[[u8; 4]]) -> rgb::Image {
+        let mut rgb = rgb::Image {
+            width,
+            height,
+            depth: 8,
+            format: rgb::Format::Rgba,
+            ..Default::default()
+        };
+        rgb.allocate().unwrap();
+        for y in 0..height {
+            let row = rgb.row_mut(y).unwrap();
+            for x in 0..width as usize {
+                row[x * 4..x * 4 + 4].copy_from_slice(&pixels[y as usize * width as usize + x]);
+            }
+        }
+        rgb
+    }
+
+    #[test]
+    fn straight_alpha_does_not_bleed() {
+        // Transparent black next to opaque red.
+        let mut rgb = rgba_image(2, 1, &[[0, 0, 0, 0], [255, 0, 0, 255]]);
+        let settings = ResizeSettings {
+            filter: ScaleFilter::Box,
+            ..Default::default()
+        };
+        assert!(rgb
+            .resize(1, 1, None, TransferCharacteristics::Srgb, &settings)
+            .is_ok());
+        assert_eq!(rgb.row(0).unwrap(), [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn premultiplied_alpha() {
+        let mut rgb = rgba_image(2, 1, &[[0, 0, 0, 0], [200, 0, 0, 200]]);
+        rgb.premultiply_alpha = true;
+        assert!(rgb
+            .resize(
+                1,
+                1,
+                None,
+                TransferCharacteristics::Srgb,
+                &ResizeSettings::default()
+            )
+            .is_ok());
+        assert_eq!(rgb.row(0).unwrap(), [100, 0, 0, 100]);
+    }
+
+    #[test]
+    fn linear_light() {
+        let mut rgb = rgba_image(2, 1, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+        let mut gamma = rgba_image(2, 1, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+        let settings = ResizeSettings {
+            linear_light: true,
+            ..Default::default()
+        };
+        assert!(rgb
+            .resize(1, 1, None, TransferCharacteristics::Srgb, &settings)
+            .is_ok());
+        assert!(gamma
+            .resize(
+                1,
+                1,
+                None,
+                TransferCharacteristics::Srgb,
+                &ResizeSettings::default()
+            )
+            .is_ok());
+        // Half of the light of white is encoded as 188 in sRGB.
+        assert_eq!(rgb.row(0).unwrap(), [188, 188, 188, 255]);
+        assert_eq!(gamma.row(0).unwrap(), [128, 128, 128, 255]);
+    }
+
+    #[test_case(false, false)]
+    #[test_case(true, false)]
+    #[test_case(false, true)]
+    fn yuv_image(linear_light: bool, with_alpha: bool) {
+        let mut image = image::Image {
+            width: 64,
+            height: 32,
+            depth: 8,
+            yuv_format: PixelFormat::Yuv420,
+            matrix_coefficients: MatrixCoefficients::Bt601,
+            pasp: Some(PixelAspectRatio {
+                h_spacing: 1,
+                v_spacing: 2,
+            }),
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color).unwrap();
+        image.fill_plane_with_value(image::Plane::Y, 100).unwrap();
+        image.fill_plane_with_value(image::Plane::U, 128).unwrap();
+        image.fill_plane_with_value(image::Plane::V, 128).unwrap();
+        if with_alpha {
+            image.allocate_planes(Category::Alpha).unwrap();
+            image.fill_plane_with_value(image::Plane::A, 255).unwrap();
+            image.alpha_present = true;
+        }
+        let settings = ResizeSettings {
+            filter: ScaleFilter::Lanczos,
+            mode: ResizeMode::Fit,
+            linear_light,
+            max_threads: 2,
+        };
+        assert!(image.resize(20, 20, &settings).is_ok());
+        assert_eq!((image.width, image.height), (20, 20));
+        assert!(image.pasp.is_none());
+        assert_eq!(image.has_plane(image::Plane::A), with_alpha);
+        // Only linear light and straight alpha resampling go through a lossy RGB round trip.
+        let tolerance = if linear_light || with_alpha { 1 } else { 0 };
+        for y in 0..image.height {
+            for value in image.row_exact(image::Plane::Y, y).unwrap() {
+                assert!(value.abs_diff(100) <= tolerance, "{value}");
+            }
+        }
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn yuv_straight_alpha_does_not_bleed(linear_light: bool) {
+        // An opaque gray square surrounded by a transparent black border.
+        let mut image = image::Image {
+            width: 8,
+            height: 8,
+            depth: 8,
+            yuv_format: PixelFormat::Yuv444,
+            yuv_range: image::YuvRange::Full,
+            matrix_coefficients: MatrixCoefficients::Bt601,
+            alpha_present: true,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color).unwrap();
+        image.allocate_planes(Category::Alpha).unwrap();
+        image.fill_plane_with_value(image::Plane::U, 128).unwrap();
+        image.fill_plane_with_value(image::Plane::V, 128).unwrap();
+        for y in 0..8 {
+            let opaque = (1..7).contains(&y);
+            for x in 0..8 {
+                let opaque = opaque && (1..7).contains(&x);
+                image.row_mut(image::Plane::Y, y).unwrap()[x] = if opaque { 200 } else { 0 };
+                image.row_mut(image::Plane::A, y).unwrap()[x] = if opaque { 255 } else { 0 };
+            }
+        }
+        let settings = ResizeSettings {
+            filter: ScaleFilter::Box,
+            linear_light,
+            ..Default::default()
+        };
+        assert!(image.resize(4, 4, &settings).is_ok());
+        for y in 0..4 {
+            let alpha = image.row_exact(image::Plane::A, y).unwrap().to_vec();
+            let luma = image.row_exact(image::Plane::Y, y).unwrap();
+            for (alpha, luma) in alpha.iter().zip(luma) {
+                // The border pixels are partially transparent but keep the color of the square.
+                assert!(*alpha > 0);
+                assert!(luma.abs_diff(200) <= 1, "{luma}");
+            }
+            let expected_alpha = if y == 0 || y == 3 { 128 } else { 255 };
+            assert_eq!(alpha[1], expected_alpha);
+        }
+    }
+}
@@ -136,6 +136,7 @@ pub enum ChromaDownsampling {
     Fastest,
     BestQuality,
     Average,
+    // Only supported for 4:2:0. Converting to 4:2:2 with this mode fails with NotImplemented.
     SharpYuv,
 }
 
@@ -154,7 +155,7 @@ pub struct Image {
     pub row_bytes: u32,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum AlphaMultiplyMode {
     #[default]
     NoOp,
@@ -464,6 +465,13 @@ impl Image {
         if self.format == Format::Rgb565 || self.is_float {
             return AvifError::not_implemented();
         }
+        // Sharp YUV is only implemented for 4:2:0. 4:4:4 and 4:0:0 are not downsampled, so they
+        // are converted the same way with any ChromaDownsampling.
+        if self.chroma_downsampling == ChromaDownsampling::SharpYuv
+            && image.yuv_format == PixelFormat::Yuv422
+        {
+            return AvifError::not_implemented();
+        }
         image.allocate_planes(Category::Color)?;
         // TODO: b/410088660 - add a setting to ignore alpha channel.
         let has_alpha = self.has_alpha();
@@ -480,7 +488,9 @@ impl Image {
             rgb_impl::rgb_gray_to_yuv(self, image)?;
         } else {
             let mut conversion_complete = false;
-            if self.chroma_downsampling == ChromaDownsampling::SharpYuv {
+            if self.chroma_downsampling == ChromaDownsampling::SharpYuv
+                && image.yuv_format == PixelFormat::Yuv420
+            {
                 sharpyuv::rgb_to_yuv(self, image)?;
                 conversion_complete = true;
             } else if alpha_multiply_mode == AlphaMultiplyMode::NoOp {
@@ -14,6 +14,7 @@
 
 use super::rgb;
 use super::rgb::*;
+use super::rgb_simd;
 
 use crate::image::Plane;
 use crate::image::YuvRange;
@@ -507,12 +508,22 @@ pub(crate) fn yuv_to_rgb_fast(
     rgb: &mut rgb::Image,
 ) -> AvifResult<Option<()>> {
     let mode: Mode = image.into();
-    Ok(match mode {
-        Mode::Identity => match (image.depth, rgb.depth, image.yuv_range) {
+    if mode == Mode::Identity {
+        let converted = match (image.depth, rgb.depth, image.yuv_range) {
             (8, 8, YuvRange::Full) => identity_yuv8_to_rgb8_full_range(image, rgb)?,
             (16, 16, YuvRange::Full) => identity_yuv16_to_rgb16_full_range(image, rgb)?,
             _ => None,
-        },
+        };
+        if converted.is_some() {
+            return Ok(converted);
+        }
+    }
+    if let Some(kernel) = rgb_simd::Kernel::detect() {
+        if rgb_simd::yuv_to_rgb(kernel, image, rgb, AlphaMultiplyMode::NoOp, false)?.is_some() {
+            return Ok(Some(()));
+        }
+    }
+    Ok(match mode {
         Mode::YuvCoefficients(kr, kg, kb) => {
             let has_color = image.yuv_format != PixelFormat::Yuv400;
             if !cfg!(feature = "android_mediacodec") {
@@ -530,11 +541,11 @@ pub(crate) fn yuv_to_rgb_fast(
                 (true, false, false) => yuv8_to_rgb16_monochrome(image, rgb, kr, kg, kb),
             }?)
         }
-        Mode::Ycgco | Mode::YcgcoRe | Mode::YcgcoRo => None, // Not implemented
+        Mode::Identity | Mode::Ycgco | Mode::YcgcoRe | Mode::YcgcoRo => None, // Not implemented
     })
 }
 
-fn bias_and_range_y(image: &image::Image) -> (f32, f32) {
+pub(crate) fn bias_and_range_y(image: &image::Image) -> (f32, f32) {
     // Formula specified in ISO/IEC 23091-2.
     if image.yuv_range == YuvRange::Limited {
         (
@@ -546,7 +557,7 @@ fn bias_and_range_y(image: &image::Image) -> (f32, f32) {
     }
 }
 
-fn bias_and_range_uv(image: &image::Image) -> (f32, f32) {
+pub(crate) fn bias_and_range_uv(image: &image::Image) -> (f32, f32) {
     // Formula specified in ISO/IEC 23091-2.
     (
         (1 << (image.depth - 1)) as f32,
@@ -558,7 +569,7 @@ fn bias_and_range_uv(image: &image::Image) -> (f32, f32) {
     )
 }
 
-fn unorm_lookup_tables(
+pub(crate) fn unorm_lookup_tables(
     image: &image::Image,
     mode: Mode,
 ) -> AvifResult<(Vec<f32>, Option<Vec<f32>>)> {
@@ -985,6 +996,19 @@ pub(crate) fn yuv_to_rgb_any(
     {
         return AvifError::not_implemented();
     }
+    if let Some(kernel) = rgb_simd::Kernel::detect() {
+        if rgb_simd::yuv_to_rgb(
+            kernel,
+            image,
+            rgb,
+            alpha_multiply_mode,
+            !fast_or_no_chroma_subsampling,
+        )?
+        .is_some()
+        {
+            return Ok(());
+        }
+    }
     if image.depth > 8 {
         yuv16_to_rgb_any(
             image,
@@ -1003,7 +1027,7 @@ pub(crate) fn yuv_to_rgb_any(
 }
 
 #[derive(Debug, Default, Copy, Clone)]
-struct YUVBlock(f32, f32, f32);
+pub(crate) struct YUVBlock(pub f32, pub f32, pub f32);
 
 pub(crate) fn rgb_gray_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> AvifResult<()> {
     let rgb_channel_count = rgb.channel_count() as usize;
@@ -1037,7 +1061,7 @@ pub(crate) fn rgb_gray_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> Avi
     Ok(())
 }
 
-fn rgb_pixel_to_yuv_pixel(
+pub(crate) fn rgb_pixel_to_yuv_pixel(
     mode: Mode,
     r: f32,
     g: f32,
@@ -1660,14 +1684,21 @@ pub(crate) fn rgb_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> AvifResu
     match image.yuv_format {
         PixelFormat::Yuv420 => rgb_to_yuv_420(rgb, image),
         PixelFormat::Yuv422 => rgb_to_yuv_422(rgb, image),
-        PixelFormat::Yuv444 => rgb_to_yuv_444(rgb, image),
+        PixelFormat::Yuv444 => {
+            if let Some(kernel) = rgb_simd::Kernel::detect() {
+                if rgb_simd::rgb_to_yuv_444(kernel, rgb, image)?.is_some() {
+                    return Ok(());
+                }
+            }
+            rgb_to_yuv_444(rgb, image)
+        }
         PixelFormat::Yuv400 => rgb_to_yuv_400(rgb, image),
         _ => Err(AvifError::NotImplemented),
     }
 }
 
 // TODO - b/410088660: this can be a macro since it's per pixel?
-fn to_unorm(bias_y: f32, range_y: f32, max_channel: u16, v: f32) -> u16 {
+pub(crate) fn to_unorm(bias_y: f32, range_y: f32, max_channel: u16, v: f32) -> u16 {
     clamp_i32(
         (0.5 + (v * range_y + bias_y)).floor() as i32,
         0,
@@ -1678,6 +1709,7 @@ fn to_unorm(bias_y: f32, range_y: f32, max_channel: u16, v: f32) -> u16 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_case::test_matrix;
 
     #[test]
     fn yuv_to_rgb() {
@@ -1894,4 +1926,242 @@ mod tests {
             ],
         );
     }
+
+    fn fill_with_pattern(image: &mut image::Image) {
+        let max_channel = image.max_channel() as u32;
+        for plane in image::YUV_PLANES {
+            for y in 0..image.height(plane) as u32 {
+                let width = image.width(plane);
+                for x in 0..width as u32 {
+                    let value = (x * 7919 + y * 104729 + plane.as_usize() as u32 * 31)
+                        .wrapping_mul(2654435761)
+                        >> 7;
+                    let value = value % (max_channel + 1);
+                    if image.depth == 8 {
+                        image.row_mut(plane, y).unwrap()[x as usize] = value as u8;
+                    } else {
+                        image.row16_mut(plane, y).unwrap()[x as usize] = value as u16;
+                    }
+                }
+            }
+        }
+    }
+
+    fn assert_rgb_eq(rgb1: &rgb::Image, rgb2: &rgb::Image) {
+        for y in 0..rgb1.height {
+            if rgb1.depth == 8 {
+                assert_eq!(rgb1.row(y).unwrap(), rgb2.row(y).unwrap());
+            } else {
+                assert_eq!(rgb1.row16(y).unwrap(), rgb2.row16(y).unwrap());
+            }
+        }
+    }
+
+    #[test_matrix(
+        [8, 10, 12, 16],
+        [8, 16],
+        [PixelFormat::Yuv444, PixelFormat::Yuv422, PixelFormat::Yuv420],
+        [YuvRange::Full, YuvRange::Limited],
+        [MatrixCoefficients::Bt601, MatrixCoefficients::Bt709, MatrixCoefficients::Bt2020Ncl],
+        [Format::Rgba, Format::Bgr, Format::Argb]
+    )]
+    fn simd_yuv_to_rgb_matches_scalar(
+        yuv_depth: u8,
+        rgb_depth: u8,
+        yuv_format: PixelFormat,
+        yuv_range: YuvRange,
+        matrix_coefficients: MatrixCoefficients,
+        format: Format,
+    ) {
+        let mut yuv = image::Image {
+            width: 37,
+            height: 5,
+            depth: yuv_depth,
+            yuv_format,
+            yuv_range,
+            matrix_coefficients,
+            ..Default::default()
+        };
+        assert!(yuv.allocate_planes(Category::Color).is_ok());
+        fill_with_pattern(&mut yuv);
+        let Mode::YuvCoefficients(kr, kg, kb) = (&yuv).into() else {
+            panic!("unexpected mode");
+        };
+        let mut expected = rgb::Image::create_from_yuv(&yuv);
+        expected.depth = rgb_depth;
+        expected.format = format;
+        assert!(expected.allocate().is_ok());
+        assert!(match (yuv_depth == 8, rgb_depth == 8) {
+            (true, true) => yuv8_to_rgb8_color(&yuv, &mut expected, kr, kg, kb),
+            (true, false) => yuv8_to_rgb16_color(&yuv, &mut expected, kr, kg, kb),
+            (false, true) => yuv16_to_rgb8_color(&yuv, &mut expected, kr, kg, kb),
+            (false, false) => yuv16_to_rgb16_color(&yuv, &mut expected, kr, kg, kb),
+        }
+        .is_ok());
+        for kernel in rgb_simd::Kernel::available() {
+            let mut rgb = rgb::Image::create_from_yuv(&yuv);
+            rgb.depth = rgb_depth;
+            rgb.format = format;
+            assert!(rgb.allocate().is_ok());
+            assert_eq!(
+                rgb_simd::yuv_to_rgb(kernel, &yuv, &mut rgb, AlphaMultiplyMode::NoOp, false),
+                Ok(Some(()))
+            );
+            assert_rgb_eq(&rgb, &expected);
+        }
+    }
+
+    #[test_matrix(
+        [8, 12],
+        [8, 16],
+        [PixelFormat::Yuv444, PixelFormat::Yuv422, PixelFormat::Yuv420],
+        [YuvRange::Full, YuvRange::Limited],
+        [MatrixCoefficients::Bt709, MatrixCoefficients::Identity, MatrixCoefficients::Ycgco],
+        [AlphaMultiplyMode::NoOp, AlphaMultiplyMode::Multiply, AlphaMultiplyMode::UnMultiply],
+        [false, true]
+    )]
+    fn simd_yuv_to_rgb_any_matches_scalar(
+        yuv_depth: u8,
+        rgb_depth: u8,
+        yuv_format: PixelFormat,
+        yuv_range: YuvRange,
+        matrix_coefficients: MatrixCoefficients,
+        alpha_multiply_mode: AlphaMultiplyMode,
+        bilinear: bool,
+    ) {
+        let mut yuv = image::Image {
+            width: 37,
+            height: 5,
+            depth: yuv_depth,
+            yuv_format,
+            yuv_range,
+            matrix_coefficients,
+            ..Default::default()
+        };
+        assert!(yuv.allocate_planes(Category::Color).is_ok());
+        assert!(yuv.allocate_planes(Category::Alpha).is_ok());
+        fill_with_pattern(&mut yuv);
+        // Make sure that the transparent and opaque special cases are covered.
+        let max_channel = yuv.max_channel();
+        for y in 0..yuv.height {
+            for x in (0..yuv.width as usize).step_by(3) {
+                let value = if x % 2 == 0 { 0 } else { max_channel };
+                if yuv_depth == 8 {
+                    yuv.row_mut(Plane::A, y).unwrap()[x] = value as u8;
+                } else {
+                    yuv.row16_mut(Plane::A, y).unwrap()[x] = value;
+                }
+            }
+        }
+        let fast_or_no_chroma_subsampling = !bilinear || yuv_format == PixelFormat::Yuv444;
+        let mut expected = rgb::Image::create_from_yuv(&yuv);
+        expected.depth = rgb_depth;
+        expected.format = Format::Bgra;
+        assert!(expected.allocate().is_ok());
+        assert!(if yuv_depth == 8 {
+            yuv8_to_rgb_any(
+                &yuv,
+                &mut expected,
+                alpha_multiply_mode,
+                fast_or_no_chroma_subsampling,
+            )
+        } else {
+            yuv16_to_rgb_any(
+                &yuv,
+                &mut expected,
+                alpha_multiply_mode,
+                fast_or_no_chroma_subsampling,
+            )
+        }
+        .is_ok());
+        for kernel in rgb_simd::Kernel::available() {
+            let mut rgb = rgb::Image::create_from_yuv(&yuv);
+            rgb.depth = rgb_depth;
+            rgb.format = Format::Bgra;
+            assert!(rgb.allocate().is_ok());
+            assert_eq!(
+                rgb_simd::yuv_to_rgb(kernel, &yuv, &mut rgb, alpha_multiply_mode, bilinear),
+                Ok(Some(()))
+            );
+            assert_rgb_eq(&rgb, &expected);
+        }
+    }
+
+    #[test_matrix(
+        [8, 16],
+        [8, 10, 12],
+        [YuvRange::Full, YuvRange::Limited],
+        [
+            MatrixCoefficients::Bt601,
+            MatrixCoefficients::Bt709,
+            MatrixCoefficients::Bt2020Ncl,
+            MatrixCoefficients::Identity,
+            MatrixCoefficients::Ycgco
+        ],
+        [Format::Rgba, Format::Bgr, Format::Argb]
+    )]
+    fn simd_rgb_to_yuv_matches_scalar(
+        rgb_depth: u8,
+        yuv_depth: u8,
+        yuv_range: YuvRange,
+        matrix_coefficients: MatrixCoefficients,
+        format: Format,
+    ) {
+        let create_yuv = || image::Image {
+            width: 37,
+            height: 5,
+            depth: yuv_depth,
+            yuv_format: PixelFormat::Yuv444,
+            yuv_range,
+            matrix_coefficients,
+            ..Default::default()
+        };
+        // Use a YUV image to generate the RGB input.
+        let mut source = create_yuv();
+        source.depth = rgb_depth;
+        assert!(source.allocate_planes(Category::Color).is_ok());
+        fill_with_pattern(&mut source);
+        let mut rgb = rgb::Image::create_from_yuv(&source);
+        rgb.format = format;
+        assert!(rgb.allocate().is_ok());
+        for y in 0..rgb.height {
+            for x in 0..rgb.width as usize {
+                for (c, plane) in image::YUV_PLANES.iter().enumerate() {
+                    let i = x * rgb.channel_count() as usize + rgb.format.offsets()[c];
+                    if rgb_depth == 8 {
+                        rgb.row_mut(y).unwrap()[i] = source.row(*plane, y).unwrap()[x];
+                    } else {
+                        rgb.row16_mut(y).unwrap()[i] = source.row16(*plane, y).unwrap()[x];
+                    }
+                }
+            }
+        }
+
+        let mut expected = create_yuv();
+        assert!(expected.allocate_planes(Category::Color).is_ok());
+        assert!(rgb_to_yuv_444(&rgb, &mut expected).is_ok());
+        for kernel in rgb_simd::Kernel::available() {
+            let mut yuv = create_yuv();
+            assert!(yuv.allocate_planes(Category::Color).is_ok());
+            assert_eq!(
+                rgb_simd::rgb_to_yuv_444(kernel, &rgb, &mut yuv),
+                Ok(Some(()))
+            );
+            for plane in image::YUV_PLANES {
+                for y in 0..yuv.height {
+                    if yuv_depth == 8 {
+                        assert_eq!(
+                            yuv.row_exact(plane, y).unwrap(),
+                            expected.row_exact(plane, y).unwrap()
+                        );
+                    } else {
+                        assert_eq!(
+                            yuv.row16_exact(plane, y).unwrap(),
+                            expected.row16_exact(plane, y).unwrap()
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,955 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Vectorized versions of the most common kernels of rgb_impl. The pixels are converted by blocks
+// of BLOCK_SIZE. The samples are normalized with the same lookup tables and the arithmetic is
+// performed in the same order as in rgb_impl, so that the outputs are identical to the ones of
+// the scalar reference implementation.
+//
+// YUV to RGB is vectorized for the YUV coefficients, Identity and YCgCo matrices, any range, any
+// combination of YUV and RGB depths, 4:4:4, 4:2:2 and 4:2:0 with nearest or bilinear chroma
+// upsampling, and all the alpha multiply modes. RGB to YUV is vectorized for the same matrices
+// but only for 4:4:4. The YCgCo-Re/Ro matrices, monochrome images, gray and RGB565 outputs, and
+// RGB to YUV 4:2:2/4:2:0 are only implemented in rgb_impl.
+
+use super::rgb;
+use super::rgb::*;
+use super::rgb_impl::*;
+
+use crate::image::Plane;
+use crate::internal_utils::*;
+use crate::*;
+
+use std::cmp::min;
+use std::sync::OnceLock;
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+const BLOCK_SIZE: usize = 8;
+
+type Block = [f32; BLOCK_SIZE];
+type IntBlock = [i32; BLOCK_SIZE];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Kernel {
+    #[cfg(target_arch = "x86_64")]
+    Sse41,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+impl Kernel {
+    // Returns all the kernels supported by the current CPU, the fastest one first.
+    pub(crate) fn available() -> Vec<Kernel> {
+        #[allow(unused_mut)]
+        let mut kernels = Vec::new();
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                kernels.push(Kernel::Avx2);
+            }
+            if is_x86_feature_detected!("sse4.1") {
+                kernels.push(Kernel::Sse41);
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                kernels.push(Kernel::Neon);
+            }
+        }
+        kernels
+    }
+
+    // Returns the fastest kernel supported by the current CPU. The CPU features are only queried
+    // once.
+    pub(crate) fn detect() -> Option<Kernel> {
+        static KERNEL: OnceLock<Option<Kernel>> = OnceLock::new();
+        *KERNEL.get_or_init(|| Self::available().first().copied())
+    }
+
+    fn yuv_to_rgb(
+        &self,
+        constants: &YuvToRgbConstants,
+        yuva: &[Block; 4],
+        rgb: &mut [IntBlock; 3],
+    ) {
+        // SAFETY: The kernels are only created if the CPU supports the corresponding features.
+        unsafe {
+            match self {
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Sse41 => yuv_to_rgb_sse41(constants, yuva, rgb),
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Avx2 => yuv_to_rgb_avx2(constants, yuva, rgb),
+                #[cfg(target_arch = "aarch64")]
+                Kernel::Neon => yuv_to_rgb_neon(constants, yuva, rgb),
+            }
+        }
+    }
+
+    fn rgb_to_yuv(&self, constants: &RgbToYuvConstants, rgb: &[Block; 3], yuv: &mut [IntBlock; 3]) {
+        // SAFETY: The kernels are only created if the CPU supports the corresponding features.
+        unsafe {
+            match self {
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Sse41 => rgb_to_yuv_sse41(constants, rgb, yuv),
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Avx2 => rgb_to_yuv_avx2(constants, rgb, yuv),
+                #[cfg(target_arch = "aarch64")]
+                Kernel::Neon => rgb_to_yuv_neon(constants, rgb, yuv),
+            }
+        }
+    }
+}
+
+// The subset of Mode supported by the kernels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Matrix {
+    YuvCoefficients,
+    Identity,
+    Ycgco,
+}
+
+struct YuvToRgbConstants {
+    matrix: Matrix,
+    cr_to_r: f32,
+    cb_to_b: f32,
+    cr_to_g: f32,
+    cb_to_g: f32,
+    kg: f32,
+    rgb_max_channel: f32,
+    alpha_multiply_mode: AlphaMultiplyMode,
+}
+
+impl YuvToRgbConstants {
+    fn create(
+        mode: Mode,
+        rgb_max_channel: f32,
+        alpha_multiply_mode: AlphaMultiplyMode,
+    ) -> Option<Self> {
+        let (matrix, kr, kg, kb) = match mode {
+            Mode::YuvCoefficients(kr, kg, kb) => (Matrix::YuvCoefficients, kr, kg, kb),
+            // The coefficients are unused for the other matrices.
+            Mode::Identity => (Matrix::Identity, 0.0, 1.0, 0.0),
+            Mode::Ycgco => (Matrix::Ycgco, 0.0, 1.0, 0.0),
+            Mode::YcgcoRe | Mode::YcgcoRo => return None,
+        };
+        Some(Self {
+            matrix,
+            cr_to_r: 2.0 * (1.0 - kr),
+            cb_to_b: 2.0 * (1.0 - kb),
+            cr_to_g: kr * (1.0 - kr),
+            cb_to_g: kb * (1.0 - kb),
+            kg,
+            rgb_max_channel,
+            alpha_multiply_mode,
+        })
+    }
+}
+
+struct RgbToYuvConstants {
+    matrix: Matrix,
+    kr: f32,
+    kg: f32,
+    kb: f32,
+    cb_divisor: f32,
+    cr_divisor: f32,
+    bias_y: f32,
+    range_y: f32,
+    bias_uv: f32,
+    range_uv: f32,
+    yuv_max_channel: i32,
+}
+
+// Each of the kernels below computes, for every lane and depending on the matrix:
+//   YuvCoefficients: r = y + cr_to_r * cr
+//                    g = y - (2 * (cr_to_g * cr + cb_to_g * cb)) / kg
+//                    b = y + cb_to_b * cb
+//   Identity:        r = cr, g = y, b = cb
+//   YCgCo:           r = (y - cb) + cr, g = y + cb, b = (y - cb) - cr
+// Each channel x is then clamped to [0, 1], multiplied by the alpha value a (Multiply), or
+// divided by a and clamped to 1 if a is not 0 and set to 0 otherwise (UnMultiply), and the
+// kernels output trunc(0.5 + x * rgb_max_channel).
+
+/// # Safety
+/// The CPU must support SSE4.1.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn yuv_to_rgb_sse41(c: &YuvToRgbConstants, yuva: &[Block; 4], rgb: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let zero = _mm_setzero_ps();
+        let one = _mm_set1_ps(1.0);
+        let two = _mm_set1_ps(2.0);
+        let half = _mm_set1_ps(0.5);
+        let max = _mm_set1_ps(c.rgb_max_channel);
+        for i in (0..BLOCK_SIZE).step_by(4) {
+            let y = _mm_loadu_ps(yuva[0].as_ptr().add(i));
+            let cb = _mm_loadu_ps(yuva[1].as_ptr().add(i));
+            let cr = _mm_loadu_ps(yuva[2].as_ptr().add(i));
+            let a = _mm_loadu_ps(yuva[3].as_ptr().add(i));
+            let (r, g, b) = match c.matrix {
+                Matrix::YuvCoefficients => (
+                    _mm_add_ps(y, _mm_mul_ps(_mm_set1_ps(c.cr_to_r), cr)),
+                    _mm_sub_ps(
+                        y,
+                        _mm_div_ps(
+                            _mm_mul_ps(
+                                two,
+                                _mm_add_ps(
+                                    _mm_mul_ps(_mm_set1_ps(c.cr_to_g), cr),
+                                    _mm_mul_ps(_mm_set1_ps(c.cb_to_g), cb),
+                                ),
+                            ),
+                            _mm_set1_ps(c.kg),
+                        ),
+                    ),
+                    _mm_add_ps(y, _mm_mul_ps(_mm_set1_ps(c.cb_to_b), cb)),
+                ),
+                Matrix::Identity => (cr, y, cb),
+                Matrix::Ycgco => {
+                    let t = _mm_sub_ps(y, cb);
+                    (_mm_add_ps(t, cr), _mm_add_ps(y, cb), _mm_sub_ps(t, cr))
+                }
+            };
+            for (channel, value) in [r, g, b].into_iter().enumerate() {
+                let value = _mm_min_ps(_mm_max_ps(value, zero), one);
+                let value = match c.alpha_multiply_mode {
+                    AlphaMultiplyMode::NoOp => value,
+                    AlphaMultiplyMode::Multiply => _mm_mul_ps(value, a),
+                    AlphaMultiplyMode::UnMultiply => {
+                        _mm_and_ps(_mm_cmpgt_ps(a, zero), _mm_min_ps(_mm_div_ps(value, a), one))
+                    }
+                };
+                let value = _mm_cvttps_epi32(_mm_add_ps(half, _mm_mul_ps(value, max)));
+                _mm_storeu_si128(rgb[channel].as_mut_ptr().add(i) as *mut __m128i, value);
+            }
+        }
+    }
+}
+
+/// # Safety
+/// The CPU must support AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn yuv_to_rgb_avx2(c: &YuvToRgbConstants, yuva: &[Block; 4], rgb: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let zero = _mm256_setzero_ps();
+        let one = _mm256_set1_ps(1.0);
+        let two = _mm256_set1_ps(2.0);
+        let half = _mm256_set1_ps(0.5);
+        let max = _mm256_set1_ps(c.rgb_max_channel);
+        let y = _mm256_loadu_ps(yuva[0].as_ptr());
+        let cb = _mm256_loadu_ps(yuva[1].as_ptr());
+        let cr = _mm256_loadu_ps(yuva[2].as_ptr());
+        let a = _mm256_loadu_ps(yuva[3].as_ptr());
+        let (r, g, b) = match c.matrix {
+            Matrix::YuvCoefficients => (
+                _mm256_add_ps(y, _mm256_mul_ps(_mm256_set1_ps(c.cr_to_r), cr)),
+                _mm256_sub_ps(
+                    y,
+                    _mm256_div_ps(
+                        _mm256_mul_ps(
+                            two,
+                            _mm256_add_ps(
+                                _mm256_mul_ps(_mm256_set1_ps(c.cr_to_g), cr),
+                                _mm256_mul_ps(_mm256_set1_ps(c.cb_to_g), cb),
+                            ),
+                        ),
+                        _mm256_set1_ps(c.kg),
+                    ),
+                ),
+                _mm256_add_ps(y, _mm256_mul_ps(_mm256_set1_ps(c.cb_to_b), cb)),
+            ),
+            Matrix::Identity => (cr, y, cb),
+            Matrix::Ycgco => {
+                let t = _mm256_sub_ps(y, cb);
+                (
+                    _mm256_add_ps(t, cr),
+                    _mm256_add_ps(y, cb),
+                    _mm256_sub_ps(t, cr),
+                )
+            }
+        };
+        for (channel, value) in [r, g, b].into_iter().enumerate() {
+            let value = _mm256_min_ps(_mm256_max_ps(value, zero), one);
+            let value = match c.alpha_multiply_mode {
+                AlphaMultiplyMode::NoOp => value,
+                AlphaMultiplyMode::Multiply => _mm256_mul_ps(value, a),
+                AlphaMultiplyMode::UnMultiply => _mm256_and_ps(
+                    _mm256_cmp_ps::<_CMP_GT_OQ>(a, zero),
+                    _mm256_min_ps(_mm256_div_ps(value, a), one),
+                ),
+            };
+            let value = _mm256_cvttps_epi32(_mm256_add_ps(half, _mm256_mul_ps(value, max)));
+            _mm256_storeu_si256(rgb[channel].as_mut_ptr() as *mut __m256i, value);
+        }
+    }
+}
+
+/// # Safety
+/// The CPU must support NEON.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn yuv_to_rgb_neon(c: &YuvToRgbConstants, yuva: &[Block; 4], rgb: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let zero = vdupq_n_f32(0.0);
+        let one = vdupq_n_f32(1.0);
+        let two = vdupq_n_f32(2.0);
+        let half = vdupq_n_f32(0.5);
+        let max = vdupq_n_f32(c.rgb_max_channel);
+        for i in (0..BLOCK_SIZE).step_by(4) {
+            let y = vld1q_f32(yuva[0].as_ptr().add(i));
+            let cb = vld1q_f32(yuva[1].as_ptr().add(i));
+            let cr = vld1q_f32(yuva[2].as_ptr().add(i));
+            let a = vld1q_f32(yuva[3].as_ptr().add(i));
+            // vmulq/vaddq are used rather than vfmaq so that the results are rounded the same way as
+            // in the scalar code.
+            let (r, g, b) = match c.matrix {
+                Matrix::YuvCoefficients => (
+                    vaddq_f32(y, vmulq_f32(vdupq_n_f32(c.cr_to_r), cr)),
+                    vsubq_f32(
+                        y,
+                        vdivq_f32(
+                            vmulq_f32(
+                                two,
+                                vaddq_f32(
+                                    vmulq_f32(vdupq_n_f32(c.cr_to_g), cr),
+                                    vmulq_f32(vdupq_n_f32(c.cb_to_g), cb),
+                                ),
+                            ),
+                            vdupq_n_f32(c.kg),
+                        ),
+                    ),
+                    vaddq_f32(y, vmulq_f32(vdupq_n_f32(c.cb_to_b), cb)),
+                ),
+                Matrix::Identity => (cr, y, cb),
+                Matrix::Ycgco => {
+                    let t = vsubq_f32(y, cb);
+                    (vaddq_f32(t, cr), vaddq_f32(y, cb), vsubq_f32(t, cr))
+                }
+            };
+            for (channel, value) in [r, g, b].into_iter().enumerate() {
+                let value = vminq_f32(vmaxq_f32(value, zero), one);
+                let value = match c.alpha_multiply_mode {
+                    AlphaMultiplyMode::NoOp => value,
+                    AlphaMultiplyMode::Multiply => vmulq_f32(value, a),
+                    AlphaMultiplyMode::UnMultiply => vbslq_f32(
+                        vcgtq_f32(a, zero),
+                        vminq_f32(vdivq_f32(value, a), one),
+                        zero,
+                    ),
+                };
+                let value = vcvtq_s32_f32(vaddq_f32(half, vmulq_f32(value, max)));
+                vst1q_s32(rgb[channel].as_mut_ptr().add(i), value);
+            }
+        }
+    }
+}
+
+// Each of the kernels below computes, for every lane and depending on the matrix:
+//   YuvCoefficients: y = kr * r + kg * g + kb * b
+//                    u = (b - y) / cb_divisor
+//                    v = (r - y) / cr_divisor
+//   Identity:        y = g, u = b, v = r
+//   YCgCo:           y = 0.5 * g + 0.25 * (r + b)
+//                    u = 0.5 * g - 0.25 * (r + b)
+//                    v = 0.5 * (r - b)
+// and outputs clamp(floor(0.5 + (x * range + bias)), 0, yuv_max_channel) for each channel x.
+
+/// # Safety
+/// The CPU must support SSE4.1.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn rgb_to_yuv_sse41(c: &RgbToYuvConstants, rgb: &[Block; 3], yuv: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let half = _mm_set1_ps(0.5);
+        let zero = _mm_setzero_si128();
+        let max = _mm_set1_epi32(c.yuv_max_channel);
+        for i in (0..BLOCK_SIZE).step_by(4) {
+            let r = _mm_loadu_ps(rgb[0].as_ptr().add(i));
+            let g = _mm_loadu_ps(rgb[1].as_ptr().add(i));
+            let b = _mm_loadu_ps(rgb[2].as_ptr().add(i));
+            let (y, u, v) = match c.matrix {
+                Matrix::YuvCoefficients => {
+                    let y = _mm_add_ps(
+                        _mm_add_ps(
+                            _mm_mul_ps(_mm_set1_ps(c.kr), r),
+                            _mm_mul_ps(_mm_set1_ps(c.kg), g),
+                        ),
+                        _mm_mul_ps(_mm_set1_ps(c.kb), b),
+                    );
+                    (
+                        y,
+                        _mm_div_ps(_mm_sub_ps(b, y), _mm_set1_ps(c.cb_divisor)),
+                        _mm_div_ps(_mm_sub_ps(r, y), _mm_set1_ps(c.cr_divisor)),
+                    )
+                }
+                Matrix::Identity => (g, b, r),
+                Matrix::Ycgco => {
+                    let half_g = _mm_mul_ps(half, g);
+                    let quarter_rb = _mm_mul_ps(_mm_set1_ps(0.25), _mm_add_ps(r, b));
+                    (
+                        _mm_add_ps(half_g, quarter_rb),
+                        _mm_sub_ps(half_g, quarter_rb),
+                        _mm_mul_ps(half, _mm_sub_ps(r, b)),
+                    )
+                }
+            };
+            for (channel, (value, bias, range)) in [
+                (y, c.bias_y, c.range_y),
+                (u, c.bias_uv, c.range_uv),
+                (v, c.bias_uv, c.range_uv),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let value = _mm_add_ps(
+                    half,
+                    _mm_add_ps(_mm_mul_ps(value, _mm_set1_ps(range)), _mm_set1_ps(bias)),
+                );
+                let value = _mm_cvtps_epi32(_mm_floor_ps(value));
+                let value = _mm_min_epi32(_mm_max_epi32(value, zero), max);
+                _mm_storeu_si128(yuv[channel].as_mut_ptr().add(i) as *mut __m128i, value);
+            }
+        }
+    }
+}
+
+/// # Safety
+/// The CPU must support AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn rgb_to_yuv_avx2(c: &RgbToYuvConstants, rgb: &[Block; 3], yuv: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let half = _mm256_set1_ps(0.5);
+        let zero = _mm256_setzero_si256();
+        let max = _mm256_set1_epi32(c.yuv_max_channel);
+        let r = _mm256_loadu_ps(rgb[0].as_ptr());
+        let g = _mm256_loadu_ps(rgb[1].as_ptr());
+        let b = _mm256_loadu_ps(rgb[2].as_ptr());
+        let (y, u, v) = match c.matrix {
+            Matrix::YuvCoefficients => {
+                let y = _mm256_add_ps(
+                    _mm256_add_ps(
+                        _mm256_mul_ps(_mm256_set1_ps(c.kr), r),
+                        _mm256_mul_ps(_mm256_set1_ps(c.kg), g),
+                    ),
+                    _mm256_mul_ps(_mm256_set1_ps(c.kb), b),
+                );
+                (
+                    y,
+                    _mm256_div_ps(_mm256_sub_ps(b, y), _mm256_set1_ps(c.cb_divisor)),
+                    _mm256_div_ps(_mm256_sub_ps(r, y), _mm256_set1_ps(c.cr_divisor)),
+                )
+            }
+            Matrix::Identity => (g, b, r),
+            Matrix::Ycgco => {
+                let half_g = _mm256_mul_ps(half, g);
+                let quarter_rb = _mm256_mul_ps(_mm256_set1_ps(0.25), _mm256_add_ps(r, b));
+                (
+                    _mm256_add_ps(half_g, quarter_rb),
+                    _mm256_sub_ps(half_g, quarter_rb),
+                    _mm256_mul_ps(half, _mm256_sub_ps(r, b)),
+                )
+            }
+        };
+        for (channel, (value, bias, range)) in [
+            (y, c.bias_y, c.range_y),
+            (u, c.bias_uv, c.range_uv),
+            (v, c.bias_uv, c.range_uv),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let value = _mm256_add_ps(
+                half,
+                _mm256_add_ps(
+                    _mm256_mul_ps(value, _mm256_set1_ps(range)),
+                    _mm256_set1_ps(bias),
+                ),
+            );
+            let value = _mm256_cvtps_epi32(_mm256_floor_ps(value));
+            let value = _mm256_min_epi32(_mm256_max_epi32(value, zero), max);
+            _mm256_storeu_si256(yuv[channel].as_mut_ptr() as *mut __m256i, value);
+        }
+    }
+}
+
+/// # Safety
+/// The CPU must support NEON.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn rgb_to_yuv_neon(c: &RgbToYuvConstants, rgb: &[Block; 3], yuv: &mut [IntBlock; 3]) {
+    // SAFETY: The loads and stores stay within the bounds of the blocks.
+    unsafe {
+        let half = vdupq_n_f32(0.5);
+        let zero = vdupq_n_s32(0);
+        let max = vdupq_n_s32(c.yuv_max_channel);
+        for i in (0..BLOCK_SIZE).step_by(4) {
+            let r = vld1q_f32(rgb[0].as_ptr().add(i));
+            let g = vld1q_f32(rgb[1].as_ptr().add(i));
+            let b = vld1q_f32(rgb[2].as_ptr().add(i));
+            let (y, u, v) = match c.matrix {
+                Matrix::YuvCoefficients => {
+                    let y = vaddq_f32(
+                        vaddq_f32(
+                            vmulq_f32(vdupq_n_f32(c.kr), r),
+                            vmulq_f32(vdupq_n_f32(c.kg), g),
+                        ),
+                        vmulq_f32(vdupq_n_f32(c.kb), b),
+                    );
+                    (
+                        y,
+                        vdivq_f32(vsubq_f32(b, y), vdupq_n_f32(c.cb_divisor)),
+                        vdivq_f32(vsubq_f32(r, y), vdupq_n_f32(c.cr_divisor)),
+                    )
+                }
+                Matrix::Identity => (g, b, r),
+                Matrix::Ycgco => {
+                    let half_g = vmulq_f32(half, g);
+                    let quarter_rb = vmulq_f32(vdupq_n_f32(0.25), vaddq_f32(r, b));
+                    (
+                        vaddq_f32(half_g, quarter_rb),
+                        vsubq_f32(half_g, quarter_rb),
+                        vmulq_f32(half, vsubq_f32(r, b)),
+                    )
+                }
+            };
+            for (channel, (value, bias, range)) in [
+                (y, c.bias_y, c.range_y),
+                (u, c.bias_uv, c.range_uv),
+                (v, c.bias_uv, c.range_uv),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let value = vaddq_f32(
+                    half,
+                    vaddq_f32(vmulq_f32(value, vdupq_n_f32(range)), vdupq_n_f32(bias)),
+                );
+                let value = vcvtq_s32_f32(vrndmq_f32(value));
+                let value = vminq_s32(vmaxq_s32(value, zero), max);
+                vst1q_s32(yuv[channel].as_mut_ptr().add(i), value);
+            }
+        }
+    }
+}
+
+trait Sample: Copy {
+    fn index(self, max_channel: u16) -> usize;
+    fn normalize(self, max_channel_f: f32) -> f32;
+    fn from_i32(value: i32) -> Self;
+    fn yuv_row(image: &image::Image, plane: Plane, row: u32) -> AvifResult<&[Self]>;
+    fn rgb_row_mut(rgb: &mut rgb::Image, row: u32) -> AvifResult<&mut [Self]>;
+}
+
+impl Sample for u8 {
+    fn index(self, _max_channel: u16) -> usize {
+        self as usize
+    }
+    fn normalize(self, _max_channel_f: f32) -> f32 {
+        self as f32 / 255.0
+    }
+    fn from_i32(value: i32) -> Self {
+        value as u8
+    }
+    fn yuv_row(image: &image::Image, plane: Plane, row: u32) -> AvifResult<&[Self]> {
+        image.row(plane, row)
+    }
+    fn rgb_row_mut(rgb: &mut rgb::Image, row: u32) -> AvifResult<&mut [Self]> {
+        rgb.row_mut(row)
+    }
+}
+
+impl Sample for u16 {
+    fn index(self, max_channel: u16) -> usize {
+        min(self, max_channel) as usize
+    }
+    fn normalize(self, max_channel_f: f32) -> f32 {
+        self as f32 / max_channel_f
+    }
+    fn from_i32(value: i32) -> Self {
+        value as u16
+    }
+    fn yuv_row(image: &image::Image, plane: Plane, row: u32) -> AvifResult<&[Self]> {
+        image.row16(plane, row)
+    }
+    fn rgb_row_mut(rgb: &mut rgb::Image, row: u32) -> AvifResult<&mut [Self]> {
+        rgb.row16_mut(row)
+    }
+}
+
+struct RgbLayout {
+    offsets: [usize; 3],
+    channel_count: usize,
+}
+
+impl RgbLayout {
+    fn create(rgb: &rgb::Image) -> Self {
+        Self {
+            offsets: [
+                rgb.format.r_offset(),
+                rgb.format.g_offset(),
+                rgb.format.b_offset(),
+            ],
+            channel_count: rgb.channel_count() as usize,
+        }
+    }
+}
+
+// Normalizes the YUVA samples the same way as yuv8_to_rgb_any() and yuv16_to_rgb_any().
+struct YuvSampler<'a> {
+    table_y: &'a [f32],
+    table_uv: &'a [f32],
+    max_channel: u16,
+    chroma_shift: u32,
+    bilinear: bool,
+    width: usize,
+}
+
+impl YuvSampler<'_> {
+    fn unorm<T: Sample>(&self, table: &[f32], sample: T) -> f32 {
+        // SAFETY: The tables have 2^depth values and index() clamps the samples to max_channel.
+        unsafe { *table.get_unchecked(sample.index(self.max_channel)) }
+    }
+
+    fn fill_y<T: Sample>(&self, row: &[T], block_start: usize, block: &mut Block) {
+        for (value, sample) in block.iter_mut().zip(&row[block_start..]) {
+            *value = self.unorm(self.table_y, *sample);
+        }
+    }
+
+    // adj_row is the chroma row that is the closest to the current row after row. It is only
+    // used for bilinear upsampling.
+    fn fill_uv<T: Sample>(&self, row: &[T], adj_row: &[T], block_start: usize, block: &mut Block) {
+        let count = min(BLOCK_SIZE, self.width - block_start);
+        if !self.bilinear {
+            for (k, value) in block[..count].iter_mut().enumerate() {
+                *value = self.unorm(self.table_uv, row[(block_start + k) >> self.chroma_shift]);
+            }
+            return;
+        }
+        // The taps are gathered first so that the weighted sum below can be auto-vectorized.
+        let mut taps = [[0.0; BLOCK_SIZE]; 4];
+        #[allow(clippy::needless_range_loop)]
+        for k in 0..count {
+            let i = block_start + k;
+            let uv_i = i >> self.chroma_shift;
+            let uv_adj_i = if i == 0 || (i == self.width - 1 && (i % 2) != 0) {
+                uv_i
+            } else if (i % 2) != 0 {
+                uv_i + 1
+            } else {
+                uv_i - 1
+            };
+            taps[0][k] = self.unorm(self.table_uv, row[uv_i]);
+            taps[1][k] = self.unorm(self.table_uv, row[uv_adj_i]);
+            taps[2][k] = self.unorm(self.table_uv, adj_row[uv_i]);
+            taps[3][k] = self.unorm(self.table_uv, adj_row[uv_adj_i]);
+        }
+        for (k, value) in block.iter_mut().enumerate() {
+            *value = (taps[0][k] * (9.0 / 16.0))
+                + (taps[1][k] * (3.0 / 16.0))
+                + (taps[2][k] * (3.0 / 16.0))
+                + (taps[3][k] * (1.0 / 16.0));
+        }
+    }
+
+    fn fill_a<T: Sample>(&self, row: &[T], block_start: usize, block: &mut Block) {
+        for (value, sample) in block.iter_mut().zip(&row[block_start..]) {
+            *value = clamp_f32(
+                (sample.index(self.max_channel) as f32) / (self.max_channel as f32),
+                0.0,
+                1.0,
+            );
+        }
+    }
+}
+
+struct YuvRows<'a, T> {
+    y: &'a [T],
+    u: &'a [T],
+    v: &'a [T],
+    u_adj: &'a [T],
+    v_adj: &'a [T],
+    a: Option<&'a [T]>,
+}
+
+impl<'a, T: Sample> YuvRows<'a, T> {
+    fn create(image: &'a image::Image, j: u32, with_alpha: bool) -> AvifResult<Self> {
+        let uv_j = j >> image.yuv_format.chroma_shift_y();
+        // Same as in yuv8_to_rgb_any() and yuv16_to_rgb_any().
+        let uv_adj_j = if j == 0
+            || (j == image.height - 1 && (j % 2) != 0)
+            || image.yuv_format == PixelFormat::Yuv422
+        {
+            uv_j
+        } else if (j % 2) != 0 {
+            uv_j + 1
+        } else {
+            uv_j - 1
+        };
+        let width = image.width as usize;
+        Ok(Self {
+            y: &T::yuv_row(image, Plane::Y, j)?[..width],
+            u: T::yuv_row(image, Plane::U, uv_j)?,
+            v: T::yuv_row(image, Plane::V, uv_j)?,
+            u_adj: T::yuv_row(image, Plane::U, uv_adj_j)?,
+            v_adj: T::yuv_row(image, Plane::V, uv_adj_j)?,
+            a: if with_alpha { Some(&T::yuv_row(image, Plane::A, j)?[..width]) } else { None },
+        })
+    }
+}
+
+fn yuv_row_to_rgb<T: Sample, U: Sample>(
+    kernel: Kernel,
+    constants: &YuvToRgbConstants,
+    sampler: &YuvSampler,
+    yuv_rows: &YuvRows<T>,
+    layout: &RgbLayout,
+    dst: &mut [U],
+) {
+    let width = sampler.width;
+    let mut yuva = [
+        [0.0; BLOCK_SIZE],
+        [0.0; BLOCK_SIZE],
+        [0.0; BLOCK_SIZE],
+        [1.0; BLOCK_SIZE],
+    ];
+    let mut rgb = [[0; BLOCK_SIZE]; 3];
+    let dst = &mut dst[..width * layout.channel_count];
+    for (block_start, pixels) in (0..width)
+        .step_by(BLOCK_SIZE)
+        .zip(dst.chunks_mut(BLOCK_SIZE * layout.channel_count))
+    {
+        let [y, u, v, a] = &mut yuva;
+        sampler.fill_y(yuv_rows.y, block_start, y);
+        sampler.fill_uv(yuv_rows.u, yuv_rows.u_adj, block_start, u);
+        sampler.fill_uv(yuv_rows.v, yuv_rows.v_adj, block_start, v);
+        if let Some(a_row) = yuv_rows.a {
+            sampler.fill_a(a_row, block_start, a);
+        }
+        kernel.yuv_to_rgb(constants, &yuva, &mut rgb);
+        for (k, pixel) in pixels.chunks_exact_mut(layout.channel_count).enumerate() {
+            for (offset, values) in layout.offsets.iter().zip(&rgb) {
+                pixel[*offset] = U::from_i32(values[k]);
+            }
+        }
+    }
+}
+
+fn yuv_to_rgb_impl<T: Sample, U: Sample>(
+    kernel: Kernel,
+    constants: &YuvToRgbConstants,
+    sampler: &YuvSampler,
+    image: &image::Image,
+    rgb: &mut rgb::Image,
+) -> AvifResult<()> {
+    let layout = RgbLayout::create(rgb);
+    let with_alpha = constants.alpha_multiply_mode != AlphaMultiplyMode::NoOp;
+    for j in 0..image.height {
+        let yuv_rows = YuvRows::<T>::create(image, j, with_alpha)?;
+        yuv_row_to_rgb(
+            kernel,
+            constants,
+            sampler,
+            &yuv_rows,
+            &layout,
+            U::rgb_row_mut(rgb, j)?,
+        );
+    }
+    Ok(())
+}
+
+// Vectorized version of yuv8_to_rgb_any() and yuv16_to_rgb_any() for color images and RGB
+// formats, and of the yuv*_to_rgb*_color() functions when bilinear is false and
+// alpha_multiply_mode is NoOp. Returns Ok(None) if not implemented.
+pub(crate) fn yuv_to_rgb(
+    kernel: Kernel,
+    image: &image::Image,
+    rgb: &mut rgb::Image,
+    alpha_multiply_mode: AlphaMultiplyMode,
+    bilinear: bool,
+) -> AvifResult<Option<()>> {
+    if rgb.format == Format::Rgb565
+        || rgb.format.is_gray()
+        || !matches!(
+            image.yuv_format,
+            PixelFormat::Yuv444 | PixelFormat::Yuv422 | PixelFormat::Yuv420
+        )
+        || !image.has_plane(Plane::U)
+        || !image.has_plane(Plane::V)
+        || (alpha_multiply_mode != AlphaMultiplyMode::NoOp && !image.has_plane(Plane::A))
+    {
+        return Ok(None); // Not implemented.
+    }
+    let mode: Mode = image.into();
+    let Some(constants) = YuvToRgbConstants::create(mode, rgb.max_channel_f(), alpha_multiply_mode)
+    else {
+        return Ok(None); // Not implemented.
+    };
+    let (table_y, table_uv) = unorm_lookup_tables(image, mode)?;
+    let sampler = YuvSampler {
+        table_y: &table_y,
+        table_uv: table_uv.as_deref().unwrap_or(&table_y),
+        max_channel: image.max_channel(),
+        chroma_shift: image.yuv_format.chroma_shift_x().0,
+        bilinear: bilinear && image.yuv_format != PixelFormat::Yuv444,
+        width: image.width as usize,
+    };
+    match (image.depth == 8, rgb.depth == 8) {
+        (true, true) => yuv_to_rgb_impl::<u8, u8>(kernel, &constants, &sampler, image, rgb)?,
+        (true, false) => yuv_to_rgb_impl::<u8, u16>(kernel, &constants, &sampler, image, rgb)?,
+        (false, true) => yuv_to_rgb_impl::<u16, u8>(kernel, &constants, &sampler, image, rgb)?,
+        (false, false) => yuv_to_rgb_impl::<u16, u16>(kernel, &constants, &sampler, image, rgb)?,
+    }
+    Ok(Some(()))
+}
+
+fn rgb_row_to_yuv<T: Sample, U: Sample>(
+    kernel: Kernel,
+    constants: &RgbToYuvConstants,
+    rgb_max_channel_f: f32,
+    src: &[T],
+    layout: &RgbLayout,
+    yuv_rows: &mut [Vec<U>; 3],
+) {
+    let width = yuv_rows[0].len();
+    let mut rgb = [[0.0; BLOCK_SIZE]; 3];
+    let mut yuv = [[0; BLOCK_SIZE]; 3];
+    for block_start in (0..width).step_by(BLOCK_SIZE) {
+        let count = min(BLOCK_SIZE, width - block_start);
+        let pixels = src[block_start * layout.channel_count..].chunks(layout.channel_count);
+        for (k, pixel) in pixels.take(count).enumerate() {
+            for (offset, values) in layout.offsets.iter().zip(rgb.iter_mut()) {
+                values[k] = pixel[*offset].normalize(rgb_max_channel_f);
+            }
+        }
+        kernel.rgb_to_yuv(constants, &rgb, &mut yuv);
+        for (yuv_row, values) in yuv_rows.iter_mut().zip(&yuv) {
+            for (sample, value) in yuv_row[block_start..].iter_mut().zip(&values[..count]) {
+                *sample = U::from_i32(*value);
+            }
+        }
+    }
+}
+
+// Vectorized version of rgb_to_yuv_444() for the YUV coefficients, Identity and YCgCo matrices.
+// Returns Ok(None) if not implemented.
+pub(crate) fn rgb_to_yuv_444(
+    kernel: Kernel,
+    rgb: &rgb::Image,
+    image: &mut image::Image,
+) -> AvifResult<Option<()>> {
+    let (matrix, kr, kg, kb) = match (&*image).into() {
+        Mode::YuvCoefficients(kr, kg, kb) => (Matrix::YuvCoefficients, kr, kg, kb),
+        // The coefficients are unused for the other matrices.
+        Mode::Identity => (Matrix::Identity, 0.0, 1.0, 0.0),
+        Mode::Ycgco => (Matrix::Ycgco, 0.0, 1.0, 0.0),
+        Mode::YcgcoRe | Mode::YcgcoRo => return Ok(None), // Not implemented.
+    };
+    if image.yuv_format != PixelFormat::Yuv444 || rgb.format == Format::Rgb565 {
+        return Ok(None); // Not implemented.
+    }
+    let (bias_y, range_y) = bias_and_range_y(image);
+    let (bias_uv, range_uv) = if matrix == Matrix::Identity {
+        (bias_y, range_y)
+    } else {
+        bias_and_range_uv(image)
+    };
+    let constants = RgbToYuvConstants {
+        matrix,
+        kr,
+        kg,
+        kb,
+        cb_divisor: 2.0 * (1.0 - kb),
+        cr_divisor: 2.0 * (1.0 - kr),
+        bias_y,
+        range_y,
+        bias_uv,
+        range_uv,
+        yuv_max_channel: image.max_channel() as i32,
+    };
+    let layout = RgbLayout::create(rgb);
+    let rgb_max_channel_f = rgb.max_channel_f();
+    let width = image.width as usize;
+    let mut yuv_rows8: [Vec<u8>; 3] = Default::default();
+    let mut yuv_rows16: [Vec<u16>; 3] = Default::default();
+    for row in yuv_rows8.iter_mut() {
+        row.resize(if image.depth == 8 { width } else { 0 }, 0);
+    }
+    for row in yuv_rows16.iter_mut() {
+        row.resize(if image.depth == 8 { 0 } else { width }, 0);
+    }
+    for j in 0..image.height {
+        if image.depth == 8 {
+            if rgb.depth == 8 {
+                rgb_row_to_yuv(
+                    kernel,
+                    &constants,
+                    rgb_max_channel_f,
+                    rgb.row(j)?,
+                    &layout,
+                    &mut yuv_rows8,
+                );
+            } else {
+                rgb_row_to_yuv(
+                    kernel,
+                    &constants,
+                    rgb_max_channel_f,
+                    rgb.row16(j)?,
+                    &layout,
+                    &mut yuv_rows8,
+                );
+            }
+            for (plane, yuv_row) in image::YUV_PLANES.iter().zip(&yuv_rows8) {
+                image.row_mut(*plane, j)?[..width].copy_from_slice(yuv_row);
+            }
+        } else {
+            if rgb.depth == 8 {
+                rgb_row_to_yuv(
+                    kernel,
+                    &constants,
+                    rgb_max_channel_f,
+                    rgb.row(j)?,
+                    &layout,
+                    &mut yuv_rows16,
+                );
+            } else {
+                rgb_row_to_yuv(
+                    kernel,
+                    &constants,
+                    rgb_max_channel_f,
+                    rgb.row16(j)?,
+                    &layout,
+                    &mut yuv_rows16,
+                );
+            }
+            for (plane, yuv_row) in image::YUV_PLANES.iter().zip(&yuv_rows16) {
+                image.row16_mut(*plane, j)?[..width].copy_from_slice(yuv_row);
+            }
+        }
+    }
+    Ok(Some(()))
+}
@@ -14,18 +14,35 @@
 
 use crate::image::*;
 use crate::internal_utils::*;
+use crate::reformat::scale_impl::ScaleFilter;
 use crate::*;
 
 use libyuv_sys::bindings::*;
 
 impl Image {
-    pub(crate) fn scale(&mut self, width: u32, height: u32, category: Category) -> AvifResult<()> {
+    // libyuv is single threaded, so max_threads is only used by the portable scaler, which
+    // implements the filters that libyuv does not have.
+    pub(crate) fn scale(
+        &mut self,
+        width: u32,
+        height: u32,
+        category: Category,
+        filter: ScaleFilter,
+        max_threads: u32,
+    ) -> AvifResult<()> {
         if self.width == width && self.height == height {
             return Ok(());
         }
         if width == 0 || height == 0 {
             return AvifError::invalid_argument();
         }
+        let libyuv_filter = match filter {
+            ScaleFilter::Box => FilterMode_kFilterBox,
+            ScaleFilter::Bilinear => FilterMode_kFilterBilinear,
+            ScaleFilter::Lanczos => {
+                return self.scale_rust(width, height, category, filter, max_threads)
+            }
+        };
         let planes = category.planes();
         let src =
             if category != Category::Alpha && self.yuv_format == PixelFormat::AndroidP010 {
@@ -142,7 +159,7 @@ impl Image {
                     i32_from_u32(dst_uv_pd.row_bytes)?,
                     i32_from_u32(dst_y_pd.width)?,
                     i32_from_u32(dst_y_pd.height)?,
-                    FilterMode_kFilterBox,
+                    libyuv_filter,
                 )
             };
             if ret != 0 {
@@ -177,7 +194,7 @@ impl Image {
                         i32_from_u32(dst_pd.row_bytes / 2)?,
                         i32_from_u32(dst_pd.width)?,
                         i32_from_u32(dst_pd.height)?,
-                        FilterMode_kFilterBox,
+                        libyuv_filter,
                     )
                 } else {
                     let source_ptr = src.planes[plane.as_usize()].unwrap_ref().ptr();
@@ -204,10 +221,10 @@ impl Image {
                             if *plane == Plane::U || *plane == Plane::V || scale_factor >= 25 {
                                 FilterMode_kFilterBilinear
                             } else {
-                                FilterMode_kFilterBox
+                                libyuv_filter
                             }
                         } else {
-                            FilterMode_kFilterBox
+                            libyuv_filter
                         },
                     )
                 }
@@ -261,7 +278,7 @@ mod tests {
             // calling it again.
             yuv.width = 2;
             yuv.height = 2;
-            assert!(yuv.scale(4, 4, *category).is_ok());
+            assert!(yuv.scale(4, 4, *category, ScaleFilter::Box, 1).is_ok());
         }
         for plane in planes {
             let expected_samples: &[u8] = match (yuv_format, plane) {
@@ -301,7 +318,9 @@ mod tests {
             ..Default::default()
         };
         image.allocate_planes(Category::Color)?;
-        assert!(image.scale(49, 24, Category::Color).is_ok());
+        assert!(image
+            .scale(49, 24, Category::Color, ScaleFilter::Box, 1)
+            .is_ok());
         assert_eq!(image.width, 49);
         assert_eq!(image.height, 24);
         Ok(())
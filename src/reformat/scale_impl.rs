@@ -0,0 +1,648 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Portable separable image scaler. Used when libyuv is not available, or when libyuv does not
+// support the requested filter.
+
+use crate::image::*;
+use crate::internal_utils::*;
+use crate::utils::clap::CropRect;
+use crate::*;
+
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScaleFilter {
+    // Averages the covered source area when downscaling (same as libyuv's kFilterBox). Behaves
+    // like Bilinear when upscaling.
+    #[default]
+    Box,
+    // Triangle filter, widened by the scale factor when downscaling.
+    Bilinear,
+    // Lanczos filter with 3 lobes, widened by the scale factor when downscaling.
+    Lanczos,
+}
+
+impl ScaleFilter {
+    fn support(&self) -> f64 {
+        match self {
+            Self::Box | Self::Bilinear => 1.0,
+            Self::Lanczos => 3.0,
+        }
+    }
+
+    fn kernel(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Self::Box | Self::Bilinear => {
+                if x < 1.0 {
+                    1.0 - x
+                } else {
+                    0.0
+                }
+            }
+            Self::Lanczos => {
+                if x == 0.0 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+// The weights of the consecutive source samples starting at `start` that contribute to one
+// destination sample. The weights sum up to 1.
+#[derive(Debug)]
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn compute_contributions(
+    src_size: usize,
+    dst_size: usize,
+    filter: ScaleFilter,
+) -> AvifResult<Vec<Contribution>> {
+    let scale = src_size as f64 / dst_size as f64;
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+    let mut contributions: Vec<Contribution> = create_vec_exact(dst_size)?;
+    for dst_index in 0..dst_size {
+        let (start, mut weights): (usize, Vec<f64>) = if filter == ScaleFilter::Box && scale > 1.0 {
+            // Area of the intersection between the source sample and the destination sample.
+            let left = dst_index as f64 * scale;
+            let right = left + scale;
+            let start = left.floor() as usize;
+            let end = (right.ceil() as usize).min(src_size);
+            (
+                start,
+                (start..end)
+                    .map(|i| right.min(i as f64 + 1.0) - left.max(i as f64))
+                    .collect(),
+            )
+        } else {
+            let center = (dst_index as f64 + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(src_size);
+            (
+                start,
+                (start..end)
+                    .map(|i| filter.kernel((i as f64 + 0.5 - center) / filter_scale))
+                    .collect(),
+            )
+        };
+        // Drop the zero weights on both sides.
+        let first = weights.iter().position(|w| *w != 0.0);
+        let last = weights.iter().rposition(|w| *w != 0.0);
+        let (start, weights) = match (first, last) {
+            (Some(first), Some(last)) => {
+                weights.truncate(last + 1);
+                weights.drain(..first);
+                (start + first, weights)
+            }
+            // Can only happen with degenerate inputs. Fall back to the nearest sample.
+            _ => (
+                ((dst_index as f64 + 0.5) * scale).min(src_size as f64 - 1.0) as usize,
+                vec![1.0],
+            ),
+        };
+        let sum: f64 = weights.iter().sum();
+        contributions.push(Contribution {
+            start,
+            weights: weights.iter().map(|w| (w / sum) as f32).collect(),
+        });
+    }
+    Ok(contributions)
+}
+
+pub(crate) trait Sample: Copy + Send + Sync {
+    fn to_f32(self) -> f32;
+    fn from_f32(value: f32, max_value: f32) -> Self;
+}
+
+impl Sample for u8 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(value: f32, max_value: f32) -> Self {
+        (value + 0.5).clamp(0.0, max_value) as u8
+    }
+}
+
+impl Sample for u16 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(value: f32, max_value: f32) -> Self {
+        (value + 0.5).clamp(0.0, max_value) as u16
+    }
+}
+
+impl Sample for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+    fn from_f32(value: f32, max_value: f32) -> Self {
+        value.clamp(0.0, max_value)
+    }
+}
+
+// Calls f(first_row_index, rows) on up to max_threads threads, with rows split into contiguous
+// chunks.
+fn for_each_row_chunk<R: Send, F>(mut rows: Vec<R>, max_threads: u32, f: F)
+where
+    F: Fn(usize, &mut [R]) + Sync,
+{
+    let thread_count = (max_threads as usize).clamp(1, rows.len().max(1));
+    if thread_count == 1 {
+        f(0, &mut rows);
+        return;
+    }
+    let chunk_size = rows.len().div_ceil(thread_count);
+    std::thread::scope(|scope| {
+        for (chunk_index, chunk) in rows.chunks_mut(chunk_size).enumerate() {
+            let f = &f;
+            scope.spawn(move || f(chunk_index * chunk_size, chunk));
+        }
+    });
+}
+
+pub(crate) struct PlaneGeometry {
+    // Dimensions in pixels. Each pixel is made of `channels` interleaved samples.
+    pub width: usize,
+    pub height: usize,
+    // Distance between two consecutive rows, in samples.
+    pub stride: usize,
+}
+
+impl PlaneGeometry {
+    fn required_len(&self, channels: usize) -> usize {
+        if self.height == 0 {
+            0
+        } else {
+            (self.height - 1) * self.stride + self.width * channels
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn scale_plane<T: Sample>(
+    src: &[T],
+    src_geometry: &PlaneGeometry,
+    dst: &mut [T],
+    dst_geometry: &PlaneGeometry,
+    channels: usize,
+    max_value: f32,
+    filter: ScaleFilter,
+    max_threads: u32,
+) -> AvifResult<()> {
+    if src_geometry.width == 0
+        || src_geometry.height == 0
+        || dst_geometry.width == 0
+        || dst_geometry.height == 0
+        || src.len() < src_geometry.required_len(channels)
+        || dst.len() < dst_geometry.required_len(channels)
+    {
+        return AvifError::invalid_argument();
+    }
+    let horizontal = compute_contributions(src_geometry.width, dst_geometry.width, filter)?;
+    let vertical = compute_contributions(src_geometry.height, dst_geometry.height, filter)?;
+
+    // Horizontal pass into an intermediate buffer of src_height rows of dst_width pixels.
+    let row_len = dst_geometry.width * channels;
+    let mut intermediate: Vec<f32> = create_vec_exact(src_geometry.height * row_len)?;
+    intermediate.resize(src_geometry.height * row_len, 0.0);
+    for_each_row_chunk(
+        intermediate.chunks_mut(row_len).collect(),
+        max_threads,
+        |first_y, rows: &mut [&mut [f32]]| {
+            for (y, row) in rows.iter_mut().enumerate() {
+                let src_row = &src[(first_y + y) * src_geometry.stride..];
+                for (x, contribution) in horizontal.iter().enumerate() {
+                    for c in 0..channels {
+                        let mut sum = 0.0;
+                        for (i, weight) in contribution.weights.iter().enumerate() {
+                            sum +=
+                                src_row[(contribution.start + i) * channels + c].to_f32() * weight;
+                        }
+                        row[x * channels + c] = sum;
+                    }
+                }
+            }
+        },
+    );
+
+    // Vertical pass into the destination.
+    for_each_row_chunk(
+        dst.chunks_mut(dst_geometry.stride)
+            .take(dst_geometry.height)
+            .collect(),
+        max_threads,
+        |first_y, rows: &mut [&mut [T]]| {
+            for (y, row) in rows.iter_mut().enumerate() {
+                let contribution = &vertical[first_y + y];
+                for (x, dst_sample) in row[..row_len].iter_mut().enumerate() {
+                    let mut sum = 0.0;
+                    for (i, weight) in contribution.weights.iter().enumerate() {
+                        sum += intermediate[(contribution.start + i) * row_len + x] * weight;
+                    }
+                    *dst_sample = T::from_f32(sum, max_value);
+                }
+            }
+        },
+    );
+    Ok(())
+}
+
+impl Image {
+    // Returns the number of interleaved samples per pixel of the given plane.
+    fn interleaved_channel_count(&self, plane: Plane) -> usize {
+        match (self.yuv_format, plane) {
+            (
+                PixelFormat::AndroidNv12 | PixelFormat::AndroidNv21 | PixelFormat::AndroidP010,
+                Plane::U,
+            ) => 2,
+            _ => 1,
+        }
+    }
+
+    // Returns the offset (in samples) and the geometry of the area of the given plane that
+    // corresponds to rect, which is expressed in luma samples.
+    fn plane_region(&self, plane: Plane, rect: &CropRect) -> (usize, PlaneGeometry) {
+        let (shift_x, shift_y) = match plane {
+            Plane::Y | Plane::A => (0, 0),
+            Plane::U | Plane::V => (
+                self.yuv_format.chroma_shift_x().0,
+                self.yuv_format.chroma_shift_y(),
+            ),
+        };
+        let pixel_size = if self.depth == 8 { 1 } else { 2 };
+        let stride = self.row_bytes[plane.as_usize()] as usize / pixel_size;
+        let x = (rect.x >> shift_x) as usize;
+        let y = (rect.y >> shift_y) as usize;
+        (
+            y * stride + x * self.interleaved_channel_count(plane),
+            PlaneGeometry {
+                width: ((rect.width + shift_x) >> shift_x) as usize,
+                height: ((rect.height + shift_y) >> shift_y) as usize,
+                stride,
+            },
+        )
+    }
+
+    // Scales the planes of the given category with the portable scaler. The planes are
+    // reallocated and owned by the image after this call.
+    pub(crate) fn scale_rust(
+        &mut self,
+        width: u32,
+        height: u32,
+        category: Category,
+        filter: ScaleFilter,
+        max_threads: u32,
+    ) -> AvifResult<()> {
+        if self.width == width && self.height == height {
+            return Ok(());
+        }
+        let rect = CropRect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        };
+        self.scale_region_rust(&rect, width, height, category, filter, max_threads)
+    }
+
+    // Same as scale_rust() but only the area of the image covered by rect is scaled to
+    // width x height.
+    pub(crate) fn scale_region_rust(
+        &mut self,
+        rect: &CropRect,
+        width: u32,
+        height: u32,
+        category: Category,
+        filter: ScaleFilter,
+        max_threads: u32,
+    ) -> AvifResult<()> {
+        if width == 0 || height == 0 || !rect.is_valid(self.width, self.height, self.yuv_format) {
+            return AvifError::invalid_argument();
+        }
+        // The planes are scaled into a temporary image so that self is left untouched on failure.
+        let mut dst = Image {
+            width,
+            height,
+            depth: self.depth,
+            yuv_format: self.yuv_format,
+            ..Image::default()
+        };
+        if category.planes().iter().any(|plane| self.has_plane(*plane)) {
+            dst.allocate_planes(category)?;
+        }
+        // P010 samples are stored in the most significant bits.
+        let max_value = if self.yuv_format == PixelFormat::AndroidP010 {
+            u16::MAX as f32
+        } else {
+            self.max_channel_f()
+        };
+        let dst_rect = CropRect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        for plane in category.planes() {
+            let plane = *plane;
+            if !self.has_plane(plane) || !dst.has_plane(plane) {
+                continue;
+            }
+            let channels = self.interleaved_channel_count(plane);
+            let (src_offset, src_geometry) = self.plane_region(plane, rect);
+            let (_, dst_geometry) = dst.plane_region(plane, &dst_rect);
+            let src_offset = u32_from_usize(src_offset)?;
+            let src_len = u32_from_usize(src_geometry.required_len(channels))?;
+            let dst_len = u32_from_usize(dst_geometry.required_len(channels))?;
+            let src_pixels = self.planes[plane.as_usize()].unwrap_ref();
+            let dst_pixels = dst.planes[plane.as_usize()].unwrap_mut();
+            if self.depth == 8 {
+                scale_plane(
+                    src_pixels.slice(src_offset, src_len)?,
+                    &src_geometry,
+                    dst_pixels.slice_mut(0, dst_len)?,
+                    &dst_geometry,
+                    channels,
+                    max_value,
+                    filter,
+                    max_threads,
+                )?;
+            } else {
+                scale_plane(
+                    src_pixels.slice16(src_offset, src_len)?,
+                    &src_geometry,
+                    dst_pixels.slice16_mut(0, dst_len)?,
+                    &dst_geometry,
+                    channels,
+                    max_value,
+                    filter,
+                    max_threads,
+                )?;
+            }
+        }
+        for plane in category.planes() {
+            let plane = plane.as_usize();
+            self.planes[plane] = dst.planes[plane].take();
+            self.row_bytes[plane] = dst.row_bytes[plane];
+            self.image_owns_planes[plane] = dst.image_owns_planes[plane];
+        }
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "libyuv"))]
+impl Image {
+    pub(crate) fn scale(
+        &mut self,
+        width: u32,
+        height: u32,
+        category: Category,
+        filter: ScaleFilter,
+        max_threads: u32,
+    ) -> AvifResult<()> {
+        self.scale_rust(width, height, category, filter, max_threads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::pixels::*;
+    use test_case::test_case;
+    use test_case::test_matrix;
+
+    fn image_with_values(width: u32, height: u32, depth: u8, values: &[u16]) -> Image {
+        let mut image = Image {
+            width,
+            height,
+            depth,
+            yuv_format: PixelFormat::Yuv400,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color).unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                let value = values[(y * width + x) as usize];
+                if depth == 8 {
+                    image.row_mut(Plane::Y, y).unwrap()[x as usize] = value as u8;
+                } else {
+                    image.row16_mut(Plane::Y, y).unwrap()[x as usize] = value;
+                }
+            }
+        }
+        image
+    }
+
+    fn values(image: &Image) -> Vec<u16> {
+        let mut values = Vec::new();
+        for y in 0..image.height {
+            if image.depth == 8 {
+                values.extend(
+                    image
+                        .row_exact(Plane::Y, y)
+                        .unwrap()
+                        .iter()
+                        .map(|x| *x as u16),
+                );
+            } else {
+                values.extend_from_slice(image.row16_exact(Plane::Y, y).unwrap());
+            }
+        }
+        values
+    }
+
+    #[test_case(8)]
+    #[test_case(10)]
+    #[test_case(16)]
+    fn upscale_matches_libyuv(depth: u8) {
+        let mut image = image_with_values(2, 2, depth, &[10, 20, 30, 40]);
+        assert!(image
+            .scale_rust(4, 4, Category::Color, ScaleFilter::Box, 1)
+            .is_ok());
+        // Same as the output of libyuv's ScalePlane() with kFilterBox.
+        assert_eq!(
+            values(&image),
+            [10, 13, 18, 20, 15, 18, 23, 25, 25, 28, 33, 35, 30, 33, 38, 40]
+        );
+    }
+
+    #[test_case(ScaleFilter::Box, &[25, 45, 105, 125])]
+    #[test_case(ScaleFilter::Bilinear, &[36, 51, 99, 114])]
+    fn downscale(filter: ScaleFilter, expected: &[u16]) {
+        #[rustfmt::skip]
+        let mut image = image_with_values(4, 4, 8, &[
+            0, 10, 20, 30,
+            40, 50, 60, 70,
+            80, 90, 100, 110,
+            120, 130, 140, 150,
+        ]);
+        assert!(image.scale_rust(2, 2, Category::Color, filter, 1).is_ok());
+        assert_eq!(values(&image), expected);
+    }
+
+    #[test_matrix(
+        [ScaleFilter::Box, ScaleFilter::Bilinear, ScaleFilter::Lanczos],
+        [(7, 5), (3, 9), (1, 1)]
+    )]
+    fn constant_image_stays_constant(filter: ScaleFilter, dimensions: (u32, u32)) {
+        let mut image = image_with_values(5, 6, 12, &[1234; 30]);
+        assert!(image
+            .scale_rust(dimensions.0, dimensions.1, Category::Color, filter, 1)
+            .is_ok());
+        assert!(values(&image).iter().all(|x| *x == 1234));
+    }
+
+    #[test]
+    fn lanczos_is_clamped() {
+        let mut image = image_with_values(4, 1, 8, &[0, 255, 0, 255]);
+        assert!(image
+            .scale_rust(11, 1, Category::Color, ScaleFilter::Lanczos, 1)
+            .is_ok());
+        assert_eq!(image.width, 11);
+        assert_eq!(values(&image).len(), 11);
+    }
+
+    #[test_matrix(
+        [PixelFormat::Yuv444, PixelFormat::Yuv422, PixelFormat::Yuv420, PixelFormat::Yuv400],
+        [ScaleFilter::Box, ScaleFilter::Bilinear, ScaleFilter::Lanczos]
+    )]
+    fn multithreaded_matches_single_threaded(yuv_format: PixelFormat, filter: ScaleFilter) {
+        let create_image = || {
+            let mut image = Image {
+                width: 37,
+                height: 29,
+                depth: 10,
+                yuv_format,
+                ..Default::default()
+            };
+            image.allocate_planes(Category::Color).unwrap();
+            image.allocate_planes(Category::Alpha).unwrap();
+            for plane in ALL_PLANES {
+                if let Some(plane_data) = image.plane_data(plane) {
+                    for y in 0..plane_data.height {
+                        for (x, sample) in image.row16_mut(plane, y).unwrap().iter_mut().enumerate()
+                        {
+                            *sample = ((x as u32 * 31 + y * 17) % 1024) as u16;
+                        }
+                    }
+                }
+            }
+            image
+        };
+        let mut image1 = create_image();
+        let mut image4 = create_image();
+        for category in [Category::Color, Category::Alpha] {
+            for (image, max_threads) in [(&mut image1, 1), (&mut image4, 4)] {
+                image.width = 37;
+                image.height = 29;
+                assert!(image
+                    .scale_rust(15, 20, category, filter, max_threads)
+                    .is_ok());
+            }
+        }
+        for plane in ALL_PLANES {
+            assert_eq!(image1.width(plane), image4.width(plane));
+            for y in 0..image1.height(plane) as u32 {
+                assert_eq!(
+                    image1.row16_exact(plane, y).unwrap(),
+                    image4.row16_exact(plane, y).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scale_pointer_input() {
+        let mut values = [10u8, 20, 30, 40];
+        let mut image = Image {
+            width: 2,
+            height: 2,
+            depth: 8,
+            yuv_format: PixelFormat::Yuv400,
+            ..Default::default()
+        };
+        image.planes[0] = Some(Pixels::Pointer(unsafe {
+            PointerSlice::create(values.as_mut_ptr(), values.len()).unwrap()
+        }));
+        image.row_bytes[0] = 2;
+        assert!(image
+            .scale_rust(1, 1, Category::Color, ScaleFilter::Box, 1)
+            .is_ok());
+        assert!(image.image_owns_planes[0]);
+        assert_eq!(self::values(&image), [25]);
+        // The input was not modified.
+        assert_eq!(values, [10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn scale_failure_keeps_image() {
+        let mut image = image_with_values(2, 2, 8, &[10, 20, 30, 40]);
+        // The plane is too small for its dimensions.
+        image.planes[0] = Some(Pixels::Buffer(vec![10, 20]));
+        assert!(image
+            .scale_rust(1, 1, Category::Color, ScaleFilter::Box, 1)
+            .is_err());
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.row_bytes[0], 2);
+        assert_eq!(image.row(Plane::Y, 0).unwrap(), [10, 20]);
+    }
+
+    #[test_case(PixelFormat::AndroidNv12, 8)]
+    #[test_case(PixelFormat::AndroidP010, 10)]
+    fn scale_interleaved_odd_dimension(yuv_format: PixelFormat, depth: u8) -> AvifResult<()> {
+        let mut image = Image {
+            width: 99,
+            height: 49,
+            depth,
+            yuv_format,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color)?;
+        // The interleaved chroma plane holds 50 U and V pairs per row.
+        for y in 0..25 {
+            if depth == 8 {
+                image.row_mut(Plane::U, y)?[..100].fill(100);
+            } else {
+                image.row16_mut(Plane::U, y)?[..100].fill(100);
+            }
+        }
+        assert!(image
+            .scale_rust(49, 24, Category::Color, ScaleFilter::Bilinear, 2)
+            .is_ok());
+        assert_eq!(image.width, 49);
+        assert_eq!(image.height, 24);
+        // The interleaved chroma plane holds 25 U and V pairs per row.
+        for y in 0..12 {
+            if depth == 8 {
+                assert!(image.row(Plane::U, y)?[..50].iter().all(|x| *x == 100));
+            } else {
+                assert!(image.row16(Plane::U, y)?[..50].iter().all(|x| *x == 100));
+            }
+        }
+        Ok(())
+    }
+}
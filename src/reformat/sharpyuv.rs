@@ -35,7 +35,8 @@ pub(crate) fn rgb_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> AvifResu
                 SharpYuvRange_kSharpYuvRangeFull
             },
         },
-        _ => return AvifError::not_implemented(),
+        // libsharpyuv only supports the YUV matrices.
+        _ => return crate::reformat::sharpyuv_impl::rgb_to_yuv(rgb, image),
     };
     let mut matrix_uninit: MaybeUninit<SharpYuvConversionMatrix> = MaybeUninit::uninit();
     // SAFETY: Calling into a C function with pointers that are guaranteed to be not null.
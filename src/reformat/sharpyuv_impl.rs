@@ -0,0 +1,437 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Portable implementation of the "sharp" RGB to YUV 4:2:0 conversion of libsharpyuv. The luma
+// and chroma samples are iteratively refined so that the RGB image reconstructed with bilinear
+// chroma upsampling is as close as possible to the source, which avoids the color bleeding and
+// darkening around sharp color edges caused by plain chroma averaging.
+//
+// Like in libsharpyuv, each pixel is represented by W, its gray level computed in linear light,
+// plus chroma residuals (R - W, G - W, B - W) that are shared by 2x2 blocks of pixels.
+
+use super::rgb;
+use super::rgb::*;
+use super::rgb_impl::*;
+use super::transfer::*;
+
+use crate::image::Plane;
+use crate::internal_utils::*;
+use crate::*;
+
+use std::cmp::min;
+
+const NUM_ITERATIONS: usize = 4;
+
+type Channels = [Vec<f32>; 3];
+
+fn create_channels(size: usize) -> AvifResult<Channels> {
+    let mut channels: Channels = Default::default();
+    for channel in channels.iter_mut() {
+        *channel = create_vec_exact(size)?;
+        channel.resize(size, 0.0);
+    }
+    Ok(channels)
+}
+
+struct SharpYuv {
+    transfer_characteristics: TransferCharacteristics,
+    gray_weights: [f32; 3],
+    // Dimensions of the image rounded up to even values.
+    width: usize,
+    height: usize,
+    uv_width: usize,
+    uv_height: usize,
+}
+
+impl SharpYuv {
+    fn gray(&self, rgb: [f32; 3]) -> f32 {
+        self.gray_weights[0] * rgb[0]
+            + self.gray_weights[1] * rgb[1]
+            + self.gray_weights[2] * rgb[2]
+    }
+
+    // Computes W for each pixel.
+    fn compute_w(&self, rgb: &Channels, w: &mut [f32]) {
+        let tc = self.transfer_characteristics;
+        for (i, w) in w.iter_mut().enumerate() {
+            let linear = [
+                to_linear(tc, rgb[0][i]),
+                to_linear(tc, rgb[1][i]),
+                to_linear(tc, rgb[2][i]),
+            ];
+            *w = from_linear(tc, self.gray(linear));
+        }
+    }
+
+    // Averages each 2x2 block in linear light and computes its chroma residuals.
+    fn compute_uv(&self, rgb: &Channels, uv: &mut Channels) {
+        let tc = self.transfer_characteristics;
+        for by in 0..self.uv_height {
+            for bx in 0..self.uv_width {
+                let top_left = 2 * by * self.width + 2 * bx;
+                let mut average = [0.0; 3];
+                for (c, average) in average.iter_mut().enumerate() {
+                    let sum = to_linear(tc, rgb[c][top_left])
+                        + to_linear(tc, rgb[c][top_left + 1])
+                        + to_linear(tc, rgb[c][top_left + self.width])
+                        + to_linear(tc, rgb[c][top_left + self.width + 1]);
+                    *average = from_linear(tc, sum / 4.0);
+                }
+                let w = self.gray(average);
+                for (c, average) in average.iter().enumerate() {
+                    uv[c][by * self.uv_width + bx] = average - w;
+                }
+            }
+        }
+    }
+
+    // Reconstructs the RGB image from W and the chroma residuals upsampled with the bilinear
+    // (9, 3, 3, 1) / 16 filter.
+    fn reconstruct(&self, w: &[f32], uv: &Channels, rgb: &mut Channels) {
+        for y in 0..self.height {
+            let by = y >> 1;
+            let neighbor_by =
+                if y & 1 == 0 { by.saturating_sub(1) } else { min(by + 1, self.uv_height - 1) };
+            let row = by * self.uv_width;
+            let neighbor_row = neighbor_by * self.uv_width;
+            for x in 0..self.width {
+                let bx = x >> 1;
+                let neighbor_bx =
+                    if x & 1 == 0 { bx.saturating_sub(1) } else { min(bx + 1, self.uv_width - 1) };
+                let i = y * self.width + x;
+                for c in 0..3 {
+                    let residual = (9.0 * uv[c][row + bx]
+                        + 3.0 * uv[c][row + neighbor_bx]
+                        + 3.0 * uv[c][neighbor_row + bx]
+                        + uv[c][neighbor_row + neighbor_bx])
+                        / 16.0;
+                    rgb[c][i] = (w[i] + residual).clamp(0.0, 1.0);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn rgb_to_yuv(rgb: &rgb::Image, image: &mut image::Image) -> AvifResult<()> {
+    if image.yuv_format != PixelFormat::Yuv420 || rgb.format == Format::Rgb565 {
+        return AvifError::not_implemented();
+    }
+    let mode: Mode = (image as &image::Image).into();
+    let gray_weights = match mode {
+        Mode::YuvCoefficients(kr, kg, kb) => [kr, kg, kb],
+        Mode::Ycgco | Mode::YcgcoRe | Mode::YcgcoRo => [0.25, 0.5, 0.25],
+        // Identity requires 4:4:4.
+        Mode::Identity => return AvifError::not_implemented(),
+    };
+    let src_width = image.width as usize;
+    let src_height = image.height as usize;
+    if src_width == 0 || src_height == 0 {
+        return AvifError::invalid_argument();
+    }
+    let uv_width = src_width.div_ceil(2);
+    let uv_height = src_height.div_ceil(2);
+    let sharp_yuv = SharpYuv {
+        transfer_characteristics: image.transfer_characteristics,
+        gray_weights,
+        width: uv_width * 2,
+        height: uv_height * 2,
+        uv_width,
+        uv_height,
+    };
+    let size = sharp_yuv.width * sharp_yuv.height;
+    let uv_size = uv_width * uv_height;
+
+    // Import the source samples, replicating the last column and row if the dimensions are odd.
+    let offsets = [
+        rgb.format.r_offset(),
+        rgb.format.g_offset(),
+        rgb.format.b_offset(),
+    ];
+    let channel_count = rgb.channel_count() as usize;
+    let rgb_max_channel_f = rgb.max_channel_f();
+    let mut source = create_channels(size)?;
+    for y in 0..sharp_yuv.height {
+        let src_y = u32_from_usize(min(y, src_height - 1))?;
+        for x in 0..sharp_yuv.width {
+            let pixel = min(x, src_width - 1) * channel_count;
+            for c in 0..3 {
+                let value = if rgb.depth == 8 {
+                    rgb.row(src_y)?[pixel + offsets[c]] as f32
+                } else {
+                    rgb.row16(src_y)?[pixel + offsets[c]] as f32
+                };
+                source[c][y * sharp_yuv.width + x] = value / rgb_max_channel_f;
+            }
+        }
+    }
+
+    let mut target_w: Vec<f32> = create_vec_exact(size)?;
+    target_w.resize(size, 0.0);
+    let mut target_uv = create_channels(uv_size)?;
+    sharp_yuv.compute_w(&source, &mut target_w);
+    sharp_yuv.compute_uv(&source, &mut target_uv);
+
+    let mut best_w = target_w.clone();
+    let mut best_uv = target_uv.clone();
+    // Reuse the source buffers for the reconstructed image.
+    let mut reconstructed = source;
+    let mut reconstructed_w = target_w.clone();
+    let mut reconstructed_uv = target_uv.clone();
+    // Same as libsharpyuv: stop when the average luma difference is below 3/4 of a code value.
+    let diff_threshold = 3.0 * size as f32 / (4.0 * (rgb_max_channel_f + 1.0));
+    let mut prev_diff = f32::MAX;
+    for iteration in 0..NUM_ITERATIONS {
+        sharp_yuv.reconstruct(&best_w, &best_uv, &mut reconstructed);
+        sharp_yuv.compute_w(&reconstructed, &mut reconstructed_w);
+        sharp_yuv.compute_uv(&reconstructed, &mut reconstructed_uv);
+        let mut diff = 0.0;
+        for i in 0..size {
+            let delta = target_w[i] - reconstructed_w[i];
+            diff += delta.abs();
+            best_w[i] = (best_w[i] + delta).clamp(0.0, 1.0);
+        }
+        for c in 0..3 {
+            for i in 0..uv_size {
+                best_uv[c][i] += target_uv[c][i] - reconstructed_uv[c][i];
+            }
+        }
+        if iteration > 0 && (diff < diff_threshold || diff > prev_diff) {
+            break;
+        }
+        prev_diff = diff;
+    }
+
+    // Convert W and the chroma residuals to YUV.
+    let (bias_y, range_y) = bias_and_range_y(image);
+    let (bias_uv, range_uv) = bias_and_range_uv(image);
+    let yuv_max_channel = image.max_channel();
+    for y in 0..src_height {
+        let row = y * sharp_yuv.width;
+        let uv_row = (y >> 1) * uv_width;
+        let j = u32_from_usize(y)?;
+        for x in 0..src_width {
+            let w = best_w[row + x];
+            let block = uv_row + (x >> 1);
+            let yuv = rgb_pixel_to_yuv_pixel(
+                mode,
+                w + best_uv[0][block],
+                w + best_uv[1][block],
+                w + best_uv[2][block],
+                rgb_max_channel_f,
+                range_y,
+                range_uv,
+            );
+            let value = to_unorm(bias_y, range_y, yuv_max_channel, yuv.0);
+            if image.depth == 8 {
+                image.row_mut(Plane::Y, j)?[x] = value as u8;
+            } else {
+                image.row16_mut(Plane::Y, j)?[x] = value;
+            }
+        }
+    }
+    for by in 0..uv_height {
+        let j = u32_from_usize(by)?;
+        for bx in 0..uv_width {
+            let top_left = 2 * by * sharp_yuv.width + 2 * bx;
+            let w = (best_w[top_left]
+                + best_w[top_left + 1]
+                + best_w[top_left + sharp_yuv.width]
+                + best_w[top_left + sharp_yuv.width + 1])
+                / 4.0;
+            let block = by * uv_width + bx;
+            let yuv = rgb_pixel_to_yuv_pixel(
+                mode,
+                w + best_uv[0][block],
+                w + best_uv[1][block],
+                w + best_uv[2][block],
+                rgb_max_channel_f,
+                range_y,
+                range_uv,
+            );
+            let u = to_unorm(bias_uv, range_uv, yuv_max_channel, yuv.1);
+            let v = to_unorm(bias_uv, range_uv, yuv_max_channel, yuv.2);
+            if image.depth == 8 {
+                image.row_mut(Plane::U, j)?[bx] = u as u8;
+                image.row_mut(Plane::V, j)?[bx] = v as u8;
+            } else {
+                image.row16_mut(Plane::U, j)?[bx] = u;
+                image.row16_mut(Plane::V, j)?[bx] = v;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reformat::rgb_impl;
+    use test_case::test_case;
+
+    fn create_rgb(
+        width: u32,
+        height: u32,
+        depth: u8,
+        pixel: impl Fn(u32, u32) -> [u16; 3],
+    ) -> rgb::Image {
+        let mut rgb = rgb::Image {
+            width,
+            height,
+            depth,
+            format: Format::Rgb,
+            ..Default::default()
+        };
+        assert!(rgb.allocate().is_ok());
+        for y in 0..height {
+            for x in 0..width {
+                let i = x as usize * 3;
+                let value = pixel(x, y);
+                if depth == 8 {
+                    let row = rgb.row_mut(y).unwrap();
+                    for c in 0..3 {
+                        row[i + c] = value[c] as u8;
+                    }
+                } else {
+                    rgb.row16_mut(y).unwrap()[i..i + 3].copy_from_slice(&value);
+                }
+            }
+        }
+        rgb
+    }
+
+    fn create_yuv(
+        rgb: &rgb::Image,
+        depth: u8,
+        matrix_coefficients: MatrixCoefficients,
+    ) -> image::Image {
+        let mut image = image::Image {
+            width: rgb.width,
+            height: rgb.height,
+            depth,
+            yuv_format: PixelFormat::Yuv420,
+            yuv_range: YuvRange::Full,
+            matrix_coefficients,
+            ..Default::default()
+        };
+        assert!(image.allocate_planes(Category::Color).is_ok());
+        image
+    }
+
+    fn linear_luminance(rgb: &rgb::Image, x: usize, y: u32) -> f32 {
+        let pixel = if rgb.depth == 8 {
+            rgb.row(y).unwrap()[x * 3..x * 3 + 3]
+                .iter()
+                .map(|v| *v as f32)
+                .collect::<Vec<_>>()
+        } else {
+            rgb.row16(y).unwrap()[x * 3..x * 3 + 3]
+                .iter()
+                .map(|v| *v as f32)
+                .collect::<Vec<_>>()
+        };
+        let tc = TransferCharacteristics::Srgb;
+        let max = rgb.max_channel_f();
+        0.2126 * to_linear(tc, pixel[0] / max)
+            + 0.7152 * to_linear(tc, pixel[1] / max)
+            + 0.0722 * to_linear(tc, pixel[2] / max)
+    }
+
+    // Returns the sum of absolute differences between the linear light luminance of rgb and of
+    // the RGB image reconstructed from image with bilinear chroma upsampling.
+    fn luminance_error(rgb: &rgb::Image, image: &image::Image) -> f32 {
+        let mut reconstructed = rgb::Image::create_from_yuv(image);
+        reconstructed.depth = rgb.depth;
+        reconstructed.format = Format::Rgb;
+        reconstructed.chroma_upsampling = ChromaUpsampling::Bilinear;
+        assert!(reconstructed.allocate().is_ok());
+        assert!(yuv_to_rgb_any(image, &mut reconstructed, AlphaMultiplyMode::NoOp).is_ok());
+        let mut error = 0.0;
+        for y in 0..rgb.height {
+            for x in 0..rgb.width as usize {
+                error +=
+                    (linear_luminance(rgb, x, y) - linear_luminance(&reconstructed, x, y)).abs();
+            }
+        }
+        error
+    }
+
+    #[test_case(8, 8, MatrixCoefficients::Bt601)]
+    #[test_case(8, 10, MatrixCoefficients::Bt709)]
+    #[test_case(16, 12, MatrixCoefficients::Bt2020Ncl)]
+    #[test_case(10, 10, MatrixCoefficients::Ycgco)]
+    #[test_case(8, 10, MatrixCoefficients::YcgcoRe)]
+    fn flat_image(rgb_depth: u8, yuv_depth: u8, matrix_coefficients: MatrixCoefficients) {
+        let max = ((1u32 << rgb_depth) - 1) as u16;
+        let rgb = create_rgb(5, 3, rgb_depth, |_, _| [max / 3, max / 2, max / 5]);
+        let mut sharp = create_yuv(&rgb, yuv_depth, matrix_coefficients);
+        let mut average = create_yuv(&rgb, yuv_depth, matrix_coefficients);
+        assert!(rgb_to_yuv(&rgb, &mut sharp).is_ok());
+        assert!(rgb_impl::rgb_to_yuv(&rgb, &mut average).is_ok());
+        for plane in image::YUV_PLANES {
+            for y in 0..sharp.height(plane) as u32 {
+                for x in 0..sharp.width(plane) {
+                    let (a, b) = if yuv_depth == 8 {
+                        (
+                            sharp.row(plane, y).unwrap()[x] as u16,
+                            average.row(plane, y).unwrap()[x] as u16,
+                        )
+                    } else {
+                        (
+                            sharp.row16(plane, y).unwrap()[x],
+                            average.row16(plane, y).unwrap()[x],
+                        )
+                    };
+                    assert!(a.abs_diff(b) <= 1, "{plane:?} {x}x{y}: {a} vs {b}");
+                }
+            }
+        }
+    }
+
+    #[test_case(8, 8, MatrixCoefficients::Bt601, 16, 16)]
+    #[test_case(8, 8, MatrixCoefficients::Bt709, 15, 9)]
+    #[test_case(16, 12, MatrixCoefficients::Bt709, 16, 16)]
+    #[test_case(10, 10, MatrixCoefficients::Ycgco, 13, 16)]
+    fn sharp_edges(
+        rgb_depth: u8,
+        yuv_depth: u8,
+        matrix_coefficients: MatrixCoefficients,
+        width: u32,
+        height: u32,
+    ) {
+        // A red rectangle on a green background, with edges that are not aligned to the chroma
+        // sample grid.
+        let max = ((1u32 << rgb_depth) - 1) as u16;
+        let rgb = create_rgb(width, height, rgb_depth, |x, y| {
+            if (3..width - 4).contains(&x) && (5..height - 2).contains(&y) {
+                [max, 0, 0]
+            } else {
+                [0, max, 0]
+            }
+        });
+        let mut sharp = create_yuv(&rgb, yuv_depth, matrix_coefficients);
+        let mut average = create_yuv(&rgb, yuv_depth, matrix_coefficients);
+        assert!(rgb_to_yuv(&rgb, &mut sharp).is_ok());
+        assert!(rgb_impl::rgb_to_yuv(&rgb, &mut average).is_ok());
+        assert!(luminance_error(&rgb, &sharp) < luminance_error(&rgb, &average) / 2.0);
+    }
+
+    #[test_case(PixelFormat::Yuv444, MatrixCoefficients::Bt601)]
+    #[test_case(PixelFormat::Yuv420, MatrixCoefficients::Identity)]
+    fn not_implemented(yuv_format: PixelFormat, matrix_coefficients: MatrixCoefficients) {
+        let rgb = create_rgb(4, 4, 8, |_, _| [0, 0, 0]);
+        let mut image = create_yuv(&rgb, 8, matrix_coefficients);
+        image.yuv_format = yuv_format;
+        assert_eq!(rgb_to_yuv(&rgb, &mut image), Err(AvifError::NotImplemented));
+    }
+}
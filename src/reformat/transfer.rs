@@ -0,0 +1,186 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::*;
+
+// SMPTE ST 2084 constants.
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+// Converts a non-linear signal value in [0, 1] into linear light in [0, 1].
+pub(crate) fn to_linear(transfer_characteristics: TransferCharacteristics, value: f32) -> f32 {
+    let v = value.clamp(0.0, 1.0);
+    match transfer_characteristics {
+        TransferCharacteristics::Linear => v,
+        TransferCharacteristics::Bt470m => v.powf(2.2),
+        TransferCharacteristics::Bt470bg => v.powf(2.8),
+        TransferCharacteristics::Bt709
+        | TransferCharacteristics::Bt601
+        | TransferCharacteristics::Bt1361
+        | TransferCharacteristics::Bt2020_10bit
+        | TransferCharacteristics::Bt2020_12bit => {
+            if v < 0.081 {
+                v / 4.5
+            } else {
+                ((v + 0.099) / 1.099).powf(1.0 / 0.45)
+            }
+        }
+        TransferCharacteristics::Smpte240 => {
+            if v < 0.0913 {
+                v / 4.0
+            } else {
+                ((v + 0.1115) / 1.1115).powf(1.0 / 0.45)
+            }
+        }
+        TransferCharacteristics::Log100 => {
+            if v == 0.0 {
+                0.0
+            } else {
+                10f32.powf(2.0 * (v - 1.0))
+            }
+        }
+        TransferCharacteristics::Log100Sqrt10 => {
+            if v == 0.0 {
+                0.0
+            } else {
+                10f32.powf(2.5 * (v - 1.0))
+            }
+        }
+        TransferCharacteristics::Pq => {
+            let vp = v.powf(1.0 / PQ_M2);
+            ((vp - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * vp)).powf(1.0 / PQ_M1)
+        }
+        TransferCharacteristics::Hlg => {
+            if v <= 0.5 {
+                v * v / 3.0
+            } else {
+                (((v - 0.5599107) / 0.17883277).exp() + 0.28466892) / 12.0
+            }
+        }
+        // The 52.37 / 48 scale factor is omitted so that the output stays within [0, 1].
+        TransferCharacteristics::Smpte428 => v.powf(2.6),
+        // sRGB is the most likely interpretation of the unspecified transfer characteristics.
+        TransferCharacteristics::Srgb
+        | TransferCharacteristics::Iec61966
+        | TransferCharacteristics::Unknown
+        | TransferCharacteristics::Unspecified
+        | TransferCharacteristics::Reserved => {
+            if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        }
+    }
+    .clamp(0.0, 1.0)
+}
+
+// Inverse of to_linear().
+pub(crate) fn from_linear(transfer_characteristics: TransferCharacteristics, value: f32) -> f32 {
+    let l = value.clamp(0.0, 1.0);
+    match transfer_characteristics {
+        TransferCharacteristics::Linear => l,
+        TransferCharacteristics::Bt470m => l.powf(1.0 / 2.2),
+        TransferCharacteristics::Bt470bg => l.powf(1.0 / 2.8),
+        TransferCharacteristics::Bt709
+        | TransferCharacteristics::Bt601
+        | TransferCharacteristics::Bt1361
+        | TransferCharacteristics::Bt2020_10bit
+        | TransferCharacteristics::Bt2020_12bit => {
+            if l < 0.018 {
+                l * 4.5
+            } else {
+                1.099 * l.powf(0.45) - 0.099
+            }
+        }
+        TransferCharacteristics::Smpte240 => {
+            if l < 0.0228 {
+                l * 4.0
+            } else {
+                1.1115 * l.powf(0.45) - 0.1115
+            }
+        }
+        TransferCharacteristics::Log100 => {
+            if l <= 0.01 {
+                0.0
+            } else {
+                1.0 + l.log10() / 2.0
+            }
+        }
+        TransferCharacteristics::Log100Sqrt10 => {
+            if l <= 0.0031622777 {
+                0.0
+            } else {
+                1.0 + l.log10() / 2.5
+            }
+        }
+        TransferCharacteristics::Pq => {
+            let lp = l.powf(PQ_M1);
+            ((PQ_C1 + PQ_C2 * lp) / (1.0 + PQ_C3 * lp)).powf(PQ_M2)
+        }
+        TransferCharacteristics::Hlg => {
+            if l <= 1.0 / 12.0 {
+                (3.0 * l).sqrt()
+            } else {
+                0.17883277 * (12.0 * l - 0.28466892).ln() + 0.5599107
+            }
+        }
+        TransferCharacteristics::Smpte428 => l.powf(1.0 / 2.6),
+        TransferCharacteristics::Srgb
+        | TransferCharacteristics::Iec61966
+        | TransferCharacteristics::Unknown
+        | TransferCharacteristics::Unspecified
+        | TransferCharacteristics::Reserved => {
+            if l <= 0.0031308 {
+                l * 12.92
+            } else {
+                1.055 * l.powf(1.0 / 2.4) - 0.055
+            }
+        }
+    }
+    .clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(TransferCharacteristics::Srgb)]
+    #[test_case(TransferCharacteristics::Bt709)]
+    #[test_case(TransferCharacteristics::Bt470m)]
+    #[test_case(TransferCharacteristics::Smpte240)]
+    #[test_case(TransferCharacteristics::Pq)]
+    #[test_case(TransferCharacteristics::Hlg)]
+    #[test_case(TransferCharacteristics::Log100)]
+    #[test_case(TransferCharacteristics::Smpte428)]
+    fn transfer_round_trip(transfer_characteristics: TransferCharacteristics) {
+        for i in 0..=100 {
+            let v = i as f32 / 100.0;
+            let linear = to_linear(transfer_characteristics, v);
+            assert!((0.0..=1.0).contains(&linear));
+            if transfer_characteristics == TransferCharacteristics::Log100 && linear <= 0.01 {
+                // All the linear values below the range of the curve map to 0.
+                continue;
+            }
+            assert!(
+                (from_linear(transfer_characteristics, linear) - v).abs() < 0.002,
+                "{v}"
+            );
+        }
+    }
+}
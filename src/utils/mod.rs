@@ -48,6 +48,19 @@ impl Fraction {
     }
 }
 
+impl Fraction {
+    // This function is not used in all configurations.
+    #[allow(dead_code)]
+    pub(crate) fn from_f64(value: f64) -> AvifResult<Self> {
+        let (numerator, denominator) = approximate_fraction(value.abs(), i32::MAX as u64)?;
+        let numerator = numerator as i32;
+        Ok(Self(
+            if value < 0.0 { -numerator } else { numerator },
+            denominator,
+        ))
+    }
+}
+
 impl UFraction {
     pub(crate) fn is_valid(&self) -> AvifResult<()> {
         match self.1 {
@@ -55,6 +68,52 @@ impl UFraction {
             _ => Ok(()),
         }
     }
+
+    // This function is not used in all configurations.
+    #[allow(dead_code)]
+    pub(crate) fn from_f64(value: f64) -> AvifResult<Self> {
+        if value < 0.0 {
+            return AvifError::invalid_argument();
+        }
+        let (numerator, denominator) = approximate_fraction(value, u32::MAX as u64)?;
+        Ok(Self(numerator as u32, denominator))
+    }
+}
+
+// Returns the closest continued fraction approximation of the non-negative value whose numerator
+// is at most max_numerator and whose denominator fits in a u32.
+fn approximate_fraction(value: f64, max_numerator: u64) -> AvifResult<(u64, u32)> {
+    if !value.is_finite() || value > max_numerator as f64 {
+        return AvifError::invalid_argument();
+    }
+    // Convergents h/k of the continued fraction, starting with h(-2)/k(-2) = 0/1 and
+    // h(-1)/k(-1) = 1/0.
+    let (mut h0, mut h1) = (0u64, 1u64);
+    let (mut k0, mut k1) = (1u64, 0u64);
+    let mut remainder = value;
+    loop {
+        let term = remainder.floor();
+        if term > u32::MAX as f64 {
+            break;
+        }
+        let term = term as u64;
+        let h2 = term * h1 + h0;
+        let k2 = term * k1 + k0;
+        if h2 > max_numerator || k2 > u32::MAX as u64 {
+            break;
+        }
+        (h0, h1) = (h1, h2);
+        (k0, k1) = (k1, k2);
+        let fractional_part = remainder - term as f64;
+        if fractional_part == 0.0 || (h1 as f64 / k1 as f64 - value).abs() <= f64::EPSILON * value {
+            break;
+        }
+        remainder = 1.0 / fractional_part;
+    }
+    if k1 == 0 {
+        return AvifError::invalid_argument();
+    }
+    Ok((h1, k1 as u32))
 }
 
 // 'clap' fractions do not follow this pattern: both numerators and denominators
@@ -171,3 +230,45 @@ impl IFraction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0.0, Fraction(0, 1))]
+    #[test_case(3.0, Fraction(3, 1))]
+    #[test_case(-0.5, Fraction(-1, 2))]
+    #[test_case(0.015625, Fraction(1, 64))]
+    #[test_case(-2.3, Fraction(-23, 10))]
+    #[test_case(1.0 / 3.0, Fraction(1, 3))]
+    fn fraction_from_f64(value: f64, expected: Fraction) {
+        assert_eq!(Fraction::from_f64(value), Ok(expected));
+    }
+
+    #[test]
+    fn fraction_from_f64_precision() {
+        for value in [std::f64::consts::PI, -1e-6, 123456.789, 1e9] {
+            let fraction = Fraction::from_f64(value).unwrap();
+            let approximation = fraction.as_f64().unwrap();
+            assert!((approximation - value).abs() <= 1e-9 * value.abs().max(1.0));
+        }
+    }
+
+    #[test_case(-1.0)]
+    #[test_case(f64::NAN)]
+    #[test_case(f64::INFINITY)]
+    #[test_case(1e10)]
+    fn ufraction_from_f64_invalid(value: f64) {
+        assert!(UFraction::from_f64(value).is_err());
+    }
+
+    #[test]
+    fn ufraction_from_f64() {
+        assert_eq!(UFraction::from_f64(2.5), Ok(UFraction(5, 2)));
+        assert_eq!(
+            UFraction::from_f64(u32::MAX as f64),
+            Ok(UFraction(u32::MAX, 1))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::AvifError;
+use crate::AvifResult;
+
+use super::image_crate::DecodedImage;
+use super::Config;
+use super::Reader;
+
+use image::codecs::bmp::BmpDecoder;
+use std::fs::File;
+use std::io::BufReader;
+
+pub struct BmpReader {
+    image: Option<DecodedImage>,
+}
+
+impl BmpReader {
+    pub fn create(filename: &str) -> AvifResult<Self> {
+        let file = File::open(filename).map_err(AvifError::map_unknown_error)?;
+        let decoder =
+            BmpDecoder::new(BufReader::new(file)).map_err(AvifError::map_unknown_error)?;
+        Ok(Self {
+            image: Some(DecodedImage::decode(decoder)?),
+        })
+    }
+}
+
+impl Reader for BmpReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        match self.image.take() {
+            Some(image) => Ok((image.into_yuv(config)?, 0)),
+            None => AvifError::unknown_error("no more bmp frames"),
+        }
+    }
+
+    fn has_more_frames(&mut self) -> bool {
+        self.image.is_some()
+    }
+}
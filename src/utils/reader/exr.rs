@@ -0,0 +1,300 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::internal_utils::stream::IStream;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+use crate::TransferCharacteristics;
+
+use super::check_image_size;
+use super::linear_to_yuv;
+use super::Config;
+use super::Reader;
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+// Version field flags.
+const TILED_FLAG: u32 = 1 << 9;
+const NON_IMAGE_FLAG: u32 = 1 << 11;
+const MULTIPART_FLAG: u32 = 1 << 12;
+// Pixel types.
+const HALF: i32 = 1;
+const FLOAT: i32 = 2;
+const NO_COMPRESSION: u8 = 0;
+
+struct Channel {
+    name: String,
+    pixel_type: i32,
+    // Index of the channel in the samples of a pixel, if it is used.
+    index: Option<usize>,
+}
+
+impl Channel {
+    fn sample_size(&self) -> usize {
+        if self.pixel_type == HALF {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+// Reads single part, scanline, uncompressed OpenEXR images with half or float samples. The R, G, B
+// (or Y) and A channels are read, other channels are ignored. The samples are linear light and
+// are converted into the transfer characteristics set with set_transfer_characteristics() (PQ by
+// default).
+pub struct ExrReader {
+    width: u32,
+    height: u32,
+    format: rgb::Format,
+    samples: Option<Vec<f32>>,
+    transfer_characteristics: TransferCharacteristics,
+}
+
+fn read_u32_le(stream: &mut IStream) -> AvifResult<u32> {
+    Ok(u32::from_le_bytes(stream.get_slice(4)?.try_into().unwrap()))
+}
+
+fn read_i32_le(stream: &mut IStream) -> AvifResult<i32> {
+    Ok(i32::from_le_bytes(stream.get_slice(4)?.try_into().unwrap()))
+}
+
+fn read_u64_le(stream: &mut IStream) -> AvifResult<u64> {
+    Ok(u64::from_le_bytes(stream.get_slice(8)?.try_into().unwrap()))
+}
+
+pub(crate) fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    let bits = match exponent {
+        0 => {
+            // Zero or subnormal number.
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            return if sign == 0 { magnitude } else { -magnitude };
+        }
+        // Infinity or NaN.
+        31 => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+fn parse_channels(stream: &mut IStream) -> AvifResult<Vec<Channel>> {
+    let mut channels = Vec::new();
+    loop {
+        let name = stream.read_c_string()?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+        let pixel_type = read_i32_le(stream)?;
+        // pLinear and reserved bytes.
+        stream.skip(4)?;
+        let x_sampling = read_i32_le(stream)?;
+        let y_sampling = read_i32_le(stream)?;
+        if pixel_type != HALF && pixel_type != FLOAT {
+            return AvifError::not_implemented();
+        }
+        if x_sampling != 1 || y_sampling != 1 {
+            return AvifError::not_implemented();
+        }
+        channels.push(Channel {
+            name,
+            pixel_type,
+            index: None,
+        });
+    }
+}
+
+// Chooses the format of the image and the position of each used channel in a pixel.
+fn map_channels(channels: &mut [Channel]) -> AvifResult<rgb::Format> {
+    let has = |name: &str| channels.iter().any(|channel| channel.name == name);
+    let (format, names) = match (has("R") && has("G") && has("B"), has("Y"), has("A")) {
+        (true, _, false) => (rgb::Format::Rgb, vec!["R", "G", "B"]),
+        (true, _, true) => (rgb::Format::Rgba, vec!["R", "G", "B", "A"]),
+        (false, true, false) => (rgb::Format::Gray, vec!["Y"]),
+        (false, true, true) => (rgb::Format::GrayA, vec!["Y", "A"]),
+        _ => return AvifError::unknown_error("exr image has no R, G, B or Y channels"),
+    };
+    for channel in channels {
+        channel.index = names.iter().position(|name| *name == channel.name);
+    }
+    Ok(format)
+}
+
+impl ExrReader {
+    pub fn create(filename: &str) -> AvifResult<Self> {
+        let data = std::fs::read(filename).map_err(AvifError::map_unknown_error)?;
+        let mut stream = IStream::create(&data);
+        if stream.get_slice(4)? != MAGIC {
+            return AvifError::unknown_error("not an exr file");
+        }
+        let version = read_u32_le(&mut stream)?;
+        if version & 0xff != 2 {
+            return AvifError::unknown_error("unsupported exr version");
+        }
+        if version & (TILED_FLAG | NON_IMAGE_FLAG | MULTIPART_FLAG) != 0 {
+            return AvifError::not_implemented();
+        }
+        let mut channels = None;
+        let mut compression = None;
+        let mut data_window = None;
+        loop {
+            let name = stream.read_c_string()?;
+            if name.is_empty() {
+                break;
+            }
+            let _type = stream.read_c_string()?;
+            let size =
+                usize::try_from(read_i32_le(&mut stream)?).map_err(AvifError::map_unknown_error)?;
+            let mut value = IStream::create(stream.get_slice(size)?);
+            match name.as_str() {
+                "channels" => channels = Some(parse_channels(&mut value)?),
+                "compression" => compression = Some(value.read_u8()?),
+                "dataWindow" => {
+                    let mut window = [0i32; 4];
+                    for coordinate in &mut window {
+                        *coordinate = read_i32_le(&mut value)?;
+                    }
+                    data_window = Some(window);
+                }
+                _ => {}
+            }
+        }
+        let (Some(mut channels), Some(compression), Some([x_min, y_min, x_max, y_max])) =
+            (channels, compression, data_window)
+        else {
+            return AvifError::unknown_error("missing exr header attribute");
+        };
+        if compression != NO_COMPRESSION {
+            return AvifError::not_implemented();
+        }
+        let width =
+            u32::try_from(x_max as i64 - x_min as i64 + 1).map_err(AvifError::map_unknown_error)?;
+        let height =
+            u32::try_from(y_max as i64 - y_min as i64 + 1).map_err(AvifError::map_unknown_error)?;
+        if width == 0 || height == 0 {
+            return AvifError::unknown_error("invalid exr data window");
+        }
+        check_image_size(width, height)?;
+        let format = map_channels(&mut channels)?;
+        let channel_count = format.channel_count() as usize;
+        let line_size: usize = channels
+            .iter()
+            .map(|channel| channel.sample_size() * width as usize)
+            .sum();
+        // Uncompressed files have one scanline per chunk. The chunks may be stored in any order.
+        // The offset table and the chunks must fit in the file.
+        let too_large = || AvifError::UnknownError("exr data window exceeds the file size".into());
+        let chunks_size = (line_size + 8)
+            .checked_mul(height as usize)
+            .ok_or_else(too_large)?;
+        if height as usize * 8 > stream.bytes_left()? || chunks_size > data.len() {
+            return Err(too_large());
+        }
+        let mut offsets = Vec::with_capacity(height as usize);
+        for _ in 0..height {
+            offsets.push(read_u64_le(&mut stream)?);
+        }
+        let sample_count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixel_count| pixel_count.checked_mul(channel_count))
+            .ok_or_else(too_large)?;
+        let mut samples = vec![0.0; sample_count];
+        for offset in offsets {
+            let Some(chunk) = data.get(offset as usize..) else {
+                return AvifError::unknown_error("invalid exr chunk offset");
+            };
+            let mut chunk = IStream::create(chunk);
+            let y = read_i32_le(&mut chunk)? as i64 - y_min as i64;
+            let size = read_u32_le(&mut chunk)? as usize;
+            if y < 0 || y >= height as i64 || size != line_size {
+                return AvifError::unknown_error("invalid exr chunk");
+            }
+            let row = &mut samples[y as usize * width as usize * channel_count..]
+                [..width as usize * channel_count];
+            // The samples of each channel are stored one after the other.
+            for channel in &channels {
+                let bytes = chunk.get_slice(channel.sample_size() * width as usize)?;
+                let Some(index) = channel.index else {
+                    continue;
+                };
+                for (x, sample) in bytes.chunks_exact(channel.sample_size()).enumerate() {
+                    row[x * channel_count + index] = if channel.pixel_type == HALF {
+                        half_to_f32(u16::from_le_bytes(sample.try_into().unwrap()))
+                    } else {
+                        f32::from_le_bytes(sample.try_into().unwrap())
+                    };
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            format,
+            samples: Some(samples),
+            transfer_characteristics: TransferCharacteristics::Pq,
+        })
+    }
+
+    pub fn set_transfer_characteristics(
+        &mut self,
+        transfer_characteristics: TransferCharacteristics,
+    ) {
+        self.transfer_characteristics = transfer_characteristics;
+    }
+}
+
+impl Reader for ExrReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        match self.samples.take() {
+            Some(samples) => Ok((
+                linear_to_yuv(
+                    self.width,
+                    self.height,
+                    self.format,
+                    &samples,
+                    self.transfer_characteristics,
+                    config,
+                )?,
+                0,
+            )),
+            None => AvifError::unknown_error("no more exr frames"),
+        }
+    }
+
+    fn has_more_frames(&mut self) -> bool {
+        self.samples.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0x0000, 0.0)]
+    #[test_case(0x8000, -0.0)]
+    #[test_case(0x3c00, 1.0)]
+    #[test_case(0xc000, -2.0)]
+    #[test_case(0x3555, 0.33325195)]
+    #[test_case(0x7bff, 65504.0)]
+    #[test_case(0x0001, 5.9604645e-8)]
+    #[test_case(0x03ff, 6.097555e-5)]
+    #[test_case(0x7c00, f32::INFINITY)]
+    fn half(half: u16, expected: f32) {
+        assert_eq!(half_to_f32(half), expected);
+    }
+}
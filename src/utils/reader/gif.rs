@@ -90,9 +90,9 @@ impl Reader for GifReader {
                 .unwrap_or(MatrixCoefficients::Bt601),
             ..Default::default()
         };
-        rgb.convert_to_yuv(&mut yuv)?;
+        config.convert_to_yuv(rgb, &mut yuv)?;
         // GIF delay is in centi-seconds.
-        let duration_ms = self.frame.unwrap_ref().delay as u64 * 10;
+        let duration_ms = super::frame_duration_ms(self.frame.unwrap_ref().delay as u64 * 10);
         self.frame = None;
         Ok((yuv, duration_ms))
     }
@@ -107,4 +107,11 @@ impl Reader for GifReader {
         });
         true
     }
+
+    fn repetition_count(&self) -> Option<RepetitionCount> {
+        Some(match self.decoder.repeat() {
+            gif::Repeat::Finite(count) => RepetitionCount::Finite(count as u32),
+            gif::Repeat::Infinite => RepetitionCount::Infinite,
+        })
+    }
 }
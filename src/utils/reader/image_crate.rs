@@ -0,0 +1,165 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::utils::pixels::Pixels;
+use crate::AvifError;
+use crate::AvifResult;
+use crate::MatrixCoefficients;
+use crate::PixelFormat;
+use crate::TransferCharacteristics;
+use crate::YuvRange;
+
+use super::linear_to_yuv;
+use super::Config;
+
+use image::DynamicImage;
+use image::ImageDecoder;
+
+// Still image decoded by the image crate, converted to YUV when read.
+pub(crate) struct DecodedImage {
+    image: DynamicImage,
+    icc: Vec<u8>,
+    exif: Vec<u8>,
+    xmp: Vec<u8>,
+}
+
+impl DecodedImage {
+    pub(crate) fn decode(mut decoder: impl ImageDecoder) -> AvifResult<Self> {
+        // Metadata that cannot be parsed is ignored rather than failing the whole image.
+        let icc = decoder.icc_profile().ok().flatten().unwrap_or_default();
+        let exif = decoder.exif_metadata().ok().flatten().unwrap_or_default();
+        let xmp = decoder.xmp_metadata().ok().flatten().unwrap_or_default();
+        let image = DynamicImage::from_decoder(decoder).map_err(AvifError::map_unknown_error)?;
+        Ok(Self {
+            image,
+            icc,
+            exif,
+            xmp,
+        })
+    }
+
+    pub(crate) fn into_yuv(self, config: &Config) -> AvifResult<Image> {
+        let width = self.image.width();
+        let height = self.image.height();
+        let (format, depth, pixels) = match self.image {
+            DynamicImage::ImageLuma8(image) => {
+                (rgb::Format::Gray, 8, Pixels::Buffer(image.into_raw()))
+            }
+            DynamicImage::ImageLumaA8(image) => {
+                (rgb::Format::GrayA, 8, Pixels::Buffer(image.into_raw()))
+            }
+            DynamicImage::ImageRgb8(image) => {
+                (rgb::Format::Rgb, 8, Pixels::Buffer(image.into_raw()))
+            }
+            DynamicImage::ImageRgba8(image) => {
+                (rgb::Format::Rgba, 8, Pixels::Buffer(image.into_raw()))
+            }
+            DynamicImage::ImageLuma16(image) => {
+                (rgb::Format::Gray, 16, Pixels::Buffer16(image.into_raw()))
+            }
+            DynamicImage::ImageLumaA16(image) => {
+                (rgb::Format::GrayA, 16, Pixels::Buffer16(image.into_raw()))
+            }
+            DynamicImage::ImageRgb16(image) => {
+                (rgb::Format::Rgb, 16, Pixels::Buffer16(image.into_raw()))
+            }
+            DynamicImage::ImageRgba16(image) => {
+                (rgb::Format::Rgba, 16, Pixels::Buffer16(image.into_raw()))
+            }
+            DynamicImage::ImageRgb32F(image) => {
+                return Self::float_into_yuv(
+                    width,
+                    height,
+                    rgb::Format::Rgb,
+                    image.as_raw(),
+                    self.exif,
+                    self.xmp,
+                    config,
+                )
+            }
+            DynamicImage::ImageRgba32F(image) => {
+                return Self::float_into_yuv(
+                    width,
+                    height,
+                    rgb::Format::Rgba,
+                    image.as_raw(),
+                    self.exif,
+                    self.xmp,
+                    config,
+                )
+            }
+            image => (
+                rgb::Format::Rgba,
+                16,
+                Pixels::Buffer16(image.into_rgba16().into_raw()),
+            ),
+        };
+        let rgb = rgb::Image {
+            width,
+            height,
+            depth,
+            format,
+            row_bytes: width * format.channel_count() * if depth == 8 { 1 } else { 2 },
+            pixels: Some(pixels),
+            ..Default::default()
+        };
+        let mut yuv = Image {
+            width,
+            height,
+            depth: config.depth.unwrap_or(depth),
+            yuv_format: config.yuv_format.unwrap_or(if format.is_gray() {
+                PixelFormat::Yuv400
+            } else {
+                PixelFormat::Yuv420
+            }),
+            yuv_range: YuvRange::Full,
+            matrix_coefficients: config
+                .matrix_coefficients
+                .unwrap_or(MatrixCoefficients::Bt601),
+            icc: self.icc,
+            exif: self.exif,
+            xmp: self.xmp,
+            ..Default::default()
+        };
+        config.convert_to_yuv(rgb, &mut yuv)?;
+        Ok(yuv)
+    }
+
+    // Floating point samples are treated as linear light, like in PFM and OpenEXR files, and are
+    // kept as HDR by encoding them with PQ. The ICC profile is dropped since it does not describe
+    // the PQ encoded samples.
+    fn float_into_yuv(
+        width: u32,
+        height: u32,
+        format: rgb::Format,
+        samples: &[f32],
+        exif: Vec<u8>,
+        xmp: Vec<u8>,
+        config: &Config,
+    ) -> AvifResult<Image> {
+        let mut yuv = linear_to_yuv(
+            width,
+            height,
+            format,
+            samples,
+            TransferCharacteristics::Pq,
+            config,
+        )?;
+        yuv.exif = exif;
+        yuv.xmp = xmp;
+        Ok(yuv)
+    }
+}
@@ -12,77 +12,608 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::gainmap::GainMap;
+use crate::gainmap::GainMapMetadata;
+use crate::image::Plane;
+use crate::internal_utils::stream::IStream;
+use crate::parser::mp4box;
 use crate::reformat::*;
 use crate::utils::pixels::Pixels;
+use crate::utils::Fraction;
+use crate::utils::UFraction;
 use crate::AvifError;
 use crate::AvifResult;
 use crate::*;
 
+use super::check_image_size;
 use super::Config;
 use super::Reader;
 
-use std::fs::File;
-use std::io::BufReader;
-
-use ::image::codecs::jpeg;
-use ::image::ColorType;
-use ::image::ImageDecoder;
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_jpeg::JpegDecoder;
 
 pub struct JpegReader {
     filename: String,
+    ycbcr_passthrough: bool,
+    gainmap: AvifResult<Option<GainMap>>,
 }
 
 impl JpegReader {
     pub fn create(filename: &str) -> AvifResult<Self> {
         Ok(Self {
             filename: filename.into(),
+            ycbcr_passthrough: false,
+            gainmap: Ok(None),
         })
     }
+
+    // When enabled, the samples of YCbCr and grayscale JPEGs are copied as is instead of being
+    // converted to RGB and back, and the YUV format defaults to the chroma subsampling of the
+    // JPEG. With the mozjpeg feature, the chroma planes of 4:2:0 and 4:2:2 JPEGs are decoded at
+    // their native resolution. Otherwise, or if the YUV format of the config differs from the one
+    // of the JPEG, or if the JPEG uses other sampling factors, the chroma planes are upsampled by
+    // the JPEG decoder and averaged back to the requested YUV format.
+    pub fn set_ycbcr_passthrough(&mut self, ycbcr_passthrough: bool) {
+        self.ycbcr_passthrough = ycbcr_passthrough;
+    }
 }
 
-impl Reader for JpegReader {
-    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
-        let mut reader = BufReader::new(
-            File::open(self.filename.clone()).map_err(AvifError::map_unknown_error)?,
-        );
-        let decoder = jpeg::JpegDecoder::new(&mut reader).map_err(AvifError::map_unknown_error)?;
-        let color_type = decoder.color_type();
-        if color_type != ColorType::Rgb8 {
-            return AvifError::unknown_error(format!(
-                "jpeg color type was something other than rgb8: {color_type:#?}"
-            ));
-        }
-        let (width, height) = decoder.dimensions();
-        let total_bytes = decoder.total_bytes() as usize;
-        let mut rgb_bytes = vec![0u8; total_bytes];
-        decoder
-            .read_image(&mut rgb_bytes)
-            .map_err(AvifError::map_unknown_error)?;
-        let rgb = rgb::Image {
-            width,
-            height,
-            depth: 8,
-            format: rgb::Format::Rgb,
-            pixels: Some(Pixels::Buffer(rgb_bytes)),
-            row_bytes: width * 3,
-            ..Default::default()
+pub(crate) struct Segment<'a> {
+    pub marker: u8,
+    // Offset of the payload in the file.
+    pub offset: usize,
+    pub payload: &'a [u8],
+}
+
+// Returns the segments preceding the image data.
+pub(crate) fn jpeg_segments(data: &[u8]) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return segments;
+    }
+    let mut offset = 2;
+    while offset + 4 <= data.len() {
+        if data[offset] != 0xFF {
+            break;
+        }
+        let marker = data[offset + 1];
+        if marker == 0xFF {
+            // Fill byte.
+            offset += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            // Standalone markers.
+            offset += 2;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if length < 2 || offset + 2 + length > data.len() {
+            break;
+        }
+        segments.push(Segment {
+            marker,
+            offset: offset + 4,
+            payload: &data[offset + 4..offset + 2 + length],
+        });
+        if marker == 0xDA {
+            // Start of scan.
+            break;
+        }
+        offset += 2 + length;
+    }
+    segments
+}
+
+// Returns the chroma subsampling of a YCbCr JPEG, based on the sampling factors of its start of
+// frame segment.
+fn find_yuv_format(data: &[u8]) -> Option<PixelFormat> {
+    let sof = jpeg_segments(data)
+        .into_iter()
+        .find(|segment| {
+            (0xC0..=0xCF).contains(&segment.marker) && !matches!(segment.marker, 0xC4 | 0xC8 | 0xCC)
+        })?
+        .payload;
+    // Skip the precision, the height, the width and the component count.
+    let components = sof.get(6..)?;
+    if sof[5] != 3 || components.len() < 9 {
+        return None;
+    }
+    let sampling_factors = |i: usize| (components[i * 3 + 1] >> 4, components[i * 3 + 1] & 0xF);
+    let (luma_h, luma_v) = sampling_factors(0);
+    let (chroma_h, chroma_v) = sampling_factors(1);
+    if sampling_factors(2) != (chroma_h, chroma_v) {
+        return None;
+    }
+    match (luma_h / chroma_h.max(1), luma_v / chroma_v.max(1)) {
+        (1, 1) => Some(PixelFormat::Yuv444),
+        (2, 1) => Some(PixelFormat::Yuv422),
+        (2, 2) => Some(PixelFormat::Yuv420),
+        _ => None,
+    }
+}
+
+// Copies the interleaved Y, Cb and Cr (or Y only) samples of the JPEG decoder into image,
+// averaging the chroma samples if image is subsampled.
+fn copy_interleaved_ycbcr(
+    samples: &[u8],
+    channel_count: usize,
+    image: &mut Image,
+) -> AvifResult<()> {
+    image.allocate_planes(Category::Color)?;
+    let width = image.width as usize;
+    let height = image.height as usize;
+    for y in 0..height {
+        let row = &samples[y * width * channel_count..(y + 1) * width * channel_count];
+        let dst = image.row_mut(Plane::Y, y as u32)?;
+        for (dst, src) in dst[..width].iter_mut().zip(row.chunks_exact(channel_count)) {
+            *dst = src[0];
+        }
+    }
+    if image.yuv_format == PixelFormat::Yuv400 {
+        return Ok(());
+    }
+    if channel_count == 1 {
+        image.fill_plane_with_value(Plane::U, 128)?;
+        return image.fill_plane_with_value(Plane::V, 128);
+    }
+    let shift_x = image.yuv_format.chroma_shift_x().0 as usize;
+    let shift_y = image.yuv_format.chroma_shift_y() as usize;
+    for (plane, channel) in [(Plane::U, 1), (Plane::V, 2)] {
+        for chroma_y in 0..image.height(plane) {
+            let y_range = (chroma_y << shift_y)..((chroma_y + 1) << shift_y).min(height);
+            for chroma_x in 0..image.width(plane) {
+                let x_range = (chroma_x << shift_x)..((chroma_x + 1) << shift_x).min(width);
+                let mut sum = 0;
+                let mut count = 0;
+                for y in y_range.clone() {
+                    for x in x_range.clone() {
+                        sum += samples[(y * width + x) * channel_count + channel] as usize;
+                        count += 1;
+                    }
+                }
+                image.row_mut(plane, chroma_y as u32)?[chroma_x] =
+                    ((sum + count / 2) / count) as u8;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Decodes the Y, Cb and Cr planes of a subsampled JPEG at their native resolution into image.
+#[cfg(feature = "mozjpeg")]
+fn copy_native_ycbcr(data: &[u8], image: &mut Image) -> AvifResult<()> {
+    use mozjpeg::CompInfoExt;
+    // mozjpeg reports decoding errors by unwinding.
+    let (planes, row_strides) = std::panic::catch_unwind(|| -> std::io::Result<_> {
+        let mut decompress = mozjpeg::Decompress::new_mem(data)?.raw()?;
+        let row_strides: Vec<usize> = decompress
+            .components()
+            .iter()
+            .map(|component| component.row_stride())
+            .collect();
+        let mut planes = vec![Vec::new(); row_strides.len()];
+        decompress.read_raw_data(&mut planes.iter_mut().collect::<Vec<_>>());
+        decompress.finish()?;
+        Ok((planes, row_strides))
+    })
+    .map_err(|_| AvifError::UnknownError("failed to decode the jpeg".into()))?
+    .map_err(AvifError::map_unknown_error)?;
+    if planes.len() != 3 {
+        return AvifError::unknown_error("unexpected jpeg component count");
+    }
+    image.allocate_planes(Category::Color)?;
+    for (plane, (samples, row_stride)) in YUV_PLANES.into_iter().zip(planes.iter().zip(row_strides))
+    {
+        let width = image.width(plane);
+        for y in 0..image.height(plane) {
+            let Some(row) = samples.get(y * row_stride..y * row_stride + width) else {
+                return AvifError::unknown_error("unexpected jpeg component size");
+            };
+            image.row_mut(plane, y as u32)?[..width].copy_from_slice(row);
+        }
+    }
+    Ok(())
+}
+
+fn read_jpeg(data: &[u8], config: &Config, ycbcr_passthrough: bool) -> AvifResult<Image> {
+    // Use the same limits as the AVIF decoder rather than the ones of the JPEG decoder.
+    let dimension_limit = crate::decoder::DEFAULT_IMAGE_DIMENSION_LIMIT as usize;
+    let options = DecoderOptions::default()
+        .set_strict_mode(false)
+        .set_max_width(dimension_limit)
+        .set_max_height(dimension_limit);
+    let mut decoder = JpegDecoder::new_with_options(ZCursor::new(data), options);
+    decoder
+        .decode_headers()
+        .map_err(AvifError::map_unknown_error)?;
+    let (Some((width, height)), Some(input_colorspace)) =
+        (decoder.dimensions(), decoder.input_colorspace())
+    else {
+        return AvifError::unknown_error("failed to read the jpeg headers");
+    };
+    check_image_size(width as u32, height as u32)?;
+    let is_gray = input_colorspace == ColorSpace::Luma;
+    let mut yuv = Image {
+        width: width as u32,
+        height: height as u32,
+        depth: config.depth.unwrap_or(8),
+        yuv_format: config.yuv_format.unwrap_or(if is_gray {
+            PixelFormat::Yuv400
+        } else {
+            PixelFormat::Yuv420
+        }),
+        yuv_range: YuvRange::Full,
+        matrix_coefficients: config
+            .matrix_coefficients
+            .unwrap_or(MatrixCoefficients::Bt601),
+        ..Default::default()
+    };
+    // CMYK and YCCK JPEGs are naively converted to RGB by the decoder, so their ICC profile
+    // does not apply to the output.
+    if !matches!(input_colorspace, ColorSpace::CMYK | ColorSpace::YCCK) {
+        if let Some(icc) = decoder.icc_profile() {
+            yuv.icc = icc;
+        }
+    }
+    if let Some(exif) = decoder.exif() {
+        yuv.exif = exif.clone();
+    }
+    if let Some(xmp) = decoder.xmp() {
+        yuv.xmp = xmp.clone();
+    }
+    if ycbcr_passthrough && matches!(input_colorspace, ColorSpace::YCbCr | ColorSpace::Luma) {
+        if yuv.depth != 8 || yuv.matrix_coefficients != MatrixCoefficients::Bt601 {
+            return AvifError::unknown_error(
+                "jpeg ycbcr passthrough requires a depth of 8 and bt601 matrix coefficients",
+            );
+        }
+        let jpeg_yuv_format = if is_gray {
+            PixelFormat::Yuv400
+        } else {
+            find_yuv_format(data).unwrap_or(PixelFormat::Yuv444)
         };
-        let mut yuv = Image {
-            width,
-            height,
-            depth: config.depth.unwrap_or(8),
-            yuv_format: config.yuv_format.unwrap_or(PixelFormat::Yuv420),
-            yuv_range: YuvRange::Full,
-            matrix_coefficients: config
-                .matrix_coefficients
-                .unwrap_or(MatrixCoefficients::Bt601),
-            ..Default::default()
+        if config.yuv_format.is_none() {
+            yuv.yuv_format = jpeg_yuv_format;
+        }
+        if yuv.yuv_format.plane_count() == 0 {
+            return AvifError::not_implemented();
+        }
+        // The JPEG decoder always upsamples the chroma planes. Without the mozjpeg feature, they
+        // are averaged back to the resolution of the YUV format by copy_interleaved_ycbcr().
+        #[cfg(feature = "mozjpeg")]
+        if yuv.yuv_format == jpeg_yuv_format
+            && matches!(yuv.yuv_format, PixelFormat::Yuv420 | PixelFormat::Yuv422)
+        {
+            copy_native_ycbcr(data, &mut yuv)?;
+            return Ok(yuv);
+        }
+        decoder.set_options(decoder.options().jpeg_set_out_colorspace(input_colorspace));
+        let samples = decoder.decode().map_err(AvifError::map_unknown_error)?;
+        copy_interleaved_ycbcr(&samples, input_colorspace.num_components(), &mut yuv)?;
+        return Ok(yuv);
+    }
+    let (out_colorspace, format) = if is_gray {
+        (ColorSpace::Luma, rgb::Format::Gray)
+    } else {
+        (ColorSpace::RGB, rgb::Format::Rgb)
+    };
+    decoder.set_options(decoder.options().jpeg_set_out_colorspace(out_colorspace));
+    let rgb_bytes = decoder.decode().map_err(AvifError::map_unknown_error)?;
+    let rgb = rgb::Image {
+        width: width as u32,
+        height: height as u32,
+        depth: 8,
+        format,
+        pixels: Some(Pixels::Buffer(rgb_bytes)),
+        row_bytes: (width * out_colorspace.num_components()) as u32,
+        ..Default::default()
+    };
+    config.convert_to_yuv(rgb, &mut yuv)?;
+    Ok(yuv)
+}
+
+// Returns the offsets and the sizes of the secondary images listed in the Multi-Picture Format
+// segment of a JPEG, such as the gain map image of an Ultra HDR JPEG or a preview image.
+fn find_mpf_images(data: &[u8]) -> Vec<(usize, usize)> {
+    find_mpf_entries(data).unwrap_or_default()
+}
+
+fn find_mpf_entries(data: &[u8]) -> Option<Vec<(usize, usize)>> {
+    let segment = jpeg_segments(data)
+        .into_iter()
+        .find(|segment| segment.marker == 0xE2 && segment.payload.starts_with(b"MPF\0"))?;
+    // Offsets are relative to the TIFF header following the MPF identifier.
+    let tiff_offset = segment.offset + 4;
+    let tiff = &segment.payload[4..];
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<usize> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(
+            if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) } as usize,
+        )
+    };
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(
+            if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) } as usize,
+        )
+    };
+    let ifd_offset = read_u32(4)?;
+    let entry_count = read_u16(ifd_offset)?;
+    for i in 0..entry_count {
+        let entry_offset = ifd_offset + 2 + i * 12;
+        // MPEntry tag.
+        if read_u16(entry_offset)? != 0xB002 {
+            continue;
+        }
+        let count = read_u32(entry_offset + 4)?;
+        let mp_entries_offset = read_u32(entry_offset + 8)?;
+        // Each MP entry is made of the image attributes, size and offset followed by two
+        // dependent image entry numbers. The first image is the primary image.
+        let mut images = Vec::new();
+        for j in 1..count / 16 {
+            let size = read_u32(mp_entries_offset + j * 16 + 4)?;
+            let offset = read_u32(mp_entries_offset + j * 16 + 8)?;
+            let start = tiff_offset.checked_add(offset)?;
+            if offset != 0 && size != 0 && start.checked_add(size)? <= data.len() {
+                images.push((start, size));
+            }
+        }
+        return Some(images);
+    }
+    None
+}
+
+// Returns the standard XMP packet of a JPEG, if any.
+fn find_xmp(data: &[u8]) -> Option<&[u8]> {
+    const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+    jpeg_segments(data)
+        .into_iter()
+        .find(|segment| segment.marker == 0xE1 && segment.payload.starts_with(XMP_NAMESPACE))
+        .map(|segment| &segment.payload[XMP_NAMESPACE.len()..])
+}
+
+// Parses the ISO 21496-1 metadata stored in an APP2 segment of the gain map image.
+fn parse_iso_gainmap_metadata(data: &[u8]) -> AvifResult<Option<GainMapMetadata>> {
+    const ISO_NAMESPACE: &[u8] = b"urn:iso:std:iso:ts:21496:-1\0";
+    let Some(segment) = jpeg_segments(data)
+        .into_iter()
+        .find(|segment| segment.marker == 0xE2 && segment.payload.starts_with(ISO_NAMESPACE))
+    else {
+        return Ok(None);
+    };
+    let mut stream = IStream::create(&segment.payload[ISO_NAMESPACE.len()..]);
+    Ok(Some(mp4box::parse_gainmap_metadata(
+        &mut stream,
+        /*allow_common_denominator=*/ true,
+    )?))
+}
+
+// Returns the values of the given property of the Adobe gain map namespace in an XMP packet. The
+// property can be stored as an attribute, as an element or as an rdf:Seq of elements.
+fn find_xmp_values<'a>(xmp: &'a str, prefix: &str, name: &str) -> Option<Vec<&'a str>> {
+    let property = format!("{prefix}:{name}");
+    for quote in ['"', '\''] {
+        let attribute = format!("{property}={quote}");
+        // Attributes are separated by white space, which also avoids matching the properties of
+        // another prefix ending with this one.
+        if let Some((start, _)) = xmp
+            .match_indices(&attribute)
+            .find(|(start, _)| xmp[..*start].ends_with(|c: char| c.is_ascii_whitespace()))
+        {
+            let start = start + attribute.len();
+            let end = start + xmp[start..].find(quote)?;
+            return Some(vec![xmp[start..end].trim()]);
+        }
+    }
+    let open = format!("<{property}>");
+    let close = format!("</{property}>");
+    let start = xmp.find(&open)? + open.len();
+    let content = &xmp[start..start + xmp[start..].find(&close)?];
+    if !content.contains("<rdf:li") {
+        return Some(vec![content.trim()]);
+    }
+    content
+        .split("<rdf:li")
+        .skip(1)
+        .map(|item| {
+            let start = item.find('>')? + 1;
+            let end = item.find("</rdf:li>")?;
+            item.get(start..end).map(str::trim)
+        })
+        .collect()
+}
+
+// Returns the rdf:Description element of an XMP packet that holds the properties of the Adobe gain
+// map namespace, so that properties with the same name in other descriptions are not picked up.
+fn find_xmp_description<'a>(xmp: &'a str, prefix: &str) -> Option<&'a str> {
+    const OPEN: &str = "<rdf:Description";
+    const CLOSE: &str = "</rdf:Description>";
+    let mut remaining = xmp;
+    while let Some(start) = remaining.find(OPEN) {
+        let description = &remaining[start..];
+        let tag_end = description.find('>')? + 1;
+        let end = if description[..tag_end].ends_with("/>") {
+            tag_end
+        } else {
+            description.find(CLOSE)? + CLOSE.len()
+        };
+        if find_xmp_values(&description[..end], prefix, "Version").is_some() {
+            return Some(&description[..end]);
+        }
+        remaining = &description[end..];
+    }
+    None
+}
+
+// Parses the gain map metadata stored in XMP as per the Adobe gain map specification.
+fn parse_xmp_gainmap_metadata(xmp: &[u8]) -> AvifResult<Option<GainMapMetadata>> {
+    const HDRGM_NAMESPACE: &str = "http://ns.adobe.com/hdr-gain-map/1.0/";
+    let Ok(xmp) = std::str::from_utf8(xmp) else {
+        return Ok(None);
+    };
+    // Find the prefix bound to the gain map namespace, usually hdrgm.
+    let prefix = xmp
+        .find(&format!("=\"{HDRGM_NAMESPACE}\""))
+        .and_then(|end| {
+            let start = xmp[..end].rfind("xmlns:")? + "xmlns:".len();
+            xmp.get(start..end)
+        })
+        .unwrap_or("hdrgm");
+    let Some(xmp) = find_xmp_description(xmp, prefix) else {
+        return Ok(None);
+    };
+    let values = |name: &str, default: Option<f64>| -> AvifResult<[f64; 3]> {
+        let Some(values) = find_xmp_values(xmp, prefix, name) else {
+            return default
+                .map(|default| [default; 3])
+                .ok_or(AvifError::InvalidArgument);
+        };
+        let values = values
+            .iter()
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AvifError::map_unknown_error)?;
+        match values.len() {
+            1 => Ok([values[0]; 3]),
+            3 => Ok([values[0], values[1], values[2]]),
+            _ => AvifError::invalid_argument(),
+        }
+    };
+    let base_rendition_is_hdr = find_xmp_values(xmp, prefix, "BaseRenditionIsHDR")
+        .is_some_and(|values| values[0].eq_ignore_ascii_case("true"));
+    let min = values("GainMapMin", Some(0.0))?;
+    let max = values("GainMapMax", None)?;
+    let gamma = values("Gamma", Some(1.0))?;
+    let offset_sdr = values("OffsetSDR", Some(1.0 / 64.0))?;
+    let offset_hdr = values("OffsetHDR", Some(1.0 / 64.0))?;
+    let hdr_capacity_min = values("HDRCapacityMin", Some(0.0))?[0];
+    let hdr_capacity_max = values("HDRCapacityMax", None)?[0];
+    let (base_offset, alternate_offset, base_hdr_headroom, alternate_hdr_headroom) =
+        if base_rendition_is_hdr {
+            (offset_hdr, offset_sdr, hdr_capacity_max, hdr_capacity_min)
+        } else {
+            (offset_sdr, offset_hdr, hdr_capacity_min, hdr_capacity_max)
         };
-        rgb.convert_to_yuv(&mut yuv)?;
-        Ok((yuv, 0))
+    let mut metadata = GainMapMetadata {
+        base_hdr_headroom: UFraction::from_f64(base_hdr_headroom)?,
+        alternate_hdr_headroom: UFraction::from_f64(alternate_hdr_headroom)?,
+        use_base_color_space: true,
+        ..Default::default()
+    };
+    for i in 0..3 {
+        metadata.min[i] = Fraction::from_f64(min[i])?;
+        metadata.max[i] = Fraction::from_f64(max[i])?;
+        metadata.gamma[i] = UFraction::from_f64(gamma[i])?;
+        metadata.base_offset[i] = Fraction::from_f64(base_offset[i])?;
+        metadata.alternate_offset[i] = Fraction::from_f64(alternate_offset[i])?;
+    }
+    metadata.is_valid()?;
+    Ok(Some(metadata))
+}
+
+// Returns the gain map metadata of a secondary image of a JPEG, or None if it is not a gain map.
+fn parse_gainmap_metadata(
+    gainmap_data: &[u8],
+    image: &Image,
+) -> AvifResult<Option<GainMapMetadata>> {
+    // The ISO 21496-1 metadata takes precedence over the XMP metadata.
+    if let Some(metadata) = parse_iso_gainmap_metadata(gainmap_data)? {
+        return Ok(Some(metadata));
+    }
+    if let Some(xmp) = find_xmp(gainmap_data) {
+        if let Some(metadata) = parse_xmp_gainmap_metadata(xmp)? {
+            return Ok(Some(metadata));
+        }
+    }
+    parse_xmp_gainmap_metadata(&image.xmp)
+}
+
+// Reads the gain map image of an Ultra HDR JPEG, if any. Its YCbCr samples are copied without
+// an RGB round trip. Without the mozjpeg feature, the chroma planes of subsampled color gain maps
+// are upsampled to 4:4:4 by the JPEG decoder.
+fn read_gainmap(data: &[u8], image: &Image) -> AvifResult<Option<GainMap>> {
+    // Only decode the first secondary image with gain map metadata, and not any preview or
+    // thumbnail image.
+    let mut gainmap = None;
+    for (offset, size) in find_mpf_images(data) {
+        let gainmap_data = &data[offset..offset + size];
+        if let Some(metadata) = parse_gainmap_metadata(gainmap_data, image)? {
+            gainmap = Some((gainmap_data, metadata));
+            break;
+        }
+    }
+    let Some((gainmap_data, metadata)) = gainmap else {
+        return Ok(None);
+    };
+    let config = Config {
+        depth: Some(8),
+        yuv_format: match find_yuv_format(gainmap_data) {
+            Some(PixelFormat::Yuv420 | PixelFormat::Yuv422) if !cfg!(feature = "mozjpeg") => {
+                Some(PixelFormat::Yuv444)
+            }
+            _ => None,
+        },
+        ..Default::default()
+    };
+    let mut gainmap_image = read_jpeg(gainmap_data, &config, /*ycbcr_passthrough=*/ true)?;
+    gainmap_image.icc.clear();
+    gainmap_image.exif.clear();
+    gainmap_image.xmp.clear();
+    Ok(Some(GainMap {
+        image: gainmap_image,
+        metadata,
+        alt_color_primaries: image.color_primaries,
+        alt_plane_count: if image.yuv_format == PixelFormat::Yuv400 { 1 } else { 3 },
+        ..Default::default()
+    }))
+}
+
+impl Reader for JpegReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        let data = std::fs::read(&self.filename).map_err(AvifError::map_unknown_error)?;
+        let image = read_jpeg(&data, config, self.ycbcr_passthrough)?;
+        // The gain map is optional. The primary image is returned even if the gain map cannot be
+        // read, and the error is reported by take_gainmap().
+        self.gainmap = read_gainmap(&data, &image);
+        Ok((image, 0))
     }
 
     fn has_more_frames(&mut self) -> bool {
         false
     }
+
+    fn take_gainmap(&mut self) -> AvifResult<Option<GainMap>> {
+        std::mem::replace(&mut self.gainmap, Ok(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(&[0x11, 0x11, 0x11], Some(PixelFormat::Yuv444))]
+    #[test_case(&[0x21, 0x11, 0x11], Some(PixelFormat::Yuv422))]
+    #[test_case(&[0x22, 0x11, 0x11], Some(PixelFormat::Yuv420))]
+    #[test_case(&[0x22, 0x21, 0x11], None)]
+    #[test_case(&[0x41, 0x11, 0x11], None)]
+    #[test_case(&[0x11], None)]
+    fn yuv_format(sampling_factors: &[u8], expected: Option<PixelFormat>) {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0];
+        data.extend_from_slice(&(8 + 3 * sampling_factors.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[8, 0, 16, 0, 16, sampling_factors.len() as u8]);
+        for (i, sampling_factor) in sampling_factors.iter().enumerate() {
+            data.extend_from_slice(&[i as u8 + 1, *sampling_factor, 0]);
+        }
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        assert_eq!(find_yuv_format(&data), expected);
+    }
 }
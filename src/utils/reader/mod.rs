@@ -15,27 +15,160 @@
 // Not all sub-modules are used by all targets. Ignore dead code warnings.
 #![allow(dead_code)]
 
+#[cfg(feature = "bmp")]
+pub mod bmp;
+pub mod exr;
 #[cfg(feature = "gif")]
 pub mod gif;
+#[cfg(any(feature = "bmp", feature = "pnm", feature = "tiff"))]
+mod image_crate;
 #[cfg(feature = "jpeg")]
 pub mod jpeg;
+pub mod pfm;
 #[cfg(feature = "png")]
 pub mod png;
+#[cfg(feature = "pnm")]
+pub mod pnm;
+#[cfg(feature = "tiff")]
+pub mod tiff;
+#[cfg(feature = "webp")]
+pub mod webp;
 pub mod y4m;
+pub mod yuv;
 
+use crate::decoder;
+use crate::gainmap::GainMap;
 use crate::image::Image;
+use crate::reformat::rgb;
+use crate::reformat::transfer;
+use crate::utils::pixels::Pixels;
+use crate::AvifError;
 use crate::AvifResult;
 use crate::MatrixCoefficients;
 use crate::PixelFormat;
+use crate::RepetitionCount;
+use crate::TransferCharacteristics;
+use crate::YuvRange;
 
 #[derive(Default)]
 pub struct Config {
     pub yuv_format: Option<PixelFormat>,
     pub depth: Option<u8>,
     pub matrix_coefficients: Option<MatrixCoefficients>,
+    // The following fields describe inputs without a header, such as raw YUV files.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub yuv_range: Option<YuvRange>,
+    // Byte order of the samples stored with more than 8 bits. Little-endian by default.
+    pub big_endian: bool,
+    // Frames per second.
+    pub frame_rate: Option<f64>,
+    // The following fields apply to inputs converted from RGB.
+    pub chroma_downsampling: rgb::ChromaDownsampling,
+    // Stores the color samples multiplied by the alpha samples.
+    pub premultiply_alpha: bool,
+}
+
+impl Config {
+    pub(crate) fn convert_to_yuv(&self, mut rgb: rgb::Image, yuv: &mut Image) -> AvifResult<()> {
+        rgb.chroma_downsampling = self.chroma_downsampling;
+        // Gray samples with alpha are not premultiplied, as rgb::Image::premultiply_alpha() only
+        // supports formats with four channels.
+        if self.premultiply_alpha && rgb.has_alpha() && !rgb.format.is_gray() {
+            rgb.premultiply_alpha()?;
+            rgb.premultiply_alpha = true;
+            yuv.alpha_premultiplied = true;
+        }
+        rgb.convert_to_yuv(yuv)
+    }
 }
 
 pub trait Reader {
     fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)>;
     fn has_more_frames(&mut self) -> bool;
+    // Number of times the animation should be repeated, if specified in the input file.
+    fn repetition_count(&self) -> Option<RepetitionCount> {
+        None
+    }
+    // Gain map of the last frame read, if the input file has one. An error means that the input
+    // file has a gain map that could not be read, which does not prevent using the frame itself.
+    fn take_gainmap(&mut self) -> AvifResult<Option<GainMap>> {
+        Ok(None)
+    }
+}
+
+// Frame durations of 10 ms or less are played as 100 ms by web browsers, for both GIF and APNG.
+pub(crate) fn frame_duration_ms(duration_ms: u64) -> u64 {
+    if duration_ms <= 10 {
+        100
+    } else {
+        duration_ms
+    }
+}
+
+// Returns an error if the dimensions read from the header of an input file exceed the default
+// limits of the AVIF decoder. Meant to be called before allocating buffers of that size.
+pub(crate) fn check_image_size(width: u32, height: u32) -> AvifResult<()> {
+    if width > decoder::DEFAULT_IMAGE_DIMENSION_LIMIT
+        || height > decoder::DEFAULT_IMAGE_DIMENSION_LIMIT
+        || width as u64 * height as u64 > decoder::DEFAULT_IMAGE_SIZE_LIMIT as u64
+    {
+        return AvifError::unknown_error("image is too large");
+    }
+    Ok(())
+}
+
+// Converts tightly packed linear light samples into an image encoded with the given transfer
+// characteristics. Color samples are clamped to [0, 1], 1.0 being the maximum signal value of the
+// transfer characteristics (10000 cd/m2 for PQ).
+pub(crate) fn linear_to_yuv(
+    width: u32,
+    height: u32,
+    format: rgb::Format,
+    samples: &[f32],
+    transfer_characteristics: TransferCharacteristics,
+    config: &Config,
+) -> AvifResult<Image> {
+    let channel_count = format.channel_count() as usize;
+    let alpha_offset = if format.has_alpha() { channel_count - 1 } else { channel_count };
+    let pixels: Vec<u16> = samples
+        .iter()
+        .enumerate()
+        .map(|(index, sample)| {
+            let value = if index % channel_count == alpha_offset {
+                sample.clamp(0.0, 1.0)
+            } else {
+                transfer::from_linear(transfer_characteristics, *sample)
+            };
+            (value * 65535.0).round() as u16
+        })
+        .collect();
+    let rgb = rgb::Image {
+        width,
+        height,
+        depth: 16,
+        format,
+        row_bytes: width * format.channel_count() * 2,
+        pixels: Some(Pixels::Buffer16(pixels)),
+        ..Default::default()
+    };
+    let mut yuv = Image {
+        width,
+        height,
+        // Linear light inputs are usually HDR, which needs more than 8 bits once encoded.
+        depth: config.depth.unwrap_or(10),
+        yuv_format: config.yuv_format.unwrap_or(if format.is_gray() {
+            PixelFormat::Yuv400
+        } else {
+            PixelFormat::Yuv420
+        }),
+        yuv_range: YuvRange::Full,
+        matrix_coefficients: config
+            .matrix_coefficients
+            .unwrap_or(MatrixCoefficients::Bt601),
+        transfer_characteristics,
+        ..Default::default()
+    };
+    config.convert_to_yuv(rgb, &mut yuv)?;
+    Ok(yuv)
 }
@@ -0,0 +1,143 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+use crate::TransferCharacteristics;
+
+use super::check_image_size;
+use super::linear_to_yuv;
+use super::Config;
+use super::Reader;
+
+// Reads Portable FloatMap images. The samples are linear light and are converted into the
+// transfer characteristics set with set_transfer_characteristics() (PQ by default).
+pub struct PfmReader {
+    width: u32,
+    height: u32,
+    format: rgb::Format,
+    // Samples in top to bottom row order.
+    samples: Option<Vec<f32>>,
+    transfer_characteristics: TransferCharacteristics,
+}
+
+// Returns the next whitespace separated token of the header and skips the whitespace character
+// that follows it.
+fn next_token<'a>(data: &'a [u8], offset: &mut usize) -> AvifResult<&'a str> {
+    while data.get(*offset).is_some_and(|c| c.is_ascii_whitespace()) {
+        *offset += 1;
+    }
+    let start = *offset;
+    while data.get(*offset).is_some_and(|c| !c.is_ascii_whitespace()) {
+        *offset += 1;
+    }
+    if start == *offset || *offset == data.len() {
+        return AvifError::unknown_error("invalid pfm header");
+    }
+    let token = std::str::from_utf8(&data[start..*offset]).map_err(AvifError::map_unknown_error)?;
+    *offset += 1;
+    Ok(token)
+}
+
+fn parse<T: std::str::FromStr>(token: &str) -> AvifResult<T> {
+    token
+        .parse()
+        .map_err(|_| AvifError::UnknownError(format!("invalid pfm header value: {token}")))
+}
+
+impl PfmReader {
+    pub fn create(filename: &str) -> AvifResult<Self> {
+        let data = std::fs::read(filename).map_err(AvifError::map_unknown_error)?;
+        let mut offset = 0;
+        let format = match next_token(&data, &mut offset)? {
+            "PF" => rgb::Format::Rgb,
+            "Pf" => rgb::Format::Gray,
+            magic => {
+                return Err(AvifError::UnknownError(format!(
+                    "unsupported pfm magic: {magic}"
+                )))
+            }
+        };
+        let width: u32 = parse(next_token(&data, &mut offset)?)?;
+        let height: u32 = parse(next_token(&data, &mut offset)?)?;
+        // The sign of the scale gives the endianness of the samples. Its absolute value is a
+        // factor applied to all the samples.
+        let scale: f32 = parse(next_token(&data, &mut offset)?)?;
+        if width == 0 || height == 0 || scale == 0.0 || !scale.is_finite() {
+            return AvifError::unknown_error("invalid pfm header");
+        }
+        check_image_size(width, height)?;
+        let row_size = width as usize * format.channel_count() as usize;
+        let Some(bytes) = row_size
+            .checked_mul(height as usize)
+            .and_then(|sample_count| sample_count.checked_mul(4))
+            .and_then(|size| data.get(offset..offset.checked_add(size)?))
+        else {
+            return AvifError::unknown_error("truncated pfm file");
+        };
+        let values: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|sample| {
+                let sample = sample.try_into().unwrap();
+                let value = if scale < 0.0 {
+                    f32::from_le_bytes(sample)
+                } else {
+                    f32::from_be_bytes(sample)
+                };
+                value * scale.abs()
+            })
+            .collect();
+        // Rows are stored from bottom to top.
+        let samples = values.rchunks_exact(row_size).flatten().copied().collect();
+        Ok(Self {
+            width,
+            height,
+            format,
+            samples: Some(samples),
+            transfer_characteristics: TransferCharacteristics::Pq,
+        })
+    }
+
+    pub fn set_transfer_characteristics(
+        &mut self,
+        transfer_characteristics: TransferCharacteristics,
+    ) {
+        self.transfer_characteristics = transfer_characteristics;
+    }
+}
+
+impl Reader for PfmReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        match self.samples.take() {
+            Some(samples) => Ok((
+                linear_to_yuv(
+                    self.width,
+                    self.height,
+                    self.format,
+                    &samples,
+                    self.transfer_characteristics,
+                    config,
+                )?,
+                0,
+            )),
+            None => AvifError::unknown_error("no more pfm frames"),
+        }
+    }
+
+    fn has_more_frames(&mut self) -> bool {
+        self.samples.is_some()
+    }
+}
@@ -21,29 +21,496 @@ use crate::*;
 use super::Config;
 use super::Reader;
 
-use std::fs::File;
+use std::io::Cursor;
+use std::io::Read;
 
 pub struct PngReader {
-    filename: String,
+    reader: png::Reader<Cursor<Vec<u8>>>,
+    // Properties and metadata shared by all the frames, without pixels.
+    header: Image,
+    animation: Option<Animation>,
+    remaining_frames: u32,
+}
+
+// State of an animated PNG (APNG) file.
+struct Animation {
+    // Interleaved RGBA samples of the composited frame.
+    canvas: Vec<u16>,
+    // Frame control of the last returned frame, whose dispose operation is applied before
+    // compositing the next frame, and the canvas to restore for png::DisposeOp::Previous.
+    previous_frame: Option<(png::FrameControl, Option<Vec<u16>>)>,
+    repetition_count: RepetitionCount,
+    // Whether the canvas is opaque after compositing each of the frames, in which case the frames
+    // are returned without alpha.
+    opaque: bool,
 }
 
 impl PngReader {
     pub fn create(filename: &str) -> AvifResult<Self> {
+        let data = std::fs::read(filename).map_err(AvifError::map_unknown_error)?;
+        let mut header = Image::default();
+        import_chunks(&data, &mut header);
+        let animation = scan_animation(&data, &mut header)?;
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        // Expand palettes, tRNS chunks and bit depths lower than 8 so that the output is always
+        // 8-bit or 16-bit gray, gray alpha, RGB or RGBA.
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(AvifError::map_unknown_error)?;
+        let info = reader.info();
+        header.width = info.width;
+        header.height = info.height;
+        let Some((animation, frame_count)) = animation else {
+            return Ok(Self {
+                reader,
+                header,
+                animation: None,
+                remaining_frames: 1,
+            });
+        };
+        skip_default_image(&mut reader)?;
         Ok(Self {
-            filename: filename.into(),
+            reader,
+            header,
+            animation: Some(animation),
+            remaining_frames: frame_count,
         })
     }
 }
 
-impl Reader for PngReader {
-    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
-        let file = File::open(self.filename.clone()).map_err(AvifError::map_unknown_error)?;
-        let decoder = png::Decoder::new(file);
-        let mut reader = decoder.read_info().map_err(AvifError::map_unknown_error)?;
+// Returns the initial state and the frame count of an animated PNG, or None for a still image.
+// The frames are decoded once upfront to find whether the animation needs alpha, which also
+// gives access to the chunks that come after the image data, such as iTXt.
+fn scan_animation(data: &[u8], header: &mut Image) -> AvifResult<Option<(Animation, u32)>> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(AvifError::map_unknown_error)?;
+    let info = reader.info();
+    let Some(animation_control) = info.animation_control else {
+        return Ok(None);
+    };
+    if animation_control.num_frames == 0 {
+        return AvifError::unknown_error("apng has no frames");
+    }
+    let (width, height) = (info.width, info.height);
+    super::check_image_size(width, height)?;
+    let repetition_count = match animation_control.num_plays {
+        0 => RepetitionCount::Infinite,
+        plays => RepetitionCount::Finite(plays - 1),
+    };
+    let mut scan = Animation::create(width, height, repetition_count)?;
+    skip_default_image(&mut reader)?;
+    let mut opaque = true;
+    for _ in 0..animation_control.num_frames {
+        let (info, _) = scan.composite_next_frame(&mut reader, width, height)?;
+        let max_channel = if info.bit_depth == png::BitDepth::Eight { 255 } else { 65535 };
+        if !scan
+            .canvas
+            .chunks_exact(4)
+            .all(|rgba| rgba[3] == max_channel)
+        {
+            opaque = false;
+            break;
+        }
+    }
+    // Skips the remaining frames, if any, and parses the chunks that come after them.
+    reader.finish().map_err(AvifError::map_unknown_error)?;
+    import_info(reader.info(), header)?;
+    let mut animation = Animation::create(width, height, repetition_count)?;
+    animation.opaque = opaque;
+    Ok(Some((animation, animation_control.num_frames)))
+}
+
+// Skips the default image of an animation if it is not part of the animation.
+fn skip_default_image<R: Read>(reader: &mut png::Reader<R>) -> AvifResult<()> {
+    if reader.info().frame_control.is_none() {
+        let mut decoded_bytes = vec![0u8; reader.output_buffer_size()];
+        reader
+            .next_frame(&mut decoded_bytes)
+            .map_err(AvifError::map_unknown_error)?;
+    }
+    Ok(())
+}
+
+// Returns the payload of the first chunk of one of the given types, if any.
+fn find_chunk<'a>(data: &'a [u8], chunk_types: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    // Skip the 8-byte PNG signature.
+    let mut offset = 8;
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let chunk_type = &data[offset + 4..offset + 8];
+        let start = offset + 8;
+        let end = start.checked_add(length)?;
+        if end.checked_add(4)? > data.len() {
+            return None;
+        }
+        if chunk_types.iter().any(|x| x.as_slice() == chunk_type) {
+            return Some(&data[start..end]);
+        }
+        if chunk_type == b"IEND" {
+            return None;
+        }
+        offset = end + 4;
+    }
+    None
+}
+
+fn read_u16(data: &[u8], index: usize) -> u16 {
+    u16::from_be_bytes([data[2 * index], data[2 * index + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+// The png crate does not parse the eXIf chunk nor the cLLi and mDCv chunks that were used before
+// they got renamed in the third edition of the PNG specification, so those chunks are read
+// directly from the file.
+fn import_chunks(data: &[u8], image: &mut Image) {
+    if let Some(exif) = find_chunk(data, &[b"eXIf"]) {
+        image.exif = exif.to_vec();
+    }
+    if let Some(clli) = find_chunk(data, &[b"cLLI", b"cLLi"]) {
+        if clli.len() == 8 {
+            // The values are in units of 0.0001 cd/m2.
+            let to_nits = |value: u32| ((value + 5000) / 10000).min(u16::MAX as u32) as u16;
+            image.clli = Some(ContentLightLevelInformation {
+                max_cll: to_nits(read_u32(clli, 0)),
+                max_pall: to_nits(read_u32(clli, 4)),
+            });
+        }
+    }
+    if let Some(mdcv) = find_chunk(data, &[b"mDCV", b"mDCv"]) {
+        if mdcv.len() == 24 {
+            // The primaries are stored in the red, green, blue order in PNG and in the green,
+            // blue, red order in the mdcv box. The units are the same.
+            image.mdcv = Some(MasteringDisplayColourVolume {
+                display_primaries_x: [read_u16(mdcv, 2), read_u16(mdcv, 4), read_u16(mdcv, 0)],
+                display_primaries_y: [read_u16(mdcv, 3), read_u16(mdcv, 5), read_u16(mdcv, 1)],
+                white_point_x: read_u16(mdcv, 6),
+                white_point_y: read_u16(mdcv, 7),
+                max_display_mastering_luminance: read_u32(mdcv, 16),
+                min_display_mastering_luminance: read_u32(mdcv, 20),
+            });
+        }
+    }
+}
+
+// Decodes the "Raw profile type" text chunks written by ImageMagick and libavif, whose text has
+// the form "\n<profile name>\n<payload length>\n<hexadecimal payload split into lines>\n".
+fn parse_raw_profile(text: &str) -> Option<Vec<u8>> {
+    let mut lines = text.trim_start().splitn(3, '\n');
+    let _name = lines.next()?;
+    let length: usize = lines.next()?.trim().parse().ok()?;
+    let hex: Vec<u8> = lines
+        .next()?
+        .bytes()
+        .filter(|x| !x.is_ascii_whitespace())
+        .collect();
+    if hex.len() < length.checked_mul(2)? {
+        return None;
+    }
+    hex[..length * 2]
+        .chunks_exact(2)
+        .map(|x| u8::from_str_radix(std::str::from_utf8(x).ok()?, 16).ok())
+        .collect()
+}
+
+fn import_info(info: &png::Info, image: &mut Image) -> AvifResult<()> {
+    if let Some(icc) = &info.icc_profile {
+        image.icc = icc.to_vec();
+    }
+    let mut texts: Vec<(&str, String)> = Vec::new();
+    for text in &info.uncompressed_latin1_text {
+        texts.push((&text.keyword, text.text.clone()));
+    }
+    for text in &info.compressed_latin1_text {
+        texts.push((
+            &text.keyword,
+            text.get_text().map_err(AvifError::map_unknown_error)?,
+        ));
+    }
+    for text in &info.utf8_text {
+        texts.push((
+            &text.keyword,
+            text.get_text().map_err(AvifError::map_unknown_error)?,
+        ));
+    }
+    for (keyword, text) in texts {
+        match keyword {
+            "XML:com.adobe.xmp" => image.xmp = text.into_bytes(),
+            // The eXIf chunk takes precedence over the raw profile.
+            "Raw profile type exif" | "Raw profile type APP1" if image.exif.is_empty() => {
+                if let Some(exif) = parse_raw_profile(&text) {
+                    image.exif = exif;
+                }
+            }
+            "Raw profile type xmp" => {
+                if let Some(xmp) = parse_raw_profile(&text) {
+                    image.xmp = xmp;
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(cicp) = &info.coding_independent_code_points {
+        // cICP takes precedence over all the other color space chunks.
+        image.color_primaries = ColorPrimaries::from(cicp.color_primaries as u16);
+        image.transfer_characteristics =
+            TransferCharacteristics::from(cicp.transfer_function as u16);
+        return Ok(());
+    }
+    if !image.icc.is_empty() {
+        // gAMA, cHRM and sRGB must be ignored when there is an ICC profile.
+        return Ok(());
+    }
+    if info.srgb.is_some() {
+        image.color_primaries = ColorPrimaries::Srgb;
+        image.transfer_characteristics = TransferCharacteristics::Srgb;
+        return Ok(());
+    }
+    if let Some(chrm) = &info.chrm_chunk {
+        let chromaticities = [
+            chrm.red.0.into_value(),
+            chrm.red.1.into_value(),
+            chrm.green.0.into_value(),
+            chrm.green.1.into_value(),
+            chrm.blue.0.into_value(),
+            chrm.blue.1.into_value(),
+            chrm.white.0.into_value(),
+            chrm.white.1.into_value(),
+        ];
+        if let Some(color_primaries) = ColorPrimaries::find(&chromaticities) {
+            image.color_primaries = color_primaries;
+        }
+    }
+    if let Some(gamma) = &info.gama_chunk {
+        // gAMA stores the inverse of the gamma of the transfer function.
+        const TOLERANCE: f32 = 0.001;
+        let gamma = gamma.into_value();
+        if (gamma - 1.0 / 2.2).abs() <= TOLERANCE {
+            image.transfer_characteristics = TransferCharacteristics::Bt470m;
+        } else if (gamma - 1.0 / 2.8).abs() <= TOLERANCE {
+            image.transfer_characteristics = TransferCharacteristics::Bt470bg;
+        } else if (gamma - 1.0).abs() <= TOLERANCE {
+            image.transfer_characteristics = TransferCharacteristics::Linear;
+        }
+    }
+    Ok(())
+}
+
+fn sample(bytes: &[u8], depth: u8, index: usize) -> u16 {
+    if depth == 8 {
+        bytes[index] as u16
+    } else {
+        u16::from_be_bytes([bytes[2 * index], bytes[2 * index + 1]])
+    }
+}
+
+impl Animation {
+    fn create(width: u32, height: u32, repetition_count: RepetitionCount) -> AvifResult<Self> {
+        let canvas_size = checked_mul!(checked_mul!(width as usize, height as usize)?, 4)?;
+        Ok(Self {
+            canvas: vec![0; canvas_size],
+            previous_frame: None,
+            repetition_count,
+            opaque: false,
+        })
+    }
+
+    // Decodes the next frame and composites it onto the canvas. Returns the output info and the
+    // frame control of the decoded frame.
+    fn composite_next_frame<R: Read>(
+        &mut self,
+        reader: &mut png::Reader<R>,
+        width: u32,
+        height: u32,
+    ) -> AvifResult<(png::OutputInfo, png::FrameControl)> {
         let mut decoded_bytes = vec![0u8; reader.output_buffer_size()];
         let info = reader
             .next_frame(&mut decoded_bytes)
             .map_err(AvifError::map_unknown_error)?;
+        let frame_control = *reader.info().frame_control.unwrap_ref();
+        if checked_add!(frame_control.x_offset, frame_control.width)? > width
+            || checked_add!(frame_control.y_offset, frame_control.height)? > height
+        {
+            return AvifError::unknown_error("apng frame is outside of the canvas");
+        }
+        self.dispose(width as usize);
+        let mut dispose_op = frame_control.dispose_op;
+        if dispose_op == png::DisposeOp::Previous && frame_control.sequence_number == 0 {
+            // The first frame has no previous canvas to restore.
+            dispose_op = png::DisposeOp::Background;
+        }
+        let previous_canvas = if dispose_op == png::DisposeOp::Previous {
+            Some(self.canvas.clone())
+        } else {
+            None
+        };
+        self.blend(&frame_control, &info, &decoded_bytes, width as usize);
+        self.previous_frame = Some((
+            png::FrameControl {
+                dispose_op,
+                ..frame_control
+            },
+            previous_canvas,
+        ));
+        Ok((info, frame_control))
+    }
+
+    // Applies the dispose operation of the previous frame to the canvas.
+    fn dispose(&mut self, width: usize) {
+        let Some((frame_control, previous_canvas)) = self.previous_frame.take() else {
+            return;
+        };
+        match frame_control.dispose_op {
+            png::DisposeOp::None => {}
+            png::DisposeOp::Background => {
+                let x = frame_control.x_offset as usize;
+                for y in 0..frame_control.height as usize {
+                    let start = ((frame_control.y_offset as usize + y) * width + x) * 4;
+                    self.canvas[start..start + frame_control.width as usize * 4].fill(0);
+                }
+            }
+            png::DisposeOp::Previous => self.canvas = previous_canvas.unwrap(),
+        }
+    }
+
+    // Composites the decoded frame onto the canvas.
+    fn blend(
+        &mut self,
+        frame_control: &png::FrameControl,
+        info: &png::OutputInfo,
+        bytes: &[u8],
+        width: usize,
+    ) {
+        let depth = info.bit_depth as u8;
+        let max_channel = if depth == 8 { 255.0 } else { 65535.0 };
+        let channel_count = info.color_type.samples();
+        for y in 0..info.height as usize {
+            let row = &bytes[y * info.line_size..];
+            for x in 0..info.width as usize {
+                let index = x * channel_count;
+                let src = match info.color_type {
+                    png::ColorType::Grayscale => {
+                        let gray = sample(row, depth, index);
+                        [gray, gray, gray, max_channel as u16]
+                    }
+                    png::ColorType::GrayscaleAlpha => {
+                        let gray = sample(row, depth, index);
+                        [gray, gray, gray, sample(row, depth, index + 1)]
+                    }
+                    png::ColorType::Rgb => [
+                        sample(row, depth, index),
+                        sample(row, depth, index + 1),
+                        sample(row, depth, index + 2),
+                        max_channel as u16,
+                    ],
+                    _ => [
+                        sample(row, depth, index),
+                        sample(row, depth, index + 1),
+                        sample(row, depth, index + 2),
+                        sample(row, depth, index + 3),
+                    ],
+                };
+                let start = ((frame_control.y_offset as usize + y) * width
+                    + frame_control.x_offset as usize
+                    + x)
+                    * 4;
+                let dst = &mut self.canvas[start..start + 4];
+                if frame_control.blend_op == png::BlendOp::Source || src[3] as f32 == max_channel {
+                    dst.copy_from_slice(&src);
+                } else if src[3] != 0 {
+                    // Straight alpha "over" operator.
+                    let src_alpha = src[3] as f32 / max_channel;
+                    let dst_alpha = dst[3] as f32 / max_channel * (1.0 - src_alpha);
+                    let alpha = src_alpha + dst_alpha;
+                    for c in 0..3 {
+                        let value = (src[c] as f32 * src_alpha + dst[c] as f32 * dst_alpha) / alpha;
+                        dst[c] = value.round() as u16;
+                    }
+                    dst[3] = (alpha * max_channel).round() as u16;
+                }
+            }
+        }
+    }
+}
+
+impl PngReader {
+    fn create_image(&self, config: &Config, is_gray: bool, depth: u8) -> Image {
+        let mut image = self.header.shallow_clone();
+        image.depth = config.depth.unwrap_or(depth);
+        image.yuv_format = config.yuv_format.unwrap_or(if is_gray {
+            PixelFormat::Yuv400
+        } else {
+            PixelFormat::Yuv420
+        });
+        image.yuv_range = YuvRange::Full;
+        image.matrix_coefficients = config
+            .matrix_coefficients
+            .unwrap_or(MatrixCoefficients::Bt601);
+        image
+    }
+
+    fn read_animation_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        let animation = self.animation.unwrap_mut();
+        let (info, frame_control) = animation.composite_next_frame(
+            &mut self.reader,
+            self.header.width,
+            self.header.height,
+        )?;
+
+        let depth = info.bit_depth as u8;
+        let channel_count = if animation.opaque { 3 } else { 4 };
+        let samples = animation
+            .canvas
+            .chunks_exact(4)
+            .flat_map(|rgba| &rgba[..channel_count]);
+        let rgb = rgb::Image {
+            width: self.header.width,
+            height: self.header.height,
+            depth,
+            format: if animation.opaque { rgb::Format::Rgb } else { rgb::Format::Rgba },
+            pixels: Some(if depth == 8 {
+                Pixels::Buffer(samples.map(|x| *x as u8).collect())
+            } else {
+                Pixels::Buffer16(samples.copied().collect())
+            }),
+            row_bytes: self.header.width * channel_count as u32 * (depth as u32 / 8),
+            ..Default::default()
+        };
+        let is_gray = matches!(
+            info.color_type,
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha
+        );
+        let mut yuv = self.create_image(config, is_gray, depth);
+        config.convert_to_yuv(rgb, &mut yuv)?;
+        // The delay is expressed as a fraction of a second. A denominator of 0 means 1/100.
+        let delay_den = match frame_control.delay_den {
+            0 => 100,
+            den => den as u64,
+        };
+        let duration_ms = (frame_control.delay_num as u64 * 1000 + delay_den / 2) / delay_den;
+        Ok((yuv, super::frame_duration_ms(duration_ms)))
+    }
+}
+
+impl Reader for PngReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        if self.remaining_frames == 0 {
+            return AvifError::unknown_error("no more png frames");
+        }
+        self.remaining_frames -= 1;
+        if self.animation.is_some() {
+            return self.read_animation_frame(config);
+        }
+        let mut decoded_bytes = vec![0u8; self.reader.output_buffer_size()];
+        let info = self
+            .reader
+            .next_frame(&mut decoded_bytes)
+            .map_err(AvifError::map_unknown_error)?;
+        // Parse the chunks that come after the image data, such as iTXt.
+        self.reader.finish().map_err(AvifError::map_unknown_error)?;
+        import_info(self.reader.info(), &mut self.header)?;
         let rgb_bytes = &decoded_bytes[..info.buffer_size()];
         let rgb = rgb::Image {
             width: info.width,
@@ -61,6 +528,8 @@ impl Reader for PngReader {
             format: match info.color_type {
                 png::ColorType::Rgb => rgb::Format::Rgb,
                 png::ColorType::Rgba => rgb::Format::Rgba,
+                png::ColorType::Grayscale => rgb::Format::Gray,
+                png::ColorType::GrayscaleAlpha => rgb::Format::GrayA,
                 _ => {
                     return AvifError::unknown_error(format!(
                         "png color type not supported: {:#?}",
@@ -87,23 +556,18 @@ impl Reader for PngReader {
             row_bytes: info.line_size as u32,
             ..Default::default()
         };
-        let mut yuv = Image {
-            width: info.width,
-            height: info.height,
-            depth: config.depth.unwrap_or(std::cmp::min(rgb.depth, 16)),
-            yuv_format: config.yuv_format.unwrap_or(PixelFormat::Yuv420),
-            yuv_range: YuvRange::Full,
-            matrix_coefficients: config
-                .matrix_coefficients
-                .unwrap_or(MatrixCoefficients::Bt601),
-            ..Default::default()
-        };
-        rgb.convert_to_yuv(&mut yuv)?;
+        let mut yuv = self.create_image(config, rgb.format.is_gray(), rgb.depth);
+        config.convert_to_yuv(rgb, &mut yuv)?;
         Ok((yuv, 0))
     }
 
     fn has_more_frames(&mut self) -> bool {
-        // TODO: b/403090413 - maybe support APNG?
-        false
+        self.remaining_frames > 0
+    }
+
+    fn repetition_count(&self) -> Option<RepetitionCount> {
+        self.animation
+            .as_ref()
+            .map(|animation| animation.repetition_count)
     }
 }
@@ -0,0 +1,120 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::utils::pixels::Pixels;
+use crate::*;
+
+use super::Config;
+use super::Reader;
+
+use image_webp::LoopCount;
+use image_webp::WebPDecoder;
+use std::fs::File;
+use std::io::BufReader;
+
+// Reads still and animated WebP images. Animation frames are composited onto the canvas.
+pub struct WebpReader {
+    decoder: WebPDecoder<BufReader<File>>,
+    remaining_frames: u32,
+    icc: Vec<u8>,
+    exif: Vec<u8>,
+    xmp: Vec<u8>,
+}
+
+impl WebpReader {
+    pub fn create(filename: &str) -> AvifResult<Self> {
+        let file = File::open(filename).map_err(AvifError::map_unknown_error)?;
+        let mut decoder =
+            WebPDecoder::new(BufReader::new(file)).map_err(AvifError::map_unknown_error)?;
+        let remaining_frames = if decoder.is_animated() { decoder.num_frames() } else { 1 };
+        let icc = decoder.icc_profile().ok().flatten().unwrap_or_default();
+        let exif = decoder.exif_metadata().ok().flatten().unwrap_or_default();
+        let xmp = decoder.xmp_metadata().ok().flatten().unwrap_or_default();
+        Ok(Self {
+            decoder,
+            remaining_frames,
+            icc,
+            exif,
+            xmp,
+        })
+    }
+}
+
+impl Reader for WebpReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        if self.remaining_frames == 0 {
+            return AvifError::unknown_error("no more webp frames");
+        }
+        self.remaining_frames -= 1;
+        let buffer_size = self
+            .decoder
+            .output_buffer_size()
+            .ok_or(AvifError::UnknownError("webp image is too large".into()))?;
+        let mut pixels = vec![0u8; buffer_size];
+        let duration_ms = if self.decoder.is_animated() {
+            self.decoder
+                .read_frame(&mut pixels)
+                .map_err(AvifError::map_unknown_error)? as u64
+        } else {
+            self.decoder
+                .read_image(&mut pixels)
+                .map_err(AvifError::map_unknown_error)?;
+            0
+        };
+        let (width, height) = self.decoder.dimensions();
+        let format = if self.decoder.has_alpha() { rgb::Format::Rgba } else { rgb::Format::Rgb };
+        let rgb = rgb::Image {
+            width,
+            height,
+            depth: 8,
+            format,
+            row_bytes: width * format.channel_count(),
+            pixels: Some(Pixels::Buffer(pixels)),
+            ..Default::default()
+        };
+        let mut yuv = Image {
+            width,
+            height,
+            depth: config.depth.unwrap_or(8),
+            yuv_format: config.yuv_format.unwrap_or(PixelFormat::Yuv420),
+            yuv_range: YuvRange::Full,
+            matrix_coefficients: config
+                .matrix_coefficients
+                .unwrap_or(MatrixCoefficients::Bt601),
+            icc: self.icc.clone(),
+            exif: self.exif.clone(),
+            xmp: self.xmp.clone(),
+            ..Default::default()
+        };
+        config.convert_to_yuv(rgb, &mut yuv)?;
+        Ok((yuv, duration_ms))
+    }
+
+    fn has_more_frames(&mut self) -> bool {
+        self.remaining_frames > 0
+    }
+
+    fn repetition_count(&self) -> Option<RepetitionCount> {
+        if !self.decoder.is_animated() {
+            return None;
+        }
+        // The loop count is the number of times the animation is played.
+        Some(match self.decoder.loop_count() {
+            LoopCount::Forever => RepetitionCount::Infinite,
+            LoopCount::Times(count) => RepetitionCount::Finite(count.get() as u32 - 1),
+        })
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::*;
+use crate::*;
+
+use super::Config;
+use super::Reader;
+
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+
+// Reads headerless YUV files made of consecutive frames. The geometry of the frames is given by
+// the Config passed to read_frame():
+//  * width and height are required.
+//  * yuv_format defaults to Yuv420. AndroidNv12 and AndroidNv21 are 8-bit 4:2:0 frames with
+//    interleaved chroma samples (UV and VU respectively). AndroidP010 is a 10-bit NV12 layout
+//    with the samples stored in the most significant bits of 16-bit words.
+//  * depth defaults to 8 for planar formats.
+//  * yuv_range defaults to limited.
+//  * frame_rate gives the durations of the frames, which are 0 otherwise.
+pub struct YuvReader {
+    reader: BufReader<File>,
+    frame_index: u64,
+}
+
+impl YuvReader {
+    pub fn create(filename: &str) -> AvifResult<Self> {
+        Ok(Self {
+            reader: BufReader::new(File::open(filename).map_err(AvifError::map_unknown_error)?),
+            frame_index: 0,
+        })
+    }
+
+    // Reads samples stored with more than 8 bits, shifted left by shift bits.
+    fn read_samples16(
+        &mut self,
+        samples: &mut [u16],
+        big_endian: bool,
+        shift: u8,
+    ) -> AvifResult<()> {
+        let mut bytes = vec![0u8; samples.len() * 2];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(AvifError::map_unknown_error)?;
+        for (sample, bytes) in samples.iter_mut().zip(bytes.chunks_exact(2)) {
+            let bytes = [bytes[0], bytes[1]];
+            let value =
+                if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) };
+            *sample = value >> shift;
+        }
+        Ok(())
+    }
+
+    fn read_interleaved_chroma(
+        &mut self,
+        image: &mut Image,
+        v_first: bool,
+        big_endian: bool,
+        shift: u8,
+    ) -> AvifResult<()> {
+        let (first, second) = if v_first { (Plane::V, Plane::U) } else { (Plane::U, Plane::V) };
+        let plane_data = image.plane_data(Plane::U).unwrap();
+        let width = plane_data.width as usize;
+        let mut row = vec![0u16; width * 2];
+        for y in 0..plane_data.height {
+            if image.depth == 8 {
+                let mut bytes = vec![0u8; width * 2];
+                self.reader
+                    .read_exact(&mut bytes)
+                    .map_err(AvifError::map_unknown_error)?;
+                for (sample, byte) in row.iter_mut().zip(bytes) {
+                    *sample = byte as u16;
+                }
+            } else {
+                self.read_samples16(&mut row, big_endian, shift)?;
+            }
+            for (plane, offset) in [(first, 0), (second, 1)] {
+                if image.depth == 8 {
+                    for (x, sample) in image.row_exact_mut(plane, y)?.iter_mut().enumerate() {
+                        *sample = row[x * 2 + offset] as u8;
+                    }
+                } else {
+                    for (x, sample) in image.row16_exact_mut(plane, y)?.iter_mut().enumerate() {
+                        *sample = row[x * 2 + offset];
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Reader for YuvReader {
+    fn read_frame(&mut self, config: &Config) -> AvifResult<(Image, u64)> {
+        let (Some(width), Some(height)) = (config.width, config.height) else {
+            return AvifError::unknown_error("the dimensions of raw yuv frames must be given");
+        };
+        let layout = config.yuv_format.unwrap_or(PixelFormat::Yuv420);
+        // Chroma layout and shift of the samples within 16-bit words.
+        let (yuv_format, depth, interleaved_chroma, shift) = match layout {
+            PixelFormat::AndroidNv12 => (PixelFormat::Yuv420, 8, Some(false), 0),
+            PixelFormat::AndroidNv21 => (PixelFormat::Yuv420, 8, Some(true), 0),
+            PixelFormat::AndroidP010 => (PixelFormat::Yuv420, 10, Some(false), 6),
+            PixelFormat::None => return AvifError::invalid_argument(),
+            _ => (layout, config.depth.unwrap_or(8), None, 0),
+        };
+        if config
+            .depth
+            .is_some_and(|config_depth| config_depth != depth)
+            || !matches!(depth, 8 | 10 | 12)
+        {
+            return AvifError::unknown_error("unsupported raw yuv depth");
+        }
+        let mut image = Image {
+            width,
+            height,
+            depth,
+            yuv_format,
+            yuv_range: config.yuv_range.unwrap_or(YuvRange::Limited),
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color)?;
+        let planes: &[Plane] = match (interleaved_chroma, yuv_format) {
+            (Some(_), _) | (None, PixelFormat::Yuv400) => &[Plane::Y],
+            _ => &YUV_PLANES,
+        };
+        for plane in planes {
+            let plane_data = image.plane_data(*plane).unwrap();
+            for y in 0..plane_data.height {
+                if depth == 8 {
+                    let row = image.row_exact_mut(*plane, y)?;
+                    self.reader
+                        .read_exact(row)
+                        .map_err(AvifError::map_unknown_error)?;
+                } else {
+                    let row = image.row16_exact_mut(*plane, y)?;
+                    self.read_samples16(row, config.big_endian, shift)?;
+                }
+            }
+        }
+        if let Some(v_first) = interleaved_chroma {
+            self.read_interleaved_chroma(&mut image, v_first, config.big_endian, shift)?;
+        }
+        // Round the end time of the frame rather than its duration so that the rounding errors do
+        // not accumulate.
+        let duration = match config.frame_rate {
+            Some(frame_rate) if frame_rate > 0.0 => {
+                let end_time = |index: u64| (index as f64 * 1000.0 / frame_rate).round() as u64;
+                end_time(self.frame_index + 1) - end_time(self.frame_index)
+            }
+            _ => 0,
+        };
+        self.frame_index += 1;
+        Ok((image, duration))
+    }
+
+    fn has_more_frames(&mut self) -> bool {
+        match self.reader.fill_buf() {
+            Ok(buffer) => !buffer.is_empty(),
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::AvifResult;
+
+use super::image_crate;
+use super::rgb_format;
+use super::Writer;
+
+use image::codecs::bmp::BmpEncoder;
+use std::fs::File;
+
+// Writes 8-bit BMP images.
+#[derive(Default)]
+pub struct BmpWriter {}
+
+impl Writer for BmpWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let format = rgb_format(image, true);
+        image_crate::encode(BmpEncoder::new(file), image, format, 8)
+    }
+}
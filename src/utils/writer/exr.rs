@@ -0,0 +1,209 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+
+use super::linear_samples;
+use super::rgb_format;
+use super::Writer;
+
+use std::fs::File;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExrPixelType {
+    // IEEE 754 half-precision binary16.
+    #[default]
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn value(&self) -> i32 {
+        match self {
+            Self::Half => 1,
+            Self::Float => 2,
+        }
+    }
+}
+
+// Writes single part, scanline, uncompressed OpenEXR images of linear light samples.
+#[derive(Default)]
+pub struct ExrWriter {
+    pub pixel_type: ExrPixelType,
+}
+
+pub(crate) fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // Infinity or NaN.
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // Rounds to the nearest value, ties to even. A carry out of the mantissa correctly increments
+    // the exponent.
+    let round = |value: u32, shift: u32| {
+        let truncated = value >> shift;
+        let remainder = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if remainder > halfway || (remainder == halfway && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        // Subnormal number, with the implicit leading bit made explicit.
+        return sign | round(mantissa | 0x80_0000, (14 - half_exponent) as u32) as u16;
+    }
+    sign | round(((half_exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+fn write_attribute(data: &mut Vec<u8>, name: &str, attribute_type: &str, value: &[u8]) {
+    data.extend_from_slice(name.as_bytes());
+    data.push(0);
+    data.extend_from_slice(attribute_type.as_bytes());
+    data.push(0);
+    data.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    data.extend_from_slice(value);
+}
+
+fn box2i(width: u32, height: u32) -> Vec<u8> {
+    [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+impl Writer for ExrWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let format = rgb_format(image, true);
+        if !image.icc.is_empty() || !image.exif.is_empty() || !image.xmp.is_empty() {
+            eprintln!("Warning: Ignoring metadata, which is not supported by the EXR writer");
+        }
+        let samples = linear_samples(image, format)?;
+        // Channel names with their index in the samples of a pixel, in the alphabetical order
+        // required by the format.
+        let channels: &[(&str, usize)] = match format {
+            rgb::Format::Gray => &[("Y", 0)],
+            rgb::Format::GrayA => &[("A", 1), ("Y", 0)],
+            rgb::Format::Rgb => &[("B", 2), ("G", 1), ("R", 0)],
+            _ => &[("A", 3), ("B", 2), ("G", 1), ("R", 0)],
+        };
+        let mut channel_list = Vec::new();
+        for (name, _) in channels {
+            channel_list.extend_from_slice(name.as_bytes());
+            channel_list.push(0);
+            channel_list.extend_from_slice(&self.pixel_type.value().to_le_bytes());
+            // pLinear and reserved bytes.
+            channel_list.extend_from_slice(&[0; 4]);
+            // Horizontal and vertical sampling.
+            channel_list.extend_from_slice(&1i32.to_le_bytes());
+            channel_list.extend_from_slice(&1i32.to_le_bytes());
+        }
+        channel_list.push(0);
+
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        write_attribute(&mut data, "channels", "chlist", &channel_list);
+        write_attribute(&mut data, "compression", "compression", &[0]);
+        let window = box2i(image.width, image.height);
+        write_attribute(&mut data, "dataWindow", "box2i", &window);
+        write_attribute(&mut data, "displayWindow", "box2i", &window);
+        // Increasing y.
+        write_attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+        write_attribute(&mut data, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+        write_attribute(&mut data, "screenWindowCenter", "v2f", &[0; 8]);
+        write_attribute(&mut data, "screenWindowWidth", "float", &1f32.to_le_bytes());
+        data.push(0);
+
+        // Each chunk holds one scanline: its y coordinate, its size and the samples of each
+        // channel one after the other.
+        let channel_count = format.channel_count() as usize;
+        let sample_size = if self.pixel_type == ExrPixelType::Half { 2 } else { 4 };
+        let line_size = image.width as usize * channel_count * sample_size;
+        let chunk_size = 8 + line_size;
+        let first_chunk_offset = data.len() + image.height as usize * 8;
+        for y in 0..image.height as usize {
+            data.extend_from_slice(&((first_chunk_offset + y * chunk_size) as u64).to_le_bytes());
+        }
+        for (y, row) in samples
+            .chunks_exact(image.width as usize * channel_count)
+            .enumerate()
+        {
+            data.extend_from_slice(&(y as i32).to_le_bytes());
+            data.extend_from_slice(&(line_size as i32).to_le_bytes());
+            for (_, index) in channels {
+                for pixel in row.chunks_exact(channel_count) {
+                    match self.pixel_type {
+                        ExrPixelType::Half => {
+                            data.extend_from_slice(&f32_to_half(pixel[*index]).to_le_bytes())
+                        }
+                        ExrPixelType::Float => data.extend_from_slice(&pixel[*index].to_le_bytes()),
+                    }
+                }
+            }
+        }
+        file.write_all(&data).map_err(AvifError::map_unknown_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0.0, 0x0000)]
+    #[test_case(-0.0, 0x8000)]
+    #[test_case(1.0, 0x3c00)]
+    #[test_case(-2.0, 0xc000)]
+    #[test_case(1.0 / 3.0, 0x3555)]
+    #[test_case(65504.0, 0x7bff)]
+    #[test_case(65520.0, 0x7c00 ; "rounded to infinity")]
+    #[test_case(1e10, 0x7c00)]
+    #[test_case(5.9604645e-8, 0x0001)]
+    #[test_case(2.0e-8, 0x0000 ; "rounded to zero")]
+    #[test_case(6.1035156e-5, 0x0400 ; "smallest normal")]
+    #[test_case(1.0 + 1.0 / 2048.0, 0x3c00 ; "tie to even")]
+    #[test_case(1.0 + 3.0 / 2048.0, 0x3c02 ; "tie to even rounded up")]
+    #[test_case(f32::NEG_INFINITY, 0xfc00)]
+    fn half(value: f32, expected: u16) {
+        assert_eq!(f32_to_half(value), expected);
+    }
+
+    #[test]
+    fn half_round_trip() {
+        for half in 0..0x7c00u16 {
+            assert_eq!(
+                f32_to_half(crate::utils::reader::exr::half_to_f32(half)),
+                half
+            );
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::decoder::track::ImageTiming;
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+use crate::OptionExtension;
+use crate::RepetitionCount;
+
+use super::AnimationWriter;
+use super::Writer;
+
+use color_quant::NeuQuant;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+// Pixels with a lower alpha value are written as fully transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+// Sampling factor of the NeuQuant network, in 1..=30. Lower is slower and more accurate.
+const NEUQUANT_SAMPLING_FACTOR: i32 = 10;
+
+pub struct GifWriter {
+    pub dithering: bool,
+    repetition_count: RepetitionCount,
+    encoder: Option<gif::Encoder<File>>,
+}
+
+impl GifWriter {
+    pub fn create(repetition_count: RepetitionCount, dithering: bool) -> Self {
+        Self {
+            dithering,
+            repetition_count,
+            encoder: None,
+        }
+    }
+}
+
+// Returns the delay of the frame with the given timing, in centiseconds. The delay is the
+// difference between the rounded start and end timestamps of the frame, so that the rounding
+// errors do not accumulate over the frames.
+fn frame_delay(timing: &ImageTiming) -> u16 {
+    let centiseconds = |seconds: f64| (seconds * 100.0).round().max(0.0) as u64;
+    let delay = centiseconds(timing.pts + timing.duration).saturating_sub(centiseconds(timing.pts));
+    delay.min(u16::MAX as u64) as u16
+}
+
+struct QuantizedImage {
+    palette: Vec<u8>,
+    indices: Vec<u8>,
+    transparent: Option<u8>,
+}
+
+// Maps the 8-bit RGBA pixels to at most 256 palette entries, one of them being reserved for
+// transparency if needed. The palette is exact if the image has few enough colors.
+fn quantize(rgba: &[u8], width: usize, dithering: bool) -> QuantizedImage {
+    let is_opaque = |pixel: &[u8]| pixel[3] >= ALPHA_THRESHOLD;
+    let has_transparency = rgba.chunks_exact(4).any(|pixel| !is_opaque(pixel));
+    let max_color_count = if has_transparency { 255 } else { 256 };
+    let transparent_index = |palette: &Vec<u8>| (palette.len() / 3) as u8;
+
+    let mut exact_colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut palette: Vec<u8> = Vec::new();
+    for pixel in rgba.chunks_exact(4).filter(|pixel| is_opaque(pixel)) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        if exact_colors.contains_key(&color) {
+            continue;
+        }
+        if exact_colors.len() == max_color_count {
+            exact_colors.clear();
+            break;
+        }
+        exact_colors.insert(color, exact_colors.len() as u8);
+        palette.extend_from_slice(&color);
+    }
+    if !exact_colors.is_empty() || !rgba.chunks_exact(4).any(is_opaque) {
+        let transparent = has_transparency.then(|| transparent_index(&palette));
+        let indices = rgba
+            .chunks_exact(4)
+            .map(
+                |pixel| match exact_colors.get(&[pixel[0], pixel[1], pixel[2]]) {
+                    Some(index) if is_opaque(pixel) => *index,
+                    _ => transparent.unwrap_or(0),
+                },
+            )
+            .collect();
+        if has_transparency {
+            palette.extend_from_slice(&[0, 0, 0]);
+        }
+        return QuantizedImage {
+            palette,
+            indices,
+            transparent,
+        };
+    }
+
+    let opaque_pixels: Vec<u8> = rgba
+        .chunks_exact(4)
+        .filter(|pixel| is_opaque(pixel))
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    let quantizer = NeuQuant::new(NEUQUANT_SAMPLING_FACTOR, max_color_count, &opaque_pixels);
+    let mut palette = quantizer.color_map_rgb();
+    let transparent = has_transparency.then(|| transparent_index(&palette));
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    // Floyd-Steinberg error diffusion. The errors are stored in sixteenths, with one extra
+    // column on each side of the row.
+    let mut errors = vec![[0i32; 3]; width + 2];
+    let mut next_errors = vec![[0i32; 3]; width + 2];
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            if !is_opaque(pixel) {
+                indices.push(transparent.unwrap_or(0));
+                continue;
+            }
+            let mut color = [pixel[0], pixel[1], pixel[2], 255];
+            if dithering {
+                for c in 0..3 {
+                    color[c] = (color[c] as i32 + errors[x + 1][c] / 16).clamp(0, 255) as u8;
+                }
+            }
+            let index = quantizer.index_of(&color);
+            indices.push(index as u8);
+            if dithering {
+                for c in 0..3 {
+                    let error = color[c] as i32 - palette[index * 3 + c] as i32;
+                    errors[x + 2][c] += error * 7;
+                    next_errors[x][c] += error * 3;
+                    next_errors[x + 1][c] += error * 5;
+                    next_errors[x + 2][c] += error;
+                }
+            }
+        }
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.fill([0; 3]);
+    }
+    if has_transparency {
+        palette.extend_from_slice(&[0, 0, 0]);
+    }
+    QuantizedImage {
+        palette,
+        indices,
+        transparent,
+    }
+}
+
+fn create_frame(image: &Image, dithering: bool) -> AvifResult<gif::Frame<'static>> {
+    let (Ok(width), Ok(height)) = (u16::try_from(image.width), u16::try_from(image.height)) else {
+        return AvifError::unknown_error("image is too large to be written as GIF");
+    };
+    let mut rgb = rgb::Image::create_from_yuv(image);
+    rgb.depth = 8;
+    rgb.format = rgb::Format::Rgba;
+    rgb.allocate()?;
+    rgb.convert_from_yuv(image)?;
+    let mut rgba = Vec::with_capacity(image.width as usize * image.height as usize * 4);
+    for y in 0..image.height {
+        rgba.extend_from_slice(&rgb.row(y)?[..image.width as usize * 4]);
+    }
+    let quantized = quantize(&rgba, image.width as usize, dithering);
+    Ok(gif::Frame {
+        width,
+        height,
+        buffer: Cow::Owned(quantized.indices),
+        palette: Some(quantized.palette),
+        transparent: quantized.transparent,
+        // Each frame covers the whole canvas, including its transparent pixels.
+        dispose: gif::DisposalMethod::Background,
+        ..Default::default()
+    })
+}
+
+fn create_encoder<W: Write>(w: W, image: &Image) -> AvifResult<gif::Encoder<W>> {
+    // The frames have local palettes.
+    gif::Encoder::new(w, image.width as u16, image.height as u16, &[])
+        .map_err(AvifError::map_unknown_error)
+}
+
+impl Writer for GifWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let frame = create_frame(image, self.dithering)?;
+        let mut encoder = create_encoder(file, image)?;
+        encoder
+            .write_frame(&frame)
+            .map_err(AvifError::map_unknown_error)?;
+        encoder.into_inner().map_err(AvifError::map_unknown_error)?;
+        Ok(())
+    }
+}
+
+impl AnimationWriter for GifWriter {
+    fn write_animation_frame(
+        &mut self,
+        file: &mut File,
+        image: &Image,
+        timing: &ImageTiming,
+    ) -> AvifResult<()> {
+        let mut frame = create_frame(image, self.dithering)?;
+        if self.encoder.is_none() {
+            let file = file.try_clone().map_err(AvifError::map_unknown_error)?;
+            let mut encoder = create_encoder(file, image)?;
+            // The NETSCAPE2.0 loop count is the number of repetitions, 0 meaning infinitely.
+            // Without the extension, the animation is played once. Larger repetition counts are
+            // clamped to the largest loop count that can be stored.
+            let repeat = match self.repetition_count {
+                RepetitionCount::Finite(0) => None,
+                RepetitionCount::Finite(count) => Some(gif::Repeat::Finite(
+                    u16::try_from(count).unwrap_or(u16::MAX),
+                )),
+                RepetitionCount::Unknown | RepetitionCount::Infinite => Some(gif::Repeat::Infinite),
+            };
+            if let Some(repeat) = repeat {
+                encoder
+                    .set_repeat(repeat)
+                    .map_err(AvifError::map_unknown_error)?;
+            }
+            self.encoder = Some(encoder);
+        }
+        frame.delay = frame_delay(timing);
+        self.encoder
+            .unwrap_mut()
+            .write_frame(&frame)
+            .map_err(AvifError::map_unknown_error)
+    }
+
+    fn finish(&mut self) -> AvifResult<()> {
+        match self.encoder.take() {
+            Some(encoder) => {
+                encoder.into_inner().map_err(AvifError::map_unknown_error)?;
+                Ok(())
+            }
+            None => AvifError::unknown_error("no frame was written"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn timing(pts: f64, duration: f64) -> ImageTiming {
+        ImageTiming {
+            pts,
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frame_delays_do_not_drift() {
+        // 30 frames per second cannot be represented exactly in centiseconds.
+        let delays: Vec<u16> = (0..6)
+            .map(|i| frame_delay(&timing(i as f64 / 30.0, 1.0 / 30.0)))
+            .collect();
+        assert_eq!(delays, [3, 4, 3, 3, 4, 3]);
+        assert_eq!(delays.iter().sum::<u16>(), 20);
+    }
+
+    #[test_case(0.0, 0.0, 0 ; "zero duration")]
+    #[test_case(0.0, 0.001, 0 ; "short duration")]
+    #[test_case(1.0, 0.5, 50 ; "offset")]
+    #[test_case(0.0, 1000.0, 65535 ; "long duration")]
+    fn frame_delay_bounds(pts: f64, duration: f64, expected: u16) {
+        assert_eq!(frame_delay(&timing(pts, duration)), expected);
+    }
+
+    #[test]
+    fn quantize_exact() {
+        let rgba = [
+            10, 20, 30, 255, 40, 50, 60, 255, 10, 20, 30, 255, 0, 0, 0, 0,
+        ];
+        let quantized = quantize(&rgba, 2, false);
+        assert_eq!(quantized.palette, [10, 20, 30, 40, 50, 60, 0, 0, 0]);
+        assert_eq!(quantized.indices, [0, 1, 0, 2]);
+        assert_eq!(quantized.transparent, Some(2));
+    }
+
+    #[test_case(false ; "nearest")]
+    #[test_case(true ; "dithering")]
+    fn quantize_many_colors(dithering: bool) {
+        let width = 64;
+        let rgba: Vec<u8> = (0..width * 64)
+            .flat_map(|i| [(i % width * 4) as u8, (i / width * 4) as u8, 128, 255])
+            .collect();
+        let quantized = quantize(&rgba, width, dithering);
+        assert_eq!(quantized.palette.len(), 256 * 3);
+        assert_eq!(quantized.transparent, None);
+        // The average color is preserved.
+        for c in 0..3 {
+            let sum: u64 = rgba.chunks_exact(4).map(|pixel| pixel[c] as u64).sum();
+            let quantized_sum: u64 = quantized
+                .indices
+                .iter()
+                .map(|index| quantized.palette[*index as usize * 3 + c] as u64)
+                .sum();
+            let pixel_count = (width * 64) as u64;
+            assert!((sum / pixel_count).abs_diff(quantized_sum / pixel_count) <= 4);
+        }
+    }
+}
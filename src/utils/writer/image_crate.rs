@@ -0,0 +1,63 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+
+use super::exif_from_tiff_header;
+use super::rgb_samples;
+
+use image::ExtendedColorType;
+use image::ImageEncoder;
+
+// Encodes image with an encoder of the image crate, carrying the metadata it supports.
+pub(crate) fn encode(
+    mut encoder: impl ImageEncoder,
+    image: &Image,
+    format: rgb::Format,
+    depth: u8,
+) -> AvifResult<()> {
+    let color_type = match (format, depth) {
+        (rgb::Format::Gray, 8) => ExtendedColorType::L8,
+        (rgb::Format::GrayA, 8) => ExtendedColorType::La8,
+        (rgb::Format::Rgb, 8) => ExtendedColorType::Rgb8,
+        (rgb::Format::Rgba, 8) => ExtendedColorType::Rgba8,
+        (rgb::Format::Gray, 16) => ExtendedColorType::L16,
+        (rgb::Format::GrayA, 16) => ExtendedColorType::La16,
+        (rgb::Format::Rgb, 16) => ExtendedColorType::Rgb16,
+        (rgb::Format::Rgba, 16) => ExtendedColorType::Rgba16,
+        _ => return AvifError::not_implemented(),
+    };
+    if !image.icc.is_empty() && encoder.set_icc_profile(image.icc.clone()).is_err() {
+        eprintln!("Warning: Ignoring ICC profile");
+    }
+    if !image.exif.is_empty() {
+        let supported = match exif_from_tiff_header(image) {
+            Some(exif) => encoder.set_exif_metadata(exif.to_vec()).is_ok(),
+            None => false,
+        };
+        if !supported {
+            eprintln!("Warning: Ignoring Exif data");
+        }
+    }
+    if !image.xmp.is_empty() {
+        eprintln!("Warning: Ignoring XMP data");
+    }
+    let samples = rgb_samples(image, format, depth)?;
+    encoder
+        .write_image(&samples, image.width, image.height, color_type)
+        .map_err(AvifError::map_unknown_error)
+}
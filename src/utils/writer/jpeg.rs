@@ -12,39 +12,287 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::gainmap::GainMap;
+use crate::gainmap::GainMapMetadata;
 use crate::image::*;
 use crate::reformat::rgb;
+use crate::utils::Fraction;
+use crate::utils::UFraction;
 use crate::AvifError;
 use crate::AvifResult;
+use crate::PixelFormat;
 
 use super::Writer;
 
 use image::codecs::jpeg;
 use std::fs::File;
+use std::io::Write;
 
 #[derive(Default)]
 pub struct JpegWriter {
     pub quality: Option<u8>,
 }
 
-impl Writer for JpegWriter {
-    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const ISO_NAMESPACE: &[u8] = b"urn:iso:std:iso:ts:21496:-1\0";
+
+impl JpegWriter {
+    fn encode(&self, image: &Image, format: rgb::Format) -> AvifResult<Vec<u8>> {
         let mut rgb = rgb::Image::create_from_yuv(image);
         rgb.depth = 8;
-        rgb.format = rgb::Format::Rgb;
+        rgb.format = format;
         rgb.allocate()?;
         rgb.convert_from_yuv(image)?;
 
-        let rgba_pixels = rgb.pixels.as_ref().unwrap();
-        let mut encoder = jpeg::JpegEncoder::new_with_quality(file, self.quality.unwrap_or(90));
+        let pixels = rgb.pixels.as_ref().unwrap();
+        let mut data = Vec::new();
+        let mut encoder =
+            jpeg::JpegEncoder::new_with_quality(&mut data, self.quality.unwrap_or(90));
         encoder
             .encode(
-                rgba_pixels.slice(0, rgba_pixels.size() as u32)?,
+                pixels.slice(0, pixels.size() as u32)?,
                 image.width,
                 image.height,
-                image::ExtendedColorType::Rgb8,
+                if format == rgb::Format::Gray {
+                    image::ExtendedColorType::L8
+                } else {
+                    image::ExtendedColorType::Rgb8
+                },
             )
             .map_err(AvifError::map_unknown_error)?;
+        Ok(data)
+    }
+}
+
+fn segment(marker: u8, payload: &[&[u8]]) -> AvifResult<Vec<u8>> {
+    let length = payload.iter().map(|part| part.len()).sum::<usize>() + 2;
+    let length = u16::try_from(length).map_err(AvifError::map_unknown_error)?;
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&length.to_be_bytes());
+    for part in payload {
+        segment.extend_from_slice(part);
+    }
+    Ok(segment)
+}
+
+// Returns the offset right after the start of image marker and the JFIF segment if any, where
+// the other application segments are inserted.
+fn header_size(jpeg: &[u8]) -> usize {
+    if jpeg.len() >= 6 && jpeg[2..4] == [0xFF, 0xE0] {
+        4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize
+    } else {
+        2
+    }
+}
+
+fn fraction_to_string(fraction: Fraction) -> String {
+    format!("{}", fraction.0 as f64 / fraction.1 as f64)
+}
+
+fn ufraction_to_string(fraction: UFraction) -> String {
+    format!("{}", fraction.0 as f64 / fraction.1 as f64)
+}
+
+// Returns the gain map XMP as per the Adobe gain map specification. Per channel values are
+// written as an rdf:Seq if they differ.
+fn gainmap_xmp(metadata: &GainMapMetadata) -> String {
+    let base_rendition_is_hdr = metadata.base_hdr_headroom.0 as f64
+        / metadata.base_hdr_headroom.1 as f64
+        > metadata.alternate_hdr_headroom.0 as f64 / metadata.alternate_hdr_headroom.1 as f64;
+    let (offset_sdr, offset_hdr, hdr_capacity_min, hdr_capacity_max) = if base_rendition_is_hdr {
+        (
+            metadata.alternate_offset,
+            metadata.base_offset,
+            metadata.alternate_hdr_headroom,
+            metadata.base_hdr_headroom,
+        )
+    } else {
+        (
+            metadata.base_offset,
+            metadata.alternate_offset,
+            metadata.base_hdr_headroom,
+            metadata.alternate_hdr_headroom,
+        )
+    };
+    let mut attributes = format!(
+        "hdrgm:Version=\"1.0\" hdrgm:BaseRenditionIsHDR=\"{}\" hdrgm:HDRCapacityMin=\"{}\" \
+         hdrgm:HDRCapacityMax=\"{}\"",
+        if base_rendition_is_hdr { "True" } else { "False" },
+        ufraction_to_string(hdr_capacity_min),
+        ufraction_to_string(hdr_capacity_max)
+    );
+    let mut elements = String::new();
+    let channels: [(&str, [String; 3]); 5] = [
+        ("GainMapMin", metadata.min.map(fraction_to_string)),
+        ("GainMapMax", metadata.max.map(fraction_to_string)),
+        ("Gamma", metadata.gamma.map(ufraction_to_string)),
+        ("OffsetSDR", offset_sdr.map(fraction_to_string)),
+        ("OffsetHDR", offset_hdr.map(fraction_to_string)),
+    ];
+    for (name, values) in channels {
+        if values[0] == values[1] && values[0] == values[2] {
+            attributes += &format!(" hdrgm:{name}=\"{}\"", values[0]);
+        } else {
+            elements += &format!(
+                "<hdrgm:{name}><rdf:Seq><rdf:li>{}</rdf:li><rdf:li>{}</rdf:li><rdf:li>{}</rdf:li>\
+                 </rdf:Seq></hdrgm:{name}>",
+                values[0], values[1], values[2]
+            );
+        }
+    }
+    format!(
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF \
+         xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"><rdf:Description \
+         rdf:about=\"\" xmlns:hdrgm=\"http://ns.adobe.com/hdr-gain-map/1.0/\" {attributes}>\
+         {elements}</rdf:Description></rdf:RDF></x:xmpmeta>"
+    )
+}
+
+// Returns the primary image XMP of an Ultra HDR file, listing the gain map image of the given
+// size.
+fn primary_xmp(gainmap_size: usize) -> String {
+    format!(
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF \
+         xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"><rdf:Description \
+         rdf:about=\"\" xmlns:Container=\"http://ns.google.com/photos/1.0/container/\" \
+         xmlns:Item=\"http://ns.google.com/photos/1.0/container/item/\" \
+         xmlns:hdrgm=\"http://ns.adobe.com/hdr-gain-map/1.0/\" hdrgm:Version=\"1.0\">\
+         <Container:Directory><rdf:Seq><rdf:li rdf:parseType=\"Resource\"><Container:Item \
+         Item:Semantic=\"Primary\" Item:Mime=\"image/jpeg\"/></rdf:li><rdf:li \
+         rdf:parseType=\"Resource\"><Container:Item Item:Semantic=\"GainMap\" \
+         Item:Mime=\"image/jpeg\" Item:Length=\"{gainmap_size}\"/></rdf:li></rdf:Seq>\
+         </Container:Directory></rdf:Description></rdf:RDF></x:xmpmeta>"
+    )
+}
+
+// GainMapMetadata syntax as per clause C.2.2 of ISO 21496-1.
+fn iso_gainmap_metadata(metadata: &GainMapMetadata) -> Vec<u8> {
+    let mut data = Vec::new();
+    // unsigned int(16) minimum_version;
+    data.extend_from_slice(&0u16.to_be_bytes());
+    // unsigned int(16) writer_version;
+    data.extend_from_slice(&0u16.to_be_bytes());
+    let channel_count = metadata.channel_count();
+    // unsigned int(1) is_multichannel;
+    // unsigned int(1) use_base_colour_space;
+    // unsigned int(6) reserved;
+    data.push(
+        if channel_count == 3 { 0x80 } else { 0 }
+            | if metadata.use_base_color_space { 0x40 } else { 0 },
+    );
+    let push_ufraction = |data: &mut Vec<u8>, fraction: UFraction| {
+        data.extend_from_slice(&fraction.0.to_be_bytes());
+        data.extend_from_slice(&fraction.1.to_be_bytes());
+    };
+    let push_fraction = |data: &mut Vec<u8>, fraction: Fraction| {
+        data.extend_from_slice(&fraction.0.to_be_bytes());
+        data.extend_from_slice(&fraction.1.to_be_bytes());
+    };
+    push_ufraction(&mut data, metadata.base_hdr_headroom);
+    push_ufraction(&mut data, metadata.alternate_hdr_headroom);
+    for i in 0..channel_count as usize {
+        push_fraction(&mut data, metadata.min[i]);
+        push_fraction(&mut data, metadata.max[i]);
+        push_ufraction(&mut data, metadata.gamma[i]);
+        push_fraction(&mut data, metadata.base_offset[i]);
+        push_fraction(&mut data, metadata.alternate_offset[i]);
+    }
+    data
+}
+
+// Size of the Multi-Picture Format segment payload: the MPF identifier, the TIFF header, an IFD
+// with three entries and two MP entries.
+const MPF_SIZE: usize = 4 + 8 + 2 + 3 * 12 + 4 + 2 * 16;
+
+// Returns the Multi-Picture Format segment payload listing the primary image and the gain map
+// image. Offsets are relative to the TIFF header, located at tiff_offset in the file.
+fn mpf(primary_size: usize, gainmap_size: usize, tiff_offset: usize) -> AvifResult<Vec<u8>> {
+    let to_u32 = |value: usize| u32::try_from(value).map_err(AvifError::map_unknown_error);
+    let mut data = b"MPF\0MM\0*".to_vec();
+    // Offset of the IFD.
+    data.extend_from_slice(&8u32.to_be_bytes());
+    // MPFVersion, NumberOfImages and MPEntry tags.
+    data.extend_from_slice(&3u16.to_be_bytes());
+    data.extend_from_slice(&[0xB0, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x04]);
+    data.extend_from_slice(b"0100");
+    data.extend_from_slice(&[0xB0, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+    data.extend_from_slice(&2u32.to_be_bytes());
+    data.extend_from_slice(&[0xB0, 0x02, 0x00, 0x07, 0x00, 0x00, 0x00, 0x20]);
+    // The MP entries follow the IFD: 8 + 2 + 3 * 12 + 4 = 50.
+    data.extend_from_slice(&50u32.to_be_bytes());
+    // Offset of the next IFD.
+    data.extend_from_slice(&0u32.to_be_bytes());
+    // Representative baseline MP primary image.
+    data.extend_from_slice(&0x20030000u32.to_be_bytes());
+    data.extend_from_slice(&to_u32(primary_size)?.to_be_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes());
+    // Gain map image.
+    data.extend_from_slice(&0u32.to_be_bytes());
+    data.extend_from_slice(&to_u32(gainmap_size)?.to_be_bytes());
+    data.extend_from_slice(&to_u32(primary_size - tiff_offset)?.to_be_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes());
+    debug_assert_eq!(data.len(), MPF_SIZE);
+    Ok(data)
+}
+
+impl Writer for JpegWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let data = self.encode(image, rgb::Format::Rgb)?;
+        file.write_all(&data).map_err(AvifError::map_unknown_error)
+    }
+
+    // Writes an Ultra HDR JPEG: the base image is the primary image and the gain map image is
+    // appended as a secondary image listed in a Multi-Picture Format segment. The gain map
+    // metadata is stored both as XMP and as ISO 21496-1.
+    fn write_frame_with_gainmap(
+        &mut self,
+        file: &mut File,
+        image: &Image,
+        gainmap: &GainMap,
+    ) -> AvifResult<()> {
+        gainmap.metadata.is_valid()?;
+        let gainmap_format = if gainmap.image.yuv_format == PixelFormat::Yuv400 {
+            rgb::Format::Gray
+        } else {
+            rgb::Format::Rgb
+        };
+        let gainmap_jpeg = self.encode(&gainmap.image, gainmap_format)?;
+        let offset = header_size(&gainmap_jpeg);
+        let gainmap_jpeg = [
+            &gainmap_jpeg[..offset],
+            &segment(
+                0xE1,
+                &[XMP_NAMESPACE, gainmap_xmp(&gainmap.metadata).as_bytes()],
+            )?,
+            &segment(
+                0xE2,
+                &[ISO_NAMESPACE, &iso_gainmap_metadata(&gainmap.metadata)],
+            )?,
+            &gainmap_jpeg[offset..],
+        ]
+        .concat();
+
+        let primary_jpeg = self.encode(image, rgb::Format::Rgb)?;
+        let offset = header_size(&primary_jpeg);
+        let mut header = primary_jpeg[..offset].to_vec();
+        header.extend(segment(
+            0xE1,
+            &[XMP_NAMESPACE, primary_xmp(gainmap_jpeg.len()).as_bytes()],
+        )?);
+        // The primary image only signals the version of the ISO 21496-1 metadata.
+        header.extend(segment(0xE2, &[ISO_NAMESPACE, &[0, 0, 0, 0]])?);
+        // The MPF segment has a fixed size, so the final size of the primary image is known
+        // before writing it.
+        let tiff_offset = header.len() + 4 + 4;
+        let primary_size = header.len() + 4 + MPF_SIZE + primary_jpeg.len() - offset;
+        header.extend(segment(
+            0xE2,
+            &[&mpf(primary_size, gainmap_jpeg.len(), tiff_offset)?],
+        )?);
+        for data in [&header, &primary_jpeg[offset..], &gainmap_jpeg] {
+            file.write_all(data).map_err(AvifError::map_unknown_error)?;
+        }
         Ok(())
     }
 }
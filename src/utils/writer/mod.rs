@@ -15,17 +15,119 @@
 // Not all sub-modules are used by all targets. Ignore dead code warnings.
 #![allow(dead_code)]
 
+#[cfg(feature = "bmp")]
+pub mod bmp;
+pub mod exr;
+#[cfg(feature = "gif")]
+pub mod gif;
+#[cfg(any(feature = "bmp", feature = "tiff"))]
+mod image_crate;
 #[cfg(feature = "jpeg")]
 pub mod jpeg;
+pub mod pfm;
 #[cfg(feature = "png")]
 pub mod png;
+#[cfg(feature = "pnm")]
+pub mod pnm;
+#[cfg(feature = "tiff")]
+pub mod tiff;
+#[cfg(feature = "webp")]
+pub mod webp;
 pub mod y4m;
 
+use crate::decoder::track::ImageTiming;
+use crate::gainmap::GainMap;
 use crate::image::Image;
+use crate::internal_utils::stream::IStream;
+use crate::parser::exif;
+use crate::reformat::rgb;
+use crate::reformat::transfer;
+use crate::AvifError;
 use crate::AvifResult;
+use crate::PixelFormat;
 
 use std::fs::File;
 
 pub trait Writer {
     fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()>;
+    // Writes the image along with its gain map, if the output format supports gain maps.
+    fn write_frame_with_gainmap(
+        &mut self,
+        _file: &mut File,
+        _image: &Image,
+        _gainmap: &GainMap,
+    ) -> AvifResult<()> {
+        AvifError::not_implemented()
+    }
+}
+
+// Writes the frames of an image sequence into a single animated file.
+pub trait AnimationWriter {
+    fn write_animation_frame(
+        &mut self,
+        file: &mut File,
+        image: &Image,
+        timing: &ImageTiming,
+    ) -> AvifResult<()>;
+    // Completes the file once all the frames were written.
+    fn finish(&mut self) -> AvifResult<()>;
+}
+
+// Returns the format matching the channels of image. Alpha is dropped if it is not supported.
+pub(crate) fn rgb_format(image: &Image, alpha_supported: bool) -> rgb::Format {
+    let is_monochrome = image.yuv_format == PixelFormat::Yuv400;
+    match (is_monochrome, image.alpha_present && alpha_supported) {
+        (true, false) => rgb::Format::Gray,
+        (true, true) => rgb::Format::GrayA,
+        (false, false) => rgb::Format::Rgb,
+        (false, true) => rgb::Format::Rgba,
+    }
+}
+
+// Returns the tightly packed samples of image in the given format and depth. 16-bit samples are
+// in native endianness.
+pub(crate) fn rgb_samples(image: &Image, format: rgb::Format, depth: u8) -> AvifResult<Vec<u8>> {
+    let mut rgb = rgb::Image::create_from_yuv(image);
+    rgb.depth = depth;
+    rgb.format = format;
+    rgb.allocate()?;
+    rgb.convert_from_yuv(image)?;
+    let row_size = (image.width * format.channel_count()) as usize;
+    let mut samples = Vec::with_capacity(row_size * image.height as usize * (depth as usize / 8));
+    for y in 0..image.height {
+        if depth == 8 {
+            samples.extend_from_slice(&rgb.row(y)?[..row_size]);
+        } else {
+            for sample in &rgb.row16(y)?[..row_size] {
+                samples.extend_from_slice(&sample.to_ne_bytes());
+            }
+        }
+    }
+    Ok(samples)
+}
+
+// Returns the tightly packed samples of image in the given format, converted into linear light in
+// [0, 1] according to the transfer characteristics of image. Alpha samples are only normalized.
+pub(crate) fn linear_samples(image: &Image, format: rgb::Format) -> AvifResult<Vec<f32>> {
+    let channel_count = format.channel_count() as usize;
+    let alpha_offset = if format.has_alpha() { channel_count - 1 } else { channel_count };
+    Ok(rgb_samples(image, format, 16)?
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(index, sample)| {
+            let value = u16::from_ne_bytes([sample[0], sample[1]]) as f32 / 65535.0;
+            if index % channel_count == alpha_offset {
+                value
+            } else {
+                transfer::to_linear(image.transfer_characteristics, value)
+            }
+        })
+        .collect())
+}
+
+// Returns the Exif data of image starting at the TIFF header, if it has one.
+pub(crate) fn exif_from_tiff_header(image: &Image) -> Option<&[u8]> {
+    let mut stream = IStream::create(&image.exif);
+    let offset = exif::parse_exif_tiff_header_offset(&mut stream).ok()?;
+    image.exif.get(offset as usize..)
 }
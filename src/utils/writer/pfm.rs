@@ -0,0 +1,51 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+
+use super::linear_samples;
+use super::rgb_format;
+use super::Writer;
+
+use std::fs::File;
+use std::io::Write;
+
+// Writes little-endian Portable FloatMap images of linear light samples. The alpha channel is
+// dropped.
+#[derive(Default)]
+pub struct PfmWriter {}
+
+impl Writer for PfmWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let format = rgb_format(image, false);
+        if !image.icc.is_empty() || !image.exif.is_empty() || !image.xmp.is_empty() {
+            eprintln!("Warning: Ignoring metadata, which is not supported by PFM");
+        }
+        let samples = linear_samples(image, format)?;
+        let magic = if format == rgb::Format::Gray { "Pf" } else { "PF" };
+        // A negative scale means that the samples are little-endian.
+        let mut data = format!("{magic}\n{} {}\n-1.0\n", image.width, image.height).into_bytes();
+        let row_size = (image.width * format.channel_count()) as usize;
+        // Rows are stored from bottom to top.
+        for row in samples.rchunks_exact(row_size) {
+            for sample in row {
+                data.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        file.write_all(&data).map_err(AvifError::map_unknown_error)
+    }
+}
@@ -12,14 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::decoder::track::ImageTiming;
 use crate::image::*;
+use crate::internal_utils::stream::IStream;
+use crate::parser::exif;
 use crate::reformat::rgb;
 use crate::AvifError;
 use crate::AvifResult;
+use crate::ColorPrimaries;
+use crate::OptionExtension;
 use crate::PixelFormat;
+use crate::RepetitionCount;
+use crate::TransferCharacteristics;
 
+use std::borrow::Cow;
 use std::fs::File;
+use std::io::Write;
 
+use super::AnimationWriter;
 use super::Writer;
 
 #[derive(Default)]
@@ -35,87 +45,292 @@ fn scale_to_16bit(pixel: u16, max_channel: u16) -> u16 {
     ((pixel as u32 * 65535) / max_channel as u32) as u16
 }
 
-impl Writer for PngWriter {
-    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
-        let is_monochrome = image.yuv_format == PixelFormat::Yuv400;
-        let png_color_type = match (is_monochrome, image.alpha_present) {
-            (true, _) => png::ColorType::Grayscale,
-            (_, false) => png::ColorType::Rgb,
-            (_, true) => png::ColorType::Rgba,
-        };
-        let depth = self.depth.unwrap_or(if image.depth == 8 { 8 } else { 16 });
-        let mut rgb = rgb::Image::create_from_yuv(image);
-        if !is_monochrome {
-            rgb.depth = depth;
-            rgb.format = if image.alpha_present { rgb::Format::Rgba } else { rgb::Format::Rgb };
-            rgb.allocate()?;
-            rgb.convert_from_yuv(image)?;
+// Returns the PNG header carrying the color space, Exif and pixel aspect ratio of image.
+pub(crate) fn png_info(image: &Image) -> png::Info<'_> {
+    let mut info = png::Info::with_size(image.width, image.height);
+    if !image.icc.is_empty() {
+        info.icc_profile = Some(Cow::Borrowed(&image.icc));
+    } else if image.color_primaries == ColorPrimaries::Srgb
+        && image.transfer_characteristics == TransferCharacteristics::Srgb
+    {
+        info.srgb = Some(png::SrgbRenderingIntent::Perceptual);
+    }
+    if !image.exif.is_empty() {
+        // The eXIf chunk starts at the TIFF header.
+        let mut stream = IStream::create(&image.exif);
+        match exif::parse_exif_tiff_header_offset(&mut stream) {
+            Ok(offset) => info.exif_metadata = Some(Cow::Borrowed(&image.exif[offset as usize..])),
+            Err(_) => eprintln!("Warning: Ignoring Exif data because it has no TIFF header"),
         }
+    }
+    if let Some(pasp) = image.pasp {
+        // The number of pixels per unit is inversely proportional to the pixel spacing.
+        if pasp.h_spacing != 0 && pasp.v_spacing != 0 && pasp.h_spacing != pasp.v_spacing {
+            info.pixel_dims = Some(png::PixelDimensions {
+                xppu: pasp.v_spacing,
+                yppu: pasp.h_spacing,
+                unit: png::Unit::Unspecified,
+            });
+        }
+    }
+    info
+}
 
-        let mut encoder = png::Encoder::new(file, image.width, image.height);
-        encoder.set_color(png_color_type);
-        encoder.set_depth(if depth == 8 { png::BitDepth::Eight } else { png::BitDepth::Sixteen });
-        if !image.xmp.is_empty() {
-            if let Ok(text) = String::from_utf8(image.xmp.clone()) {
-                if encoder
-                    .add_itxt_chunk("XML:com.adobe.xmp".to_string(), text)
-                    .is_err()
-                {
-                    eprintln!("Warning: Ignoring XMP data");
-                }
-            } else {
-                eprintln!("Warning: Ignoring XMP data because it is not a valid UTF-8 string");
-            }
+// Writes the cICP, mDCV and cLLI chunks of image. They are not supported by png::Info.
+pub(crate) fn write_hdr_chunks<W: Write>(
+    writer: &mut png::Writer<W>,
+    image: &Image,
+) -> AvifResult<()> {
+    let mut write_chunk = |name: &[u8; 4], data: &[u8]| {
+        writer
+            .write_chunk(png::chunk::ChunkType(*name), data)
+            .map_err(AvifError::map_unknown_error)
+    };
+    // The ICC profile is preferred when present. The sRGB chunk is enough for sRGB.
+    if image.icc.is_empty()
+        && image.color_primaries != ColorPrimaries::Unspecified
+        && image.transfer_characteristics != TransferCharacteristics::Unspecified
+        && (image.color_primaries != ColorPrimaries::Srgb
+            || image.transfer_characteristics != TransferCharacteristics::Srgb)
+    {
+        // The samples are RGB (matrix coefficients 0) and full range.
+        write_chunk(
+            b"cICP",
+            &[
+                image.color_primaries as u16 as u8,
+                image.transfer_characteristics as u16 as u8,
+                0,
+                1,
+            ],
+        )?;
+    }
+    // The chunk names are capitalized since the third edition of the PNG specification. The
+    // drafts used mDCv and cLLi.
+    if let Some(mdcv) = image.mdcv {
+        // The primaries are stored in the red, green, blue order in PNG and in the green, blue,
+        // red order in the mdcv box. The units are the same.
+        let mut data = Vec::with_capacity(24);
+        for i in [2, 0, 1] {
+            data.extend_from_slice(&mdcv.display_primaries_x[i].to_be_bytes());
+            data.extend_from_slice(&mdcv.display_primaries_y[i].to_be_bytes());
         }
-        let mut writer = encoder
-            .write_header()
-            .map_err(AvifError::map_unknown_error)?;
-        let mut rgba_pixel_buffer: Vec<u8> = Vec::new();
-        let rgba_slice = if is_monochrome {
-            for y in 0..image.height {
-                match (image.depth == 8, depth == 8) {
-                    (true, true) => {
-                        let y_row = image.row_exact(Plane::Y, y)?;
-                        rgba_pixel_buffer.extend_from_slice(y_row);
-                    }
-                    (false, false) => {
-                        let y_row = image.row16_exact(Plane::Y, y)?;
-                        for pixel in y_row {
-                            let pixel16 = scale_to_16bit(*pixel, image.max_channel());
-                            rgba_pixel_buffer.extend_from_slice(&pixel16.to_be_bytes());
-                        }
+        data.extend_from_slice(&mdcv.white_point_x.to_be_bytes());
+        data.extend_from_slice(&mdcv.white_point_y.to_be_bytes());
+        data.extend_from_slice(&mdcv.max_display_mastering_luminance.to_be_bytes());
+        data.extend_from_slice(&mdcv.min_display_mastering_luminance.to_be_bytes());
+        write_chunk(b"mDCV", &data)?;
+    }
+    if let Some(clli) = image.clli {
+        // The values are in units of 0.0001 cd/m2.
+        let mut data = Vec::with_capacity(8);
+        data.extend_from_slice(&(clli.max_cll as u32 * 10000).to_be_bytes());
+        data.extend_from_slice(&(clli.max_pall as u32 * 10000).to_be_bytes());
+        write_chunk(b"cLLI", &data)?;
+    }
+    Ok(())
+}
+
+// Returns the PNG color type and the big-endian samples of image at the given depth.
+fn png_samples(image: &Image, depth: u8) -> AvifResult<(png::ColorType, Vec<u8>)> {
+    let is_monochrome = image.yuv_format == PixelFormat::Yuv400;
+    let (png_color_type, rgb_format) = match (is_monochrome, image.alpha_present) {
+        (true, false) => (png::ColorType::Grayscale, None),
+        (true, true) => (png::ColorType::GrayscaleAlpha, Some(rgb::Format::GrayA)),
+        (false, false) => (png::ColorType::Rgb, Some(rgb::Format::Rgb)),
+        (false, true) => (png::ColorType::Rgba, Some(rgb::Format::Rgba)),
+    };
+    let mut rgba_pixel_buffer: Vec<u8> = Vec::new();
+    let Some(rgb_format) = rgb_format else {
+        for y in 0..image.height {
+            match (image.depth == 8, depth == 8) {
+                (true, true) => {
+                    let y_row = image.row_exact(Plane::Y, y)?;
+                    rgba_pixel_buffer.extend_from_slice(y_row);
+                }
+                (false, false) => {
+                    let y_row = image.row16_exact(Plane::Y, y)?;
+                    for pixel in y_row {
+                        let pixel16 = scale_to_16bit(*pixel, image.max_channel());
+                        rgba_pixel_buffer.extend_from_slice(&pixel16.to_be_bytes());
                     }
-                    (true, false) => {
-                        let y_row = image.row_exact(Plane::Y, y)?;
-                        for pixel in y_row {
-                            let pixel16 = scale_to_16bit(*pixel as u16, image.max_channel());
-                            rgba_pixel_buffer.extend_from_slice(&pixel16.to_be_bytes());
-                        }
+                }
+                (true, false) => {
+                    let y_row = image.row_exact(Plane::Y, y)?;
+                    for pixel in y_row {
+                        let pixel16 = scale_to_16bit(*pixel as u16, image.max_channel());
+                        rgba_pixel_buffer.extend_from_slice(&pixel16.to_be_bytes());
                     }
-                    (false, true) => {
-                        let y_row = image.row16_exact(Plane::Y, y)?;
-                        for pixel in y_row {
-                            rgba_pixel_buffer.push(scale_to_8bit(*pixel, image.max_channel()));
-                        }
+                }
+                (false, true) => {
+                    let y_row = image.row16_exact(Plane::Y, y)?;
+                    for pixel in y_row {
+                        rgba_pixel_buffer.push(scale_to_8bit(*pixel, image.max_channel()));
                     }
                 }
             }
-            &rgba_pixel_buffer[..]
-        } else if depth == 8 {
-            let rgba_pixels = rgb.pixels.as_ref().unwrap();
-            rgba_pixels.slice(0, rgba_pixels.size() as u32)?
-        } else {
-            let rgba_pixels = rgb.pixels.as_ref().unwrap();
-            let rgba_slice16 = rgba_pixels.slice16(0, rgba_pixels.size() as u32).unwrap();
-            for pixel in rgba_slice16 {
-                rgba_pixel_buffer.extend_from_slice(&pixel.to_be_bytes());
+        }
+        return Ok((png_color_type, rgba_pixel_buffer));
+    };
+    let mut rgb = rgb::Image::create_from_yuv(image);
+    rgb.depth = depth;
+    rgb.format = rgb_format;
+    rgb.allocate()?;
+    rgb.convert_from_yuv(image)?;
+    let rgba_pixels = rgb.pixels.as_ref().unwrap();
+    if depth == 8 {
+        rgba_pixel_buffer.extend_from_slice(rgba_pixels.slice(0, rgba_pixels.size() as u32)?);
+    } else {
+        for pixel in rgba_pixels.slice16(0, rgba_pixels.size() as u32)? {
+            rgba_pixel_buffer.extend_from_slice(&pixel.to_be_bytes());
+        }
+    }
+    Ok((png_color_type, rgba_pixel_buffer))
+}
+
+// Creates a PNG encoder for image with the given color type and depth, carrying its metadata.
+fn png_encoder<W: Write>(
+    w: W,
+    image: &Image,
+    png_color_type: png::ColorType,
+    depth: u8,
+) -> AvifResult<png::Encoder<'_, W>> {
+    let mut encoder =
+        png::Encoder::with_info(w, png_info(image)).map_err(AvifError::map_unknown_error)?;
+    encoder.set_color(png_color_type);
+    encoder.set_depth(if depth == 8 { png::BitDepth::Eight } else { png::BitDepth::Sixteen });
+    if !image.xmp.is_empty() {
+        if let Ok(text) = String::from_utf8(image.xmp.clone()) {
+            if encoder
+                .add_itxt_chunk("XML:com.adobe.xmp".to_string(), text)
+                .is_err()
+            {
+                eprintln!("Warning: Ignoring XMP data");
             }
-            &rgba_pixel_buffer[..]
-        };
+        } else {
+            eprintln!("Warning: Ignoring XMP data because it is not a valid UTF-8 string");
+        }
+    }
+    Ok(encoder)
+}
+
+fn default_depth(depth: Option<u8>, image: &Image) -> u8 {
+    depth.unwrap_or(if image.depth == 8 { 8 } else { 16 })
+}
+
+impl Writer for PngWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let depth = default_depth(self.depth, image);
+        let (png_color_type, samples) = png_samples(image, depth)?;
+        let encoder = png_encoder(file, image, png_color_type, depth)?;
+        let mut writer = encoder
+            .write_header()
+            .map_err(AvifError::map_unknown_error)?;
+        write_hdr_chunks(&mut writer, image)?;
         writer
-            .write_image_data(rgba_slice)
+            .write_image_data(&samples)
             .map_err(AvifError::map_unknown_error)?;
         writer.finish().map_err(AvifError::map_unknown_error)?;
         Ok(())
     }
 }
+
+// Returns the APNG frame delay as a fraction of seconds.
+fn apng_frame_delay(timing: &ImageTiming) -> (u16, u16) {
+    if let (Ok(numerator), Ok(denominator)) = (
+        u16::try_from(timing.duration_in_timescales),
+        u16::try_from(timing.timescale),
+    ) {
+        if denominator != 0 {
+            return (numerator, denominator);
+        }
+    }
+    // Fall back to milliseconds when the timescale does not fit.
+    let milliseconds = (timing.duration * 1000.0).round();
+    (milliseconds.clamp(0.0, u16::MAX as f64) as u16, 1000)
+}
+
+#[derive(Default)]
+pub struct ApngWriter {
+    pub depth: Option<u8>,
+    frame_count: u32,
+    repetition_count: RepetitionCount,
+    writer: Option<png::Writer<File>>,
+    frame_depth: u8,
+}
+
+impl ApngWriter {
+    pub fn create(depth: Option<u8>, frame_count: u32, repetition_count: RepetitionCount) -> Self {
+        Self {
+            depth,
+            frame_count,
+            repetition_count,
+            ..Default::default()
+        }
+    }
+}
+
+impl AnimationWriter for ApngWriter {
+    fn write_animation_frame(
+        &mut self,
+        file: &mut File,
+        image: &Image,
+        timing: &ImageTiming,
+    ) -> AvifResult<()> {
+        if self.writer.is_none() {
+            // The header and the metadata are taken from the first frame.
+            self.frame_depth = default_depth(self.depth, image);
+            let png_color_type = png_samples(image, self.frame_depth)?.0;
+            let file = file.try_clone().map_err(AvifError::map_unknown_error)?;
+            let mut encoder = png_encoder(file, image, png_color_type, self.frame_depth)?;
+            // num_plays is the number of times the animation is played, 0 meaning infinitely.
+            let num_plays = match self.repetition_count {
+                RepetitionCount::Finite(count) => count.saturating_add(1),
+                RepetitionCount::Infinite | RepetitionCount::Unknown => 0,
+            };
+            encoder
+                .set_animated(self.frame_count, num_plays)
+                .map_err(AvifError::map_unknown_error)?;
+            let mut writer = encoder
+                .write_header()
+                .map_err(AvifError::map_unknown_error)?;
+            write_hdr_chunks(&mut writer, image)?;
+            self.writer = Some(writer);
+        }
+        let (_, samples) = png_samples(image, self.frame_depth)?;
+        let writer = self.writer.unwrap_mut();
+        let (numerator, denominator) = apng_frame_delay(timing);
+        writer
+            .set_frame_delay(numerator, denominator)
+            .map_err(AvifError::map_unknown_error)?;
+        writer
+            .write_image_data(&samples)
+            .map_err(AvifError::map_unknown_error)
+    }
+
+    fn finish(&mut self) -> AvifResult<()> {
+        match self.writer.take() {
+            Some(writer) => writer.finish().map_err(AvifError::map_unknown_error),
+            None => AvifError::unknown_error("no frame was written"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(10, 1, (1, 10) ; "fits")]
+    #[test_case(1000, 33, (33, 1000) ; "milliseconds")]
+    #[test_case(90000, 3003, (33, 1000) ; "large timescale")]
+    #[test_case(2, 200000, (65535, 1000) ; "long frame")]
+    fn frame_delay(timescale: u64, duration_in_timescales: u64, expected: (u16, u16)) {
+        let timing = ImageTiming {
+            timescale,
+            duration_in_timescales,
+            duration: duration_in_timescales as f64 / timescale as f64,
+            ..Default::default()
+        };
+        assert_eq!(apng_frame_delay(&timing), expected);
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+
+use super::rgb_format;
+use super::rgb_samples;
+use super::Writer;
+
+use std::fs::File;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PnmFormat {
+    // PGM if the image is monochrome, PPM otherwise.
+    #[default]
+    Auto,
+    Pgm,
+    Ppm,
+    // Keeps the alpha channel.
+    Pam,
+}
+
+#[derive(Default)]
+pub struct PnmWriter {
+    pub depth: Option<u8>,
+    pub format: PnmFormat,
+}
+
+impl Writer for PnmWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let depth = self.depth.unwrap_or(if image.depth == 8 { 8 } else { 16 });
+        let format = match self.format {
+            PnmFormat::Auto => rgb_format(image, false),
+            PnmFormat::Pgm => rgb::Format::Gray,
+            PnmFormat::Ppm => rgb::Format::Rgb,
+            PnmFormat::Pam => rgb_format(image, true),
+        };
+        let max_value = if depth == 8 { 255 } else { 65535 };
+        let header = match format {
+            rgb::Format::Gray if self.format != PnmFormat::Pam => {
+                format!("P5\n{} {}\n{max_value}\n", image.width, image.height)
+            }
+            rgb::Format::Rgb if self.format != PnmFormat::Pam => {
+                format!("P6\n{} {}\n{max_value}\n", image.width, image.height)
+            }
+            _ => {
+                let tuple_type = match format {
+                    rgb::Format::Gray => "GRAYSCALE",
+                    rgb::Format::GrayA => "GRAYSCALE_ALPHA",
+                    rgb::Format::Rgb => "RGB",
+                    _ => "RGB_ALPHA",
+                };
+                format!(
+                    "P7\nWIDTH {}\nHEIGHT {}\nDEPTH {}\nMAXVAL {max_value}\nTUPLTYPE {tuple_type}\nENDHDR\n",
+                    image.width,
+                    image.height,
+                    format.channel_count()
+                )
+            }
+        };
+        if !image.icc.is_empty() || !image.exif.is_empty() || !image.xmp.is_empty() {
+            eprintln!("Warning: Ignoring metadata, which is not supported by PNM");
+        }
+        let mut samples = rgb_samples(image, format, depth)?;
+        if depth != 8 {
+            // 16-bit samples are big-endian.
+            for sample in samples.chunks_exact_mut(2) {
+                let value = u16::from_ne_bytes([sample[0], sample[1]]);
+                sample.copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        file.write_all(header.as_bytes())
+            .and_then(|_| file.write_all(&samples))
+            .map_err(AvifError::map_unknown_error)
+    }
+}
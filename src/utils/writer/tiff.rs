@@ -0,0 +1,41 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifResult;
+
+use super::image_crate;
+use super::rgb_format;
+use super::Writer;
+
+use image::codecs::tiff::TiffEncoder;
+use std::fs::File;
+
+#[derive(Default)]
+pub struct TiffWriter {
+    pub depth: Option<u8>,
+}
+
+impl Writer for TiffWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let depth = self.depth.unwrap_or(if image.depth == 8 { 8 } else { 16 });
+        let format = match rgb_format(image, true) {
+            // TIFF has no grayscale with alpha in the image crate.
+            rgb::Format::GrayA => rgb::Format::Rgba,
+            format => format,
+        };
+        image_crate::encode(TiffEncoder::new(file), image, format, depth)
+    }
+}
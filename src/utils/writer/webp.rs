@@ -0,0 +1,219 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::decoder::track::ImageTiming;
+use crate::image::Image;
+use crate::reformat::rgb;
+use crate::AvifError;
+use crate::AvifResult;
+use crate::RepetitionCount;
+
+use super::exif_from_tiff_header;
+use super::rgb_format;
+use super::rgb_samples;
+use super::AnimationWriter;
+use super::Writer;
+
+use image_webp::ColorType;
+use image_webp::WebPEncoder;
+use std::fs::File;
+use std::io::Write;
+
+// VP8X flags.
+const ICC_FLAG: u8 = 1 << 5;
+const ALPHA_FLAG: u8 = 1 << 4;
+const EXIF_FLAG: u8 = 1 << 3;
+const XMP_FLAG: u8 = 1 << 2;
+const ANIMATION_FLAG: u8 = 1 << 1;
+// ANMF flags.
+const NO_BLENDING_FLAG: u8 = 1 << 1;
+// Frame durations are stored with 24 bits.
+const MAX_DURATION: u64 = (1 << 24) - 1;
+
+// Writes lossless WebP images. Animations are written once all the frames are known, because the
+// RIFF header contains the size of the file.
+#[derive(Default)]
+pub struct WebpWriter {
+    repetition_count: RepetitionCount,
+    file: Option<File>,
+    header: Image,
+    has_alpha: bool,
+    // ANMF chunks.
+    frames: Vec<Vec<u8>>,
+    // Sum of the durations of the frames written so far, in milliseconds.
+    elapsed: u64,
+}
+
+impl WebpWriter {
+    pub fn create(repetition_count: RepetitionCount) -> Self {
+        Self {
+            repetition_count,
+            ..Default::default()
+        }
+    }
+}
+
+fn color_type(format: rgb::Format) -> ColorType {
+    match format {
+        rgb::Format::Gray => ColorType::L8,
+        rgb::Format::GrayA => ColorType::La8,
+        rgb::Format::Rgb => ColorType::Rgb8,
+        _ => ColorType::Rgba8,
+    }
+}
+
+fn encode<W: Write>(encoder: WebPEncoder<W>, image: &Image) -> AvifResult<()> {
+    let format = rgb_format(image, true);
+    let samples = rgb_samples(image, format, 8)?;
+    if image.depth != 8 {
+        eprintln!("Warning: WebP only supports 8-bit samples");
+    }
+    encoder
+        .encode(&samples, image.width, image.height, color_type(format))
+        .map_err(AvifError::map_unknown_error)
+}
+
+fn write_chunk(data: &mut Vec<u8>, fourcc: &[u8; 4], payload: &[u8]) {
+    data.extend_from_slice(fourcc);
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(payload);
+    // Chunks are padded to an even size.
+    if payload.len() % 2 == 1 {
+        data.push(0);
+    }
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let bytes = value.to_le_bytes();
+    [bytes[0], bytes[1], bytes[2]]
+}
+
+// Returns the VP8L bitstream of image.
+fn encode_vp8l(image: &Image) -> AvifResult<Vec<u8>> {
+    let mut data = Vec::new();
+    encode(WebPEncoder::new(&mut data), image)?;
+    // Without metadata, the file is made of the RIFF header followed by a single VP8L chunk.
+    if data.len() < 20 || &data[12..16] != b"VP8L" {
+        return AvifError::unknown_error("unexpected webp encoder output");
+    }
+    let size = u32::from_le_bytes(data[16..20].try_into().unwrap()) as usize;
+    Ok(data[20..20 + size].to_vec())
+}
+
+impl Writer for WebpWriter {
+    fn write_frame(&mut self, file: &mut File, image: &Image) -> AvifResult<()> {
+        let mut encoder = WebPEncoder::new(file);
+        if !image.icc.is_empty() {
+            encoder.set_icc_profile(image.icc.clone());
+        }
+        if let Some(exif) = exif_from_tiff_header(image) {
+            encoder.set_exif_metadata(exif.to_vec());
+        } else if !image.exif.is_empty() {
+            eprintln!("Warning: Ignoring Exif data because it has no TIFF header");
+        }
+        if !image.xmp.is_empty() {
+            encoder.set_xmp_metadata(image.xmp.clone());
+        }
+        encode(encoder, image)
+    }
+}
+
+impl AnimationWriter for WebpWriter {
+    fn write_animation_frame(
+        &mut self,
+        file: &mut File,
+        image: &Image,
+        timing: &ImageTiming,
+    ) -> AvifResult<()> {
+        if self.file.is_none() {
+            self.file = Some(file.try_clone().map_err(AvifError::map_unknown_error)?);
+            // The canvas size and the metadata are taken from the first frame.
+            self.header = image.shallow_clone();
+        }
+        if image.width != self.header.width || image.height != self.header.height {
+            return AvifError::unknown_error("webp frames must have the same dimensions");
+        }
+        self.has_alpha |= image.alpha_present;
+        // Round the end time of the frame rather than its duration so that the rounding errors
+        // do not accumulate.
+        let end = ((timing.pts + timing.duration) * 1000.0).round().max(0.0) as u64;
+        let duration = end.saturating_sub(self.elapsed).min(MAX_DURATION);
+        self.elapsed += duration;
+
+        let mut anmf = Vec::new();
+        anmf.extend_from_slice(&u24(0)); // X offset divided by 2.
+        anmf.extend_from_slice(&u24(0)); // Y offset divided by 2.
+        anmf.extend_from_slice(&u24(image.width - 1));
+        anmf.extend_from_slice(&u24(image.height - 1));
+        anmf.extend_from_slice(&u24(duration as u32));
+        // Each frame covers the whole canvas and replaces the previous one.
+        anmf.push(NO_BLENDING_FLAG);
+        write_chunk(&mut anmf, b"VP8L", &encode_vp8l(image)?);
+        self.frames.push(anmf);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> AvifResult<()> {
+        let Some(mut file) = self.file.take() else {
+            return AvifError::unknown_error("no frame was written");
+        };
+        let exif = exif_from_tiff_header(&self.header);
+        let mut flags = ANIMATION_FLAG;
+        if !self.header.icc.is_empty() {
+            flags |= ICC_FLAG;
+        }
+        if self.has_alpha {
+            flags |= ALPHA_FLAG;
+        }
+        if exif.is_some() {
+            flags |= EXIF_FLAG;
+        }
+        if !self.header.xmp.is_empty() {
+            flags |= XMP_FLAG;
+        }
+        let mut vp8x = vec![flags, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(self.header.width - 1));
+        vp8x.extend_from_slice(&u24(self.header.height - 1));
+        // The loop count is the number of times the animation is played, 0 meaning infinitely.
+        let loop_count = match self.repetition_count {
+            RepetitionCount::Finite(count) if count < u16::MAX as u32 => count as u16 + 1,
+            _ => 0,
+        };
+        // Transparent background color, in blue, green, red, alpha order.
+        let mut anim = vec![0, 0, 0, 0];
+        anim.extend_from_slice(&loop_count.to_le_bytes());
+
+        let mut chunks = Vec::new();
+        write_chunk(&mut chunks, b"VP8X", &vp8x);
+        if !self.header.icc.is_empty() {
+            write_chunk(&mut chunks, b"ICCP", &self.header.icc);
+        }
+        write_chunk(&mut chunks, b"ANIM", &anim);
+        for anmf in &self.frames {
+            write_chunk(&mut chunks, b"ANMF", anmf);
+        }
+        if let Some(exif) = exif {
+            write_chunk(&mut chunks, b"EXIF", exif);
+        }
+        if !self.header.xmp.is_empty() {
+            write_chunk(&mut chunks, b"XMP ", &self.header.xmp);
+        }
+        let riff_size = u32::try_from(chunks.len() + 4).map_err(AvifError::map_unknown_error)?;
+        let mut write = |data: &[u8]| file.write_all(data).map_err(AvifError::map_unknown_error);
+        write(b"RIFF")?;
+        write(&riff_size.to_le_bytes())?;
+        write(b"WEBP")?;
+        write(&chunks)
+    }
+}
@@ -42,7 +42,7 @@ impl Y4MWriter {
         self.write_alpha = false;
 
         if image.alpha_present && (image.depth != 8 || image.yuv_format != PixelFormat::Yuv444) {
-            println!("WARNING: writing alpha is currently only supported in 8bpc YUV444, ignoring alpha channel");
+            eprintln!("WARNING: writing alpha is currently only supported in 8bpc YUV444, ignoring alpha channel");
         }
 
         let y4m_format = match image.depth {
@@ -14,5 +14,7 @@
  * limitations under the License.
  */
 
+#include <aom/aom_decoder.h>
 #include <aom/aom_encoder.h>
 #include <aom/aomcx.h>
+#include <aom/aomdx.h>
@@ -0,0 +1,321 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crabby_avif::codecs::custom::*;
+use crabby_avif::image::*;
+use crabby_avif::parser::dump::parse_item_graph;
+use crabby_avif::*;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+mod utils;
+use utils::*;
+
+// The AV1 payload of the primary item of white_1x1.avif.
+fn white_1x1_payload() -> Vec<u8> {
+    let data = std::fs::read(get_test_file("white_1x1.avif")).unwrap();
+    let graph = parse_item_graph(&data).unwrap();
+    let extent = &graph.items[0].extents[0];
+    let offset = extent.offset as usize;
+    data[offset..offset + extent.size].to_vec()
+}
+
+#[derive(Default)]
+struct MockState {
+    config: Option<CustomDecoderConfig>,
+    payloads: Vec<Vec<u8>>,
+    #[cfg(feature = "encoder")]
+    encoded_images: Vec<(u32, u32, Category)>,
+}
+
+// Decodes every frame into a mid-gray image and encodes every image into the AV1 payload of
+// white_1x1.avif.
+#[derive(Default)]
+struct MockFactory {
+    can_decode: bool,
+    #[cfg(feature = "encoder")]
+    can_encode: bool,
+    state: Arc<Mutex<MockState>>,
+}
+
+struct MockDecoder {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[cfg(feature = "encoder")]
+struct MockEncoder {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl CodecFactory for MockFactory {
+    fn version(&self) -> String {
+        "mock".into()
+    }
+
+    fn create_decoder(&self) -> Option<Box<dyn CustomDecoder>> {
+        if !self.can_decode {
+            return None;
+        }
+        Some(Box::new(MockDecoder {
+            state: self.state.clone(),
+        }))
+    }
+
+    #[cfg(feature = "encoder")]
+    fn create_encoder(&self) -> Option<Box<dyn CustomEncoder>> {
+        if !self.can_encode {
+            return None;
+        }
+        Some(Box::new(MockEncoder {
+            state: self.state.clone(),
+        }))
+    }
+}
+
+impl CustomDecoder for MockDecoder {
+    fn initialize(&mut self, config: &CustomDecoderConfig) -> AvifResult<()> {
+        self.state.lock().unwrap().config = Some(config.clone());
+        Ok(())
+    }
+
+    fn decode(
+        &mut self,
+        av1_payload: &[u8],
+        _spatial_id: u8,
+        _category: Category,
+    ) -> AvifResult<Image> {
+        let mut state = self.state.lock().unwrap();
+        state.payloads.push(av1_payload.to_vec());
+        let config = state.config.as_ref().unwrap();
+        let mut image = Image {
+            width: config.width,
+            height: config.height,
+            depth: 8,
+            yuv_format: PixelFormat::Yuv420,
+            ..Default::default()
+        };
+        image.allocate_planes(Category::Color)?;
+        for plane in YUV_PLANES {
+            for y in 0..image.height(plane) as u32 {
+                image.row_exact_mut(plane, y)?.fill(128);
+            }
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(feature = "encoder")]
+impl CustomEncoder for MockEncoder {
+    fn encode_image(
+        &mut self,
+        image: &Image,
+        category: Category,
+        _config: &CustomEncoderConfig,
+        output_samples: &mut Vec<encoder::Sample>,
+    ) -> AvifResult<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .encoded_images
+            .push((image.width, image.height, category));
+        output_samples.push(encoder::Sample {
+            data: white_1x1_payload(),
+            sync: true,
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self, _output_samples: &mut Vec<encoder::Sample>) -> AvifResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn decode_with_custom_codec() -> AvifResult<()> {
+    let factory = Arc::new(MockFactory {
+        can_decode: true,
+        ..Default::default()
+    });
+    let state = factory.state.clone();
+    let mut decoder = get_decoder("white_1x1.avif");
+    decoder.settings.codec_choice = CodecChoice::Custom(factory.clone());
+    decoder.parse()?;
+    decoder.next_image()?;
+
+    let image = decoder.image().unwrap();
+    assert_eq!((image.width, image.height), (1, 1));
+    for plane in YUV_PLANES {
+        assert_eq!(image.row(plane, 0)?[0], 128);
+    }
+    let state = state.lock().unwrap();
+    let config = state.config.as_ref().unwrap();
+    assert_eq!((config.width, config.height), (1, 1));
+    assert_eq!(config.category, Category::Color);
+    // av1C starts with the marker bit and version 1.
+    assert_eq!(config.av1_codec_configuration[0], 0x81);
+    assert_eq!(state.payloads, [white_1x1_payload()]);
+    assert_eq!(decoder.settings.codec_choice, CodecChoice::Custom(factory));
+    Ok(())
+}
+
+// Decodes the given file with the mock decoder, so that no AV1 decoder is needed.
+fn decode_with_mock(
+    filename: &str,
+    strictness: decoder::Strictness,
+) -> AvifResult<decoder::Decoder> {
+    let mut decoder = get_decoder(filename);
+    decoder.settings.codec_choice = CodecChoice::Custom(Arc::new(MockFactory {
+        can_decode: true,
+        ..Default::default()
+    }));
+    decoder.settings.strictness = strictness;
+    decoder.parse()?;
+    decoder.next_image()?;
+    Ok(decoder)
+}
+
+#[test]
+fn decode_hdr_metadata_obus() -> AvifResult<()> {
+    // The mdcv property lists the primaries in red, green, blue order, which is not the
+    // recommended order but is equivalent to the HDR_MDCV metadata OBU.
+    let decoder = decode_with_mock(
+        "white_1x1_hdr_metadata.avif",
+        decoder::Strictness::SpecificInclude(vec![decoder::StrictnessFlag::HdrMetadataConsistent]),
+    )?;
+    let image = decoder.image().unwrap();
+    let mdcv = image.mdcv.unwrap();
+    assert_eq!(mdcv.display_primaries_x, [35400, 8500, 6550]);
+    assert_eq!(image.av1_metadata.len(), 2);
+    assert_eq!(
+        image.av1_metadata[0],
+        Av1Metadata::HdrCll(ContentLightLevelInformation {
+            max_cll: 1000,
+            max_pall: 400
+        })
+    );
+    match &image.av1_metadata[1] {
+        Av1Metadata::HdrMdcv(obu_mdcv) => {
+            assert_eq!(obu_mdcv.display_primaries_x, [8500, 6550, 35400]);
+            assert!(obu_mdcv.is_equivalent(&mdcv));
+        }
+        other => panic!("unexpected metadata {other:?}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn decode_malformed_metadata_obu() -> AvifResult<()> {
+    // The first HDR_CLL metadata OBU is truncated.
+    let filename = "white_1x1_malformed_metadata.avif";
+    assert!(matches!(
+        decode_with_mock(
+            filename,
+            decoder::Strictness::SpecificInclude(vec![
+                decoder::StrictnessFlag::HdrMetadataConsistent
+            ])
+        ),
+        Err(AvifError::BmffParseFailed(_))
+    ));
+    // Metadata OBUs are informative, so they are parsed leniently by default.
+    for strictness in [decoder::Strictness::All, decoder::Strictness::None] {
+        let decoder = decode_with_mock(filename, strictness)?;
+        let image = decoder.image().unwrap();
+        assert_eq!(
+            image.av1_metadata,
+            [Av1Metadata::HdrCll(ContentLightLevelInformation {
+                max_cll: 1000,
+                max_pall: 400
+            })]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn custom_codec_without_decoder() {
+    let mut decoder = get_decoder("white_1x1.avif");
+    decoder.settings.codec_choice = CodecChoice::Custom(Arc::new(MockFactory::default()));
+    assert!(decoder.parse().is_ok());
+    assert_eq!(decoder.next_image(), Err(AvifError::NoCodecAvailable));
+}
+
+#[test]
+fn custom_codec_equality() {
+    let factory: Arc<dyn CodecFactory> = Arc::new(MockFactory::default());
+    assert_eq!(
+        CodecChoice::Custom(factory.clone()),
+        CodecChoice::Custom(factory)
+    );
+    assert_ne!(
+        CodecChoice::Custom(Arc::new(MockFactory::default())),
+        CodecChoice::Custom(Arc::new(MockFactory::default()))
+    );
+    assert_ne!(
+        CodecChoice::Custom(Arc::new(MockFactory::default())),
+        CodecChoice::Auto
+    );
+}
+
+#[test]
+#[cfg(feature = "encoder")]
+fn encode_with_custom_codec() -> AvifResult<()> {
+    let factory = Arc::new(MockFactory {
+        can_decode: true,
+        can_encode: true,
+        ..Default::default()
+    });
+    let image = generate_gradient_image(1, 1, 8, PixelFormat::Yuv420, YuvRange::Full, false)?;
+    let settings = encoder::Settings {
+        codec_choice: CodecChoice::Custom(factory.clone()),
+        ..Default::default()
+    };
+    let mut encoder = encoder::Encoder::create_with_settings(&settings)?;
+    encoder.add_image(&image)?;
+    let edata = encoder.finish()?;
+    assert_eq!(
+        factory.state.lock().unwrap().encoded_images,
+        [(1, 1, Category::Color)]
+    );
+
+    // The av1C property is harvested from the sequence header of the encoded payload.
+    let graph = parse_item_graph(&edata)?;
+    let color = &graph.items[0];
+    assert_eq!(color.item_type, "av01");
+    assert!(color.properties.iter().any(|x| x.box_type == "av1C"));
+
+    let mut decoder = decoder::Decoder::default();
+    decoder.settings.codec_choice = CodecChoice::Custom(factory.clone());
+    decoder.set_io_vec(edata);
+    decoder.parse()?;
+    decoder.next_image()?;
+    assert_eq!(
+        factory.state.lock().unwrap().payloads,
+        [white_1x1_payload()]
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encoder")]
+fn custom_codec_without_encoder() -> AvifResult<()> {
+    let image = generate_gradient_image(1, 1, 8, PixelFormat::Yuv420, YuvRange::Full, false)?;
+    let settings = encoder::Settings {
+        codec_choice: CodecChoice::Custom(Arc::new(MockFactory::default())),
+        ..Default::default()
+    };
+    let mut encoder = encoder::Encoder::create_with_settings(&settings)?;
+    assert_eq!(encoder.add_image(&image), Err(AvifError::NoCodecAvailable));
+    Ok(())
+}
@@ -278,6 +278,118 @@ fn idat(filename: &str) {
     assert_eq!(res, Ok(()));
 }
 
+// Decodes |filename| with |codec_choice| and checks the output against the properties
+// signalled in the container.
+#[cfg(any(feature = "aom", feature = "libgav1"))]
+fn decode_with_codec(filename: &str, codec_choice: CodecChoice) -> decoder::Decoder {
+    let mut decoder = get_decoder(filename);
+    decoder.settings.codec_choice = codec_choice;
+    decoder.settings.allow_progressive = true;
+    assert!(decoder.parse().is_ok());
+    let image = decoder.image().expect("image was none");
+    let expected = (
+        image.width,
+        image.height,
+        image.depth,
+        image.yuv_format,
+        image.alpha_present,
+    );
+    assert_eq!(decoder.next_image(), Ok(()));
+    let image = decoder.image().expect("image was none");
+    assert_eq!(
+        (
+            image.width,
+            image.height,
+            image.depth,
+            image.yuv_format,
+            image.alpha_present
+        ),
+        expected
+    );
+    assert!(image.has_plane(Plane::Y));
+    assert_eq!(image.has_plane(Plane::A), image.alpha_present);
+    decoder
+}
+
+// Decodes |filename| with |codec_choice| and compares the output with dav1d.
+#[cfg(all(feature = "dav1d", any(feature = "aom", feature = "libgav1")))]
+fn compare_with_dav1d(filename: &str, codec_choice: CodecChoice) {
+    let decoder = decode_with_codec(filename, codec_choice);
+    let image = decoder.image().expect("image was none");
+    // AV1 decoding is bit exact, so all the decoders must return the same samples.
+    let mut reference_decoder = get_decoder(filename);
+    reference_decoder.settings.codec_choice = CodecChoice::Dav1d;
+    reference_decoder.settings.allow_progressive = true;
+    assert!(reference_decoder.parse().is_ok());
+    assert_eq!(reference_decoder.next_image(), Ok(()));
+    let reference_image = reference_decoder.image().expect("image was none");
+    assert!(are_images_equal(image, reference_image).unwrap());
+}
+
+#[cfg(feature = "aom")]
+#[test_case("white_1x1.avif")]
+#[test_case("alpha.avif")]
+#[test_case("paris_10bpc.avif")]
+#[test_case("sofa_grid1x5_420.avif")]
+#[test_case("color_grid_alpha_nogrid.avif")]
+#[test_case("progressive/progressive_layered_grid.avif")]
+fn aom_decoder(filename: &str) {
+    decode_with_codec(filename, CodecChoice::Aom);
+}
+
+#[cfg(feature = "aom")]
+#[test_matrix(0usize..4)]
+fn aom_decoder_overlay(index: usize) {
+    let info = &EXPECTED_OVERLAY_IMAGE_INFOS[index];
+    let mut decoder = get_decoder(info.filename);
+    decoder.settings.codec_choice = CodecChoice::Aom;
+    verify_overlay(info, &mut decoder);
+}
+
+#[cfg(all(feature = "aom", feature = "dav1d"))]
+#[test_case("white_1x1.avif")]
+#[test_case("alpha.avif")]
+#[test_case("paris_10bpc.avif")]
+#[test_case("sofa_grid1x5_420.avif")]
+#[test_case("color_grid_alpha_nogrid.avif")]
+#[test_case("progressive/progressive_layered_grid.avif")]
+fn aom_decoder_matches_dav1d(filename: &str) {
+    compare_with_dav1d(filename, CodecChoice::Aom);
+}
+
+#[cfg(feature = "libgav1")]
+#[test_case("white_1x1.avif")]
+#[test_case("alpha.avif")]
+#[test_case("paris_10bpc.avif")]
+#[test_case("sofa_grid1x5_420.avif")]
+#[test_case("color_grid_alpha_nogrid.avif")]
+#[test_case("progressive/progressive_layered_grid.avif")]
+#[test_case("progressive/tiger_3layer_3res.avif")]
+fn libgav1_decoder(filename: &str) {
+    decode_with_codec(filename, CodecChoice::Libgav1);
+}
+
+#[cfg(feature = "libgav1")]
+#[test_matrix(0usize..4)]
+fn libgav1_decoder_overlay(index: usize) {
+    let info = &EXPECTED_OVERLAY_IMAGE_INFOS[index];
+    let mut decoder = get_decoder(info.filename);
+    decoder.settings.codec_choice = CodecChoice::Libgav1;
+    verify_overlay(info, &mut decoder);
+}
+
+#[cfg(all(feature = "libgav1", feature = "dav1d"))]
+#[test_case("white_1x1.avif")]
+#[test_case("alpha.avif")]
+#[test_case("paris_10bpc.avif")]
+#[test_case("sofa_grid1x5_420.avif")]
+#[test_case("color_grid_alpha_nogrid.avif")]
+#[test_case("progressive/progressive_layered_grid.avif")]
+#[test_case("progressive/tiger_3layer_3res.avif")]
+fn libgav1_decoder_matches_dav1d(filename: &str) {
+    compare_with_dav1d(filename, CodecChoice::Libgav1);
+}
+
 // From avifprogressivetest.cc
 #[test_case("progressive_dimension_change.avif", 2, 256, 256; "progressive_dimension_change")]
 #[test_case("progressive_layered_grid.avif", 2, 512, 256; "progressive_layered_grid")]
@@ -1459,6 +1571,10 @@ macro_rules! pixel_eq {
 fn overlay(index: usize) {
     let info = &EXPECTED_OVERLAY_IMAGE_INFOS[index];
     let mut decoder = get_decoder(info.filename);
+    verify_overlay(info, &mut decoder);
+}
+
+fn verify_overlay(info: &ExpectedOverlayImageInfo, decoder: &mut decoder::Decoder) {
     decoder.settings.strictness = decoder::Strictness::None;
     let res = decoder.parse();
     assert!(res.is_ok());
@@ -0,0 +1,177 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crabby_avif::parser::dump::*;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+fn read_test_file(filename: &str) -> Vec<u8> {
+    std::fs::read(get_test_file(filename)).expect("could not read test file")
+}
+
+#[test]
+fn box_tree() {
+    let data = read_test_file("white_1x1.avif");
+    let root = parse_box_tree(&data);
+    assert_eq!(root.size, data.len() as u64);
+    assert!(root.fields.is_empty());
+    let box_types: Vec<&str> = root.children.iter().map(|x| x.box_type.as_str()).collect();
+    assert_eq!(box_types, ["ftyp", "meta", "mdat"]);
+    assert_eq!(
+        root.children.iter().map(|x| x.size).sum::<u64>(),
+        data.len() as u64
+    );
+
+    let ftyp = root.find("ftyp").unwrap();
+    assert_eq!(ftyp.offset, 0);
+    assert_eq!(ftyp.header_size, 8);
+    assert_eq!(ftyp.version_and_flags, None);
+    assert_eq!(ftyp.field("major_brand"), Some("avif"));
+
+    let meta = root.find("meta").unwrap();
+    assert_eq!(meta.version_and_flags, Some((0, 0)));
+    assert_eq!(meta.children[0].box_type, "hdlr");
+    assert_eq!(meta.children[0].field("handler_type"), Some("pict"));
+    let infe = root.find("infe").unwrap();
+    assert_eq!(infe.version_and_flags, Some((2, 0)));
+    assert_eq!(infe.field("item_type"), Some("av01"));
+    assert_eq!(root.find("ispe").unwrap().field("value"), Some("1x1"));
+    assert_eq!(
+        root.find("ipma").unwrap().field("item 1"),
+        Some("1, 2, 3 (essential), 4")
+    );
+}
+
+#[test]
+fn box_tree_track() {
+    let root = parse_box_tree(&read_test_file("colors-animated-8bpc.avif"));
+    let tkhd = root.find("tkhd").unwrap();
+    assert_eq!(tkhd.field("track_id"), Some("1"));
+    assert_eq!(tkhd.field("width"), Some("150"));
+    assert_eq!(root.find("mdhd").unwrap().field("timescale"), Some("30"));
+    assert_eq!(root.find("stts").unwrap().field("sample_count"), Some("5"));
+    let sample_entry = &root.find("stsd").unwrap().children[0];
+    assert_eq!(sample_entry.box_type, "av01");
+    assert_eq!(sample_entry.children[0].box_type, "av1C");
+}
+
+#[test]
+fn box_tree_truncated() {
+    let data = read_test_file("white_1x1.avif");
+    let root = parse_box_tree(&data[..150]);
+    let meta = root.find("meta").unwrap();
+    assert_eq!(meta.size, 242);
+    assert!(meta.field("error").is_some());
+    // The boxes that fit are still decoded.
+    assert_eq!(root.find("pitm").unwrap().field("item_id"), Some("1"));
+    assert!(parse_item_graph(&data[..150]).is_err());
+}
+
+#[test]
+fn item_graph() -> AvifResult<()> {
+    let graph = parse_item_graph(&read_test_file("seine_sdr_gainmap_srgb.avif"))?;
+    assert!(!graph.from_mini);
+    assert_eq!(graph.primary_item_id, 1);
+    let item_types: Vec<&str> = graph.items.iter().map(|x| x.item_type.as_str()).collect();
+    assert_eq!(item_types, ["av01", "tmap", "av01", "Exif", "mime"]);
+
+    let color = &graph.items[0];
+    let properties: Vec<(&str, bool)> = color
+        .properties
+        .iter()
+        .map(|x| (x.box_type.as_str(), x.essential))
+        .collect();
+    assert_eq!(
+        properties,
+        [
+            ("ispe", false),
+            ("pixi", false),
+            ("av1C", true),
+            ("colr", false)
+        ]
+    );
+    assert_eq!(color.extents.len(), 1);
+
+    let tmap = &graph.items[1];
+    let references: Vec<(&str, u32)> = tmap
+        .references
+        .iter()
+        .map(|x| (x.reference_type.as_str(), x.to_item_id))
+        .collect();
+    assert_eq!(references, [("dimg", 1), ("dimg", 3)]);
+
+    assert_eq!(graph.entity_groups.len(), 1);
+    assert_eq!(graph.entity_groups[0].grouping_type, "altr");
+    assert_eq!(graph.entity_groups[0].entity_ids, [2, 1]);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encoder")]
+fn item_graph_mini() -> AvifResult<()> {
+    use crabby_avif::image::*;
+
+    if !HAS_ENCODER {
+        return Ok(());
+    }
+    let image = generate_gradient_image(16, 16, 8, PixelFormat::Yuv420, YuvRange::Full, true)?;
+    let settings = encoder::Settings {
+        speed: Some(10),
+        header_format: HeaderFormat::Mini,
+        ..Default::default()
+    };
+    let mut encoder = encoder::Encoder::create_with_settings(&settings)?;
+    encoder.add_image(&image)?;
+    let edata = encoder.finish()?;
+
+    let root = parse_box_tree(&edata);
+    assert!(root.find("mini").is_some());
+    assert!(root.find("meta").is_none());
+    let graph = parse_item_graph(&edata)?;
+    assert!(graph.from_mini);
+    let color = graph
+        .items
+        .iter()
+        .find(|x| x.id == graph.primary_item_id)
+        .unwrap();
+    assert!(color.properties.iter().any(|x| x.box_type == "av1C"));
+    assert!(graph.items.iter().any(|x| x
+        .references
+        .iter()
+        .any(|r| r.reference_type == "auxl" && r.to_item_id == color.id)));
+    Ok(())
+}
+
+#[test]
+fn box_tree_deeply_nested() {
+    // Empty 'moov' boxes nested in each other, which would overflow the stack without a depth
+    // limit.
+    const NESTING: usize = 100_000;
+    let mut data = Vec::with_capacity(NESTING * 8);
+    for i in 0..NESTING {
+        data.extend_from_slice(&(((NESTING - i) * 8) as u32).to_be_bytes());
+        data.extend_from_slice(b"moov");
+    }
+    let root = parse_box_tree(&data);
+    let mut node = &root.children[0];
+    let mut depth = 1;
+    while let Some(child) = node.children.first() {
+        node = child;
+        depth += 1;
+    }
+    assert_eq!(depth, 32);
+    assert!(node.field("error").unwrap().contains("nested"));
+}
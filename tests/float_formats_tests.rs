@@ -0,0 +1,204 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// PFM and OpenEXR readers and writers.
+
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::exr::ExrReader;
+use crabby_avif::utils::reader::pfm::PfmReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::exr::ExrPixelType;
+use crabby_avif::utils::writer::exr::ExrWriter;
+use crabby_avif::utils::writer::pfm::PfmWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use std::fs::File;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+fn create_image(
+    yuv_format: PixelFormat,
+    alpha: bool,
+    transfer_characteristics: TransferCharacteristics,
+) -> AvifResult<Image> {
+    let mut image = generate_gradient_image(16, 8, 10, yuv_format, YuvRange::Full, alpha)?;
+    image.matrix_coefficients = MatrixCoefficients::Identity;
+    image.transfer_characteristics = transfer_characteristics;
+    Ok(image)
+}
+
+fn write(writer: &mut dyn Writer, image: &Image) -> String {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    writer.write_frame(&mut file, image).unwrap();
+    filename
+}
+
+fn read(mut reader: impl Reader, image: &Image) -> AvifResult<Image> {
+    let config = Config {
+        yuv_format: Some(image.yuv_format),
+        depth: Some(image.depth),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    };
+    let (read_image, duration) = reader.read_frame(&config)?;
+    assert_eq!(duration, 0);
+    assert!(!reader.has_more_frames());
+    assert_eq!(
+        read_image.transfer_characteristics,
+        image.transfer_characteristics
+    );
+    Ok(read_image)
+}
+
+// The samples go through floating point linear light, so they may be off by one.
+fn assert_near_lossless(read_image: &Image, image: &Image) {
+    for plane in ALL_PLANES {
+        assert_eq!(read_image.has_plane(plane), image.has_plane(plane));
+        if image.has_plane(plane) {
+            for (read_value, value) in plane_values(read_image, plane)
+                .iter()
+                .zip(plane_values(image, plane))
+            {
+                assert!(read_value.abs_diff(value) <= 1, "{read_value} vs {value}");
+            }
+        }
+    }
+}
+
+#[test_case(PixelFormat::Yuv444, TransferCharacteristics::Pq)]
+#[test_case(PixelFormat::Yuv444, TransferCharacteristics::Hlg)]
+#[test_case(PixelFormat::Yuv400, TransferCharacteristics::Srgb)]
+fn pfm(
+    yuv_format: PixelFormat,
+    transfer_characteristics: TransferCharacteristics,
+) -> AvifResult<()> {
+    let image = create_image(yuv_format, false, transfer_characteristics)?;
+    let filename = write(&mut PfmWriter::default(), &image);
+    let magic = if yuv_format == PixelFormat::Yuv400 { b"Pf" } else { b"PF" };
+    assert_eq!(&std::fs::read(&filename).unwrap()[..2], magic);
+    let mut reader = PfmReader::create(&filename)?;
+    reader.set_transfer_characteristics(transfer_characteristics);
+    assert_near_lossless(&read(reader, &image)?, &image);
+    Ok(())
+}
+
+#[test]
+fn pfm_samples() -> AvifResult<()> {
+    // Big-endian samples (positive scale) of a 2x2 image, stored from bottom to top and scaled by
+    // 0.5.
+    let filename = get_tempfile();
+    let mut data = b"PF\n2 2\n0.5\n".to_vec();
+    let samples: [f32; 12] = [
+        0.0, 0.0, 0.0, /**/ 2.0, 2.0, 2.0, // Bottom row.
+        1.0, 1.0, 0.0, /**/ 1.0, 0.0, 4.0, // Top row.
+    ];
+    for sample in samples {
+        data.extend_from_slice(&sample.to_be_bytes());
+    }
+    std::fs::write(&filename, data).unwrap();
+    let mut reader = PfmReader::create(&filename)?;
+    reader.set_transfer_characteristics(TransferCharacteristics::Linear);
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        depth: Some(12),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    };
+    let (image, _) = reader.read_frame(&config)?;
+    assert_eq!(
+        image.transfer_characteristics,
+        TransferCharacteristics::Linear
+    );
+    // With identity matrix coefficients, Y is green, U is blue and V is red.
+    assert_eq!(plane_values(&image, Plane::Y), [2048, 0, 0, 4095]);
+    assert_eq!(plane_values(&image, Plane::U), [0, 4095, 0, 4095]);
+    assert_eq!(plane_values(&image, Plane::V), [2048, 2048, 0, 4095]);
+    Ok(())
+}
+
+#[test_case(ExrPixelType::Half, PixelFormat::Yuv444, false)]
+#[test_case(ExrPixelType::Half, PixelFormat::Yuv444, true)]
+#[test_case(ExrPixelType::Float, PixelFormat::Yuv444, true)]
+#[test_case(ExrPixelType::Half, PixelFormat::Yuv400, false)]
+#[test_case(ExrPixelType::Float, PixelFormat::Yuv400, true)]
+fn exr(pixel_type: ExrPixelType, yuv_format: PixelFormat, alpha: bool) -> AvifResult<()> {
+    let image = create_image(yuv_format, alpha, TransferCharacteristics::Pq)?;
+    let filename = write(&mut ExrWriter { pixel_type }, &image);
+    let reader = ExrReader::create(&filename)?;
+    assert_near_lossless(&read(reader, &image)?, &image);
+    Ok(())
+}
+
+#[test]
+fn exr_unsupported() {
+    let filename = get_tempfile();
+    let mut data = vec![0x76, 0x2f, 0x31, 0x01];
+    // Tiled file.
+    data.extend_from_slice(&(2u32 | 1 << 9).to_le_bytes());
+    data.push(0);
+    std::fs::write(&filename, data).unwrap();
+    assert!(matches!(
+        ExrReader::create(&filename),
+        Err(AvifError::NotImplemented)
+    ));
+    std::fs::write(&filename, b"PF\n1 1\n-1.0\n").unwrap();
+    assert!(ExrReader::create(&filename).is_err());
+}
+
+#[test_case(b"PF\n4294967295 4294967295\n-1.0\n" ; "too_large")]
+#[test_case(b"PF\n16384 16384\n-1.0\n" ; "truncated")]
+fn pfm_invalid_size(header: &[u8]) {
+    let filename = get_tempfile();
+    std::fs::write(&filename, [header, &[0; 12]].concat()).unwrap();
+    assert!(PfmReader::create(&filename).is_err());
+}
+
+#[test_case(0x7fff_ffff, 0 ; "too_wide")]
+#[test_case(30000, 30000 ; "too_large")]
+#[test_case(16000, 16000 ; "truncated")]
+fn exr_invalid_data_window(x_max: i32, y_max: i32) {
+    let image = create_image(PixelFormat::Yuv444, false, TransferCharacteristics::Pq).unwrap();
+    let filename = write(
+        &mut ExrWriter {
+            pixel_type: ExrPixelType::Half,
+        },
+        &image,
+    );
+    let mut data = std::fs::read(&filename).unwrap();
+    // The value of the dataWindow attribute is x_min, y_min, x_max and y_max.
+    let attribute = b"dataWindow\0box2i\0\x10\0\0\0";
+    let offset = data
+        .windows(attribute.len())
+        .position(|window| window == attribute)
+        .unwrap()
+        + attribute.len();
+    data[offset + 8..offset + 12].copy_from_slice(&x_max.to_le_bytes());
+    data[offset + 12..offset + 16].copy_from_slice(&y_max.to_le_bytes());
+    std::fs::write(&filename, data).unwrap();
+    assert!(ExrReader::create(&filename).is_err());
+}
@@ -0,0 +1,136 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "gif")]
+
+use crabby_avif::decoder::track::ImageTiming;
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::gif::GifReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::gif::GifWriter;
+use crabby_avif::utils::writer::AnimationWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use std::fs::File;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+fn create_frame(index: u8, alpha: bool) -> AvifResult<Image> {
+    let mut image = generate_gradient_image(4, 2, 8, PixelFormat::Yuv444, YuvRange::Full, alpha)?;
+    image.matrix_coefficients = MatrixCoefficients::Identity;
+    for y in 0..image.height {
+        for pixel in image.row_exact_mut(Plane::Y, y)? {
+            *pixel = pixel.wrapping_add(index * 50);
+        }
+    }
+    Ok(image)
+}
+
+fn read_config() -> Config {
+    Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn write_still() -> AvifResult<()> {
+    let image = create_frame(0, true)?;
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    GifWriter::create(RepetitionCount::Finite(0), false).write_frame(&mut file, &image)?;
+
+    let mut reader = GifReader::create(&filename)?;
+    // There is no NETSCAPE2.0 looping extension.
+    assert_eq!(reader.repetition_count(), Some(RepetitionCount::Finite(0)));
+    let (read_image, duration) = reader.read_frame(&read_config())?;
+    assert!(!reader.has_more_frames());
+    // A zero delay is played as 100 ms.
+    assert_eq!(duration, 100);
+    // Pixels are either opaque or fully transparent.
+    let opaque: Vec<bool> = plane_values(&image, Plane::A)
+        .iter()
+        .map(|alpha| *alpha >= 128)
+        .collect();
+    let expected_alpha: Vec<u16> = opaque.iter().map(|o| if *o { 255 } else { 0 }).collect();
+    assert_eq!(plane_values(&read_image, Plane::A), expected_alpha);
+    // The image has few enough colors for the opaque pixels to be written without loss.
+    for plane in [Plane::Y, Plane::U, Plane::V] {
+        let read_values = plane_values(&read_image, plane);
+        let values = plane_values(&image, plane);
+        for i in 0..values.len() {
+            if opaque[i] {
+                assert_eq!(read_values[i], values[i]);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test_case(RepetitionCount::Finite(0), RepetitionCount::Finite(0) ; "once")]
+#[test_case(RepetitionCount::Finite(3), RepetitionCount::Finite(3) ; "finite")]
+#[test_case(RepetitionCount::Finite(100000), RepetitionCount::Finite(65535) ; "clamped")]
+#[test_case(RepetitionCount::Infinite, RepetitionCount::Infinite ; "infinite")]
+fn write_animation(
+    repetition_count: RepetitionCount,
+    expected_repetition_count: RepetitionCount,
+) -> AvifResult<()> {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    let mut writer = GifWriter::create(repetition_count, true);
+    let mut frames = Vec::new();
+    // 30 frames per second.
+    for index in 0..3 {
+        let image = create_frame(index, false)?;
+        let timing = ImageTiming {
+            timescale: 30,
+            pts: index as f64 / 30.0,
+            pts_in_timescales: index as u64,
+            duration: 1.0 / 30.0,
+            duration_in_timescales: 1,
+        };
+        writer.write_animation_frame(&mut file, &image, &timing)?;
+        frames.push(image);
+    }
+    writer.finish()?;
+
+    let mut reader = GifReader::create(&filename)?;
+    assert_eq!(reader.repetition_count(), Some(expected_repetition_count));
+    let mut durations = Vec::new();
+    for frame in &frames {
+        let (image, duration) = reader.read_frame(&read_config())?;
+        for plane in [Plane::Y, Plane::U, Plane::V] {
+            assert_eq!(plane_values(&image, plane), plane_values(frame, plane));
+        }
+        durations.push(duration);
+    }
+    assert!(!reader.has_more_frames());
+    // The delays are rounded to centiseconds without drifting.
+    assert_eq!(durations, [30, 40, 30]);
+    Ok(())
+}
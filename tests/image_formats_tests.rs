@@ -0,0 +1,173 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// TIFF, BMP and PNM readers and writers.
+#![cfg(all(feature = "tiff", feature = "bmp", feature = "pnm"))]
+
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::bmp::BmpReader;
+use crabby_avif::utils::reader::pnm::PnmReader;
+use crabby_avif::utils::reader::tiff::TiffReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::bmp::BmpWriter;
+use crabby_avif::utils::writer::pnm::PnmFormat;
+use crabby_avif::utils::writer::pnm::PnmWriter;
+use crabby_avif::utils::writer::tiff::TiffWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use ::image::ImageEncoder;
+use std::fs::File;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+fn create_image(depth: u8, yuv_format: PixelFormat, alpha: bool) -> AvifResult<Image> {
+    let mut image = generate_gradient_image(4, 2, depth, yuv_format, YuvRange::Full, alpha)?;
+    image.matrix_coefficients = MatrixCoefficients::Identity;
+    Ok(image)
+}
+
+fn write(writer: &mut dyn Writer, image: &Image) -> String {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    writer.write_frame(&mut file, image).unwrap();
+    filename
+}
+
+fn read(mut reader: impl Reader, image: &Image) -> AvifResult<Image> {
+    let config = Config {
+        yuv_format: Some(image.yuv_format),
+        depth: Some(image.depth),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    };
+    let (read_image, duration) = reader.read_frame(&config)?;
+    assert_eq!(duration, 0);
+    assert!(!reader.has_more_frames());
+    Ok(read_image)
+}
+
+fn assert_lossless(read_image: &Image, image: &Image) {
+    for plane in ALL_PLANES {
+        assert_eq!(read_image.has_plane(plane), image.has_plane(plane));
+        if image.has_plane(plane) {
+            assert_eq!(plane_values(read_image, plane), plane_values(image, plane));
+        }
+    }
+}
+
+#[test_case(8, PixelFormat::Yuv444, false)]
+#[test_case(8, PixelFormat::Yuv444, true)]
+#[test_case(10, PixelFormat::Yuv444, true)]
+#[test_case(12, PixelFormat::Yuv400, false)]
+#[test_case(10, PixelFormat::Yuv400, true)]
+fn tiff(depth: u8, yuv_format: PixelFormat, alpha: bool) -> AvifResult<()> {
+    let mut image = create_image(depth, yuv_format, alpha)?;
+    // Profiles of up to 4 bytes are stored inline in the TIFF directory and are not read back by
+    // the image crate.
+    image.icc = vec![7; 200];
+    let filename = write(&mut TiffWriter::default(), &image);
+    let read_image = read(TiffReader::create(&filename)?, &image)?;
+    assert_lossless(&read_image, &image);
+    assert_eq!(read_image.icc, image.icc);
+    Ok(())
+}
+
+#[test]
+fn tiff_float() -> AvifResult<()> {
+    let filename = get_tempfile();
+    let samples: [f32; 6] = [0.0, 0.5, 1.0, 2.0, -1.0, 0.25];
+    let bytes: Vec<u8> = samples.iter().flat_map(|x| x.to_ne_bytes()).collect();
+    ::image::codecs::tiff::TiffEncoder::new(File::create(&filename).unwrap())
+        .write_image(&bytes, 2, 1, ::image::ExtendedColorType::Rgb32F)
+        .unwrap();
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        depth: Some(16),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    };
+    let (image, _) = TiffReader::create(&filename)?.read_frame(&config)?;
+    // The samples are linear light encoded with PQ, 1.0 being 10000 cd/m2. With identity matrix
+    // coefficients, Y is green, U is blue and V is red.
+    assert_eq!(image.transfer_characteristics, TransferCharacteristics::Pq);
+    // Samples outside of [0, 1] are clamped.
+    assert_eq!(plane_values(&image, Plane::Y), [60721, 0]);
+    assert_eq!(plane_values(&image, Plane::U), [65535, 55816]);
+    assert_eq!(plane_values(&image, Plane::V), [0, 65535]);
+    Ok(())
+}
+
+#[test_case(PixelFormat::Yuv444, false)]
+#[test_case(PixelFormat::Yuv444, true)]
+#[test_case(PixelFormat::Yuv400, false)]
+fn bmp(yuv_format: PixelFormat, alpha: bool) -> AvifResult<()> {
+    let image = create_image(8, yuv_format, alpha)?;
+    let filename = write(&mut BmpWriter::default(), &image);
+    let read_image = read(BmpReader::create(&filename)?, &image)?;
+    // Monochrome images are read back as RGB.
+    if yuv_format == PixelFormat::Yuv400 {
+        assert_eq!(
+            plane_values(&read_image, Plane::Y),
+            plane_values(&image, Plane::Y)
+        );
+    } else {
+        assert_lossless(&read_image, &image);
+    }
+    Ok(())
+}
+
+#[test_case(PnmFormat::Auto, PixelFormat::Yuv444, false, b"P6")]
+#[test_case(PnmFormat::Auto, PixelFormat::Yuv400, false, b"P5")]
+#[test_case(PnmFormat::Ppm, PixelFormat::Yuv444, false, b"P6")]
+#[test_case(PnmFormat::Pgm, PixelFormat::Yuv400, false, b"P5")]
+#[test_case(PnmFormat::Pam, PixelFormat::Yuv444, true, b"P7")]
+#[test_case(PnmFormat::Pam, PixelFormat::Yuv400, true, b"P7")]
+fn pnm(format: PnmFormat, yuv_format: PixelFormat, alpha: bool, magic: &[u8; 2]) -> AvifResult<()> {
+    for depth in [8, 10] {
+        let image = create_image(depth, yuv_format, alpha)?;
+        let filename = write(
+            &mut PnmWriter {
+                depth: None,
+                format,
+            },
+            &image,
+        );
+        assert_eq!(&std::fs::read(&filename).unwrap()[..2], magic);
+        let read_image = read(PnmReader::create(&filename)?, &image)?;
+        assert_lossless(&read_image, &image);
+    }
+    Ok(())
+}
+
+#[test]
+fn pnm_drops_alpha() -> AvifResult<()> {
+    let image = create_image(8, PixelFormat::Yuv444, true)?;
+    let filename = write(&mut PnmWriter::default(), &image);
+    let read_image = read(PnmReader::create(&filename)?, &image)?;
+    assert!(!read_image.has_plane(Plane::A));
+    Ok(())
+}
@@ -0,0 +1,563 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "jpeg")]
+
+use crabby_avif::gainmap::*;
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::jpeg::JpegReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::jpeg::JpegWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::utils::*;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use ::image::codecs::jpeg::JpegEncoder;
+use ::image::ExtendedColorType;
+use std::fs::File;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+// Returns a 16x16 JPEG filled with the given color.
+fn encode_jpeg(color: &[u8]) -> Vec<u8> {
+    let color_type = if color.len() == 1 { ExtendedColorType::L8 } else { ExtendedColorType::Rgb8 };
+    let pixels: Vec<u8> = color
+        .iter()
+        .copied()
+        .cycle()
+        .take(16 * 16 * color.len())
+        .collect();
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, 100)
+        .encode(&pixels, 16, 16, color_type)
+        .expect("failed to encode jpeg");
+    data
+}
+
+// Writes a 16x16 JPEG file filled with the given color and returns its filename.
+fn write_jpeg(color: &[u8]) -> String {
+    let filename = get_tempfile();
+    std::fs::write(&filename, encode_jpeg(color)).expect("failed to write jpeg");
+    filename
+}
+
+fn read_jpeg(filename: &str, ycbcr_passthrough: bool, config: &Config) -> AvifResult<Image> {
+    let mut reader = JpegReader::create(filename)?;
+    reader.set_ycbcr_passthrough(ycbcr_passthrough);
+    let (image, _) = reader.read_frame(config)?;
+    assert!(!reader.has_more_frames());
+    Ok(image)
+}
+
+fn assert_plane_near(image: &Image, plane: Plane, expected: u16) {
+    for value in plane_values(image, plane) {
+        assert!(
+            value.abs_diff(expected) <= 1,
+            "{plane:?}: {value} vs {expected}"
+        );
+    }
+}
+
+#[test]
+fn gray() -> AvifResult<()> {
+    let filename = write_jpeg(&[100]);
+    let image = read_jpeg(&filename, false, &Config::default())?;
+    assert_eq!(image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(image.yuv_range, YuvRange::Full);
+    assert_plane_near(&image, Plane::Y, 100);
+    Ok(())
+}
+
+#[test_case(false ; "rgb_round_trip")]
+#[test_case(true ; "ycbcr_passthrough")]
+fn color(ycbcr_passthrough: bool) -> AvifResult<()> {
+    // BT.601 full range YCbCr of (200, 100, 50) is (124.2, 86.1, 182.1).
+    let filename = write_jpeg(&[200, 100, 50]);
+    let image = read_jpeg(&filename, ycbcr_passthrough, &Config::default())?;
+    assert_eq!(image.depth, 8);
+    assert_eq!(image.matrix_coefficients, MatrixCoefficients::Bt601);
+    // The encoder does not subsample chroma.
+    assert_eq!(
+        image.yuv_format,
+        if ycbcr_passthrough { PixelFormat::Yuv444 } else { PixelFormat::Yuv420 }
+    );
+    assert_plane_near(&image, Plane::Y, 124);
+    assert_plane_near(&image, Plane::U, 86);
+    assert_plane_near(&image, Plane::V, 182);
+    Ok(())
+}
+
+#[test_case(PixelFormat::Yuv444)]
+#[test_case(PixelFormat::Yuv422)]
+#[test_case(PixelFormat::Yuv420)]
+#[test_case(PixelFormat::Yuv400)]
+fn ycbcr_passthrough_yuv_format(yuv_format: PixelFormat) -> AvifResult<()> {
+    let filename = write_jpeg(&[200, 100, 50]);
+    let config = Config {
+        yuv_format: Some(yuv_format),
+        ..Default::default()
+    };
+    let image = read_jpeg(&filename, true, &config)?;
+    assert_eq!(image.yuv_format, yuv_format);
+    assert_plane_near(&image, Plane::Y, 124);
+    if yuv_format != PixelFormat::Yuv400 {
+        assert_plane_near(&image, Plane::U, 86);
+        assert_plane_near(&image, Plane::V, 182);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "mozjpeg")]
+fn ycbcr_passthrough_native_chroma() -> AvifResult<()> {
+    // 4:2:0 JPEG whose 2x2 blocks alternate between (200, 100, 50) and (50, 100, 200), whose
+    // BT.601 full range Cb samples are 86.1 and 186.4.
+    let pixels: Vec<u8> = (0..16 * 16u32)
+        .flat_map(|i| {
+            let (x, y) = (i % 16, i / 16);
+            if (x / 2 + y / 2).is_multiple_of(2) {
+                [200, 100, 50]
+            } else {
+                [50, 100, 200]
+            }
+        })
+        .collect();
+    let mut compress = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+    compress.set_size(16, 16);
+    compress.set_quality(100.0);
+    compress.set_chroma_sampling_pixel_sizes((2, 2), (2, 2));
+    let mut compress = compress.start_compress(Vec::new()).unwrap();
+    compress.write_scanlines(&pixels).unwrap();
+    let filename = get_tempfile();
+    std::fs::write(&filename, compress.finish().unwrap()).unwrap();
+
+    let image = read_jpeg(&filename, true, &Config::default())?;
+    assert_eq!(image.yuv_format, PixelFormat::Yuv420);
+    // Resampling the upsampled chroma would blur the alternating samples together.
+    for y in 0..8 {
+        for (x, &value) in image.row(Plane::U, y)?[..8].iter().enumerate() {
+            let expected: u8 = if (x as u32 + y).is_multiple_of(2) { 86 } else { 186 };
+            assert!(value.abs_diff(expected) <= 4, "{value} vs {expected}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn ycbcr_passthrough_subsampled_chroma() -> AvifResult<()> {
+    // 4:2:0 JPEG whose left half is (200, 100, 50) and right half is (50, 100, 200), whose BT.601
+    // full range Y samples are 124.2 and 96.5 and Cb samples are 86.1 and 186.4. Without the mozjpeg feature, the chroma planes
+    // are upsampled by the JPEG decoder and averaged back to 4:2:0.
+    let image = read_jpeg(&get_test_file("ycbcr_420.jpg"), true, &Config::default())?;
+    assert_eq!(image.yuv_format, PixelFormat::Yuv420);
+    assert_eq!((image.width, image.height), (16, 16));
+    for y in 0..16 {
+        let row = image.row(Plane::Y, y)?;
+        for (x, expected) in [(0, 124), (5, 124), (10, 96), (15, 96)] {
+            assert!(row[x].abs_diff(expected) <= 1, "{} vs {expected}", row[x]);
+        }
+    }
+    for y in 0..8 {
+        let row = image.row(Plane::U, y)?;
+        // Skip the samples next to the edge, which are blurred by the chroma upsampling.
+        for (x, expected) in [(0, 86), (2, 86), (5, 186), (7, 186)] {
+            assert!(row[x].abs_diff(expected) <= 4, "{} vs {expected}", row[x]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn ycbcr_passthrough_gray() -> AvifResult<()> {
+    let filename = write_jpeg(&[100]);
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv420),
+        ..Default::default()
+    };
+    let image = read_jpeg(&filename, true, &config)?;
+    assert_plane_near(&image, Plane::Y, 100);
+    assert_plane_near(&image, Plane::U, 128);
+    assert_plane_near(&image, Plane::V, 128);
+    Ok(())
+}
+
+#[test_case(Some(10), None)]
+#[test_case(None, Some(MatrixCoefficients::Bt709))]
+fn ycbcr_passthrough_invalid_config(
+    depth: Option<u8>,
+    matrix_coefficients: Option<MatrixCoefficients>,
+) {
+    let filename = write_jpeg(&[200, 100, 50]);
+    let config = Config {
+        depth,
+        matrix_coefficients,
+        ..Default::default()
+    };
+    assert!(read_jpeg(&filename, true, &config).is_err());
+    assert!(read_jpeg(&filename, false, &config).is_ok());
+}
+
+#[test]
+fn metadata() -> AvifResult<()> {
+    let image = read_jpeg(
+        &get_test_file("paris_exif_xmp_icc.jpg"),
+        false,
+        &Config::default(),
+    )?;
+    assert!(!image.icc.is_empty());
+    // The Exif payload starts at the TIFF header.
+    assert!(image.exif.starts_with(b"II*\0") || image.exif.starts_with(b"MM\0*"));
+    assert!(String::from_utf8_lossy(&image.xmp).contains("x:xmpmeta"));
+    Ok(())
+}
+
+// Inserts a segment with the given marker and payload right after the start of image marker.
+fn insert_segment(jpeg: &[u8], marker: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = jpeg[..2].to_vec();
+    data.extend_from_slice(&[0xFF, marker]);
+    data.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    data.extend_from_slice(payload);
+    data.extend_from_slice(&jpeg[2..]);
+    data
+}
+
+// Writes an Ultra HDR JPEG file made of a primary image and a gain map image listed in a
+// Multi-Picture Format segment, and returns its filename.
+fn write_ultra_hdr_jpeg(primary_xmp: Option<&str>, gainmap_segments: &[(u8, Vec<u8>)]) -> String {
+    let mut primary = encode_jpeg(&[200, 100, 50]);
+    if let Some(xmp) = primary_xmp {
+        primary = insert_segment(
+            &primary,
+            0xE1,
+            &[b"http://ns.adobe.com/xap/1.0/\0", xmp.as_bytes()].concat(),
+        );
+    }
+    let mut gainmap = encode_jpeg(&[100]);
+    for (marker, payload) in gainmap_segments {
+        gainmap = insert_segment(&gainmap, *marker, payload);
+    }
+    // Big endian TIFF header, one IFD entry (MPEntry) and two MP entries.
+    const MPF_SIZE: usize = 4 + 8 + 2 + 12 + 4 + 32;
+    // The MPF segment is inserted right after the start of image marker.
+    const TIFF_OFFSET: usize = 2 + 4 + 4;
+    let primary_size = primary.len() + 4 + MPF_SIZE;
+    let mut mpf = b"MPF\0MM\0*".to_vec();
+    mpf.extend_from_slice(&8u32.to_be_bytes());
+    mpf.extend_from_slice(&1u16.to_be_bytes());
+    mpf.extend_from_slice(&[0xB0, 0x02, 0x00, 0x07]);
+    mpf.extend_from_slice(&32u32.to_be_bytes());
+    mpf.extend_from_slice(&26u32.to_be_bytes());
+    mpf.extend_from_slice(&0u32.to_be_bytes());
+    for (size, offset) in [
+        (primary_size, 0),
+        (gainmap.len(), primary_size - TIFF_OFFSET),
+    ] {
+        mpf.extend_from_slice(&0u32.to_be_bytes());
+        mpf.extend_from_slice(&(size as u32).to_be_bytes());
+        mpf.extend_from_slice(&(offset as u32).to_be_bytes());
+        mpf.extend_from_slice(&0u32.to_be_bytes());
+    }
+    assert_eq!(mpf.len(), MPF_SIZE);
+    let data = [insert_segment(&primary, 0xE2, &mpf), gainmap].concat();
+    let filename = get_tempfile();
+    std::fs::write(&filename, data).expect("failed to write jpeg");
+    filename
+}
+
+fn iso_segment(use_common_denominator: bool) -> (u8, Vec<u8>) {
+    let mut payload = b"urn:iso:std:iso:ts:21496:-1\0".to_vec();
+    // minimum_version, writer_version.
+    payload.extend_from_slice(&[0, 0, 0, 0]);
+    if use_common_denominator {
+        // Flags, common denominator, headrooms then min, max, gamma, base and alternate offsets.
+        payload.push(0x08);
+        for value in [64u32, 0, 128, (-32i32) as u32, 192, 64, 1, 1] {
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+    } else {
+        payload.push(0x00);
+        for (numerator, denominator) in [
+            (0u32, 1u32),
+            (2, 1),
+            ((-1i32) as u32, 2),
+            (3, 1),
+            (1, 1),
+            (1, 64),
+            (1, 64),
+        ] {
+            payload.extend_from_slice(&numerator.to_be_bytes());
+            payload.extend_from_slice(&denominator.to_be_bytes());
+        }
+    }
+    (0xE2, payload)
+}
+
+fn xmp_segment(xmp: &str) -> (u8, Vec<u8>) {
+    (
+        0xE1,
+        [b"http://ns.adobe.com/xap/1.0/\0", xmp.as_bytes()].concat(),
+    )
+}
+
+const XMP_ATTRIBUTES: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:hdrgm="http://ns.adobe.com/hdr-gain-map/1.0/"
+   hdrgm:Version="1.0" hdrgm:GainMapMin="-0.5" hdrgm:GainMapMax="3"
+   hdrgm:Gamma="1" hdrgm:OffsetSDR="0.015625" hdrgm:OffsetHDR="0.015625"
+   hdrgm:HDRCapacityMin="0" hdrgm:HDRCapacityMax="2"/>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+const XMP_ELEMENTS: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:gm="http://ns.adobe.com/hdr-gain-map/1.0/">
+   <gm:Version>1.0</gm:Version>
+   <gm:BaseRenditionIsHDR>True</gm:BaseRenditionIsHDR>
+   <gm:GainMapMin><rdf:Seq><rdf:li>-0.5</rdf:li><rdf:li>-0.25</rdf:li><rdf:li>0</rdf:li></rdf:Seq></gm:GainMapMin>
+   <gm:GainMapMax><rdf:Seq><rdf:li>3</rdf:li><rdf:li>2.5</rdf:li><rdf:li>2</rdf:li></rdf:Seq></gm:GainMapMax>
+   <gm:OffsetSDR>0.25</gm:OffsetSDR>
+   <gm:HDRCapacityMin>0.5</gm:HDRCapacityMin>
+   <gm:HDRCapacityMax>2</gm:HDRCapacityMax>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+fn read_gainmap(filename: &str) -> AvifResult<(Image, Option<GainMap>)> {
+    let mut reader = JpegReader::create(filename)?;
+    let (image, _) = reader.read_frame(&Config::default())?;
+    Ok((image, reader.take_gainmap()?))
+}
+
+fn assert_fractions(fractions: &[Fraction; 3], expected: [f64; 3]) {
+    for (fraction, expected) in fractions.iter().zip(expected) {
+        assert_eq!(fraction.0 as f64 / fraction.1 as f64, expected);
+    }
+}
+
+#[test_case(false ; "explicit_denominators")]
+#[test_case(true ; "common_denominator")]
+fn ultra_hdr_iso(use_common_denominator: bool) -> AvifResult<()> {
+    // The ISO 21496-1 metadata takes precedence over the XMP metadata.
+    let filename = write_ultra_hdr_jpeg(
+        None,
+        &[
+            iso_segment(use_common_denominator),
+            xmp_segment(XMP_ELEMENTS),
+        ],
+    );
+    let (image, gainmap) = read_gainmap(&filename)?;
+    assert_eq!(image.yuv_format, PixelFormat::Yuv420);
+    let gainmap = gainmap.expect("gain map not found");
+    assert_eq!(gainmap.image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(gainmap.image.depth, 8);
+    assert_plane_near(&gainmap.image, Plane::Y, 100);
+    assert!(gainmap.image.xmp.is_empty());
+    let metadata = &gainmap.metadata;
+    assert_eq!(metadata.base_hdr_headroom.0, 0);
+    assert_eq!(
+        metadata.alternate_hdr_headroom.0 as f64 / metadata.alternate_hdr_headroom.1 as f64,
+        2.0
+    );
+    assert_fractions(&metadata.min, [-0.5; 3]);
+    assert_fractions(&metadata.max, [3.0; 3]);
+    assert_eq!(metadata.gamma[0].0, metadata.gamma[0].1);
+    assert_fractions(&metadata.base_offset, [1.0 / 64.0; 3]);
+    assert_fractions(&metadata.alternate_offset, [1.0 / 64.0; 3]);
+    assert!(!metadata.use_base_color_space);
+    assert_eq!(gainmap.alt_plane_count, 3);
+    Ok(())
+}
+
+#[test_case(true ; "gainmap_xmp")]
+#[test_case(false ; "primary_xmp")]
+fn ultra_hdr_xmp_attributes(in_gainmap: bool) -> AvifResult<()> {
+    let filename = if in_gainmap {
+        write_ultra_hdr_jpeg(None, &[xmp_segment(XMP_ATTRIBUTES)])
+    } else {
+        write_ultra_hdr_jpeg(Some(XMP_ATTRIBUTES), &[])
+    };
+    let (_, gainmap) = read_gainmap(&filename)?;
+    let metadata = gainmap.expect("gain map not found").metadata;
+    assert_eq!(metadata.base_hdr_headroom.0, 0);
+    assert_eq!(metadata.alternate_hdr_headroom, UFraction(2, 1));
+    assert_eq!(metadata.min, [Fraction(-1, 2); 3]);
+    assert_eq!(metadata.max, [Fraction(3, 1); 3]);
+    assert_eq!(metadata.gamma, [UFraction(1, 1); 3]);
+    assert_eq!(metadata.base_offset, [Fraction(1, 64); 3]);
+    assert_eq!(metadata.alternate_offset, [Fraction(1, 64); 3]);
+    assert!(metadata.use_base_color_space);
+    Ok(())
+}
+
+#[test]
+fn ultra_hdr_xmp_elements() -> AvifResult<()> {
+    let filename = write_ultra_hdr_jpeg(None, &[xmp_segment(XMP_ELEMENTS)]);
+    let (_, gainmap) = read_gainmap(&filename)?;
+    let metadata = gainmap.expect("gain map not found").metadata;
+    // The base rendition is HDR.
+    assert_eq!(metadata.base_hdr_headroom, UFraction(2, 1));
+    assert_eq!(metadata.alternate_hdr_headroom, UFraction(1, 2));
+    assert_eq!(
+        metadata.min,
+        [Fraction(-1, 2), Fraction(-1, 4), Fraction(0, 1)]
+    );
+    assert_eq!(
+        metadata.max,
+        [Fraction(3, 1), Fraction(5, 2), Fraction(2, 1)]
+    );
+    assert_eq!(metadata.base_offset, [Fraction(1, 64); 3]);
+    assert_eq!(metadata.alternate_offset, [Fraction(1, 4); 3]);
+    Ok(())
+}
+
+#[test]
+fn ultra_hdr_xmp_other_description() -> AvifResult<()> {
+    // Only the properties of the description of the gain map namespace are read, even if
+    // another description uses a prefix ending with the same letters.
+    let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:xgm="http://example.com/xgm/"
+   xgm:Version="2.0" xgm:GainMapMax="9" xgm:HDRCapacityMax="9"/>
+  <rdf:Description xmlns:gm="http://ns.adobe.com/hdr-gain-map/1.0/"
+   gm:Version="1.0" gm:GainMapMax="3" gm:HDRCapacityMax="2"/>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+    let filename = write_ultra_hdr_jpeg(None, &[xmp_segment(xmp)]);
+    let (_, gainmap) = read_gainmap(&filename)?;
+    let metadata = gainmap.expect("gain map not found").metadata;
+    assert_eq!(metadata.max, [Fraction(3, 1); 3]);
+    assert_eq!(metadata.alternate_hdr_headroom, UFraction(2, 1));
+    Ok(())
+}
+
+#[test]
+fn ultra_hdr_invalid_xmp() -> AvifResult<()> {
+    // The primary image is still read if the gain map is invalid, and the error is reported
+    // separately.
+    let xmp = XMP_ATTRIBUTES.replace(" hdrgm:GainMapMax=\"3\"", "");
+    let filename = write_ultra_hdr_jpeg(None, &[xmp_segment(&xmp)]);
+    let mut reader = JpegReader::create(&filename)?;
+    let (image, _) = reader.read_frame(&Config::default())?;
+    assert_plane_near(&image, Plane::Y, 124);
+    assert!(reader.take_gainmap().is_err());
+    Ok(())
+}
+
+#[test]
+fn no_gainmap() -> AvifResult<()> {
+    // A secondary image without gain map metadata is ignored.
+    let filename = write_ultra_hdr_jpeg(None, &[]);
+    assert!(read_gainmap(&filename)?.1.is_none());
+    let filename = write_jpeg(&[200, 100, 50]);
+    assert!(read_gainmap(&filename)?.1.is_none());
+    Ok(())
+}
+
+fn create_image(yuv_format: PixelFormat, values: [u8; 3]) -> AvifResult<Image> {
+    let mut image = Image {
+        width: 16,
+        height: 16,
+        depth: 8,
+        yuv_format,
+        yuv_range: YuvRange::Full,
+        matrix_coefficients: MatrixCoefficients::Bt601,
+        ..Default::default()
+    };
+    image.allocate_planes(Category::Color)?;
+    for (plane, value) in [
+        (Plane::Y, values[0]),
+        (Plane::U, values[1]),
+        (Plane::V, values[2]),
+    ] {
+        if !image.has_plane(plane) {
+            continue;
+        }
+        for y in 0..image.height(plane) as u32 {
+            image.row_mut(plane, y)?.fill(value);
+        }
+    }
+    Ok(image)
+}
+
+#[test_case(false, false ; "single_channel_iso")]
+#[test_case(true, false ; "multichannel_iso")]
+#[test_case(false, true ; "single_channel_xmp")]
+#[test_case(true, true ; "multichannel_xmp")]
+fn ultra_hdr_round_trip(multichannel: bool, xmp_only: bool) -> AvifResult<()> {
+    let image = create_image(PixelFormat::Yuv420, [124, 86, 182])?;
+    let mut metadata = GainMapMetadata {
+        min: [Fraction(-1, 2); 3],
+        max: [Fraction(3, 1); 3],
+        gamma: [UFraction(1, 1); 3],
+        base_offset: [Fraction(1, 64); 3],
+        alternate_offset: [Fraction(1, 32); 3],
+        base_hdr_headroom: UFraction(0, 1),
+        alternate_hdr_headroom: UFraction(5, 2),
+        use_base_color_space: true,
+    };
+    let gainmap_image = if multichannel {
+        metadata.min[1] = Fraction(-1, 4);
+        metadata.max[2] = Fraction(2, 1);
+        create_image(PixelFormat::Yuv444, [100, 128, 128])?
+    } else {
+        create_image(PixelFormat::Yuv400, [100, 0, 0])?
+    };
+    let gainmap = GainMap {
+        image: gainmap_image,
+        metadata: metadata.clone(),
+        ..Default::default()
+    };
+    let filename = get_tempfile();
+    let mut writer = JpegWriter { quality: Some(100) };
+    let mut file = File::create(&filename).expect("unable to create file");
+    writer.write_frame_with_gainmap(&mut file, &image, &gainmap)?;
+    drop(file);
+    if xmp_only {
+        // Hide the ISO 21496-1 segments so that the XMP metadata is used.
+        let mut data = std::fs::read(&filename).expect("unable to read file");
+        let namespace = b"urn:iso:std:iso:ts:21496:-1";
+        let mut i = 0;
+        while i + namespace.len() <= data.len() {
+            if &data[i..i + namespace.len()] == namespace {
+                data[i] = b'x';
+            }
+            i += 1;
+        }
+        std::fs::write(&filename, data).expect("unable to write file");
+    }
+
+    let (read_image, read_gainmap) = read_gainmap(&filename)?;
+    assert_eq!((read_image.width, read_image.height), (16, 16));
+    assert_plane_near(&read_image, Plane::Y, 124);
+    let read_gainmap = read_gainmap.expect("gain map not found");
+    assert_eq!(read_gainmap.metadata, metadata);
+    assert_eq!(
+        read_gainmap.image.yuv_format,
+        if multichannel { PixelFormat::Yuv444 } else { PixelFormat::Yuv400 }
+    );
+    assert_plane_near(&read_gainmap.image, Plane::Y, 100);
+    Ok(())
+}
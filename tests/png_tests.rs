@@ -0,0 +1,599 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "png")]
+
+use crabby_avif::decoder::track::ImageTiming;
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::png::PngReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::png::ApngWriter;
+use crabby_avif::utils::writer::png::PngWriter;
+use crabby_avif::utils::writer::AnimationWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use std::fs::File;
+use std::io::BufWriter;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+// Writes a 4x2 PNG file with the given pixels and returns its filename. setup is called on the
+// encoder before the header is written and add_chunks on the writer before the image data.
+fn write_png(
+    color_type: png::ColorType,
+    bit_depth: png::BitDepth,
+    pixels: &[u8],
+    setup: impl FnOnce(&mut png::Encoder<BufWriter<File>>),
+    add_chunks: impl FnOnce(&mut png::Writer<BufWriter<File>>),
+) -> String {
+    let filename = get_tempfile();
+    let file = File::create(&filename).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), 4, 2);
+    encoder.set_color(color_type);
+    encoder.set_depth(bit_depth);
+    setup(&mut encoder);
+    let mut writer = encoder.write_header().unwrap();
+    add_chunks(&mut writer);
+    writer.write_image_data(pixels).unwrap();
+    writer.finish().unwrap();
+    filename
+}
+
+fn read_png(filename: &str) -> Image {
+    let mut reader = PngReader::create(filename).unwrap();
+    let (image, _) = reader.read_frame(&Config::default()).unwrap();
+    image
+}
+
+#[test]
+fn gray() {
+    let pixels = [0, 10, 20, 30, 200, 220, 240, 255];
+    let filename = write_png(
+        png::ColorType::Grayscale,
+        png::BitDepth::Eight,
+        &pixels,
+        |_| {},
+        |_| {},
+    );
+    let image = read_png(&filename);
+    assert_eq!(image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(image.depth, 8);
+    assert!(!image.has_alpha());
+    assert_eq!(plane_values(&image, Plane::Y), pixels.map(|x| x as u16));
+}
+
+#[test]
+fn gray_alpha_16bit() {
+    let values: [u16; 16] = [
+        0, 65535, 1000, 0, 2000, 100, 3000, 200, 40000, 300, 50000, 400, 60000, 500, 65535, 65535,
+    ];
+    let pixels: Vec<u8> = values.iter().flat_map(|x| x.to_be_bytes()).collect();
+    let filename = write_png(
+        png::ColorType::GrayscaleAlpha,
+        png::BitDepth::Sixteen,
+        &pixels,
+        |_| {},
+        |_| {},
+    );
+    let image = read_png(&filename);
+    assert_eq!(image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(image.depth, 16);
+    let gray: Vec<u16> = values.iter().step_by(2).copied().collect();
+    let alpha: Vec<u16> = values.iter().skip(1).step_by(2).copied().collect();
+    assert_eq!(plane_values(&image, Plane::Y), gray);
+    assert_eq!(plane_values(&image, Plane::A), alpha);
+}
+
+#[test]
+fn rgba_premultiplied() {
+    // Gray pixels with alpha values of 255, 128, 0 and 51.
+    let pixels = [
+        200, 200, 200, 255, 200, 200, 200, 128, 200, 200, 200, 0, 100, 100, 100, 51, 0, 0, 0, 0,
+        255, 255, 255, 255, 10, 10, 10, 10, 255, 255, 255, 0,
+    ];
+    let filename = write_png(
+        png::ColorType::Rgba,
+        png::BitDepth::Eight,
+        &pixels,
+        |_| {},
+        |_| {},
+    );
+    let mut reader = PngReader::create(&filename).unwrap();
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        premultiply_alpha: true,
+        ..Default::default()
+    };
+    let (image, _) = reader.read_frame(&config).unwrap();
+    assert!(image.alpha_premultiplied);
+    assert_eq!(
+        plane_values(&image, Plane::Y),
+        [200, 100, 0, 20, 0, 255, 0, 0]
+    );
+    assert_eq!(
+        plane_values(&image, Plane::A),
+        [255, 128, 0, 51, 0, 255, 10, 0]
+    );
+}
+
+#[test_case(png::BitDepth::One, &[0b1010_0000, 0b0101_0000], &[255, 0, 255, 0, 0, 255, 0, 255])]
+#[test_case(png::BitDepth::Two, &[0b0001_1011, 0b1110_0100], &[0, 85, 170, 255, 255, 170, 85, 0])]
+#[test_case(png::BitDepth::Four, &[0x0f, 0x5a, 0xf0, 0x31], &[0, 255, 85, 170, 255, 0, 51, 17])]
+fn gray_low_bit_depth(bit_depth: png::BitDepth, pixels: &[u8], expected: &[u16]) {
+    let filename = write_png(png::ColorType::Grayscale, bit_depth, pixels, |_| {}, |_| {});
+    let image = read_png(&filename);
+    assert_eq!(image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(image.depth, 8);
+    assert_eq!(plane_values(&image, Plane::Y), expected);
+}
+
+#[test]
+fn palette_with_trns() {
+    // Four palette entries, the first two of which are translucent.
+    let palette = [0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 255];
+    let trns = [0, 128];
+    let pixels = [0x01, 0x23, 0x32, 0x10];
+    let filename = write_png(
+        png::ColorType::Indexed,
+        png::BitDepth::Four,
+        &pixels,
+        |encoder| {
+            encoder.set_palette(palette.to_vec());
+            encoder.set_trns(trns.to_vec());
+        },
+        |_| {},
+    );
+    let mut reader = PngReader::create(&filename).unwrap();
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        ..Default::default()
+    };
+    let (image, _) = reader.read_frame(&config).unwrap();
+    assert_eq!(image.yuv_format, PixelFormat::Yuv444);
+    assert_eq!(
+        plane_values(&image, Plane::A),
+        [0, 128, 255, 255, 255, 255, 128, 0]
+    );
+    // Black, white, red and blue have distinct luma values.
+    let y = plane_values(&image, Plane::Y);
+    assert_eq!(y[0], 0);
+    assert_eq!(y[1], 255);
+    assert_eq!(y[0..4], [y[7], y[6], y[5], y[4]]);
+}
+
+#[test]
+fn metadata_file() {
+    let image = read_png(&get_test_file("paris_icc_exif_xmp.png"));
+    assert!(!image.icc.is_empty());
+    assert!(!image.exif.is_empty());
+    assert!(String::from_utf8_lossy(&image.xmp).contains("x:xmpmeta"));
+}
+
+#[test]
+fn metadata_chunks() {
+    let exif = [b'M', b'M', 0, 42, 0, 0, 0, 8];
+    let xmp = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"></x:xmpmeta>";
+    // 1000 and 400 cd/m2.
+    let clli = [0x00, 0x98, 0x96, 0x80, 0x00, 0x3d, 0x09, 0x00];
+    // BT.2020 primaries, D65 white point, 1000 and 0.005 cd/m2.
+    let mdcv = [
+        0x8a, 0x48, 0x39, 0x08, 0x21, 0x34, 0x9b, 0xaa, 0x19, 0x96, 0x08, 0xfc, 0x3d, 0x13, 0x40,
+        0x42, 0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x00, 0x32,
+    ];
+    let filename = write_png(
+        png::ColorType::Rgb,
+        png::BitDepth::Eight,
+        &[128; 24],
+        |_| {},
+        |writer| {
+            // cICP: BT.2020 primaries, PQ transfer, RGB, full range.
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"cICP"), &[9, 16, 0, 1])
+                .unwrap();
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"cLLi"), &clli)
+                .unwrap();
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"mDCv"), &mdcv)
+                .unwrap();
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"eXIf"), &exif)
+                .unwrap();
+            writer
+                .write_text_chunk(&png::text_metadata::ITXtChunk::new(
+                    "XML:com.adobe.xmp",
+                    xmp,
+                ))
+                .unwrap();
+        },
+    );
+    let image = read_png(&filename);
+    assert_eq!(image.color_primaries, ColorPrimaries::Bt2020);
+    assert_eq!(image.transfer_characteristics, TransferCharacteristics::Pq);
+    assert_eq!(image.exif, exif);
+    assert_eq!(image.xmp, xmp.as_bytes());
+    assert_eq!(
+        image.clli,
+        Some(ContentLightLevelInformation {
+            max_cll: 1000,
+            max_pall: 400
+        })
+    );
+    assert_eq!(
+        image.mdcv,
+        Some(MasteringDisplayColourVolume {
+            display_primaries_x: [8500, 6550, 35400],
+            display_primaries_y: [39850, 2300, 14600],
+            white_point_x: 15635,
+            white_point_y: 16450,
+            max_display_mastering_luminance: 10000000,
+            min_display_mastering_luminance: 50,
+        })
+    );
+}
+
+#[test_case(Some(png::SrgbRenderingIntent::Perceptual), None, None,
+            ColorPrimaries::Srgb, TransferCharacteristics::Srgb; "srgb")]
+#[test_case(None, Some(1.0 / 2.2), Some(ColorPrimaries::Bt2020),
+            ColorPrimaries::Bt2020, TransferCharacteristics::Bt470m; "gamma22")]
+#[test_case(None, Some(1.0), Some(ColorPrimaries::Smpte432),
+            ColorPrimaries::Smpte432, TransferCharacteristics::Linear; "linear")]
+#[test_case(None, Some(0.7), None,
+            ColorPrimaries::Unspecified, TransferCharacteristics::Unspecified; "unknown_gamma")]
+fn color_chunks(
+    srgb: Option<png::SrgbRenderingIntent>,
+    gamma: Option<f32>,
+    primaries: Option<ColorPrimaries>,
+    expected_primaries: ColorPrimaries,
+    expected_transfer: TransferCharacteristics,
+) {
+    let filename = write_png(
+        png::ColorType::Rgb,
+        png::BitDepth::Eight,
+        &[128; 24],
+        |encoder| {
+            if let Some(srgb) = srgb {
+                encoder.set_source_srgb(srgb);
+            }
+            if let Some(gamma) = gamma {
+                encoder.set_source_gamma(png::ScaledFloat::new(gamma));
+            }
+            if let Some(primaries) = primaries {
+                let c = match primaries {
+                    ColorPrimaries::Bt2020 => {
+                        [0.708, 0.292, 0.17, 0.797, 0.131, 0.046, 0.3127, 0.329]
+                    }
+                    _ => [0.68, 0.32, 0.265, 0.69, 0.15, 0.06, 0.3127, 0.329],
+                };
+                let pair = |x: f32, y: f32| (png::ScaledFloat::new(x), png::ScaledFloat::new(y));
+                encoder.set_source_chromaticities(png::SourceChromaticities {
+                    white: pair(c[6], c[7]),
+                    red: pair(c[0], c[1]),
+                    green: pair(c[2], c[3]),
+                    blue: pair(c[4], c[5]),
+                });
+            }
+        },
+        |_| {},
+    );
+    let image = read_png(&filename);
+    assert_eq!(image.color_primaries, expected_primaries);
+    assert_eq!(image.transfer_characteristics, expected_transfer);
+}
+
+// Writes a 4x2 RGBA APNG file with three frames, optionally preceded by a default image that is
+// not part of the animation.
+fn write_apng(separate_default_image: bool) -> String {
+    let filename = get_tempfile();
+    let file = File::create(&filename).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), 4, 2);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(3, 3).unwrap();
+    encoder.set_sep_def_img(separate_default_image).unwrap();
+    let mut writer = encoder.write_header().unwrap();
+    if separate_default_image {
+        writer.write_image_data(&[255; 32]).unwrap();
+    }
+    // Opaque red, displayed for 100ms.
+    writer.set_frame_delay(1, 10).unwrap();
+    writer.set_blend_op(png::BlendOp::Source).unwrap();
+    writer.set_dispose_op(png::DisposeOp::None).unwrap();
+    writer
+        .write_image_data(&[255, 0, 0, 255].repeat(8))
+        .unwrap();
+    // Translucent blue over the two middle pixels of the second row, displayed for 50ms and then
+    // cleared.
+    writer.set_frame_delay(5, 0).unwrap();
+    writer.set_frame_dimension(2, 1).unwrap();
+    writer.set_frame_position(1, 1).unwrap();
+    writer.set_blend_op(png::BlendOp::Over).unwrap();
+    writer.set_dispose_op(png::DisposeOp::Background).unwrap();
+    writer
+        .write_image_data(&[0, 0, 255, 128].repeat(2))
+        .unwrap();
+    // Opaque green over the top left pixel, displayed for 1/3s.
+    writer.set_frame_delay(1, 3).unwrap();
+    writer.set_frame_dimension(1, 1).unwrap();
+    writer.set_frame_position(0, 0).unwrap();
+    writer.set_blend_op(png::BlendOp::Source).unwrap();
+    writer.set_dispose_op(png::DisposeOp::None).unwrap();
+    writer.write_image_data(&[0, 255, 0, 255]).unwrap();
+    writer.finish().unwrap();
+    filename
+}
+
+#[test_case(false; "default_image_in_animation")]
+#[test_case(true; "separate_default_image")]
+fn apng(separate_default_image: bool) {
+    let filename = write_apng(separate_default_image);
+    let mut reader = PngReader::create(&filename).unwrap();
+    assert_eq!(reader.repetition_count(), Some(RepetitionCount::Finite(2)));
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        ..Default::default()
+    };
+    let mut frames = Vec::new();
+    let mut durations = Vec::new();
+    loop {
+        let (image, duration) = reader.read_frame(&config).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        frames.push(image);
+        durations.push(duration);
+        if !reader.has_more_frames() {
+            break;
+        }
+    }
+    assert_eq!(durations, [100, 50, 333]);
+    assert_eq!(plane_values(&frames[0], Plane::A), [255; 8]);
+    assert_eq!(plane_values(&frames[1], Plane::A), [255; 8]);
+    assert_eq!(
+        plane_values(&frames[2], Plane::A),
+        [255, 255, 255, 255, 255, 0, 0, 255]
+    );
+    let red_y = plane_values(&frames[0], Plane::Y)[0];
+    assert!(plane_values(&frames[0], Plane::Y)
+        .iter()
+        .all(|y| *y == red_y));
+    // Blue blended over red is darker than red.
+    let y1 = plane_values(&frames[1], Plane::Y);
+    assert_eq!(y1[0..5], [red_y; 5]);
+    assert!(y1[5] < red_y && y1[5] == y1[6]);
+    assert_eq!(y1[7], red_y);
+    // Green is brighter than red.
+    let y2 = plane_values(&frames[2], Plane::Y);
+    assert!(y2[0] > red_y);
+    assert_eq!(y2[1..5], [red_y; 4]);
+    assert_eq!(y2[7], red_y);
+}
+
+#[test]
+fn still_png_has_one_frame() {
+    let filename = write_png(
+        png::ColorType::Rgb,
+        png::BitDepth::Eight,
+        &[128; 24],
+        |_| {},
+        |_| {},
+    );
+    let mut reader = PngReader::create(&filename).unwrap();
+    assert_eq!(reader.repetition_count(), None);
+    assert!(reader.read_frame(&Config::default()).is_ok());
+    assert!(!reader.has_more_frames());
+    assert!(reader.read_frame(&Config::default()).is_err());
+}
+
+fn write_image(image: &Image) -> String {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    PngWriter::default().write_frame(&mut file, image).unwrap();
+    filename
+}
+
+fn read_info(filename: &str) -> png::Info<'static> {
+    let decoder = png::Decoder::new(File::open(filename).unwrap());
+    let reader = decoder.read_info().unwrap();
+    reader.info().clone()
+}
+
+#[test]
+fn write_gray_alpha() -> AvifResult<()> {
+    let image = generate_gradient_image(4, 2, 8, PixelFormat::Yuv400, YuvRange::Full, true)?;
+    let filename = write_image(&image);
+    assert_eq!(
+        read_info(&filename).color_type,
+        png::ColorType::GrayscaleAlpha
+    );
+    let read_image = read_png(&filename);
+    assert_eq!(read_image.yuv_format, PixelFormat::Yuv400);
+    assert_eq!(
+        plane_values(&read_image, Plane::Y),
+        plane_values(&image, Plane::Y)
+    );
+    assert_eq!(
+        plane_values(&read_image, Plane::A),
+        plane_values(&image, Plane::A)
+    );
+    Ok(())
+}
+
+#[test]
+fn write_metadata() -> AvifResult<()> {
+    let mut image = generate_gradient_image(4, 2, 8, PixelFormat::Yuv444, YuvRange::Full, false)?;
+    image.icc = vec![1, 2, 3, 4];
+    // The Exif payload is written starting at the TIFF header.
+    image.exif = b"Exif\0\0MM\0*\0\0\0\x08\0\0".to_vec();
+    image.xmp = b"<x:xmpmeta/>".to_vec();
+    image.clli = Some(ContentLightLevelInformation {
+        max_cll: 1000,
+        max_pall: 400,
+    });
+    image.mdcv = Some(MasteringDisplayColourVolume {
+        display_primaries_x: [8500, 6550, 35400],
+        display_primaries_y: [39850, 2300, 14600],
+        white_point_x: 15635,
+        white_point_y: 16450,
+        max_display_mastering_luminance: 10000000,
+        min_display_mastering_luminance: 50,
+    });
+    image.pasp = Some(PixelAspectRatio {
+        h_spacing: 4,
+        v_spacing: 3,
+    });
+    let filename = write_image(&image);
+    let info = read_info(&filename);
+    let pixel_dims = info.pixel_dims.unwrap();
+    assert_eq!((pixel_dims.xppu, pixel_dims.yppu), (3, 4));
+    assert_eq!(pixel_dims.unit, png::Unit::Unspecified);
+    let read_image = read_png(&filename);
+    assert_eq!(read_image.icc, image.icc);
+    assert_eq!(read_image.exif, image.exif[6..]);
+    assert_eq!(read_image.xmp, image.xmp);
+    assert_eq!(read_image.clli, image.clli);
+    assert_eq!(read_image.mdcv, image.mdcv);
+    Ok(())
+}
+
+#[test_case(
+    ColorPrimaries::Bt2020,
+    TransferCharacteristics::Smpte2084,
+    true,
+    false
+)]
+#[test_case(ColorPrimaries::Srgb, TransferCharacteristics::Srgb, false, true)]
+#[test_case(
+    ColorPrimaries::Unspecified,
+    TransferCharacteristics::Unspecified,
+    false,
+    false
+)]
+fn write_color_space(
+    color_primaries: ColorPrimaries,
+    transfer_characteristics: TransferCharacteristics,
+    expect_cicp: bool,
+    expect_srgb: bool,
+) -> AvifResult<()> {
+    let mut image = generate_gradient_image(4, 2, 8, PixelFormat::Yuv444, YuvRange::Full, false)?;
+    image.color_primaries = color_primaries;
+    image.transfer_characteristics = transfer_characteristics;
+    let filename = write_image(&image);
+    let info = read_info(&filename);
+    assert_eq!(info.coding_independent_code_points.is_some(), expect_cicp);
+    assert_eq!(info.srgb.is_some(), expect_srgb);
+    let read_image = read_png(&filename);
+    assert_eq!(read_image.color_primaries, color_primaries);
+    assert_eq!(
+        read_image.transfer_characteristics,
+        transfer_characteristics
+    );
+    Ok(())
+}
+
+#[test]
+fn apng_opaque_with_trailing_metadata() {
+    let filename = get_tempfile();
+    let file = File::create(&filename).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), 4, 2);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(2, 0).unwrap();
+    let mut writer = encoder.write_header().unwrap();
+    writer.set_frame_delay(0, 0).unwrap();
+    writer.write_image_data(&[255, 0, 0].repeat(8)).unwrap();
+    writer.write_image_data(&[0, 0, 255].repeat(8)).unwrap();
+    // XMP stored after the image data.
+    writer
+        .write_text_chunk(&png::text_metadata::ITXtChunk::new(
+            "XML:com.adobe.xmp",
+            "<x:xmpmeta/>",
+        ))
+        .unwrap();
+    writer.finish().unwrap();
+
+    let mut reader = PngReader::create(&filename).unwrap();
+    assert_eq!(reader.repetition_count(), Some(RepetitionCount::Infinite));
+    for _ in 0..2 {
+        let (image, duration) = reader.read_frame(&Config::default()).unwrap();
+        assert!(!image.has_alpha());
+        assert_eq!(image.xmp, b"<x:xmpmeta/>");
+        // A zero delay is played as 100 ms.
+        assert_eq!(duration, 100);
+    }
+    assert!(!reader.has_more_frames());
+}
+
+#[test_case(RepetitionCount::Finite(0), 1 ; "once")]
+#[test_case(RepetitionCount::Finite(2), 3 ; "finite")]
+#[test_case(RepetitionCount::Infinite, 0 ; "infinite")]
+fn write_animation(repetition_count: RepetitionCount, num_plays: u32) -> AvifResult<()> {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    let mut writer = ApngWriter::create(None, 3, repetition_count);
+    let mut frames = Vec::new();
+    let mut pts_in_timescales = 0;
+    for (index, duration_in_timescales) in [1, 2, 70000].into_iter().enumerate() {
+        let mut image =
+            generate_gradient_image(4, 2, 8, PixelFormat::Yuv400, YuvRange::Full, false)?;
+        for y in 0..image.height {
+            for pixel in image.row_exact_mut(Plane::Y, y)? {
+                *pixel = pixel.wrapping_add(index as u8 * 50);
+            }
+        }
+        let timing = ImageTiming {
+            timescale: 10,
+            pts_in_timescales,
+            duration_in_timescales,
+            duration: duration_in_timescales as f64 / 10.0,
+            ..Default::default()
+        };
+        pts_in_timescales += duration_in_timescales;
+        writer.write_animation_frame(&mut file, &image, &timing)?;
+        frames.push(image);
+    }
+    writer.finish()?;
+
+    let animation_control = read_info(&filename).animation_control.unwrap();
+    assert_eq!(animation_control.num_frames, 3);
+    assert_eq!(animation_control.num_plays, num_plays);
+    let mut reader = PngReader::create(&filename)?;
+    assert_eq!(reader.repetition_count(), Some(repetition_count));
+    let mut durations = Vec::new();
+    for frame in &frames {
+        let (image, duration) = reader.read_frame(&Config::default())?;
+        assert_eq!(image.yuv_format, PixelFormat::Yuv400);
+        assert_eq!(
+            plane_values(&image, Plane::Y),
+            plane_values(frame, Plane::Y)
+        );
+        durations.push(duration);
+    }
+    assert!(!reader.has_more_frames());
+    // The last duration does not fit in 16 bits and is written in milliseconds, clamped.
+    assert_eq!(durations, [100, 200, 65535]);
+    Ok(())
+}
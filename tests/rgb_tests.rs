@@ -554,6 +554,22 @@ fn sharpyuv_16bit(yuv_depth: u8) -> AvifResult<()> {
     })
 }
 
+#[test]
+fn sharpyuv_422_not_implemented() -> AvifResult<()> {
+    let mut yuv = image::Image {
+        width: 4,
+        height: 4,
+        depth: 8,
+        yuv_format: PixelFormat::Yuv422,
+        ..Default::default()
+    };
+    let mut rgb = rgb::Image::create_from_yuv(&yuv);
+    rgb.chroma_downsampling = ChromaDownsampling::SharpYuv;
+    rgb.allocate()?;
+    assert_eq!(rgb.convert_to_yuv(&mut yuv), Err(AvifError::NotImplemented));
+    Ok(())
+}
+
 #[test_matrix(
     [(8, 128), (10, 512), (12, 2048), (16, 32768)],
     [PixelFormat::Yuv420, PixelFormat::Yuv422, PixelFormat::Yuv444, PixelFormat::Yuv400],
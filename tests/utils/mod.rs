@@ -130,6 +130,23 @@ pub fn generate_gradient_image(
     Ok(image)
 }
 
+// Returns the samples of the plane in raster order, without the row padding.
+pub fn plane_values(image: &Image, plane: Plane) -> Vec<u16> {
+    let mut values = Vec::new();
+    for y in 0..image.height(plane) as u32 {
+        if image.depth == 8 {
+            values.extend(
+                image.row(plane, y).unwrap()[..image.width(plane)]
+                    .iter()
+                    .map(|x| *x as u16),
+            );
+        } else {
+            values.extend_from_slice(&image.row16(plane, y).unwrap()[..image.width(plane)]);
+        }
+    }
+    values
+}
+
 pub fn are_planes_equal(image1: &Image, image2: &Image, plane: Plane) -> AvifResult<bool> {
     if !image1.has_same_properties_and_cicp(image2)
         || image1.has_plane(plane) != image2.has_plane(plane)
@@ -326,9 +343,11 @@ pub fn merge_cells_into_grid_image(
 pub const HAS_DECODER: bool = cfg!(any(
     feature = "dav1d",
     feature = "libgav1",
-    feature = "android_mediacodec"
+    feature = "android_mediacodec",
+    feature = "aom"
 ));
 
-pub const HAS_NON_ANDROID_DECODER: bool = cfg!(any(feature = "dav1d", feature = "libgav1",));
+pub const HAS_NON_ANDROID_DECODER: bool =
+    cfg!(any(feature = "dav1d", feature = "libgav1", feature = "aom"));
 
 pub const HAS_ENCODER: bool = cfg!(feature = "aom");
@@ -0,0 +1,133 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "webp")]
+
+use crabby_avif::decoder::track::ImageTiming;
+use crabby_avif::image::*;
+use crabby_avif::utils::reader::webp::WebpReader;
+use crabby_avif::utils::reader::Config;
+use crabby_avif::utils::reader::Reader;
+use crabby_avif::utils::writer::webp::WebpWriter;
+use crabby_avif::utils::writer::AnimationWriter;
+use crabby_avif::utils::writer::Writer;
+use crabby_avif::*;
+
+mod utils;
+use utils::*;
+
+use std::fs::File;
+use tempfile::NamedTempFile;
+use test_case::test_case;
+
+fn get_tempfile() -> String {
+    let file = NamedTempFile::new().expect("unable to open tempfile");
+    let path = file.into_temp_path();
+    let filename = String::from(path.to_str().unwrap());
+    let _ = path.close();
+    filename
+}
+
+fn create_frame(index: u8, alpha: bool) -> AvifResult<Image> {
+    let mut image = generate_gradient_image(4, 2, 8, PixelFormat::Yuv444, YuvRange::Full, alpha)?;
+    image.matrix_coefficients = MatrixCoefficients::Identity;
+    for y in 0..image.height {
+        for pixel in image.row_exact_mut(Plane::Y, y)? {
+            *pixel = pixel.wrapping_add(index * 50);
+        }
+    }
+    Ok(image)
+}
+
+fn read_config() -> Config {
+    Config {
+        yuv_format: Some(PixelFormat::Yuv444),
+        matrix_coefficients: Some(MatrixCoefficients::Identity),
+        ..Default::default()
+    }
+}
+
+fn assert_lossless(read_image: &Image, image: &Image) {
+    let mut planes = vec![Plane::Y, Plane::U, Plane::V];
+    if image.alpha_present {
+        planes.push(Plane::A);
+    }
+    for plane in planes {
+        assert_eq!(plane_values(read_image, plane), plane_values(image, plane));
+    }
+}
+
+#[test_case(false ; "opaque")]
+#[test_case(true ; "alpha")]
+fn write_still(alpha: bool) -> AvifResult<()> {
+    let mut image = create_frame(0, alpha)?;
+    image.icc = vec![1, 2, 3];
+    image.exif = b"Exif\0\0II*\0\x08\0\0\0\0\0".to_vec();
+    image.xmp = b"<x:xmpmeta/>".to_vec();
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    WebpWriter::default().write_frame(&mut file, &image)?;
+
+    let mut reader = WebpReader::create(&filename)?;
+    assert_eq!(reader.repetition_count(), None);
+    let (read_image, duration) = reader.read_frame(&read_config())?;
+    assert!(!reader.has_more_frames());
+    assert_eq!(duration, 0);
+    assert_eq!(read_image.has_plane(Plane::A), alpha);
+    assert_lossless(&read_image, &image);
+    assert_eq!(read_image.icc, image.icc);
+    // The Exif chunk starts at the TIFF header.
+    assert_eq!(read_image.exif, image.exif[6..]);
+    assert_eq!(read_image.xmp, image.xmp);
+    Ok(())
+}
+
+#[test_case(RepetitionCount::Finite(0) ; "once")]
+#[test_case(RepetitionCount::Finite(3) ; "finite")]
+#[test_case(RepetitionCount::Infinite ; "infinite")]
+fn write_animation(repetition_count: RepetitionCount) -> AvifResult<()> {
+    let filename = get_tempfile();
+    let mut file = File::create(&filename).unwrap();
+    let mut writer = WebpWriter::create(repetition_count);
+    let mut frames = Vec::new();
+    // 30 frames per second.
+    for index in 0..3 {
+        let mut image = create_frame(index, index == 1)?;
+        image.xmp = b"<x:xmpmeta/>".to_vec();
+        let timing = ImageTiming {
+            timescale: 30,
+            pts: index as f64 / 30.0,
+            pts_in_timescales: index as u64,
+            duration: 1.0 / 30.0,
+            duration_in_timescales: 1,
+        };
+        writer.write_animation_frame(&mut file, &image, &timing)?;
+        frames.push(image);
+    }
+    writer.finish()?;
+
+    let mut reader = WebpReader::create(&filename)?;
+    assert_eq!(reader.repetition_count(), Some(repetition_count));
+    let mut durations = Vec::new();
+    for frame in &frames {
+        let (image, duration) = reader.read_frame(&read_config())?;
+        assert_eq!(image.xmp, frame.xmp);
+        assert_lossless(&image, frame);
+        durations.push(duration);
+    }
+    assert!(!reader.has_more_frames());
+    // The durations are rounded to milliseconds without drifting.
+    assert_eq!(durations, [33, 34, 33]);
+    Ok(())
+}
@@ -14,6 +14,7 @@
 
 use crabby_avif::image::*;
 use crabby_avif::utils::reader::y4m::Y4MReader;
+use crabby_avif::utils::reader::yuv::YuvReader;
 use crabby_avif::utils::reader::Config;
 use crabby_avif::utils::reader::Reader;
 use crabby_avif::utils::writer::y4m::Y4MWriter;
@@ -25,6 +26,7 @@ use utils::*;
 
 use std::fs::File;
 use tempfile::NamedTempFile;
+use test_case::test_case;
 use test_case::test_matrix;
 
 fn get_tempfile() -> String {
@@ -70,3 +72,138 @@ fn roundtrip(
     are_images_equal(&image1, &image2)?;
     Ok(())
 }
+
+#[test_matrix(
+    [8, 10, 12],
+    [PixelFormat::Yuv420, PixelFormat::Yuv422, PixelFormat::Yuv444, PixelFormat::Yuv400],
+    [YuvRange::Limited, YuvRange::Full]
+)]
+fn raw_roundtrip(depth: u8, yuv_format: PixelFormat, yuv_range: YuvRange) -> AvifResult<()> {
+    let mut frames = Vec::new();
+    for index in 0..3 {
+        frames.push(generate_gradient_image(
+            13 + index,
+            7,
+            depth,
+            yuv_format,
+            yuv_range,
+            false,
+        )?);
+    }
+    let output_filename = get_tempfile();
+    {
+        let mut writer = Y4MWriter::create(true);
+        let mut output_file =
+            File::create(output_filename.clone()).expect("output file creation failed");
+        for frame in &frames {
+            writer.write_frame(&mut output_file, frame)?;
+        }
+    }
+    let mut reader = YuvReader::create(&output_filename)?;
+    for frame in &frames {
+        let config = Config {
+            yuv_format: Some(yuv_format),
+            depth: Some(depth),
+            width: Some(frame.width),
+            height: Some(frame.height),
+            yuv_range: Some(yuv_range),
+            ..Default::default()
+        };
+        assert!(reader.has_more_frames());
+        let (image, duration) = reader.read_frame(&config)?;
+        assert_eq!(duration, 0);
+        assert_eq!(image.yuv_range, yuv_range);
+        assert!(are_images_equal(frame, &image)?);
+    }
+    assert!(!reader.has_more_frames());
+    Ok(())
+}
+
+// Reads a 3x2 frame with interleaved chroma samples.
+#[test_case(PixelFormat::AndroidNv12, false, &[1, 2, 3, 4, 5, 6, 10, 20, 11, 21], 8, [10, 11], [20, 21])]
+#[test_case(PixelFormat::AndroidNv21, false, &[1, 2, 3, 4, 5, 6, 10, 20, 11, 21], 8, [20, 21], [10, 11])]
+#[test_case(
+    PixelFormat::AndroidP010,
+    false,
+    &[0x40, 0, 0x80, 0, 0xc0, 0, 0, 1, 0x40, 1, 0x80, 1, 0xc0, 0xff, 0x40, 0, 0x80, 0x02, 0, 0],
+    10,
+    [1023, 10],
+    [1, 0]
+)]
+#[test_case(
+    PixelFormat::AndroidP010,
+    true,
+    &[0, 0x40, 0, 0x80, 0, 0xc0, 1, 0, 1, 0x40, 1, 0x80, 0xff, 0xc0, 0, 0x40, 0x02, 0x80, 0, 0],
+    10,
+    [1023, 10],
+    [1, 0]
+)]
+fn raw_interleaved(
+    yuv_format: PixelFormat,
+    big_endian: bool,
+    data: &[u8],
+    depth: u8,
+    u: [u16; 2],
+    v: [u16; 2],
+) -> AvifResult<()> {
+    let filename = get_tempfile();
+    std::fs::write(&filename, data).unwrap();
+    let config = Config {
+        yuv_format: Some(yuv_format),
+        width: Some(3),
+        height: Some(2),
+        big_endian,
+        ..Default::default()
+    };
+    let mut reader = YuvReader::create(&filename)?;
+    let (image, _) = reader.read_frame(&config)?;
+    assert!(!reader.has_more_frames());
+    assert_eq!(image.yuv_format, PixelFormat::Yuv420);
+    assert_eq!(image.depth, depth);
+    assert_eq!(image.yuv_range, YuvRange::Limited);
+    assert_eq!(plane_values(&image, Plane::Y), [1, 2, 3, 4, 5, 6]);
+    assert_eq!(plane_values(&image, Plane::U), u);
+    assert_eq!(plane_values(&image, Plane::V), v);
+    Ok(())
+}
+
+#[test]
+fn raw_frame_rate() -> AvifResult<()> {
+    let filename = get_tempfile();
+    std::fs::write(&filename, [0u8; 6 * 3]).unwrap();
+    let config = Config {
+        yuv_format: Some(PixelFormat::Yuv400),
+        width: Some(3),
+        height: Some(2),
+        frame_rate: Some(30.0),
+        ..Default::default()
+    };
+    let mut reader = YuvReader::create(&filename)?;
+    let mut durations = Vec::new();
+    while reader.has_more_frames() {
+        durations.push(reader.read_frame(&config)?.1);
+    }
+    // The durations are rounded to milliseconds without drifting.
+    assert_eq!(durations, [33, 34, 33]);
+    Ok(())
+}
+
+#[test]
+fn raw_invalid() -> AvifResult<()> {
+    let filename = get_tempfile();
+    std::fs::write(&filename, [0u8; 10]).unwrap();
+    let mut config = Config::default();
+    // Missing dimensions.
+    assert!(YuvReader::create(&filename)?.read_frame(&config).is_err());
+    config.width = Some(4);
+    config.height = Some(2);
+    // NV12 is always 8-bit.
+    config.yuv_format = Some(PixelFormat::AndroidNv12);
+    config.depth = Some(10);
+    assert!(YuvReader::create(&filename)?.read_frame(&config).is_err());
+    // Truncated frame.
+    config.yuv_format = Some(PixelFormat::Yuv444);
+    config.depth = None;
+    assert!(YuvReader::create(&filename)?.read_frame(&config).is_err());
+    Ok(())
+}